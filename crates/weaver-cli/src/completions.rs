@@ -0,0 +1,136 @@
+//! `weaver completions <shell>` と `weaver man [command]`
+//!
+//! どちらも clap の定義（`Cli`）から生成する: 補完スクリプトは clap_complete、
+//! man ページは clap_mangen。コマンドやオプションを増やしても、ここを直す必要はない。
+//!
+//! パッケージングでは生成結果をファイルに落として配る:
+//!
+//! ```text
+//! weaver completions bash > /usr/share/bash-completion/completions/weaver
+//! weaver completions zsh  > /usr/share/zsh/site-functions/_weaver
+//! weaver completions fish > /usr/share/fish/vendor_completions.d/weaver.fish
+//! weaver man > /usr/share/man/man1/weaver.1
+//! for c in backfill submit completions man; do
+//!     weaver man $c > /usr/share/man/man1/weaver-$c.1
+//! done
+//! ```
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+use clap_mangen::Man;
+use clap_mangen::roff::{Roff, bold, roman};
+
+use crate::Cli;
+
+/// 補完スクリプト
+pub fn script(shell: Shell) -> String {
+    let mut out = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "weaver", &mut out);
+    String::from_utf8(out).expect("completion scripts are UTF-8")
+}
+
+/// man ページ（roff、セクション 1）
+///
+/// `command` を渡すとそのコマンドのページ（weaver-backfill(1) など）。
+/// weaver(1) にはコマンドの一覧と終了コードを載せる。
+pub fn man_page(command: Option<&str>) -> Result<String, String> {
+    let mut cmd = Cli::command();
+    // サブコマンドに weaver-<command> という名前と、グローバルオプションを伝える
+    cmd.build();
+    let target = match command {
+        None => cmd.clone(),
+        Some(name) => cmd
+            .find_subcommand(name)
+            .cloned()
+            .ok_or_else(|| format!("no command named `{name}`"))?,
+    };
+
+    let mut out = Vec::new();
+    Man::new(target)
+        .source(format!("weaver {}", env!("CARGO_PKG_VERSION")))
+        .manual("User Commands")
+        .render(&mut out)
+        .expect("writing to a Vec does not fail");
+    let mut page = String::from_utf8(out).expect("man pages are UTF-8");
+    if command.is_none() {
+        // VERSION の前に差し込む（文中に ' はないので to_roff の前置きなしの形でよい）
+        let at = page.find(".SH VERSION").unwrap_or(page.len());
+        page.insert_str(at, &exit_status().to_roff());
+    }
+    Ok(page)
+}
+
+/// `error::ErrorKind::exit_code` と揃える
+const EXIT_CODES: &[(i32, &str)] = &[
+    (0, "success"),
+    (1, "internal error"),
+    (
+        2,
+        "usage error (unknown command or option, missing argument)",
+    ),
+    (3, "validation error (bad date, number or schedule file)"),
+    (4, "not found (schedule file or schedule)"),
+    (
+        5,
+        "the queue file is locked by another process, corrupt or not writable",
+    ),
+    (6, "timed out waiting for jobs"),
+];
+
+fn exit_status() -> Roff {
+    let mut roff = Roff::new();
+    roff.control("SH", ["EXIT STATUS"]);
+    for (code, meaning) in EXIT_CODES {
+        roff.control("TP", []);
+        roff.text([bold(code.to_string())]);
+        roff.text([roman(*meaning)]);
+    }
+    roff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn every_shell_completes_every_command_and_option() {
+        let cmd = Cli::command();
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = script(shell);
+            for sub in cmd.get_subcommands() {
+                assert!(
+                    script.contains(sub.get_name()),
+                    "{shell}: {}",
+                    sub.get_name()
+                );
+                for arg in sub.get_arguments().chain(cmd.get_arguments()) {
+                    if let Some(long) = arg.get_long() {
+                        assert!(script.contains(long), "{shell}: --{long}");
+                    }
+                }
+            }
+        }
+        assert!(script(Shell::Zsh).starts_with("#compdef weaver\n"));
+    }
+
+    #[test]
+    fn man_pages_cover_commands_options_and_exit_codes() {
+        let page = man_page(None).unwrap();
+        assert!(page.contains(".TH weaver 1"), "{page}");
+        assert!(page.contains("weaver\\-backfill(1)"), "{page}");
+        let exit_status = page.find(".SH \"EXIT STATUS\"").expect("EXIT STATUS");
+        assert!(exit_status < page.find(".SH VERSION").unwrap(), "{page}");
+        assert_eq!(page.matches(".ie \\n(.g").count(), 1, "{page}");
+
+        let page = man_page(Some("backfill")).unwrap();
+        assert!(page.contains(".TH weaver-backfill 1"), "{page}");
+        assert!(page.contains("\\-\\-max\\-concurrent"), "{page}");
+        assert!(page.contains("WEAVER_QUEUE"), "{page}");
+        assert!(man_page(Some("nope")).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! CLI のエラー分類と終了コード
+//!
+//! 自動化から失敗の原因で分岐できるよう、原因ごとに終了コードを分ける。
+//! `--output json|yaml` では stderr にエラーの envelope を出す（形式は Report と同じく
+//! `schema_version` で管理する）。
+//!
+//! | kind                  | 終了コード | 例                                         |
+//! |-----------------------|-----------:|--------------------------------------------|
+//! | `internal`            | 1          | 想定外の失敗                               |
+//! | `usage`               | 2          | 不明なコマンド・オプション、必須引数の欠落（clap が判定） |
+//! | `validation`          | 3          | 日付・数値・`--param` が不正、スケジュール定義が壊れている |
+//! | `not_found`           | 4          | スケジュールファイルやスケジュールがない   |
+//! | `backend_unreachable` | 5          | queue ファイルがロック中・壊れている・書けない |
+//! | `timeout`             | 6          | `--timeout` までにジョブが終わらなかった   |
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::output::{OutputFormat, SCHEMA_VERSION};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Internal,
+    Usage,
+    Validation,
+    NotFound,
+    BackendUnreachable,
+    Timeout,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Internal => 1,
+            Self::Usage => 2,
+            Self::Validation => 3,
+            Self::NotFound => 4,
+            Self::BackendUnreachable => 5,
+            Self::Timeout => 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Validation, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    /// queue ファイルを使えない・queue の操作の失敗
+    pub fn backend(message: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::BackendUnreachable, message.to_string())
+    }
+
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Timeout, message)
+    }
+
+    /// エラーを stderr に出して終了する
+    pub fn exit(self, output: OutputFormat) -> ! {
+        let envelope = ErrorEnvelope {
+            schema_version: SCHEMA_VERSION,
+            error: ErrorBody {
+                kind: self.kind,
+                exit_code: self.kind.exit_code(),
+                message: self.message,
+            },
+        };
+        output.emit_stderr(&envelope, |envelope| {
+            eprintln!("error: {}", envelope.error.message);
+            if envelope.error.kind == ErrorKind::Usage {
+                eprintln!("\nFor more information, try 'weaver --help'.");
+            }
+        });
+        std::process::exit(self.kind.exit_code());
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// 引数の解析エラー。値を読めなかったもの（日付・数値・`--param`）は validation、
+/// それ以外（不明なオプション、必須引数の欠落など）は usage
+impl From<clap::Error> for CliError {
+    fn from(error: clap::Error) -> Self {
+        let kind = match error.kind() {
+            clap::error::ErrorKind::ValueValidation => ErrorKind::Validation,
+            _ => ErrorKind::Usage,
+        };
+        // 先頭の段落（"error: ..."）だけ。usage と `--help` の案内は `exit` が table でだけ出す
+        let rendered = error.to_string();
+        let message = rendered.split("\n\n").next().unwrap_or_default();
+        Self::new(kind, message.trim_start_matches("error: ").trim_end())
+    }
+}
+
+/// `--output json|yaml` で stderr に出すエラー
+#[derive(Debug, Serialize)]
+struct ErrorEnvelope {
+    schema_version: u32,
+    error: ErrorBody,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    kind: ErrorKind,
+    exit_code: i32,
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_names_the_kind_and_its_exit_code() {
+        let error = CliError::not_found("no schedule named `daily`");
+        let envelope = ErrorEnvelope {
+            schema_version: SCHEMA_VERSION,
+            error: ErrorBody {
+                kind: error.kind,
+                exit_code: error.kind.exit_code(),
+                message: error.message,
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&envelope).unwrap(),
+            serde_json::json!({
+                "schema_version": 1,
+                "error": {
+                    "kind": "not_found",
+                    "exit_code": 4,
+                    "message": "no schedule named `daily`",
+                },
+            })
+        );
+    }
+}
@@ -1,15 +1,31 @@
+mod completions;
+mod error;
+mod output;
+mod queue_file;
+
 use async_trait::async_trait;
-use serde::Deserialize;
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::error::ErrorKind as ClapErrorKind;
+use clap::{Args, Parser, Subcommand, ValueHint};
+use clap_complete::Shell;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
-use tokio::time::{Duration, sleep};
+use tokio::time::{Duration, Instant, sleep};
 
-use weaver_core::domain::{DefaultDecider, Outcome, TaskEnvelope, TaskId, TaskType};
+use weaver_core::domain::{
+    DefaultDecider, JobId, JobStatus, JobTemplate, Outcome, RecurringSchedule, TaskEnvelope,
+    TaskId, TaskType, TemplateError, TemplateRegistry,
+};
 use weaver_core::error::WeaverError;
-use weaver_core::queue::{InMemoryQueue, Queue, RetryPolicy};
+use weaver_core::queue::{Backfill, BackfilledJob, InMemoryQueue, Queue, RetryPolicy, TaskState};
 use weaver_core::runtime::{HandlerRegistry, Runtime, TaskHandler};
 use weaver_core::worker::WorkerGroup;
 
+use error::CliError;
+use output::{OutputFormat, SCHEMA_VERSION};
+use queue_file::QueueFile;
+
 #[derive(Debug, Deserialize)]
 struct HelloPayload {
     name: String,
@@ -18,12 +34,14 @@ struct HelloPayload {
 /// HelloHandler: 意図的に2回失敗してから成功するハンドラー
 struct HelloHandler {
     remaining_failures: AtomicU32,
+    output: OutputFormat,
 }
 
 impl HelloHandler {
-    fn new(n: u32) -> Self {
+    fn new(n: u32, output: OutputFormat) -> Self {
         Self {
             remaining_failures: AtomicU32::new(n),
+            output,
         }
     }
 }
@@ -43,21 +61,545 @@ impl TaskHandler for HelloHandler {
             )));
         }
 
-        println!("✓ Hello, {}!", p.name);
+        self.output.log(&format!("✓ Hello, {}!", p.name));
         Ok(Outcome::success())
     }
 }
 
+// 引数の定義。補完スクリプトと man ページもここから生成する（completions.rs）
+#[derive(Debug, Parser)]
+#[command(
+    name = "weaver",
+    bin_name = "weaver",
+    version,
+    about = "Run and backfill Weaver tasks",
+    long_about = "Run and backfill Weaver tasks.
+
+Without a command, weaver runs the example task. json and yaml output print one \
+report with a stable schema (see `schema_version`); progress goes to stderr.
+
+Exit codes: 0 ok, 1 internal error, 2 usage, 3 validation, 4 not found, \
+5 backend unreachable, 6 timeout waiting for jobs. With --output json|yaml errors \
+are printed to stderr as {schema_version, error: {kind, exit_code, message}}."
+)]
+struct Cli {
+    /// Result format
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Enqueue one job per period of a schedule into the queue file and run them
+    Backfill(BackfillArgs),
+    /// Enqueue the job of a named template into the queue file and run it
+    Submit(SubmitArgs),
+    /// Print a shell completion script
+    Completions {
+        /// Shell to complete
+        shell: Shell,
+    },
+    /// Print a man page (roff)
+    Man {
+        /// Print the page of this command (weaver-<command>) instead of weaver's
+        command: Option<String>,
+    },
+}
+
 #[tokio::main]
 async fn main() {
-    println!("=== Weaver CLI Example ===\n");
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e)
+            if matches!(
+                e.kind(),
+                ClapErrorKind::DisplayHelp | ClapErrorKind::DisplayVersion
+            ) =>
+        {
+            e.exit()
+        }
+        Err(e) => {
+            // 解析に失敗しても、分かる範囲で --output に従ってエラーを出す
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            CliError::from(e).exit(OutputFormat::scan(&args))
+        }
+    };
+    if let Err(e) = run(cli.command, cli.output).await {
+        e.exit(cli.output);
+    }
+}
+
+async fn run(command: Option<Command>, output: OutputFormat) -> Result<(), CliError> {
+    match command {
+        None => run_example(output).await,
+        Some(Command::Backfill(args)) => backfill(args, output).await?,
+        Some(Command::Submit(args)) => submit(args, output).await?,
+        Some(Command::Completions { shell }) => print!("{}", completions::script(shell)),
+        Some(Command::Man { command }) => {
+            print!(
+                "{}",
+                completions::man_page(command.as_deref()).map_err(CliError::not_found)?
+            )
+        }
+    }
+    Ok(())
+}
+
+/// `weaver backfill` の引数
+#[derive(Debug, Args)]
+struct BackfillArgs {
+    /// Name of the recurring schedule
+    schedule: String,
+    /// Start of the first period, YYYY-MM-DD or RFC 3339 (UTC)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    from: DateTime<Utc>,
+    /// End of the range (exclusive), YYYY-MM-DD or RFC 3339 (UTC)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    to: DateTime<Utc>,
+    /// JSON array of recurring schedules
+    #[arg(
+        long = "schedules",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        default_value = "weaver-schedules.json"
+    )]
+    schedules_file: String,
+    /// Queue file; locked while the command runs, unfinished jobs stay in it
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        env = "WEAVER_QUEUE",
+        default_value = "weaver-queue.json"
+    )]
+    queue: String,
+    /// Jobs run at the same time
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    max_concurrent: usize,
+    /// Give up waiting for the jobs after this many seconds
+    #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+    timeout: Option<Duration>,
+    /// Print the periods without enqueuing
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// "2024-01-01"（UTC の 0 時）または RFC 3339
+fn parse_date(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight exists")
+            .and_utc());
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|_| format!("invalid date `{s}` (expected YYYY-MM-DD or RFC 3339)"))
+}
+
+/// `--timeout` の秒数
+fn parse_secs(s: &str) -> Result<Duration, String> {
+    s.parse()
+        .map(Duration::from_secs)
+        .map_err(|e| format!("{e}"))
+}
+
+/// `--param <name>=<value>`。value は JSON として読めればその値、読めなければ文字列
+fn parse_param(s: &str) -> Result<(String, serde_json::Value), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("`{s}`: expected <name>=<value>"))?;
+    let value = serde_json::from_str(value)
+        .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+    Ok((name.to_string(), value))
+}
+
+/// 定義ファイル（schedules / templates）を読む
+fn read_file(path: &str) -> Result<String, CliError> {
+    std::fs::read_to_string(path).map_err(|e| {
+        let message = format!("{path}: {e}");
+        match e.kind() {
+            std::io::ErrorKind::NotFound => CliError::not_found(message),
+            _ => CliError::new(error::ErrorKind::Internal, message),
+        }
+    })
+}
+
+/// `weaver backfill --dry-run` の結果
+#[derive(Debug, Serialize)]
+struct BackfillPlanReport {
+    schema_version: u32,
+    schedule: String,
+    periods: Vec<PlannedPeriod>,
+}
+
+#[derive(Debug, Serialize)]
+struct PlannedPeriod {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    payloads: Vec<serde_json::Value>,
+}
+
+/// `weaver backfill` の結果
+#[derive(Debug, Serialize)]
+struct BackfillReport {
+    schema_version: u32,
+    schedule: String,
+    queue: String,
+    jobs: Vec<BackfilledJobReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct BackfilledJobReport {
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+    job_id: String,
+    total_tasks: usize,
+    succeeded_tasks: usize,
+    dead_tasks: usize,
+}
+
+/// 取りこぼした期間のジョブを queue ファイルにまとめて投入し、全部終わるまで待つ
+///
+/// ジョブはこのプロセスの worker が実行するので、実行されるのはこの CLI に登録した
+/// handler（例の "hello"）だけ。`--timeout` で打ち切ったジョブは queue ファイルに残る。
+async fn backfill(args: BackfillArgs, output: OutputFormat) -> Result<(), CliError> {
+    if args.from >= args.to {
+        return Err(CliError::validation("--from must be before --to"));
+    }
+    let file = read_file(&args.schedules_file)?;
+    let schedules: Vec<RecurringSchedule> = serde_json::from_str(&file)
+        .map_err(|e| CliError::validation(format!("{}: {e}", args.schedules_file)))?;
+    let schedule = schedules
+        .into_iter()
+        .find(|schedule| schedule.name == args.schedule)
+        .ok_or_else(|| CliError::not_found(format!("no schedule named `{}`", args.schedule)))?;
+
+    if args.dry_run {
+        let periods = schedule
+            .period
+            .periods(args.from, args.to)
+            .into_iter()
+            .map(|(start, end)| PlannedPeriod {
+                start,
+                end,
+                payloads: schedule
+                    .job_for(start, end)
+                    .tasks
+                    .into_iter()
+                    .map(|task| task.payload)
+                    .collect(),
+            })
+            .collect();
+        let report = BackfillPlanReport {
+            schema_version: SCHEMA_VERSION,
+            schedule: schedule.name.clone(),
+            periods,
+        };
+        output.emit(&report, |report| {
+            for period in &report.periods {
+                println!(
+                    "{} .. {}  {}",
+                    period.start,
+                    period.end,
+                    serde_json::json!(period.payloads)
+                );
+            }
+        });
+        return Ok(());
+    }
+
+    let file = QueueFile::open(args.queue).await?;
+    let queue = file.queue.clone();
+    let workers = spawn_workers(args.max_concurrent.max(1), &queue, output);
+
+    let jobs = Backfill::new()
+        .with_max_concurrent_jobs(args.max_concurrent)
+        .run(&queue, &schedule, args.from, args.to)
+        .await
+        .map_err(CliError::backend);
+
+    // 最後のジョブが終わるまで待って結果を表示
+    let deadline = args.timeout.map(|timeout| Instant::now() + timeout);
+    let reports = match jobs {
+        Ok(jobs) => wait_for_backfill(&queue, &jobs, deadline).await,
+        Err(e) => Err(e),
+    };
+    // 打ち切ったときも、終わらなかったジョブを次のコマンドに残す
+    workers.shutdown_and_join().await;
+    file.save().await?;
+    let reports = reports?;
+
+    let report = BackfillReport {
+        schema_version: SCHEMA_VERSION,
+        schedule: schedule.name.clone(),
+        queue: file.path().display().to_string(),
+        jobs: reports,
+    };
+    output.emit(&report, |report| {
+        for job in &report.jobs {
+            println!(
+                "{} .. {}  job {}: {}/{} succeeded, {} dead",
+                job.period_start,
+                job.period_end,
+                job.job_id,
+                job.succeeded_tasks,
+                job.total_tasks,
+                job.dead_tasks
+            );
+        }
+        println!(
+            "backfilled {} periods of `{}`",
+            report.jobs.len(),
+            report.schedule
+        );
+    });
+    Ok(())
+}
+
+/// queue ファイルの task を実行する worker（登録する handler は例の "hello" だけ）
+fn spawn_workers(n: usize, queue: &Arc<InMemoryQueue>, output: OutputFormat) -> WorkerGroup {
+    let mut reg = HandlerRegistry::new();
+    reg.register(
+        TaskType::new("hello"),
+        Arc::new(HelloHandler::new(0, output)),
+    )
+    .expect("register handler");
+    let runtime = Arc::new(Runtime::new(Arc::new(reg)));
+    WorkerGroup::spawn(
+        n,
+        queue.clone(),
+        runtime,
+        Arc::new(DefaultDecider::default_v1()),
+    )
+}
+
+/// backfill したジョブが順に終わるのを待つ
+async fn wait_for_backfill(
+    queue: &InMemoryQueue,
+    jobs: &[BackfilledJob],
+    deadline: Option<Instant>,
+) -> Result<Vec<BackfilledJobReport>, CliError> {
+    let mut reports = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let status = loop {
+            let status = queue
+                .get_status(job.job_id)
+                .await
+                .map_err(CliError::backend)?;
+            if status.running_tasks == 0 {
+                break status;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(CliError::timeout(format!(
+                    "job {} for {} .. {} did not finish within --timeout",
+                    job.job_id, job.period_start, job.period_end
+                )));
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+        reports.push(BackfilledJobReport {
+            period_start: job.period_start,
+            period_end: job.period_end,
+            job_id: job.job_id.to_string(),
+            total_tasks: status.total_tasks,
+            succeeded_tasks: status.completed_tasks,
+            dead_tasks: status.failed_tasks,
+        });
+    }
+    Ok(reports)
+}
+
+/// `weaver submit` の引数
+#[derive(Debug, Args)]
+struct SubmitArgs {
+    /// Name of the job template
+    template: String,
+    /// Template parameter (repeatable); the value is read as JSON if it parses
+    /// (e.g. 500, true), otherwise as a string
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_param)]
+    params: Vec<(String, serde_json::Value)>,
+    /// JSON array of job templates
+    #[arg(
+        long = "templates",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        default_value = "weaver-templates.json"
+    )]
+    templates_file: String,
+    /// Queue file; locked while the command runs, unfinished jobs stay in it
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        env = "WEAVER_QUEUE",
+        default_value = "weaver-queue.json"
+    )]
+    queue: String,
+    /// Give up waiting for the job after this many seconds
+    #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+    timeout: Option<Duration>,
+    /// Print the task payloads without enqueuing
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// `weaver submit --dry-run` の結果
+#[derive(Debug, Serialize)]
+struct SubmitPlanReport {
+    schema_version: u32,
+    template: String,
+    payloads: Vec<serde_json::Value>,
+}
+
+/// `weaver submit` の結果
+#[derive(Debug, Serialize)]
+struct SubmitReport {
+    schema_version: u32,
+    template: String,
+    queue: String,
+    job_id: String,
+    total_tasks: usize,
+    succeeded_tasks: usize,
+    dead_tasks: usize,
+}
+
+/// 登録済みのジョブテンプレートを名前で queue ファイルに投入し、終わるまで待つ
+///
+/// backfill と同じく、実行されるのはこの CLI に登録した handler だけ。
+/// `--timeout` で打ち切ったジョブは queue ファイルに残る。
+async fn submit(args: SubmitArgs, output: OutputFormat) -> Result<(), CliError> {
+    let file = read_file(&args.templates_file)?;
+    let templates: Vec<JobTemplate> = serde_json::from_str(&file)
+        .map_err(|e| CliError::validation(format!("{}: {e}", args.templates_file)))?;
+    let templates = TemplateRegistry::from_templates(templates)
+        .map_err(|e| CliError::validation(format!("{}: {e}", args.templates_file)))?;
+    let job = templates
+        .instantiate(
+            &args.template,
+            &serde_json::Value::Object(args.params.into_iter().collect()),
+        )
+        .map_err(|e| match e {
+            TemplateError::Unknown(_) => CliError::not_found(e.to_string()),
+            _ => CliError::validation(e.to_string()),
+        })?;
+
+    if args.dry_run {
+        let report = SubmitPlanReport {
+            schema_version: SCHEMA_VERSION,
+            template: args.template,
+            payloads: job.tasks.into_iter().map(|task| task.payload).collect(),
+        };
+        output.emit(&report, |report| {
+            for payload in &report.payloads {
+                println!("{payload}");
+            }
+        });
+        return Ok(());
+    }
+
+    let file = QueueFile::open(args.queue).await?;
+    let queue = file.queue.clone();
+    let workers = spawn_workers(1, &queue, output);
+
+    let finished = match queue.submit_job(job).await.map_err(CliError::backend) {
+        Ok(job_id) => {
+            output.log(&format!(
+                "📤 Submitted job {job_id} from template `{}`",
+                args.template
+            ));
+            let deadline = args.timeout.map(|timeout| Instant::now() + timeout);
+            wait_for_job(&queue, job_id, deadline)
+                .await
+                .map(|status| (job_id, status))
+        }
+        Err(e) => Err(e),
+    };
+    // 打ち切ったときも、終わらなかったジョブを次のコマンドに残す
+    workers.shutdown_and_join().await;
+    file.save().await?;
+    let (job_id, status) = finished?;
+
+    let report = SubmitReport {
+        schema_version: SCHEMA_VERSION,
+        template: args.template,
+        queue: file.path().display().to_string(),
+        job_id: job_id.to_string(),
+        total_tasks: status.total_tasks,
+        succeeded_tasks: status.completed_tasks,
+        dead_tasks: status.failed_tasks,
+    };
+    output.emit(&report, |report| {
+        println!(
+            "job {} ({}): {}/{} succeeded, {} dead",
+            report.job_id,
+            report.template,
+            report.succeeded_tasks,
+            report.total_tasks,
+            report.dead_tasks
+        );
+    });
+    Ok(())
+}
+
+/// submit したジョブが終わるのを待つ
+async fn wait_for_job(
+    queue: &InMemoryQueue,
+    job_id: JobId,
+    deadline: Option<Instant>,
+) -> Result<JobStatus, CliError> {
+    loop {
+        let status = queue.get_status(job_id).await.map_err(CliError::backend)?;
+        if status.running_tasks == 0 {
+            return Ok(status);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(CliError::timeout(format!(
+                "job {job_id} did not finish within --timeout"
+            )));
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// `weaver`（例）の結果
+#[derive(Debug, Serialize)]
+struct ExampleReport {
+    schema_version: u32,
+    task_id: String,
+    /// "succeeded" または "dead"
+    result: &'static str,
+    counts: StateCounts,
+}
+
+/// 状態ごとのタスク数
+#[derive(Debug, Serialize)]
+struct StateCounts {
+    queued: usize,
+    running: usize,
+    succeeded: usize,
+    retry_scheduled: usize,
+    dead: usize,
+}
+
+/// 例: "hello" タスクを 1 つ実行する（引数なしの `weaver`）
+async fn run_example(output: OutputFormat) {
+    output.log("=== Weaver CLI Example ===\n");
 
     // (A) Queue と HandlerRegistry を用意
-    let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+    let queue = Arc::new(
+        InMemoryQueue::new(RetryPolicy::default_v1()).with_app_version(env!("CARGO_PKG_VERSION")),
+    );
 
     let mut reg = HandlerRegistry::new();
-    reg.register(TaskType::new("hello"), Arc::new(HelloHandler::new(2)))
-        .expect("register handler");
+    reg.register(
+        TaskType::new("hello"),
+        Arc::new(HelloHandler::new(2, output)),
+    )
+    .expect("register handler");
     let runtime = Arc::new(Runtime::new(Arc::new(reg)));
 
     let default_decider = Arc::new(DefaultDecider::default_v1());
@@ -65,43 +607,51 @@ async fn main() {
     // (B) Worker を起動（1本）
     let workers = WorkerGroup::spawn(1, queue.clone(), runtime.clone(), default_decider);
 
-    // (C) タスク投入
-    let task_id = TaskId::new(1); // 手動でIDを割り当て（本来はQueueが管理）
+    // (C) タスク投入（id は Queue が振る）
     let env = TaskEnvelope::new(
-        task_id,
+        TaskId::new(0),
         TaskType::new("hello"),
         serde_json::json!({ "name": "Weaver" }),
     );
 
-    queue.enqueue(env).await.expect("enqueue");
-    println!("📤 Enqueued task: {}\n", task_id);
+    let handle = queue.submit_with_handle(env).await.expect("enqueue");
+    let task_id = handle.task_id();
+    output.log(&format!("📤 Enqueued task: {}\n", task_id));
 
-    // (D) 完了をポーリングで待つ
-    // TODO: 本来は get_status(TaskId) API を実装すべきだが、
-    // v1では counts_by_state() で全体の状態を見る
-    loop {
-        let counts = queue.counts_by_state().await.expect("counts");
-
-        println!(
-            "📊 State counts: queued={}, running={}, succeeded={}, retry_scheduled={}, dead={}",
-            counts.queued, counts.running, counts.succeeded, counts.retry_scheduled, counts.dead
-        );
-
-        // 終了条件: succeeded か dead のいずれかになったら
-        if counts.succeeded > 0 || counts.dead > 0 {
-            println!("\n✅ Task completed!");
-            if counts.succeeded > 0 {
-                println!("   Result: SUCCESS");
-            } else {
-                println!("   Result: DEAD (max retries exceeded)");
-            }
-            break;
-        }
-
-        sleep(Duration::from_millis(100)).await;
-    }
+    // (D) 終端状態になるまで待つ
+    let completion = handle.await_outcome().await.expect("await task");
+    let counts = queue.counts_by_state().await.expect("counts");
+    output.log(&format!(
+        "📊 State counts: queued={}, running={}, succeeded={}, retry_scheduled={}, dead={}",
+        counts.queued, counts.running, counts.succeeded, counts.retry_scheduled, counts.dead
+    ));
 
     // (E) Worker を graceful shutdown
     workers.shutdown_and_join().await;
-    println!("\n👋 Shutdown complete");
+
+    let report = ExampleReport {
+        schema_version: SCHEMA_VERSION,
+        task_id: task_id.to_string(),
+        result: if completion.state == TaskState::Succeeded {
+            "succeeded"
+        } else {
+            "dead"
+        },
+        counts: StateCounts {
+            queued: counts.queued,
+            running: counts.running,
+            succeeded: counts.succeeded,
+            retry_scheduled: counts.retry_scheduled,
+            dead: counts.dead,
+        },
+    };
+    output.emit(&report, |report| {
+        println!("\n✅ Task completed!");
+        if report.result == "succeeded" {
+            println!("   Result: SUCCESS");
+        } else {
+            println!("   Result: DEAD (max retries exceeded)");
+        }
+    });
+    output.log("\n👋 Shutdown complete");
 }
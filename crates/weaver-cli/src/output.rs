@@ -0,0 +1,118 @@
+//! `--output json|yaml|table` の出力形式
+//!
+//! json / yaml はスクリプトや CI 向けで、コマンドごとの Report 型をそのまま出す（serde_json / serde_yaml）。
+//! フィールドを消したり意味を変えたりするときは `schema_version` を上げる
+//! （追加だけなら上げない）。進捗などのログは stdout を汚さないよう stderr に出す。
+//!
+//! table は人間向けの従来の表示（形式は保証しない）。
+
+use serde::Serialize;
+
+/// Report 型の `schema_version`
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    #[default]
+    Table,
+}
+
+impl OutputFormat {
+    /// clap が解析に失敗したとき、エラーの出し方を決めるために `--output <format>` /
+    /// `--output=<format>` だけを拾う（なければ・読めなければ table）
+    pub fn scan(args: &[String]) -> Self {
+        let value =
+            args.iter()
+                .enumerate()
+                .find_map(|(i, arg)| match arg.strip_prefix("--output=") {
+                    Some(value) => Some(value),
+                    None if arg == "--output" => args.get(i + 1).map(String::as_str),
+                    None => None,
+                });
+        value
+            .and_then(|value| <Self as clap::ValueEnum>::from_str(value, false).ok())
+            .unwrap_or_default()
+    }
+
+    /// 人間向けの行（table では stdout、json / yaml では stderr）
+    pub fn log(self, line: &str) {
+        match self {
+            Self::Table => println!("{line}"),
+            Self::Json | Self::Yaml => eprintln!("{line}"),
+        }
+    }
+
+    /// Report を出す。table では `table` で描画する
+    pub fn emit<T: Serialize>(self, report: &T, table: impl FnOnce(&T)) {
+        match self.render(report) {
+            Some(rendered) => print!("{rendered}"),
+            None => table(report),
+        }
+    }
+
+    /// `emit` の stderr 版（エラーの envelope 用）
+    pub fn emit_stderr<T: Serialize>(self, report: &T, table: impl FnOnce(&T)) {
+        match self.render(report) {
+            Some(rendered) => eprint!("{rendered}"),
+            None => table(report),
+        }
+    }
+
+    /// json / yaml の文字列（table では None）
+    fn render<T: Serialize>(self, report: &T) -> Option<String> {
+        match self {
+            Self::Json => Some(format!(
+                "{}\n",
+                serde_json::to_string_pretty(report).expect("report serializes")
+            )),
+            Self::Yaml => Some(serde_yaml::to_string(report).expect("report serializes")),
+            Self::Table => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_finds_the_output_flag_anywhere() {
+        let args = ["backfill", "--bogus", "--output", "yaml"].map(String::from);
+        assert_eq!(OutputFormat::scan(&args), OutputFormat::Yaml);
+        assert_eq!(
+            OutputFormat::scan(&["--output=json".to_string()]),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            OutputFormat::scan(&["--output=xml".to_string()]),
+            OutputFormat::Table
+        );
+    }
+
+    #[test]
+    fn yaml_renders_nested_values_in_block_style() {
+        let value = serde_json::json!({
+            "schema_version": 1,
+            "jobs": [{ "job_id": 1, "payloads": ["a", {}, "1.0", "x: y"] }],
+            "empty": [],
+        });
+        let rendered = OutputFormat::Yaml.render(&value).unwrap();
+        assert_eq!(
+            rendered,
+            "empty: []\n\
+             jobs:\n\
+             - job_id: 1\n  \
+             payloads:\n  \
+             - a\n  \
+             - {}\n  \
+             - '1.0'\n  \
+             - 'x: y'\n\
+             schema_version: 1\n"
+        );
+        // 読み戻すと同じ値（文字列が別の型にならない）
+        let parsed: serde_json::Value = serde_yaml::from_str(&rendered).unwrap();
+        assert_eq!(parsed, value);
+    }
+}
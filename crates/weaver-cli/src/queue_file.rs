@@ -0,0 +1,164 @@
+//! `--queue <file>`: backfill / submit が投入する queue
+//!
+//! 既定は `$WEAVER_QUEUE`、なければ weaver-queue.json（clap の定義を参照）。
+//! queue の中身は `QueueSnapshot`（schema_version 付きの JSON）としてファイルに置く。
+//! コマンドの開始時に読み込み、終了時（`--timeout` で打ち切ったときも）に書き戻すので、
+//! 終わらなかったジョブは次に同じファイルを使ったコマンドが続きから実行する。
+//!
+//! # 学習ポイント
+//! - 同時に使えるのは 1 プロセスだけ。`<file>.lock` を `create_new` で作って排他する
+//!   （異常終了で残ったら手で消す）
+//! - 書き戻しは一時ファイルに書いてから rename する（途中で落ちても壊れたファイルを残さない）
+//! - 開けない・壊れている・書けない・ロック中は `backend_unreachable`（終了コード 5）
+
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use weaver_core::queue::{InMemoryQueue, JsonCodec, QueueSnapshot, RetryPolicy};
+
+use crate::error::CliError;
+
+/// ファイルから読み込んだ queue（ロックを持っている間だけ使える）
+pub struct QueueFile {
+    path: PathBuf,
+    lock: PathBuf,
+    pub queue: Arc<InMemoryQueue>,
+}
+
+impl QueueFile {
+    /// ロックを取り、`path` の queue を読み込む（ファイルがなければ空の queue）
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self, CliError> {
+        let path = path.into();
+        let lock = path.with_file_name(format!(
+            "{}.lock",
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        ));
+        match OpenOptions::new().write(true).create_new(true).open(&lock) {
+            Ok(mut file) => {
+                let _ = writeln!(file, "{}", std::process::id());
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                return Err(unreachable(
+                    &path,
+                    format!(
+                        "in use by another process (remove {} if it is stale)",
+                        lock.display()
+                    ),
+                ));
+            }
+            Err(e) => return Err(unreachable(&path, e)),
+        }
+        // ここから先で失敗したら Drop がロックを外す
+        let file = Self {
+            queue: Arc::new(
+                InMemoryQueue::new(RetryPolicy::default_v1())
+                    .with_app_version(env!("CARGO_PKG_VERSION")),
+            ),
+            path,
+            lock,
+        };
+
+        let bytes = match std::fs::read(&file.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(file),
+            Err(e) => return Err(unreachable(&file.path, e)),
+        };
+        let snapshot =
+            QueueSnapshot::decode(&bytes, &JsonCodec).map_err(|e| unreachable(&file.path, e))?;
+        file.queue
+            .restore(snapshot)
+            .await
+            .map_err(|e| unreachable(&file.path, e))?;
+        Ok(file)
+    }
+
+    /// queue の今の中身を書き戻す
+    pub async fn save(&self) -> Result<(), CliError> {
+        let bytes = self
+            .queue
+            .snapshot()
+            .await
+            .encode(&JsonCodec)
+            .map_err(|e| unreachable(&self.path, e))?;
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, bytes).map_err(|e| unreachable(&self.path, e))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| unreachable(&self.path, e))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// queue ファイルを使えない（終了コード 5）
+fn unreachable(path: &Path, e: impl std::fmt::Display) -> CliError {
+    CliError::backend(format!("queue {}: {e}", path.display()))
+}
+
+impl Drop for QueueFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.lock);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind as CliErrorKind;
+    use weaver_core::domain::{JobSpec, TaskSpec, TaskType};
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("weaver-cli-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("queue.json")
+    }
+
+    #[tokio::test]
+    async fn jobs_survive_between_opens_and_the_lock_excludes_a_second_process() {
+        let path = temp_path("reopen");
+        let _ = std::fs::remove_file(&path);
+
+        let file = QueueFile::open(&path).await.unwrap();
+        let job_id = file
+            .queue
+            .submit_job(JobSpec::new(vec![TaskSpec::new(
+                "a",
+                TaskType::new("hello"),
+                serde_json::json!({ "name": "a" }),
+            )]))
+            .await
+            .unwrap();
+        file.save().await.unwrap();
+
+        let error = QueueFile::open(&path).await.err().unwrap();
+        assert_eq!(error.kind, CliErrorKind::BackendUnreachable);
+        assert!(error.message.contains("in use"), "{error}");
+        drop(file);
+
+        let file = QueueFile::open(&path).await.unwrap();
+        let status = file.queue.get_status(job_id).await.unwrap();
+        assert_eq!(status.total_tasks, 1);
+        assert_eq!(status.running_tasks, 1);
+    }
+
+    #[tokio::test]
+    async fn a_corrupt_or_unreadable_queue_is_unreachable() {
+        let path = temp_path("corrupt");
+        std::fs::write(&path, b"not a snapshot").unwrap();
+        let error = QueueFile::open(&path).await.err().unwrap();
+        assert_eq!(error.kind, CliErrorKind::BackendUnreachable);
+        // 失敗してもロックは残らない
+        std::fs::remove_file(&path).unwrap();
+        assert!(QueueFile::open(&path).await.is_ok());
+
+        let error = QueueFile::open(path.join("missing-dir/queue.json"))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, CliErrorKind::BackendUnreachable);
+    }
+}
@@ -0,0 +1,278 @@
+//! Assignment - namespace を worker プロセスに割り当てる
+//!
+//! 多数の namespace を複数の worker プロセスで消費するとき、全プロセスが全 namespace を
+//! poll すると Redis/SQS への問い合わせが N×M に膨らみます。
+//! consistent hashing で各 namespace を担当するプロセスを `replicas` 個に絞ります。
+//!
+//! # 仕組み
+//! - 各プロセスは KvStore に `weaver/workers/{worker_id}` を TTL 付きで書き続ける（heartbeat）
+//! - 生きているプロセス一覧から全員が同じ HashRing を組み立てる
+//! - ring 上で namespace の位置から時計回りに `replicas` 個のプロセスが担当
+//!
+//! 全員が同じ membership から同じ結果を計算するため、leader 選出は不要です。
+//! プロセスの増減で担当が移るのは ring 上で隣接する namespace だけです。
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ports::{KvError, KvStore};
+
+/// membership を置く KvStore のキー prefix
+const MEMBER_PREFIX: &str = "weaver/workers/";
+
+/// FNV-1a（64-bit）+ murmur3 の finalizer
+///
+/// プロセス間・Rust のバージョン間で同じ値になる必要があるため、
+/// `DefaultHasher` ではなく固定のアルゴリズムを使う。
+/// 末尾だけが違う短いキー（"w1#0", "w1#1", ...）も ring 上に散らばるよう finalizer で混ぜる。
+fn stable_hash(key: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in key.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+/// HashRing は virtual node 付きの consistent hash ring
+#[derive(Debug, Clone)]
+pub struct HashRing {
+    ring: BTreeMap<u64, String>,
+    members: usize,
+}
+
+impl HashRing {
+    /// メンバーごとに `vnodes` 個の点を ring に置く
+    pub fn new<I, S>(members: I, vnodes: usize) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut ring = BTreeMap::new();
+        let mut count = 0;
+        for member in members {
+            let member = member.into();
+            for vnode in 0..vnodes.max(1) {
+                ring.insert(stable_hash(&format!("{member}#{vnode}")), member.clone());
+            }
+            count += 1;
+        }
+        Self {
+            ring,
+            members: count,
+        }
+    }
+
+    /// `key` を担当するメンバーを最大 `n` 個（重複なし、優先順）
+    pub fn owners(&self, key: &str, n: usize) -> Vec<&str> {
+        let n = n.min(self.members);
+        let start = stable_hash(key);
+        let mut owners: Vec<&str> = Vec::with_capacity(n);
+        for member in self
+            .ring
+            .range(start..)
+            .chain(self.ring.range(..start))
+            .map(|(_, m)| m)
+        {
+            if owners.len() == n {
+                break;
+            }
+            if !owners.contains(&member.as_str()) {
+                owners.push(member);
+            }
+        }
+        owners
+    }
+}
+
+/// NamespaceAssignment はこのプロセスが poll すべき namespace を決める
+///
+/// # 使用例
+/// ```ignore
+/// let assignment = NamespaceAssignment::new(kv, "worker-a").with_replicas(2);
+/// assignment.heartbeat().await?; // member_ttl より短い間隔で繰り返す
+/// let mine = assignment.assigned(&all_namespaces).await?;
+/// ```
+pub struct NamespaceAssignment {
+    kv: Arc<dyn KvStore>,
+    worker_id: String,
+    replicas: usize,
+    vnodes: usize,
+    member_ttl: Duration,
+}
+
+impl NamespaceAssignment {
+    /// デフォルト: replicas=1, vnodes=64, member_ttl=10 秒
+    pub fn new(kv: Arc<dyn KvStore>, worker_id: impl Into<String>) -> Self {
+        Self {
+            kv,
+            worker_id: worker_id.into(),
+            replicas: 1,
+            vnodes: 64,
+            member_ttl: Duration::from_secs(10),
+        }
+    }
+
+    /// 1 つの namespace を担当するプロセス数
+    pub fn with_replicas(mut self, replicas: usize) -> Self {
+        self.replicas = replicas.max(1);
+        self
+    }
+
+    /// メンバーあたりの virtual node 数（多いほど偏りが小さい）
+    pub fn with_vnodes(mut self, vnodes: usize) -> Self {
+        self.vnodes = vnodes.max(1);
+        self
+    }
+
+    /// heartbeat が途絶えてから membership から外れるまでの時間
+    pub fn with_member_ttl(mut self, ttl: Duration) -> Self {
+        self.member_ttl = ttl;
+        self
+    }
+
+    /// 生存を登録する（`member_ttl` より短い間隔で呼ぶ）
+    pub async fn heartbeat(&self) -> Result<(), KvError> {
+        self.kv
+            .put(
+                &self.member_key(),
+                self.worker_id.clone(),
+                Some(self.member_ttl),
+            )
+            .await
+    }
+
+    /// membership から抜ける（graceful shutdown 用）
+    pub async fn leave(&self) -> Result<(), KvError> {
+        self.kv.delete(&self.member_key()).await
+    }
+
+    /// 生きているメンバー（自分を含む）
+    pub async fn members(&self) -> Result<Vec<String>, KvError> {
+        let mut members: Vec<String> = self
+            .kv
+            .list_prefix(MEMBER_PREFIX)
+            .await?
+            .into_iter()
+            .map(|(_, worker_id)| worker_id)
+            .collect();
+        // 最初の heartbeat 前でも自分の担当を持てるようにする
+        if !members.contains(&self.worker_id) {
+            members.push(self.worker_id.clone());
+        }
+        Ok(members)
+    }
+
+    /// `namespaces` のうち、このプロセスが担当するもの
+    pub async fn assigned(&self, namespaces: &[String]) -> Result<Vec<String>, KvError> {
+        let ring = HashRing::new(self.members().await?, self.vnodes);
+        Ok(namespaces
+            .iter()
+            .filter(|ns| {
+                ring.owners(ns, self.replicas)
+                    .contains(&self.worker_id.as_str())
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn member_key(&self) -> String {
+        format!("{MEMBER_PREFIX}{}", self.worker_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::impls::InMemoryKvStore;
+
+    fn namespaces(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("tenant-{i}")).collect()
+    }
+
+    #[test]
+    fn owners_are_distinct_and_bounded() {
+        let ring = HashRing::new(["a", "b", "c"], 16);
+
+        let owners = ring.owners("tenant-1", 2);
+        assert_eq!(owners.len(), 2);
+        assert_ne!(owners[0], owners[1]);
+
+        // 要求数がメンバー数を超えても全員まで
+        assert_eq!(ring.owners("tenant-1", 10).len(), 3);
+        assert!(
+            HashRing::new(Vec::<String>::new(), 16)
+                .owners("x", 1)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn adding_a_member_moves_only_some_namespaces() {
+        let before = HashRing::new(["a", "b", "c"], 64);
+        let after = HashRing::new(["a", "b", "c", "d"], 64);
+
+        let all = namespaces(200);
+        let moved = all
+            .iter()
+            .filter(|ns| before.owners(ns, 1) != after.owners(ns, 1))
+            .count();
+
+        // 理想は 1/4。移ったものは新メンバー d に移っている
+        assert!(moved > 0 && moved < all.len() / 2, "moved = {moved}");
+        for ns in &all {
+            if before.owners(ns, 1) != after.owners(ns, 1) {
+                assert_eq!(after.owners(ns, 1), vec!["d"]);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn every_namespace_is_polled_by_exactly_replicas_processes() {
+        let kv: Arc<dyn KvStore> = Arc::new(InMemoryKvStore::new());
+        let processes: Vec<NamespaceAssignment> = ["w1", "w2", "w3"]
+            .into_iter()
+            .map(|id| NamespaceAssignment::new(kv.clone(), id).with_replicas(2))
+            .collect();
+        for process in &processes {
+            process.heartbeat().await.unwrap();
+        }
+
+        let all = namespaces(50);
+        let mut polled_by: BTreeMap<String, usize> = BTreeMap::new();
+        for process in &processes {
+            for ns in process.assigned(&all).await.unwrap() {
+                *polled_by.entry(ns).or_default() += 1;
+            }
+        }
+
+        assert_eq!(polled_by.len(), all.len());
+        assert!(polled_by.values().all(|&count| count == 2));
+    }
+
+    #[tokio::test]
+    async fn namespaces_of_a_departed_process_are_taken_over() {
+        let kv: Arc<dyn KvStore> = Arc::new(InMemoryKvStore::new());
+        let w1 = NamespaceAssignment::new(kv.clone(), "w1");
+        let w2 =
+            NamespaceAssignment::new(kv.clone(), "w2").with_member_ttl(Duration::from_millis(10));
+        w1.heartbeat().await.unwrap();
+        w2.heartbeat().await.unwrap();
+
+        let all = namespaces(20);
+        assert!(w1.assigned(&all).await.unwrap().len() < all.len());
+
+        // w2 の heartbeat が途絶えると w1 が全部を担当する
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(w1.members().await.unwrap(), vec!["w1".to_string()]);
+        assert_eq!(w1.assigned(&all).await.unwrap(), all);
+
+        w1.leave().await.unwrap();
+        assert!(kv.list_prefix(MEMBER_PREFIX).await.unwrap().is_empty());
+    }
+}
@@ -4,26 +4,81 @@
 //! - Builder パターンの実装
 //! - 起動時検証（Fail-fast 設計）
 //! - 開発体験の改善（明確なエラーメッセージ）
+//! - ports の差し込み（Hexagonal Architecture のワイヤリング）と
+//!   ループの起動・停止（watch channel による graceful shutdown）
 
-use crate::typed::{Handler, RegistryError, Task, TypedRegistry};
+#[cfg(feature = "wasm")]
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::gc_loop::{GCLoop, GcConfig};
+use super::observer::Observer;
+use super::publisher_loop::{PublisherConfig, PublisherLoop};
+use super::reaper_loop::{ReaperConfig, ReaperLoop};
+use super::worker_loop::{WorkerConfig, WorkerLoop};
+use crate::domain::DefaultDecider;
+#[cfg(feature = "wasm")]
+use crate::impls::WasmHandler;
+#[cfg(feature = "wasm")]
+use crate::ports::{WasmEngine, WasmError};
+use crate::ports::{
+    ArtifactStore, Clock, Decider, DeliveryQueue, EventSink, IdGenerator, MetricsSink,
+    NoopEventSink, NoopMetricsSink, SystemClock, TaskStore, UlidGenerator,
+};
+use crate::typed::{CodecError, Handler, RegistryError, Task, TypedRegistry};
 
 /// AppBuilder はアプリケーションを構築
 ///
 /// # 使用例
 /// ```ignore
-/// let app = AppBuilder::new()
-///     .register::<MyTask>(MyTaskHandler)
+/// let mut app = AppBuilder::new()
+///     .register::<MyTask>(MyTaskHandler)?
 ///     .expect_tasks(&["my_namespace.my_task.v1"])
+///     .with_task_store(store)
+///     .with_delivery_queue(delivery)
+///     .with_artifact_store(artifacts)
+///     .with_workers(4)
 ///     .build()?;
+/// app.start()?;
+/// // ...
+/// app.shutdown().await;
 /// ```
 ///
 /// # Fail-fast 設計
 /// - expect_tasks() で期待される task_type を登録
 /// - build() 時に「期待集合 ⊆ 登録済み集合」をチェック
+/// - TaskStore と DeliveryQueue は片方だけでは動かない（揃っていなければ BuildError）
 /// - 不足があれば BuildError を返す
+///
+/// # 起動されるループ
+/// - TaskStore + DeliveryQueue があれば: WorkerLoop（`with_workers` 個）・PublisherLoop・ReaperLoop
+/// - ArtifactStore があれば: GCLoop
 pub struct AppBuilder {
     registry: TypedRegistry,
     expected_tasks: Option<Vec<String>>,
+    /// 指標の送り先（デフォルトは NoopMetricsSink）
+    metrics_sink: Arc<dyn MetricsSink>,
+    /// ループが出すイベントの送り先（デフォルトは NoopEventSink）
+    event_sink: Arc<dyn EventSink>,
+    task_store: Option<Arc<dyn TaskStore>>,
+    delivery: Option<Arc<dyn DeliveryQueue>>,
+    artifacts: Option<Arc<dyn ArtifactStore>>,
+    /// 失敗時の判断（デフォルトは `DefaultDecider::default_v1()`）
+    decider: Arc<dyn Decider>,
+    clock: Arc<dyn Clock>,
+    /// ID の生成器（デフォルトは SystemClock の UlidGenerator）
+    id_generator: Arc<dyn IdGenerator>,
+    workers: usize,
+    worker_config: WorkerConfig,
+    publisher_config: PublisherConfig,
+    reaper_config: ReaperConfig,
+    gc_config: GcConfig,
+    /// register_wasm() のモジュールを読み込むエンジン
+    #[cfg(feature = "wasm")]
+    wasm_engine: Option<Arc<dyn WasmEngine>>,
 }
 
 /// BuildError はアプリケーション構築時のエラー
@@ -31,6 +86,28 @@ pub struct AppBuilder {
 pub enum BuildError {
     #[error("Missing task types: {0:?}. These tasks were expected but not registered.")]
     MissingTaskTypes(Vec<String>),
+
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+
+    /// 一緒に使う port の片方だけが設定された
+    #[error("{configured} is set but {missing} is not; the loops need both")]
+    MissingPort {
+        configured: &'static str,
+        missing: &'static str,
+    },
+
+    #[cfg(feature = "wasm")]
+    #[error("register_wasm needs a WasmEngine (see with_wasm_engine)")]
+    NoWasmEngine,
+
+    #[cfg(feature = "wasm")]
+    #[error("Failed to load the wasm module for '{task_type}': {source}")]
+    WasmModule {
+        task_type: String,
+        #[source]
+        source: WasmError,
+    },
 }
 
 impl AppBuilder {
@@ -39,9 +116,105 @@ impl AppBuilder {
         Self {
             registry: TypedRegistry::new(),
             expected_tasks: None,
+            metrics_sink: Arc::new(NoopMetricsSink),
+            event_sink: Arc::new(NoopEventSink),
+            task_store: None,
+            delivery: None,
+            artifacts: None,
+            decider: Arc::new(DefaultDecider::default_v1()),
+            clock: Arc::new(SystemClock),
+            id_generator: Arc::new(UlidGenerator::new(SystemClock)),
+            workers: 1,
+            worker_config: WorkerConfig::default(),
+            publisher_config: PublisherConfig::default(),
+            reaper_config: ReaperConfig::default(),
+            gc_config: GcConfig::default(),
+            #[cfg(feature = "wasm")]
+            wasm_engine: None,
         }
     }
 
+    /// 正本（状態・履歴・outbox）を設定（DeliveryQueue と一緒に）
+    pub fn with_task_store(mut self, store: Arc<dyn TaskStore>) -> Self {
+        self.task_store = Some(store);
+        self
+    }
+
+    /// 配送キューを設定（TaskStore と一緒に）
+    pub fn with_delivery_queue(mut self, delivery: Arc<dyn DeliveryQueue>) -> Self {
+        self.delivery = Some(delivery);
+        self
+    }
+
+    /// Blob ストレージを設定（GCLoop が期限切れを削除する）
+    pub fn with_artifact_store(mut self, artifacts: Arc<dyn ArtifactStore>) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
+    /// 失敗時の判断を差し替える（デフォルト: `DefaultDecider::default_v1()`）
+    pub fn with_decider(mut self, decider: Arc<dyn Decider>) -> Self {
+        self.decider = decider;
+        self
+    }
+
+    /// すべてのループが使う Clock（デフォルト: SystemClock）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// ID の生成器（デフォルト: SystemClock の UlidGenerator）
+    ///
+    /// ループ自身は ID を作らない（タスク・attempt・outbox イベントの ID は
+    /// TaskStore が採番する。InMemoryQueue を包む QueueAsTaskStore も同じ）。
+    /// `App::id_generator()` で、store の外で ID を作るコードに渡す。
+    pub fn with_id_generator(mut self, id_generator: Arc<dyn IdGenerator>) -> Self {
+        self.id_generator = id_generator;
+        self
+    }
+
+    /// すべてのループのイベントの送り先（デフォルト: NoopEventSink）
+    pub fn with_event_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.event_sink = sink;
+        self
+    }
+
+    /// WorkerLoop の数（デフォルト: 1）
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// WorkerLoop の設定
+    ///
+    /// 複数の worker を起動するときは、それぞれ `<worker_id>-<i>` を名乗る。
+    pub fn with_worker_config(mut self, config: WorkerConfig) -> Self {
+        self.worker_config = config;
+        self
+    }
+
+    pub fn with_publisher_config(mut self, config: PublisherConfig) -> Self {
+        self.publisher_config = config;
+        self
+    }
+
+    pub fn with_reaper_config(mut self, config: ReaperConfig) -> Self {
+        self.reaper_config = config;
+        self
+    }
+
+    pub fn with_gc_config(mut self, config: GcConfig) -> Self {
+        self.gc_config = config;
+        self
+    }
+
+    /// 指標の送り先を設定（Prometheus / StatsD など。`impls::PrometheusSink` など）
+    pub fn with_metrics_sink(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics_sink = sink;
+        self
+    }
+
     /// Handler を登録
     ///
     /// # Example
@@ -56,6 +229,71 @@ impl AppBuilder {
         Ok(self)
     }
 
+    /// 古い版の payload を新しい版に上げる変換を登録
+    ///
+    /// # Example
+    /// ```ignore
+    /// builder
+    ///     .register::<ChargeV2, _>(ChargeHandler)?
+    ///     .register_migration("orders.charge.v1", "orders.charge.v2", |mut payload| {
+    ///         payload["currency"] = json!("JPY");
+    ///         Ok(payload)
+    ///     })?;
+    /// ```
+    pub fn register_migration(
+        mut self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        migrate: impl Fn(serde_json::Value) -> Result<serde_json::Value, CodecError>
+        + Send
+        + Sync
+        + 'static,
+    ) -> Result<Self, RegistryError> {
+        self.registry.register_migration(from, to, migrate)?;
+        Ok(self)
+    }
+
+    /// 命名規約に従わない task_type の登録を許す（以降の register に効く）
+    ///
+    /// 既定では `acme.billing.charge.v1` の形でない task_type は
+    /// `RegistryError::InvalidTaskType` になる。
+    pub fn allow_nonconforming_task_types(mut self) -> Self {
+        self.registry.allow_nonconforming_task_types();
+        self
+    }
+
+    /// register_wasm() のモジュールを読み込む WasmEngine を設定（通常は `impls::WasmtimeEngine`）
+    #[cfg(feature = "wasm")]
+    pub fn with_wasm_engine(mut self, engine: Arc<dyn WasmEngine>) -> Self {
+        self.wasm_engine = Some(engine);
+        self
+    }
+
+    /// `task_type` を `path` の WASM モジュールで処理する（`WasmHandler`）
+    ///
+    /// モジュールはここで読み込む（起動時に失敗させるため）。
+    /// 先に with_wasm_engine() でエンジンを設定しておくこと。
+    ///
+    /// # Example
+    /// ```ignore
+    /// builder
+    ///     .with_wasm_engine(Arc::new(WasmtimeEngine::default()))
+    ///     .register_wasm("plugins.resize.v1", "plugins/resize.wasm")?;
+    /// ```
+    #[cfg(feature = "wasm")]
+    pub fn register_wasm(
+        mut self,
+        task_type: impl Into<String>,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, BuildError> {
+        let task_type = task_type.into();
+        let engine = self.wasm_engine.as_ref().ok_or(BuildError::NoWasmEngine)?;
+        let handler = WasmHandler::load(engine.as_ref(), task_type.clone(), path.as_ref())
+            .map_err(|source| BuildError::WasmModule { task_type, source })?;
+        self.registry.register_dyn(handler)?;
+        Ok(self)
+    }
+
     /// 期待される task_type のリストを設定
     ///
     /// # Example
@@ -76,6 +314,7 @@ impl AppBuilder {
     /// # 検証
     /// - expect_tasks() で設定された task_type が全て登録されているかチェック
     /// - 不足があれば BuildError::MissingTaskTypes を返す
+    /// - TaskStore と DeliveryQueue の片方だけなら BuildError::MissingPort を返す
     ///
     /// # Example
     /// ```ignore
@@ -93,8 +332,37 @@ impl AppBuilder {
                 return Err(BuildError::MissingTaskTypes(missing_tasks));
             }
         }
+        let store = match (self.task_store, self.delivery) {
+            (Some(store), Some(delivery)) => Some((store, delivery)),
+            (None, None) => None,
+            (Some(_), None) => {
+                return Err(BuildError::MissingPort {
+                    configured: "TaskStore",
+                    missing: "DeliveryQueue",
+                });
+            }
+            (None, Some(_)) => {
+                return Err(BuildError::MissingPort {
+                    configured: "DeliveryQueue",
+                    missing: "TaskStore",
+                });
+            }
+        };
         Ok(App {
             registry: self.registry,
+            metrics_sink: self.metrics_sink,
+            event_sink: self.event_sink,
+            store,
+            artifacts: self.artifacts,
+            decider: self.decider,
+            clock: self.clock,
+            id_generator: self.id_generator,
+            workers: self.workers,
+            worker_config: self.worker_config,
+            publisher_config: self.publisher_config,
+            reaper_config: self.reaper_config,
+            gc_config: self.gc_config,
+            running: None,
         })
     }
 }
@@ -107,11 +375,176 @@ impl Default for AppBuilder {
 
 /// App はアプリケーションのランタイム
 ///
-/// # v2 最小版
-/// - TypedRegistry のみを保持（起動時検証のデモ用）
-/// - 将来: TaskStore, DeliveryQueue, ArtifactStore などを追加
+/// build() で ports を受け取り、start() でループを起動、shutdown() で止める。
+/// ports を設定していなければ（registry だけ）、start() は何も起動しない。
+/// shutdown() せずに drop しても、ループには停止を伝える（終わるのは待たない）。
 pub struct App {
+    /// start() の時点の内容を worker が共有する（start() 後の登録は効かない）
     pub registry: TypedRegistry,
+    metrics_sink: Arc<dyn MetricsSink>,
+    event_sink: Arc<dyn EventSink>,
+    store: Option<(Arc<dyn TaskStore>, Arc<dyn DeliveryQueue>)>,
+    artifacts: Option<Arc<dyn ArtifactStore>>,
+    decider: Arc<dyn Decider>,
+    clock: Arc<dyn Clock>,
+    id_generator: Arc<dyn IdGenerator>,
+    workers: usize,
+    worker_config: WorkerConfig,
+    publisher_config: PublisherConfig,
+    reaper_config: ReaperConfig,
+    gc_config: GcConfig,
+    /// start() してから shutdown() するまで
+    running: Option<Running>,
+}
+
+/// 起動中のループ
+struct Running {
+    shutdown: watch::Sender<bool>,
+    handles: Vec<(String, JoinHandle<()>)>,
+}
+
+impl Drop for App {
+    /// shutdown() されずに捨てられたら、ループに停止だけ伝える
+    ///
+    /// 処理中のタスクは complete まで終えてから止まる（JoinHandle は待てないので切り離す）。
+    fn drop(&mut self) {
+        if let Some(running) = self.running.take() {
+            let _ = running.shutdown.send(true);
+        }
+    }
+}
+
+/// StartError は App::start() のエラー
+#[derive(Debug, thiserror::Error)]
+pub enum StartError {
+    #[error("App is already started")]
+    AlreadyStarted,
+}
+
+impl App {
+    /// ループを起動する（tokio のランタイムの中で呼ぶこと）
+    ///
+    /// 起動するループは AppBuilder に設定した ports で決まる（AppBuilder を参照）。
+    pub fn start(&mut self) -> Result<(), StartError> {
+        if self.running.is_some() {
+            return Err(StartError::AlreadyStarted);
+        }
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let mut handles = Vec::new();
+
+        if let Some((store, delivery)) = &self.store {
+            let registry = Arc::new(self.registry.clone());
+            for i in 0..self.workers {
+                let mut config = self.worker_config.clone();
+                if self.workers > 1 {
+                    config.worker_id = format!("{}-{i}", config.worker_id);
+                }
+                let name = config.worker_id.clone();
+                let worker = WorkerLoop::new(
+                    Arc::clone(store),
+                    Arc::clone(delivery),
+                    Arc::clone(&registry),
+                    Arc::clone(&self.decider),
+                )
+                .with_config(config)
+                .with_clock(Arc::clone(&self.clock))
+                .with_event_sink(Arc::clone(&self.event_sink));
+                let rx = shutdown_rx.clone();
+                handles.push((name, tokio::spawn(async move { worker.run(rx).await })));
+            }
+
+            let publisher = PublisherLoop::new(Arc::clone(store), Arc::clone(delivery))
+                .with_config(self.publisher_config.clone())
+                .with_clock(Arc::clone(&self.clock))
+                .with_event_sink(Arc::clone(&self.event_sink));
+            let rx = shutdown_rx.clone();
+            handles.push((
+                "publisher".to_string(),
+                tokio::spawn(async move { publisher.run(rx).await }),
+            ));
+
+            let reaper = ReaperLoop::new(Arc::clone(store))
+                .with_config(self.reaper_config.clone())
+                .with_clock(Arc::clone(&self.clock))
+                .with_event_sink(Arc::clone(&self.event_sink))
+                .with_metrics_sink(Arc::clone(&self.metrics_sink));
+            let rx = shutdown_rx.clone();
+            handles.push((
+                "reaper".to_string(),
+                tokio::spawn(async move { reaper.run(rx).await }),
+            ));
+        }
+
+        if let Some(artifacts) = &self.artifacts {
+            let gc = GCLoop::new(Arc::clone(artifacts))
+                .with_config(self.gc_config.clone())
+                .with_clock(Arc::clone(&self.clock))
+                .with_event_sink(Arc::clone(&self.event_sink))
+                .with_metrics_sink(Arc::clone(&self.metrics_sink));
+            let rx = shutdown_rx.clone();
+            handles.push((
+                "gc".to_string(),
+                tokio::spawn(async move { gc.run(rx).await }),
+            ));
+        }
+
+        self.running = Some(Running { shutdown, handles });
+        Ok(())
+    }
+
+    /// 起動中か（start() 後、shutdown() 前）
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// 起動中のループの名前（worker_id / "publisher" / "reaper" / "gc"）
+    pub fn loop_names(&self) -> Vec<&str> {
+        self.running.as_ref().map_or_else(Vec::new, |running| {
+            running
+                .handles
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect()
+        })
+    }
+
+    /// ループに停止を伝え、すべて終わるまで待つ（起動していなければ何もしない）
+    ///
+    /// worker は処理中のタスクを complete まで終えてから止まる。
+    pub async fn shutdown(&mut self) {
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        let _ = running.shutdown.send(true);
+        for (name, handle) in running.handles {
+            if let Err(e) = handle.await {
+                eprintln!("[{name}] stopped abnormally: {e}");
+            }
+        }
+    }
+
+    /// AppBuilder::with_id_generator で選んだ ID の生成器
+    ///
+    /// ループは使わない（ID は TaskStore が採番する）。store の外でタスクや
+    /// イベントを作るコードが、テストでは FixedClock の生成器と差し替えられるようにするためのもの。
+    pub fn id_generator(&self) -> Arc<dyn IdGenerator> {
+        Arc::clone(&self.id_generator)
+    }
+
+    /// AppBuilder::with_metrics_sink で選んだ指標の送り先
+    ///
+    /// Observer に渡すと `export_metrics()` でここへ送る。
+    pub fn metrics_sink(&self) -> Arc<dyn MetricsSink> {
+        Arc::clone(&self.metrics_sink)
+    }
+
+    /// 読み取り専用モードで共有の TaskStore / DeliveryQueue に接続する
+    ///
+    /// worker やループは起動せず、status / metrics / watch だけを提供する。
+    /// 本番の状態に対してダッシュボードや CLI を安全に動かすためのもの。
+    pub fn observer(store: Arc<dyn TaskStore>, delivery: Arc<dyn DeliveryQueue>) -> Observer {
+        Observer::new(store, delivery)
+    }
 }
 
 #[cfg(test)]
@@ -120,6 +553,23 @@ mod tests {
     use crate::typed::handler::{TestTaskHandler};
     use crate::typed::task::{AnotherTestTask, TestTask};
 
+    #[test]
+    fn test_build_keeps_the_configured_id_generator() {
+        use crate::ports::FixedClock;
+        use chrono::TimeZone;
+
+        let time = chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let app = AppBuilder::new()
+            .with_id_generator(Arc::new(UlidGenerator::new(FixedClock::new(time))))
+            .build()
+            .unwrap();
+        let task_id = app.id_generator().generate_task_id();
+        assert_eq!(
+            task_id.as_ulid().timestamp_ms(),
+            time.timestamp_millis() as u64
+        );
+    }
+
     #[test]
     fn test_build_success() {
         let app = AppBuilder::new()
@@ -151,4 +601,169 @@ mod tests {
             .build();
         assert!(app.is_ok());
     }
+
+    #[test]
+    fn test_build_rejects_a_store_without_a_delivery_queue() {
+        #[allow(deprecated)]
+        let queue = Arc::new(crate::queue::InMemoryQueue::new(
+            crate::queue::RetryPolicy::default_v1(),
+        ));
+        let app = AppBuilder::new()
+            .with_task_store(Arc::new(crate::impls::QueueAsTaskStore::new(queue)))
+            .build();
+        assert_eq!(
+            app.err().unwrap().to_string(),
+            "TaskStore is set but DeliveryQueue is not; the loops need both"
+        );
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_start_runs_the_loops_until_shutdown() {
+        use crate::app::Runtime;
+        use crate::impls::{InMemoryArtifactStore, QueueAsTaskStore};
+        use crate::queue::{InMemoryQueue, Queue, RetryPolicy};
+        use std::time::Duration;
+
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let adapter = Arc::new(QueueAsTaskStore::new(queue.clone()));
+        let mut app = AppBuilder::new()
+            .register::<TestTask, _>(TestTaskHandler {})
+            .unwrap()
+            .with_task_store(adapter.clone())
+            .with_delivery_queue(adapter)
+            .with_artifact_store(Arc::new(InMemoryArtifactStore::new()))
+            .with_workers(2)
+            .build()
+            .unwrap();
+        assert!(app.loop_names().is_empty());
+
+        app.start().unwrap();
+        assert!(matches!(app.start(), Err(StartError::AlreadyStarted)));
+        assert_eq!(
+            app.loop_names(),
+            ["worker-0-0", "worker-0-1", "publisher", "reaper", "gc"]
+        );
+
+        Runtime::new(queue.clone())
+            .submit(TestTask { value: 1 })
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while queue.counts_by_state().await.unwrap().succeeded == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Workers blocked in pop() and loops sleeping between passes stop at once
+        tokio::time::timeout(Duration::from_secs(1), app.shutdown())
+            .await
+            .unwrap();
+        assert!(!app.is_running());
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_dropping_a_started_app_stops_its_loops() {
+        use crate::app::Runtime;
+        use crate::domain::events::DomainEvent;
+        use crate::impls::{BufferedVecEventSink, QueueAsTaskStore};
+        use crate::queue::{InMemoryQueue, Queue, RetryPolicy};
+        use std::time::Duration;
+
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let adapter = Arc::new(QueueAsTaskStore::new(queue.clone()));
+        let events = Arc::new(BufferedVecEventSink::new());
+        let mut app = AppBuilder::new()
+            .register::<TestTask, _>(TestTaskHandler {})
+            .unwrap()
+            .with_task_store(adapter.clone())
+            .with_delivery_queue(adapter.clone())
+            .with_event_sink(events.clone())
+            .build()
+            .unwrap();
+        app.start().unwrap();
+
+        Runtime::new(queue.clone())
+            .submit(TestTask { value: 1 })
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while queue.counts_by_state().await.unwrap().succeeded == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        // The worker reports to the sink given to the builder
+        assert!(
+            events
+                .events()
+                .iter()
+                .any(|event| matches!(event, DomainEvent::TaskStarted { attempt: 1, .. }))
+        );
+
+        // The loops hold the adapter until they stop
+        drop(app);
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while Arc::strong_count(&adapter) > 1 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("loops should stop once the app is dropped");
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_register_wasm_loads_modules_up_front() {
+        use crate::ports::WasmModule;
+
+        /// `.wasm` で終わるパスだけ読めるエンジン
+        struct FakeEngine;
+
+        struct EmptyModule;
+
+        #[async_trait::async_trait]
+        impl WasmModule for EmptyModule {
+            async fn call(&self, _function: &str, _input: &[u8]) -> Result<Vec<u8>, WasmError> {
+                Ok(b"{}".to_vec())
+            }
+        }
+
+        impl WasmEngine for FakeEngine {
+            fn load(&self, path: &Path) -> Result<Arc<dyn WasmModule>, WasmError> {
+                if path.extension().is_some_and(|ext| ext == "wasm") {
+                    Ok(Arc::new(EmptyModule))
+                } else {
+                    Err(WasmError::Load(format!(
+                        "{} is not a module",
+                        path.display()
+                    )))
+                }
+            }
+        }
+
+        assert!(matches!(
+            AppBuilder::new().register_wasm("plugins.resize.v1", "resize.wasm"),
+            Err(BuildError::NoWasmEngine)
+        ));
+
+        let builder = AppBuilder::new().with_wasm_engine(Arc::new(FakeEngine));
+        let app = builder
+            .register_wasm("plugins.resize.v1", "plugins/resize.wasm")
+            .unwrap()
+            .expect_tasks(&["plugins.resize.v1"])
+            .build()
+            .unwrap();
+        assert!(app.registry.get("plugins.resize.v1").is_some());
+
+        let builder = AppBuilder::new().with_wasm_engine(Arc::new(FakeEngine));
+        assert!(matches!(
+            builder.register_wasm("plugins.resize.v1", "plugins/resize.txt"),
+            Err(BuildError::WasmModule { task_type, .. }) if task_type == "plugins.resize.v1"
+        ));
+    }
 }
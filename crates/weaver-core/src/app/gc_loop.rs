@@ -1,20 +1,509 @@
 //! GCLoop - Artifact のガベージコレクション
 //!
-//! # 実装予定
-//! - **PR-12**: expires_at < now の artifact を削除
+//! # 学習ポイント
+//! - 期限切れの判定は ArtifactStore（`list_expired`）、削除の順序（メタ情報 → 本体）も
+//!   ArtifactStore に任せる。GCLoop は「いつ・どれだけの速さで消すか」を受け持つ
+//! - 削除のレートに上限を付け、Blob ストレージへの負荷が一度に集中しないようにする
+//!   （token bucket は RateLimiter の実装をそのまま使う）
+//! - 1 件の削除の失敗でパス全体を止めない（次のパスでまた拾われる）
+//! - 失敗は stderr ではなく `DomainEvent::ArtifactGcFailed` と失敗数の指標で知らせる
+//!   （通知ルールやダッシュボードで拾えるように）
+
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::domain::events::DomainEvent;
+use crate::impls::{RateLimit, TokenBucketRateLimiter};
+use crate::ports::{
+    ArtifactError, ArtifactStore, Clock, EventSink, MetricSample, MetricsSink, NoopEventSink,
+    NoopMetricsSink, RateLimiter, SystemClock,
+};
+
+/// GcConfig は GCLoop の設定
+#[derive(Debug, Clone)]
+pub struct GcConfig {
+    /// GC する namespace（デフォルト: "default"）
+    pub ns: String,
+    /// GC の間隔（デフォルト: 60 秒）
+    pub interval: Duration,
+    /// 1 回のパスで扱う artifact 数の上限（デフォルト: 100）
+    pub batch_size: usize,
+    /// 1 秒あたりの削除数の上限（デフォルト: `None` = 上限なし）
+    ///
+    /// 0 は補充されない bucket になり GC が止まるので、型で受け付けない。
+    pub max_deletions_per_second: Option<NonZeroU32>,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        Self {
+            ns: "default".to_string(),
+            interval: Duration::from_secs(60),
+            batch_size: 100,
+            max_deletions_per_second: None,
+        }
+    }
+}
+
+/// GcReport は 1 回のパスの結果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// 期限切れとして見つけた数
+    pub expired: usize,
+    /// 削除した数
+    pub deleted: usize,
+    /// 削除した本体の合計バイト数
+    pub reclaimed_bytes: u64,
+    /// 削除に失敗した数（次のパスで再び対象になる）
+    pub failed: usize,
+}
+
+/// GcStats は起動してからの GC の累計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    /// list_expired を呼んだ回数（失敗を除く）
+    pub passes: u64,
+    pub deleted: u64,
+    pub reclaimed_bytes: u64,
+    pub failed: u64,
+    /// list_expired に失敗したパスの数
+    pub failed_passes: u64,
+}
 
 /// GCLoop は期限切れの artifact を削除
 ///
 /// # フロー
-/// 1. 定期的に expires_at < now の artifact を検索
-/// 2. PG の deleted_at を更新
-/// 3. Blob から削除
+/// 1. 定期的に ArtifactStore::list_expired() で expires_at <= now の artifact を検索
+/// 2. ArtifactStore::delete() でメタ情報（PG の deleted_at）と本体（Blob）を削除
+///    （`max_deletions_per_second` を超えないよう待つ）
+/// 3. 削除があれば `DomainEvent::ArtifactsCollected` を、失敗があれば
+///    `DomainEvent::ArtifactGcFailed` を EventSink に送る
+/// 4. 累計を `weaver_artifacts_deleted` / `weaver_artifact_bytes_reclaimed` /
+///    `weaver_artifact_gc_failures` として MetricsSink に送る
+///
+/// # 使用例
+/// ```ignore
+/// let gc = GCLoop::new(artifacts).with_config(GcConfig {
+///     max_deletions_per_second: NonZeroU32::new(50),
+///     ..GcConfig::default()
+/// });
+/// tokio::spawn(async move { gc.run(shutdown_rx).await });
+/// ```
 pub struct GCLoop {
-    // TODO(PR-12): フィールド定義
+    artifacts: Arc<dyn ArtifactStore>,
+    events: Arc<dyn EventSink>,
+    metrics: Arc<dyn MetricsSink>,
+    clock: Arc<dyn Clock>,
+    config: GcConfig,
+    /// `max_deletions_per_second` の token bucket（key は namespace）
+    limiter: Option<TokenBucketRateLimiter>,
+    stats: Mutex<GcStats>,
 }
 
 impl GCLoop {
-    // TODO(PR-12): メソッド実装
-    // - new()
-    // - run()
+    /// ArtifactStore を指定して作成（設定はデフォルト、イベント・指標は捨てる）
+    pub fn new(artifacts: Arc<dyn ArtifactStore>) -> Self {
+        Self {
+            artifacts,
+            events: Arc::new(NoopEventSink),
+            metrics: Arc::new(NoopMetricsSink),
+            clock: Arc::new(SystemClock),
+            config: GcConfig::default(),
+            limiter: None,
+            stats: Mutex::new(GcStats::default()),
+        }
+    }
+
+    /// 設定を差し替える
+    pub fn with_config(mut self, config: GcConfig) -> Self {
+        self.limiter = config.max_deletions_per_second.map(|n| {
+            TokenBucketRateLimiter::new()
+                .with_limit(config.ns.clone(), RateLimit::per_second(n.get()))
+        });
+        self.config = config;
+        self
+    }
+
+    /// Clock を差し替える（デフォルト: SystemClock）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// GC の結果のイベントの送り先（デフォルト: NoopEventSink）
+    pub fn with_event_sink(mut self, events: Arc<dyn EventSink>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// 削除数・回収バイト数の送り先（デフォルト: NoopMetricsSink）
+    pub fn with_metrics_sink(mut self, metrics: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn config(&self) -> &GcConfig {
+        &self.config
+    }
+
+    /// 起動してからの GC の累計
+    pub fn stats(&self) -> GcStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// 1 回分を GC する
+    ///
+    /// 一覧の取得に失敗したら `Err`。個々の削除の失敗は `GcReport::failed` に数える。
+    /// どちらの失敗も `DomainEvent::ArtifactGcFailed` と指標で報告する。
+    pub async fn collect_once(&self) -> Result<GcReport, ArtifactError> {
+        // 止める手段のない呼び出しなので、sender を持ったまま待つ
+        let (_tx, mut shutdown) = watch::channel(false);
+        self.collect(&mut shutdown).await
+    }
+
+    /// 1 回分を GC する（レート上限の待ちの間に shutdown が来たら、そこまでで終える）
+    async fn collect(
+        &self,
+        shutdown: &mut watch::Receiver<bool>,
+    ) -> Result<GcReport, ArtifactError> {
+        let ns = &self.config.ns;
+        let expired = match self
+            .artifacts
+            .list_expired(ns, self.clock.now(), self.config.batch_size)
+            .await
+        {
+            Ok(expired) => expired,
+            Err(e) => {
+                let stats = {
+                    let mut stats = self.stats.lock().unwrap();
+                    stats.failed_passes += 1;
+                    *stats
+                };
+                self.report_failure(0, &e).await;
+                self.emit_metrics(stats).await;
+                return Err(e);
+            }
+        };
+
+        let mut report = GcReport {
+            expired: expired.len(),
+            ..GcReport::default()
+        };
+        let mut last_error = None;
+        for meta in &expired {
+            if !self.acquire(shutdown).await {
+                break;
+            }
+            match self.artifacts.delete(ns, meta.artifact_id).await {
+                Ok(()) => {
+                    report.deleted += 1;
+                    report.reclaimed_bytes += meta.size_bytes;
+                }
+                // 他の GC が先に消した
+                Err(ArtifactError::NotFound(_)) => {}
+                Err(e) => {
+                    report.failed += 1;
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        let stats = {
+            let mut stats = self.stats.lock().unwrap();
+            stats.passes += 1;
+            stats.deleted += report.deleted as u64;
+            stats.reclaimed_bytes += report.reclaimed_bytes;
+            stats.failed += report.failed as u64;
+            *stats
+        };
+
+        if report.deleted > 0 {
+            let event = DomainEvent::ArtifactsCollected {
+                ns: ns.clone(),
+                deleted: report.deleted,
+                reclaimed_bytes: report.reclaimed_bytes,
+            };
+            if let Err(e) = self.events.emit(event).await {
+                eprintln!("[gc] {e}");
+            }
+        }
+        if let Some(e) = last_error {
+            self.report_failure(report.failed, &e).await;
+        }
+        self.emit_metrics(stats).await;
+        Ok(report)
+    }
+
+    /// 削除の token を 1 つ取る（上限がなければすぐ返る）
+    ///
+    /// 待っている間に shutdown が通知されたら `false`。
+    async fn acquire(&self, shutdown: &mut watch::Receiver<bool>) -> bool {
+        let Some(limiter) = &self.limiter else {
+            return true;
+        };
+        while let Err(wait) = limiter.try_acquire(&self.config.ns) {
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = shutdown.changed() => return false,
+            }
+        }
+        true
+    }
+
+    /// GC の失敗を EventSink に送る（EventSink 自体の失敗だけは stderr に出す）
+    async fn report_failure(&self, failed: usize, error: &ArtifactError) {
+        let event = DomainEvent::ArtifactGcFailed {
+            ns: self.config.ns.clone(),
+            failed,
+            message: error.to_string(),
+        };
+        if let Err(e) = self.events.emit(event).await {
+            eprintln!("[gc] {e}");
+        }
+    }
+
+    async fn emit_metrics(&self, stats: GcStats) {
+        if let Err(e) = self.metrics.emit(&self.samples(stats)).await {
+            eprintln!("[gc] {e}");
+        }
+    }
+
+    fn samples(&self, stats: GcStats) -> Vec<MetricSample> {
+        let labels = vec![("namespace", self.config.ns.clone())];
+        vec![
+            MetricSample {
+                name: "weaver_artifacts_deleted",
+                help: "Expired artifacts deleted by the GC",
+                labels: labels.clone(),
+                value: stats.deleted as f64,
+            },
+            MetricSample {
+                name: "weaver_artifact_bytes_reclaimed",
+                help: "Bytes of expired artifacts deleted by the GC",
+                labels: labels.clone(),
+                value: stats.reclaimed_bytes as f64,
+            },
+            MetricSample {
+                name: "weaver_artifact_gc_failures",
+                help: "Artifact deletions and GC passes that failed",
+                labels,
+                value: (stats.failed + stats.failed_passes) as f64,
+            },
+        ]
+    }
+
+    /// shutdown が通知されるまで `interval` ごとに GC する
+    ///
+    /// パスが `batch_size` に達したら、残りがありうるので待たずに続ける。
+    /// 失敗は `collect_once` と同じく EventSink と指標に報告済みなので、次のパスで再試行するだけ。
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            let full = match self.collect(&mut shutdown).await {
+                Ok(report) => report.expired >= self.config.batch_size,
+                Err(_) => false,
+            };
+            // レート上限の待ちで shutdown を受け取っていたら、もう changed() は返らない
+            if !full && !*shutdown.borrow() {
+                tokio::select! {
+                    _ = tokio::time::sleep(self.config.interval) => {}
+                    _ = shutdown.changed() => break,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::*;
+    use crate::domain::ArtifactId;
+    use crate::impls::{BufferedVecEventSink, InMemoryArtifactStore};
+    use crate::ports::FixedClock;
+
+    fn t(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn deletes_only_expired_artifacts_and_reports_reclaimed_bytes() {
+        let store = Arc::new(InMemoryArtifactStore::new());
+        let ttl = Some(Duration::from_secs(60));
+        let expired = store
+            .put("default", vec![0; 300], None, ttl, t(0))
+            .await
+            .unwrap();
+        store
+            .put("default", vec![0; 200], None, ttl, t(0))
+            .await
+            .unwrap();
+        let fresh = store
+            .put("default", vec![0; 50], None, ttl, t(100))
+            .await
+            .unwrap();
+        let kept = store
+            .put("default", vec![0; 10], None, None, t(0))
+            .await
+            .unwrap();
+        let events = Arc::new(BufferedVecEventSink::new());
+        let gc = GCLoop::new(store.clone())
+            .with_clock(Arc::new(FixedClock::new(t(120))))
+            .with_event_sink(events.clone());
+
+        let report = gc.collect_once().await.unwrap();
+        assert_eq!(
+            report,
+            GcReport {
+                expired: 2,
+                deleted: 2,
+                reclaimed_bytes: 500,
+                failed: 0
+            }
+        );
+        assert!(matches!(
+            store.get("default", expired.artifact_id).await,
+            Err(ArtifactError::NotFound(_))
+        ));
+        assert!(store.get("default", fresh.artifact_id).await.is_ok());
+        assert!(store.get("default", kept.artifact_id).await.is_ok());
+
+        assert_eq!(gc.collect_once().await.unwrap(), GcReport::default());
+        assert_eq!(gc.stats().reclaimed_bytes, 500);
+        assert!(matches!(
+            events.events()[..],
+            [DomainEvent::ArtifactsCollected {
+                deleted: 2,
+                reclaimed_bytes: 500,
+                ..
+            }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn deletions_are_spaced_out_by_the_rate_cap() {
+        let store = Arc::new(InMemoryArtifactStore::new());
+        for _ in 0..103 {
+            let ttl = Some(Duration::ZERO);
+            store
+                .put("default", vec![1], None, ttl, t(0))
+                .await
+                .unwrap();
+        }
+        let gc = GCLoop::new(store.clone())
+            .with_clock(Arc::new(FixedClock::new(t(1))))
+            .with_config(GcConfig {
+                batch_size: 200,
+                max_deletions_per_second: NonZeroU32::new(100),
+                ..GcConfig::default()
+            });
+
+        // A burst of 100, then one deletion per 10ms
+        let start = std::time::Instant::now();
+        assert_eq!(gc.collect_once().await.unwrap().deleted, 103);
+        assert!(start.elapsed() >= Duration::from_millis(25));
+        assert!(store.is_empty("default"));
+    }
+
+    #[tokio::test]
+    async fn shutdown_interrupts_a_pass_waiting_for_the_rate_cap() {
+        let store = Arc::new(InMemoryArtifactStore::new());
+        for _ in 0..5 {
+            let ttl = Some(Duration::ZERO);
+            store
+                .put("default", vec![1], None, ttl, t(0))
+                .await
+                .unwrap();
+        }
+        let gc = Arc::new(
+            GCLoop::new(store.clone())
+                .with_clock(Arc::new(FixedClock::new(t(1))))
+                .with_config(GcConfig {
+                    max_deletions_per_second: NonZeroU32::new(1),
+                    ..GcConfig::default()
+                }),
+        );
+        let (tx, rx) = watch::channel(false);
+        let handle = tokio::spawn({
+            let gc = gc.clone();
+            async move { gc.run(rx).await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_millis(500), handle)
+            .await
+            .expect("run should stop without waiting for the next token")
+            .unwrap();
+        assert_eq!(gc.stats().deleted, 1);
+    }
+
+    /// delete が常に失敗する ArtifactStore
+    struct UndeletableStore(InMemoryArtifactStore);
+
+    #[async_trait::async_trait]
+    impl ArtifactStore for UndeletableStore {
+        async fn put(
+            &self,
+            ns: &str,
+            bytes: Vec<u8>,
+            content_type: Option<&str>,
+            ttl: Option<Duration>,
+            now: DateTime<Utc>,
+        ) -> Result<crate::ports::ArtifactMeta, ArtifactError> {
+            self.0.put(ns, bytes, content_type, ttl, now).await
+        }
+
+        async fn get(&self, ns: &str, artifact_id: ArtifactId) -> Result<Vec<u8>, ArtifactError> {
+            self.0.get(ns, artifact_id).await
+        }
+
+        async fn delete(&self, _ns: &str, _artifact_id: ArtifactId) -> Result<(), ArtifactError> {
+            Err(ArtifactError::OperationFailed(
+                "blob storage is down".into(),
+            ))
+        }
+
+        async fn list_expired(
+            &self,
+            ns: &str,
+            now: DateTime<Utc>,
+            limit: usize,
+        ) -> Result<Vec<crate::ports::ArtifactMeta>, ArtifactError> {
+            self.0.list_expired(ns, now, limit).await
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_deletions_are_reported_as_an_event_and_a_metric() {
+        let store = Arc::new(UndeletableStore(InMemoryArtifactStore::new()));
+        for _ in 0..2 {
+            let ttl = Some(Duration::ZERO);
+            store
+                .put("default", vec![1], None, ttl, t(0))
+                .await
+                .unwrap();
+        }
+        let events = Arc::new(BufferedVecEventSink::new());
+        let metrics = Arc::new(crate::impls::PrometheusSink::new());
+        let gc = GCLoop::new(store)
+            .with_clock(Arc::new(FixedClock::new(t(1))))
+            .with_event_sink(events.clone())
+            .with_metrics_sink(metrics.clone());
+
+        let report = gc.collect_once().await.unwrap();
+        assert_eq!(report.failed, 2);
+        assert!(matches!(
+            &events.events()[..],
+            [DomainEvent::ArtifactGcFailed { failed: 2, message, .. }] if message.contains("blob storage is down")
+        ));
+        assert!(
+            metrics
+                .render()
+                .contains("weaver_artifact_gc_failures{namespace=\"default\"} 2")
+        );
+    }
 }
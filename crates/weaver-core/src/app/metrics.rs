@@ -0,0 +1,305 @@
+//! Metrics - ObserverMetrics を Prometheus のテキスト形式・MetricsSink 向けの sample にする
+//!
+//! # 学習ポイント
+//! - ラベルの値が増えるほど時系列が増える（カーディナリティ）。task_type は
+//!   ユーザーが自由に増やせるので、深さの大きい上位 K 個だけをラベルにし、
+//!   残りは `"other"` にまとめる
+//! - 同じ値は同じ順で出す（上位 K の同点は名前順）ので、scrape ごとに揺れない
+//!
+//! # 出力する指標
+//! - `weaver_tasks{namespace, state}`: 状態別タスク数
+//! - `weaver_queue_depth{namespace, task_type}`: task_type ごとの待ち数（Queued + RetryScheduled）
+//! - `weaver_queue_depth_by_task_namespace{namespace, task_namespace}`:
+//!   task_type の先頭セグメント（`acme.billing.v1` → `acme`）ごとの待ち数
+//! - `weaver_delivery_backlog{namespace}`: DeliveryQueue の配送待ち（数えられる実装のみ）
+//! - `weaver_run_info{namespace, run_id}`: いまの run（常に 1。run を区別する実装のみ）
+//!
+//! タスクにはまだ優先度がないため、priority のラベルは持たない。
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::app::observer::ObserverMetrics;
+use crate::ports::MetricSample;
+
+/// task_type を上位 K 個に絞ったとき、残りをまとめるラベル値
+pub const OTHER_LABEL: &str = "other";
+
+/// task_type に `.` がないとき（先頭セグメントがない）の task_namespace
+pub const NO_TASK_NAMESPACE_LABEL: &str = "none";
+
+/// MetricLabels はラベルのカーディナリティの上限
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricLabels {
+    /// `task_type` ラベルにする task_type の数（0 ならすべて `"other"`）
+    pub max_task_types: usize,
+    /// `task_namespace` ラベルにする task_namespace の数
+    pub max_task_namespaces: usize,
+}
+
+impl Default for MetricLabels {
+    /// task_type は 20 個、task_namespace は 10 個まで
+    fn default() -> Self {
+        Self {
+            max_task_types: 20,
+            max_task_namespaces: 10,
+        }
+    }
+}
+
+impl MetricLabels {
+    pub fn with_max_task_types(mut self, max_task_types: usize) -> Self {
+        self.max_task_types = max_task_types;
+        self
+    }
+
+    pub fn with_max_task_namespaces(mut self, max_task_namespaces: usize) -> Self {
+        self.max_task_namespaces = max_task_namespaces;
+        self
+    }
+}
+
+/// namespace `ns` の指標を Prometheus のテキスト形式（exposition format 0.0.4）にする
+pub fn render_prometheus(ns: &str, metrics: &ObserverMetrics, labels: &MetricLabels) -> String {
+    render_samples(&metric_samples(ns, metrics, labels))
+}
+
+/// namespace `ns` の指標を sample の列にする（MetricsSink に渡す形）
+///
+/// 同じ指標の sample は続けて並ぶ。task_type / task_namespace のラベルは
+/// `labels` の上限で絞る。
+pub fn metric_samples(
+    ns: &str,
+    metrics: &ObserverMetrics,
+    labels: &MetricLabels,
+) -> Vec<MetricSample> {
+    let counts = &metrics.counts;
+    let mut samples = Vec::new();
+    let mut push = |name, help, extra: Option<(&'static str, String)>, value: usize| {
+        let mut labels = vec![("namespace", ns.to_string())];
+        labels.extend(extra);
+        samples.push(MetricSample {
+            name,
+            help,
+            labels,
+            value: value as f64,
+        });
+    };
+
+    let states = [
+        ("queued", counts.queued),
+        ("running", counts.running),
+        ("retry_scheduled", counts.retry_scheduled),
+        ("blocked", counts.blocked),
+        ("succeeded", counts.succeeded),
+        ("dead", counts.dead),
+        ("decomposed", counts.decomposed),
+        ("cancelled", counts.cancelled),
+    ];
+    for (state, count) in states {
+        push(
+            "weaver_tasks",
+            "Tasks by state.",
+            Some(("state", state.to_string())),
+            count,
+        );
+    }
+
+    let by_task_type = top_k(
+        counts
+            .waiting_by_task_type
+            .iter()
+            .map(|(task_type, depth)| (task_type.as_str(), *depth)),
+        labels.max_task_types,
+    );
+    for (task_type, depth) in by_task_type {
+        push(
+            "weaver_queue_depth",
+            "Queued and retry-scheduled tasks by task type.",
+            Some(("task_type", task_type)),
+            depth,
+        );
+    }
+
+    let mut by_task_namespace: BTreeMap<&str, usize> = BTreeMap::new();
+    for (task_type, depth) in &counts.waiting_by_task_type {
+        let task_namespace = task_type
+            .split_once('.')
+            .map_or(NO_TASK_NAMESPACE_LABEL, |(task_namespace, _)| {
+                task_namespace
+            });
+        *by_task_namespace.entry(task_namespace).or_default() += depth;
+    }
+    for (task_namespace, depth) in top_k(by_task_namespace, labels.max_task_namespaces) {
+        push(
+            "weaver_queue_depth_by_task_namespace",
+            "Queued and retry-scheduled tasks by the first segment of the task type.",
+            Some(("task_namespace", task_namespace)),
+            depth,
+        );
+    }
+
+    if let Some(backlog) = metrics.delivery_backlog {
+        push(
+            "weaver_delivery_backlog",
+            "Task ids waiting in the delivery queue.",
+            None,
+            backlog,
+        );
+    }
+
+    if let Some(run_id) = metrics.run_id {
+        push(
+            "weaver_run_info",
+            "The current run (one per process start); always 1.",
+            Some(("run_id", run_id.to_string())),
+            1,
+        );
+    }
+    samples
+}
+
+/// sample の列を Prometheus のテキスト形式にする
+///
+/// 指標ごとに `# HELP` / `# TYPE` を 1 度だけ出す（sample のない指標は出さない）。
+pub fn render_samples(samples: &[MetricSample]) -> String {
+    let mut out = String::new();
+    let mut current = None;
+    for sample in samples {
+        if current != Some(sample.name) {
+            gauge_header(&mut out, sample.name, sample.help);
+            current = Some(sample.name);
+        }
+        let labels: Vec<String> = sample
+            .labels
+            .iter()
+            .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+            .collect();
+        let _ = writeln!(
+            out,
+            "{}{{{}}} {}",
+            sample.name,
+            labels.join(","),
+            sample.value
+        );
+    }
+    out
+}
+
+fn gauge_header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+}
+
+/// 値の大きい上位 `k` 個（同点は名前順）と、残りの合計を `"other"` にしたもの
+///
+/// 残りがなければ `"other"` は出さない。
+fn top_k<'a>(values: impl IntoIterator<Item = (&'a str, usize)>, k: usize) -> Vec<(String, usize)> {
+    let mut values: Vec<(&str, usize)> = values.into_iter().collect();
+    values.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let rest = values.split_off(k.min(values.len()));
+    let mut top: Vec<(String, usize)> = values
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+    if !rest.is_empty() {
+        top.push((
+            OTHER_LABEL.to_string(),
+            rest.iter().map(|(_, value)| value).sum(),
+        ));
+    }
+    top
+}
+
+/// ラベル値のエスケープ（`\`、`"`、改行）
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::RunId;
+    use crate::observability::QueueCounts;
+
+    fn metrics(waiting: &[(&str, usize)]) -> ObserverMetrics {
+        ObserverMetrics {
+            counts: QueueCounts {
+                queued: waiting.iter().map(|(_, depth)| depth).sum(),
+                waiting_by_task_type: waiting
+                    .iter()
+                    .map(|(task_type, depth)| (task_type.to_string(), *depth))
+                    .collect(),
+                ..QueueCounts::default()
+            },
+            delivery_backlog: Some(4),
+            run_id: None,
+        }
+    }
+
+    #[test]
+    fn task_types_beyond_the_top_k_are_folded_into_other() {
+        let metrics = metrics(&[
+            ("acme.render.v1", 9),
+            ("acme.email.v1", 3),
+            ("beta.sync.v1", 3),
+            ("cleanup", 1),
+        ]);
+        let labels = MetricLabels::default()
+            .with_max_task_types(2)
+            .with_max_task_namespaces(1);
+        let text = render_prometheus("default", &metrics, &labels);
+
+        let depth: Vec<&str> = text
+            .lines()
+            .filter(|line| line.starts_with("weaver_queue_depth{"))
+            .collect();
+        assert_eq!(
+            depth,
+            [
+                "weaver_queue_depth{namespace=\"default\",task_type=\"acme.render.v1\"} 9",
+                // ties are broken by name
+                "weaver_queue_depth{namespace=\"default\",task_type=\"acme.email.v1\"} 3",
+                "weaver_queue_depth{namespace=\"default\",task_type=\"other\"} 4",
+            ]
+        );
+        assert!(text.contains(
+            "weaver_queue_depth_by_task_namespace{namespace=\"default\",task_namespace=\"acme\"} 12\n\
+             weaver_queue_depth_by_task_namespace{namespace=\"default\",task_namespace=\"other\"} 4\n"
+        ));
+        assert!(text.contains("weaver_tasks{namespace=\"default\",state=\"queued\"} 16\n"));
+        assert!(text.contains("# TYPE weaver_delivery_backlog gauge\n"));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        let text = render_prometheus(
+            "team \"a\"",
+            &metrics(&[("odd\\type", 1)]),
+            &MetricLabels::default(),
+        );
+        assert!(text.contains(
+            "weaver_queue_depth{namespace=\"team \\\"a\\\"\",task_type=\"odd\\\\type\"} 1"
+        ));
+        assert!(text.contains("task_namespace=\"none\"} 1"));
+    }
+
+    #[test]
+    fn run_info_is_emitted_when_the_store_tracks_runs() {
+        let mut metrics = metrics(&[]);
+        assert!(
+            !render_prometheus("default", &metrics, &MetricLabels::default())
+                .contains("weaver_run_info")
+        );
+
+        let run_id = RunId::from_ulid(ulid::Ulid::new());
+        metrics.run_id = Some(run_id);
+        let text = render_prometheus("default", &metrics, &MetricLabels::default());
+        assert!(text.contains(&format!(
+            "weaver_run_info{{namespace=\"default\",run_id=\"{run_id}\"}} 1\n"
+        )));
+    }
+}
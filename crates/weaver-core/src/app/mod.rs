@@ -9,6 +9,11 @@
 //! - **PublisherLoop**: Outbox イベントの配送
 //! - **ReaperLoop**: Lease 期限切れの回収
 //! - **GCLoop**: Artifact のガベージコレクション
+//! - **NamespaceAssignment**: consistent hashing による namespace の担当割り当て
+//! - **NotificationRules**: イベントを Slack / email 通知に変換するルールエンジン
+//! - **Observer**: 読み取り専用の App（status / metrics / watch のみ）
+//! - **WatermarkMonitor**: キューの深さのしきい値コールバック（ヒステリシス付き）
+//! - **metrics**: ObserverMetrics の Prometheus テキスト出力と MetricsSink 向けの sample（ラベル数の上限付き）
 
 pub mod builder;
 pub mod runtime;
@@ -17,11 +22,23 @@ pub mod publisher_loop;
 pub mod reaper_loop;
 pub mod gc_loop;
 pub mod status;
+pub mod assignment;
+pub mod notification_rules;
+pub mod observer;
+pub mod watermark;
+pub mod metrics;
 
 // 主要な型を再エクスポート
-pub use self::builder::AppBuilder;
-pub use self::runtime::Runtime;
-pub use self::worker_loop::WorkerLoop;
-pub use self::publisher_loop::PublisherLoop;
-pub use self::reaper_loop::ReaperLoop;
-pub use self::gc_loop::GCLoop;
+pub use self::builder::{App, AppBuilder, BuildError, StartError};
+pub use self::runtime::{Runtime, RuntimeError};
+pub use self::worker_loop::{WorkerConfig, WorkerLoop};
+pub use self::publisher_loop::{PublishReport, PublisherConfig, PublisherLoop};
+pub use self::reaper_loop::{ReaperConfig, ReaperLoop, ReaperStats};
+pub use self::gc_loop::{GCLoop, GcConfig, GcReport, GcStats};
+pub use self::assignment::{HashRing, NamespaceAssignment};
+pub use self::notification_rules::{NotificationRule, NotificationRules, Trigger};
+pub use self::metrics::{MetricLabels, render_prometheus, metric_samples, render_samples};
+pub use self::observer::{Observer, ObserverError, ObserverMetrics};
+pub use self::watermark::{
+    DepthScope, Watermark, WatermarkCallback, WatermarkEvent, WatermarkLevel, WatermarkMonitor,
+};
@@ -0,0 +1,511 @@
+//! NotificationRules - ドメインイベントを Slack / email の通知に変換するルールエンジン
+//!
+//! EventSink として queue や worker supervisor に差し込み、ルールにマッチした
+//! イベントを NotificationChannel へ送ります。
+//!
+//! # まとめ（grouping）
+//! `group_window` を持つルールは、同じグループ（TaskDead なら task_type、
+//! SloBreached なら SLO 名）の通知を window ごとに最大 2 件に抑えます。
+//! - window 内の最初のイベントはすぐに通知する
+//! - 残りは数えておき、window が閉じたら
+//!   「23 tasks of type X died in the last 5m」のような 1 件にまとめて通知する
+//!
+//! window を閉じるのは `flush()`。`run()` が定期的に呼び出します。
+//!
+//! # テンプレート
+//! `{name}` をイベントの値で置き換えます。
+//! - 共通: `{rule}`, `{event}`, `{count}`, `{window}`
+//! - TaskDead: `{task_id}`, `{task_type}`, `{job_id}`, `{error}`
+//! - JobFailed: `{job_id}`, `{failed_tasks}`
+//! - SloBreached: `{slo}`, `{message}`
+//!
+//! まとめた通知の値は window 内の最後のイベントのものです。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::watch;
+
+use crate::domain::{DomainEvent, TaskType};
+use crate::ports::{Clock, EventSink, EventSinkError, Notification, NotificationChannel, SystemClock};
+
+/// Trigger はルールが反応するイベント
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    /// ジョブが失敗した
+    JobFailed,
+
+    /// タスクが Dead になった（`task_type` 指定時はその種類だけ）
+    TaskDead { task_type: Option<TaskType> },
+
+    /// SLO を外れた（`slo` 指定時はその SLO だけ）
+    SloBreached { slo: Option<String> },
+}
+
+impl Trigger {
+    /// 全種類の Dead タスク
+    pub fn task_dead() -> Self {
+        Self::TaskDead { task_type: None }
+    }
+
+    /// すべての SLO
+    pub fn slo_breached() -> Self {
+        Self::SloBreached { slo: None }
+    }
+
+    /// マッチしたらグループのキーを返す
+    fn group_key(&self, event: &DomainEvent) -> Option<String> {
+        match (self, event) {
+            (Self::JobFailed, DomainEvent::JobFailed { .. }) => Some(String::new()),
+            (Self::TaskDead { task_type: filter }, DomainEvent::TaskDead { task_type, .. }) => {
+                filter
+                    .as_ref()
+                    .is_none_or(|filter| filter == task_type)
+                    .then(|| task_type.to_string())
+            }
+            (Self::SloBreached { slo: filter }, DomainEvent::SloBreached { slo, .. }) => filter
+                .as_ref()
+                .is_none_or(|filter| filter == slo)
+                .then(|| slo.clone()),
+            _ => None,
+        }
+    }
+
+    fn default_template(&self) -> &'static str {
+        match self {
+            Self::JobFailed => "Job {job_id} failed ({failed_tasks} dead tasks)",
+            Self::TaskDead { .. } => "Task {task_id} of type {task_type} died: {error}",
+            Self::SloBreached { .. } => "SLO {slo} breached: {message}",
+        }
+    }
+
+    fn default_group_template(&self) -> &'static str {
+        match self {
+            Self::JobFailed => "{count} jobs failed in the last {window}",
+            Self::TaskDead { .. } => "{count} tasks of type {task_type} died in the last {window}",
+            Self::SloBreached { .. } => "SLO {slo} breached {count} times in the last {window}",
+        }
+    }
+}
+
+/// NotificationRule は「どのイベントを・どこへ・どんな文面で」送るか
+pub struct NotificationRule {
+    name: String,
+    trigger: Trigger,
+    channel: Arc<dyn NotificationChannel>,
+    template: Option<String>,
+    group_template: Option<String>,
+    group_window: Option<Duration>,
+}
+
+impl NotificationRule {
+    /// デフォルト: まとめない、文面は trigger ごとの既定テンプレート
+    pub fn new(
+        name: impl Into<String>,
+        trigger: Trigger,
+        channel: Arc<dyn NotificationChannel>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            trigger,
+            channel,
+            template: None,
+            group_template: None,
+            group_window: None,
+        }
+    }
+
+    /// 1 件ごとの通知の件名テンプレート
+    pub fn with_template(mut self, template: impl Into<String>) -> Self {
+        self.template = Some(template.into());
+        self
+    }
+
+    /// まとめた通知の件名テンプレート
+    pub fn with_group_template(mut self, template: impl Into<String>) -> Self {
+        self.group_template = Some(template.into());
+        self
+    }
+
+    /// window ごとに通知をまとめる
+    pub fn with_group_window(mut self, window: Duration) -> Self {
+        self.group_window = Some(window);
+        self
+    }
+
+    fn render(&self, vars: &[(&'static str, String)], count: usize) -> Notification {
+        let template = if count > 1 {
+            self.group_template
+                .as_deref()
+                .unwrap_or(self.trigger.default_group_template())
+        } else {
+            self.template
+                .as_deref()
+                .unwrap_or(self.trigger.default_template())
+        };
+        let window = self.group_window.map(format_window).unwrap_or_default();
+        let mut vars = vars.to_vec();
+        vars.push(("rule", self.name.clone()));
+        vars.push(("count", count.to_string()));
+        vars.push(("window", window));
+
+        let subject = render(template, &vars);
+        let body = vars
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Notification { subject, body }
+    }
+}
+
+/// window 内のグループの状態
+struct Group {
+    opened_at: DateTime<Utc>,
+    /// 最初の通知以降に届いた件数
+    pending: usize,
+    /// 最後のイベントの値
+    vars: Vec<(&'static str, String)>,
+}
+
+/// NotificationRules はルールにマッチしたイベントを通知する EventSink
+///
+/// # 使用例
+/// ```ignore
+/// let slack: Arc<dyn NotificationChannel> = Arc::new(SlackChannel::new(transport, url));
+/// let rules = Arc::new(
+///     NotificationRules::new()
+///         .with_rule(
+///             NotificationRule::new("dead-tasks", Trigger::task_dead(), slack.clone())
+///                 .with_group_window(Duration::from_secs(300)),
+///         )
+///         .with_rule(NotificationRule::new("failed-jobs", Trigger::JobFailed, slack)),
+/// );
+/// let queue = InMemoryQueue::new(RetryPolicy::default_v1()).with_event_sink(rules.clone());
+/// tokio::spawn(async move { rules.run(shutdown_rx).await });
+/// ```
+pub struct NotificationRules {
+    rules: Vec<NotificationRule>,
+    clock: Arc<dyn Clock>,
+    flush_interval: Duration,
+    /// (ルールの index, グループのキー) → 状態
+    groups: Mutex<HashMap<(usize, String), Group>>,
+}
+
+impl Default for NotificationRules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotificationRules {
+    /// デフォルト: ルールなし、flush 間隔 1 秒
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            clock: Arc::new(SystemClock),
+            flush_interval: Duration::from_secs(1),
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_rule(mut self, rule: NotificationRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// `run()` が window を閉じに行く間隔
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// 閉じた window のまとめ通知を送る
+    pub async fn flush(&self) -> Result<(), EventSinkError> {
+        let outgoing = self.close_windows_at(self.clock.now());
+        self.send_all(outgoing).await
+    }
+
+    /// shutdown が通知されるまで定期的に `flush()` する（終了時にも 1 回）
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) {
+        let mut interval = tokio::time::interval(self.flush_interval);
+        while !*shutdown.borrow() {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.changed() => break,
+            }
+            if let Err(e) = self.flush().await {
+                eprintln!("[notification] {e}");
+            }
+        }
+        // 終了時は開いている window もすべて閉じる
+        let outgoing = self.close_windows_at(DateTime::<Utc>::MAX_UTC);
+        if let Err(e) = self.send_all(outgoing).await {
+            eprintln!("[notification] {e}");
+        }
+    }
+
+    /// イベントを受けて、すぐに送る通知を決める（ロック内、await しない）
+    fn accept_at(&self, event: &DomainEvent, now: DateTime<Utc>) -> Vec<(usize, Notification)> {
+        let mut outgoing = Vec::new();
+        let mut groups = self.groups.lock().unwrap();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let Some(key) = rule.trigger.group_key(event) else {
+                continue;
+            };
+            let vars = event_vars(event);
+            let Some(window) = rule.group_window else {
+                outgoing.push((index, rule.render(&vars, 1)));
+                continue;
+            };
+
+            let group = groups.get_mut(&(index, key.clone()));
+            match group {
+                Some(group) if now < group.opened_at + window => {
+                    group.pending += 1;
+                    group.vars = vars;
+                }
+                _ => {
+                    // 閉じ損ねた window のまとめを先に送る
+                    if let Some(closed) = groups.remove(&(index, key.clone()))
+                        && closed.pending > 0
+                    {
+                        outgoing.push((index, rule.render(&closed.vars, closed.pending)));
+                    }
+                    outgoing.push((index, rule.render(&vars, 1)));
+                    let group = Group {
+                        opened_at: now,
+                        pending: 0,
+                        vars,
+                    };
+                    groups.insert((index, key), group);
+                }
+            }
+        }
+        outgoing
+    }
+
+    /// `now` までに閉じた window を取り除き、まとめ通知を返す
+    fn close_windows_at(&self, now: DateTime<Utc>) -> Vec<(usize, Notification)> {
+        let mut outgoing = Vec::new();
+        let mut groups = self.groups.lock().unwrap();
+        groups.retain(|(index, _), group| {
+            let rule = &self.rules[*index];
+            let window = rule.group_window.unwrap_or_default();
+            if now < group.opened_at + window {
+                return true;
+            }
+            if group.pending > 0 {
+                outgoing.push((*index, rule.render(&group.vars, group.pending)));
+            }
+            false
+        });
+        outgoing
+    }
+
+    /// 通知を送る（ロックの外で）。失敗しても残りは送る
+    async fn send_all(&self, outgoing: Vec<(usize, Notification)>) -> Result<(), EventSinkError> {
+        let mut failures = Vec::new();
+        for (index, notification) in outgoing {
+            let rule = &self.rules[index];
+            if let Err(e) = rule.channel.send(&notification).await {
+                failures.push(format!("rule {}: {e}", rule.name));
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(EventSinkError::EmitFailed(failures.join("; ")))
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for NotificationRules {
+    async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError> {
+        let outgoing = self.accept_at(&event, self.clock.now());
+        self.send_all(outgoing).await
+    }
+}
+
+/// テンプレートで使えるイベントの値
+fn event_vars(event: &DomainEvent) -> Vec<(&'static str, String)> {
+    let mut vars = vec![("event", event.kind().to_string())];
+    match event {
+        DomainEvent::TaskDead {
+            task_id,
+            task_type,
+            job_id,
+            error,
+        } => {
+            vars.push(("task_id", task_id.to_string()));
+            vars.push(("task_type", task_type.to_string()));
+            vars.push((
+                "job_id",
+                job_id.map(|id| id.to_string()).unwrap_or_default(),
+            ));
+            vars.push(("error", error.clone()));
+        }
+        DomainEvent::JobFailed {
+            job_id,
+            failed_tasks,
+        } => {
+            vars.push(("job_id", job_id.to_string()));
+            vars.push(("failed_tasks", failed_tasks.to_string()));
+        }
+        DomainEvent::SloBreached { slo, message } => {
+            vars.push(("slo", slo.clone()));
+            vars.push(("message", message.clone()));
+        }
+        _ => {}
+    }
+    vars
+}
+
+/// `{name}` を値で置き換える（未知の名前はそのまま残す）
+fn render(template: &str, vars: &[(&'static str, String)]) -> String {
+    let mut rendered = template.to_string();
+    for (name, value) in vars {
+        rendered = rendered.replace(&format!("{{{name}}}"), value);
+    }
+    rendered
+}
+
+/// 300 秒 → "5m"
+fn format_window(window: Duration) -> String {
+    let secs = window.as_secs();
+    if secs >= 3600 && secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else if secs >= 60 && secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::domain::{JobId, TaskId};
+    use crate::ports::NotificationError;
+
+    #[derive(Default)]
+    struct Inbox(Mutex<Vec<Notification>>);
+
+    impl Inbox {
+        fn subjects(&self) -> Vec<String> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|n| n.subject.clone())
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl NotificationChannel for Inbox {
+        async fn send(&self, notification: &Notification) -> Result<(), NotificationError> {
+            self.0.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    fn task_dead(id: u128, task_type: &str) -> DomainEvent {
+        DomainEvent::TaskDead {
+            task_id: TaskId::new(id),
+            task_type: TaskType::new(task_type),
+            job_id: None,
+            error: "boom".to_string(),
+        }
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    async fn emit_at(rules: &NotificationRules, event: DomainEvent, now: DateTime<Utc>) {
+        let outgoing = rules.accept_at(&event, now);
+        rules.send_all(outgoing).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rules_match_by_trigger_and_render_templates() {
+        let inbox = Arc::new(Inbox::default());
+        let rules = NotificationRules::new()
+            .with_rule(NotificationRule::new(
+                "email-dead",
+                Trigger::TaskDead {
+                    task_type: Some(TaskType::new("email")),
+                },
+                inbox.clone(),
+            ))
+            .with_rule(
+                NotificationRule::new("jobs", Trigger::JobFailed, inbox.clone())
+                    .with_template("[{rule}] job {job_id}: {failed_tasks} dead"),
+            );
+
+        rules.emit(task_dead(1, "email")).await.unwrap();
+        rules.emit(task_dead(2, "report")).await.unwrap();
+        let job_id = JobId::new(7);
+        rules
+            .emit(DomainEvent::JobFailed {
+                job_id,
+                failed_tasks: 2,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            inbox.subjects(),
+            vec![
+                format!("Task {} of type email died: boom", TaskId::new(1)),
+                format!("[jobs] job {job_id}: 2 dead"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn grouping_sends_the_first_event_and_one_summary_per_window() {
+        let inbox = Arc::new(Inbox::default());
+        let rules = NotificationRules::new().with_rule(
+            NotificationRule::new("dead", Trigger::task_dead(), inbox.clone())
+                .with_group_window(Duration::from_secs(300)),
+        );
+
+        for i in 0..24 {
+            emit_at(&rules, task_dead(i, "x"), at(i as i64)).await;
+        }
+        emit_at(&rules, task_dead(100, "y"), at(10)).await;
+        assert_eq!(inbox.subjects().len(), 2); // first "x" and first "y"
+
+        // Window still open: nothing to flush
+        rules
+            .send_all(rules.close_windows_at(at(299)))
+            .await
+            .unwrap();
+        assert_eq!(inbox.subjects().len(), 2);
+
+        rules
+            .send_all(rules.close_windows_at(at(300)))
+            .await
+            .unwrap();
+        let subjects = inbox.subjects();
+        assert_eq!(subjects.len(), 3); // "y" had nothing more to report
+        assert_eq!(subjects[2], "23 tasks of type x died in the last 5m");
+
+        // The next event opens a new window and is sent right away
+        emit_at(&rules, task_dead(200, "x"), at(400)).await;
+        assert_eq!(inbox.subjects().len(), 4);
+    }
+}
@@ -0,0 +1,266 @@
+//! Observer - 読み取り専用の App（ダッシュボード・CLI 用）
+//!
+//! # 学習ポイント
+//! - 本番の TaskStore / DeliveryQueue に接続しても状態を変えない
+//!   （claim / complete / pop / outbox 操作を公開しない）
+//! - worker やループを起動しないので、何台起動しても処理に影響しない
+
+#![allow(deprecated)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::app::metrics::{MetricLabels, metric_samples, render_prometheus};
+use crate::app::watermark::WatermarkMonitor;
+use crate::domain::ids::{RunId, TaskId};
+use crate::observability::QueueCounts;
+use crate::ports::{
+    DeliveryQueue, MetricsError, MetricsSink, NoopMetricsSink, QueueError, StoreError, TaskStore,
+};
+use crate::queue::{TaskRecord, TaskState};
+
+/// Observer は status / metrics / watch だけを提供する App
+///
+/// `App::observer()` で作る。
+///
+/// # 使用例
+/// ```ignore
+/// let observer = App::observer(store, delivery);
+/// let metrics = observer.metrics("default").await?;
+/// let mut state = observer.watch("default", task_id);
+/// while state.changed().await.is_ok() {
+///     println!("{:?}", *state.borrow());
+/// }
+/// ```
+pub struct Observer {
+    store: Arc<dyn TaskStore>,
+    delivery: Arc<dyn DeliveryQueue>,
+    poll_interval: Duration,
+    /// export_metrics() の送り先
+    metrics_sink: Arc<dyn MetricsSink>,
+}
+
+/// ObserverMetrics は namespace 単位の指標
+#[derive(Debug, Clone)]
+pub struct ObserverMetrics {
+    /// TaskStore 上の状態別タスク数
+    pub counts: QueueCounts,
+    /// DeliveryQueue で配送待ちの task_id 数（数えられない実装は `None`）
+    pub delivery_backlog: Option<usize>,
+    /// いまの run（再起動・デプロイの前後を見分ける。区別しない実装は `None`）
+    pub run_id: Option<RunId>,
+}
+
+/// ObserverError は Observer の問い合わせエラー
+#[derive(Debug, thiserror::Error)]
+pub enum ObserverError {
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error(transparent)]
+    Queue(#[from] QueueError),
+    #[error(transparent)]
+    Metrics(#[from] MetricsError),
+}
+
+impl Observer {
+    /// 共有の TaskStore / DeliveryQueue に接続する（watch の poll 間隔は 1 秒）
+    pub fn new(store: Arc<dyn TaskStore>, delivery: Arc<dyn DeliveryQueue>) -> Self {
+        Self {
+            store,
+            delivery,
+            poll_interval: Duration::from_secs(1),
+            metrics_sink: Arc::new(NoopMetricsSink),
+        }
+    }
+
+    /// export_metrics() の送り先（通常は `App::metrics_sink()`）
+    pub fn with_metrics_sink(mut self, metrics_sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics_sink = metrics_sink;
+        self
+    }
+
+    /// watch() が TaskStore を読みに行く間隔
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// タスクの現在の状態（見つからなければ `None`）
+    pub async fn status(
+        &self,
+        ns: &str,
+        task_id: TaskId,
+    ) -> Result<Option<TaskRecord>, ObserverError> {
+        Ok(self.store.get_task(ns, task_id).await?)
+    }
+
+    /// 索引を張った payload のフィールドでタスクを探す（例: 顧客 42 のタスクすべて）
+    pub async fn find_tasks_by_field(
+        &self,
+        ns: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<Vec<TaskId>, ObserverError> {
+        Ok(self.store.find_tasks_by_field(ns, field, value).await?)
+    }
+
+    /// 状態別タスク数と配送待ちの数
+    pub async fn metrics(&self, ns: &str) -> Result<ObserverMetrics, ObserverError> {
+        Ok(ObserverMetrics {
+            counts: self.store.counts(ns).await?,
+            delivery_backlog: self.delivery.len(ns).await?,
+            run_id: self.store.run_id(ns).await?,
+        })
+    }
+
+    /// metrics() を Prometheus のテキスト形式で（ラベル数は `labels` で制限）
+    pub async fn prometheus(
+        &self,
+        ns: &str,
+        labels: &MetricLabels,
+    ) -> Result<String, ObserverError> {
+        Ok(render_prometheus(ns, &self.metrics(ns).await?, labels))
+    }
+
+    /// metrics() を MetricsSink に送る（ラベル数は `labels` で制限）
+    ///
+    /// push 型の sink（StatsD など）なら定期的に呼ぶ。
+    pub async fn export_metrics(
+        &self,
+        ns: &str,
+        labels: &MetricLabels,
+    ) -> Result<(), ObserverError> {
+        let samples = metric_samples(ns, &self.metrics(ns).await?, labels);
+        self.metrics_sink.emit(&samples).await?;
+        Ok(())
+    }
+
+    /// キューの深さのしきい値を監視する WatermarkMonitor（poll 間隔は watch() と同じ）
+    pub fn watermarks(&self) -> WatermarkMonitor {
+        WatermarkMonitor::new(Arc::clone(&self.store), self.poll_interval)
+    }
+
+    /// タスクの状態を poll して、変わるたびに通知する
+    ///
+    /// 値が `None` の間はタスクが見えていない。終端状態になるか受信側が
+    /// すべて drop されると poll を止める。
+    pub fn watch(&self, ns: &str, task_id: TaskId) -> watch::Receiver<Option<TaskState>> {
+        let (tx, rx) = watch::channel(None);
+        let store = Arc::clone(&self.store);
+        let ns = ns.to_string();
+        let poll_interval = self.poll_interval;
+
+        tokio::spawn(async move {
+            loop {
+                match store.get_task(&ns, task_id).await {
+                    Ok(record) => {
+                        let state = record.map(|record| record.state);
+                        tx.send_if_modified(|current| {
+                            let changed = *current != state;
+                            *current = state;
+                            changed
+                        });
+                        if state.is_some_and(|state| state.is_terminal()) {
+                            return;
+                        }
+                    }
+                    Err(e) => eprintln!("[observer] watch {task_id} failed: {e}"),
+                }
+                tokio::select! {
+                    _ = tx.closed() => return,
+                    _ = tokio::time::sleep(poll_interval) => {}
+                }
+            }
+        });
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::app::builder::App;
+    use crate::domain::{Outcome, TaskEnvelope, TaskType};
+    use crate::impls::InMemoryDeliveryQueue;
+    use crate::impls::v1_compat::QueueAsTaskStore;
+    use crate::queue::{InMemoryQueue, Queue, RetryPolicy};
+
+    #[tokio::test]
+    async fn observer_reads_without_taking_work() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let env = TaskEnvelope::new(TaskId::new(1), TaskType::new("test"), serde_json::json!({}));
+        queue.enqueue(env).await.unwrap();
+        let store = Arc::new(QueueAsTaskStore::new(queue.clone()));
+        let delivery = Arc::new(InMemoryDeliveryQueue::new());
+        delivery.push("default", TaskId::new(1)).await.unwrap();
+
+        let observer =
+            App::observer(store.clone(), delivery).with_poll_interval(Duration::from_millis(5));
+        let metrics = observer.metrics("default").await.unwrap();
+        assert_eq!(metrics.counts.queued, 1);
+        assert_eq!(metrics.delivery_backlog, Some(1));
+        // Still queued: the observer never leases
+        assert_eq!(queue.counts_by_state().await.unwrap().queued, 1);
+
+        // A worker (not the observer) picks the task up
+        let task_id = store
+            .pop("default", Duration::from_secs(1))
+            .await
+            .unwrap()
+            .unwrap();
+        let (lease, _) = store
+            .claim(
+                "default",
+                task_id,
+                "w1",
+                Duration::from_secs(30),
+                Utc::now(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut state = observer.watch("default", task_id);
+        state.changed().await.unwrap();
+        assert_eq!(*state.borrow(), Some(TaskState::Running));
+
+        store
+            .complete("default", lease, Outcome::success(), None, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(queue.counts_by_state().await.unwrap().succeeded, 1);
+    }
+
+    #[tokio::test]
+    async fn metrics_are_exported_to_the_sink_chosen_on_the_builder() {
+        use crate::app::builder::AppBuilder;
+        use crate::impls::PrometheusSink;
+
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let env = TaskEnvelope::new(TaskId::new(1), TaskType::new("test"), serde_json::json!({}));
+        queue.enqueue(env).await.unwrap();
+        let sink = Arc::new(PrometheusSink::new());
+        let app = AppBuilder::new()
+            .with_metrics_sink(sink.clone())
+            .build()
+            .unwrap();
+
+        let observer = App::observer(
+            Arc::new(QueueAsTaskStore::new(queue)),
+            Arc::new(InMemoryDeliveryQueue::new()),
+        )
+        .with_metrics_sink(app.metrics_sink());
+        observer
+            .export_metrics("default", &MetricLabels::default())
+            .await
+            .unwrap();
+
+        let text = sink.render();
+        assert!(text.contains("weaver_tasks{namespace=\"default\",state=\"queued\"} 1\n"));
+        assert!(text.contains("weaver_queue_depth{namespace=\"default\",task_type=\"test\"} 1\n"));
+    }
+}
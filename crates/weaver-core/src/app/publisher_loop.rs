@@ -1,21 +1,427 @@
 //! PublisherLoop - Outbox イベントの配送
 //!
-//! # 実装予定
-//! - **PR-8**: outbox→push→ack
+//! # 学習ポイント
+//! - transactional outbox: 状態遷移と「配送指示」を同じ TX で書き、配送は後から行う
+//! - ack / fail はバッチでまとめて 1 往復にする
+//! - backoff と dead への遷移は TaskStore（`fail_outbox`）側の方針に任せる
+//! - shutdown はバッチの途中では割り込まない（push 済みなのに ack されない、を減らす）
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::TimeDelta;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::domain::events::DomainEvent;
+use crate::domain::ids::EventId;
+use crate::ports::{Clock, DeliveryQueue, EventSink, NoopEventSink, StoreError, SystemClock, TaskStore};
+
+/// PublisherConfig は PublisherLoop の設定
+#[derive(Debug, Clone)]
+pub struct PublisherConfig {
+    /// 配送する namespace（デフォルト: "default"）
+    pub ns: String,
+    /// 1 回の pull で取るイベント数の上限（デフォルト: 100）
+    pub batch_size: usize,
+    /// outbox が空のときに次の pull まで待つ時間（デフォルト: 200ms）
+    pub poll_interval: Duration,
+    /// compaction の間隔（デフォルト: 60 秒）
+    pub compact_interval: Duration,
+    /// sent になってからこの時間が経ったイベントを compaction で消す（デフォルト: 1 時間）
+    pub retain_sent: Duration,
+    /// 1 回の compaction で消す件数の上限（デフォルト: 1000）
+    pub compact_limit: usize,
+}
+
+impl Default for PublisherConfig {
+    fn default() -> Self {
+        Self {
+            ns: "default".to_string(),
+            batch_size: 100,
+            poll_interval: Duration::from_millis(200),
+            compact_interval: Duration::from_secs(60),
+            retain_sent: Duration::from_secs(3600),
+            compact_limit: 1000,
+        }
+    }
+}
+
+/// PublishReport は 1 バッチの配送結果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PublishReport {
+    /// pull したイベント数
+    pub pulled: usize,
+    /// push して sent にした数
+    pub sent: usize,
+    /// push に失敗してリトライ予約（または dead）にした数
+    pub failed: usize,
+}
 
 /// PublisherLoop は PG の outbox を読んで DeliveryQueue に配送
 ///
 /// # フロー
-/// 1. TaskStore::pull_outbox() で pending イベントを取得
+/// 1. TaskStore::pull_outbox() で pending イベントをバッチ取得
 /// 2. DeliveryQueue::push() で配送
-/// 3. TaskStore::ack_outbox() で sent にマーク
-/// 4. エラー時は TaskStore::fail_outbox() でリトライ
+/// 3. TaskStore::ack_outbox_batch() で成功分をまとめて sent にマーク
+/// 4. 失敗分は TaskStore::fail_outbox_batch() でまとめてリトライ予約し、
+///    `DomainEvent::OutboxDeliveryFailed` を EventSink に送る
+/// 5. 一定間隔で TaskStore::compact_outbox() を呼び、古い sent を削除
+///
+/// # 使用例
+/// ```ignore
+/// let publisher = PublisherLoop::new(store, delivery)
+///     .with_config(PublisherConfig { batch_size: 500, ..PublisherConfig::default() });
+/// tokio::spawn(async move { publisher.run(shutdown_rx).await });
+/// ```
 pub struct PublisherLoop {
-    // TODO(PR-8): フィールド定義
+    store: Arc<dyn TaskStore>,
+    delivery: Arc<dyn DeliveryQueue>,
+    events: Arc<dyn EventSink>,
+    clock: Arc<dyn Clock>,
+    config: PublisherConfig,
 }
 
 impl PublisherLoop {
-    // TODO(PR-8): メソッド実装
-    // - new()
-    // - run()
+    /// ports を指定して作成（設定はデフォルト、Clock は SystemClock）
+    pub fn new(store: Arc<dyn TaskStore>, delivery: Arc<dyn DeliveryQueue>) -> Self {
+        Self {
+            store,
+            delivery,
+            events: Arc::new(NoopEventSink),
+            clock: Arc::new(SystemClock),
+            config: PublisherConfig::default(),
+        }
+    }
+
+    /// 設定を差し替える
+    pub fn with_config(mut self, config: PublisherConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Clock を差し替える（デフォルト: SystemClock）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 配送の失敗のイベントの送り先（デフォルト: NoopEventSink）
+    pub fn with_event_sink(mut self, events: Arc<dyn EventSink>) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn config(&self) -> &PublisherConfig {
+        &self.config
+    }
+
+    /// 1 バッチ分を配送する
+    ///
+    /// push の失敗はイベントごとに記録して続ける（1 件の失敗でバッチ全体を止めない）。
+    /// 失敗があれば `DomainEvent::OutboxDeliveryFailed` を EventSink に送る。
+    /// TaskStore の失敗は `Err` で返す。
+    pub async fn publish_once(&self) -> Result<PublishReport, StoreError> {
+        let ns = &self.config.ns;
+        let events = self
+            .store
+            .pull_outbox(ns, self.clock.now(), self.config.batch_size)
+            .await?;
+
+        let mut sent: Vec<EventId> = Vec::new();
+        let mut failures: Vec<(EventId, String)> = Vec::new();
+        for event in &events {
+            match self.delivery.push(ns, event.task_id).await {
+                Ok(()) => sent.push(event.event_id),
+                Err(e) => failures.push((event.event_id, e.to_string())),
+            }
+        }
+
+        let report = PublishReport {
+            pulled: events.len(),
+            sent: sent.len(),
+            failed: failures.len(),
+        };
+        let now = self.clock.now();
+        if !sent.is_empty() {
+            self.store.ack_outbox_batch(ns, &sent, now).await?;
+        }
+        if let Some((_, message)) = failures.last() {
+            let event = DomainEvent::OutboxDeliveryFailed {
+                ns: ns.clone(),
+                failed: failures.len(),
+                message: message.clone(),
+            };
+            if let Err(e) = self.events.emit(event).await {
+                eprintln!("[publisher] {e}");
+            }
+            self.store.fail_outbox_batch(ns, failures, now).await?;
+        }
+        Ok(report)
+    }
+
+    /// `retain_sent` より前に sent になったイベントを削除する
+    ///
+    /// # Returns
+    /// 削除した件数
+    pub async fn compact_once(&self) -> Result<usize, StoreError> {
+        let retain = TimeDelta::from_std(self.config.retain_sent).unwrap_or(TimeDelta::MAX);
+        let sent_before = self
+            .clock
+            .now()
+            .checked_sub_signed(retain)
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MIN_UTC);
+        self.store
+            .compact_outbox(&self.config.ns, sent_before, self.config.compact_limit)
+            .await
+    }
+
+    /// shutdown が通知されるまで配送し続ける
+    ///
+    /// バッチが満杯なら待たずに次を取りに行き（溜まった分を早く流す）、
+    /// そうでなければ `poll_interval` だけ待つ。待っている間だけ shutdown で抜ける。
+    /// TaskStore が outbox を持たなければ（`StoreError::Unsupported`）すぐに抜ける。
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) {
+        let mut last_compaction = Instant::now();
+        while !*shutdown.borrow() {
+            let full = match self.publish_once().await {
+                Ok(report) => report.pulled >= self.config.batch_size,
+                Err(e @ StoreError::Unsupported(_)) => {
+                    eprintln!("[publisher] {e}; nothing to publish");
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("[publisher] {e}");
+                    false
+                }
+            };
+
+            if last_compaction.elapsed() >= self.config.compact_interval {
+                if let Err(e) = self.compact_once().await {
+                    eprintln!("[publisher] compaction failed: {e}");
+                }
+                last_compaction = Instant::now();
+            }
+
+            if !full {
+                tokio::select! {
+                    _ = tokio::time::sleep(self.config.poll_interval) => {}
+                    _ = shutdown.changed() => break,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(deprecated)]
+
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use chrono::{DateTime, TimeZone, Utc};
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::domain::ids::TaskId;
+    use crate::domain::{Decision, Outcome, TaskEnvelope};
+    use crate::impls::{
+        BufferedVecEventSink, InMemoryDeliveryQueue, InMemoryOutbox, OutboxRetryPolicy,
+    };
+    use crate::ports::{CompleteResult, FixedClock, Lease, OutboxEvent, OutboxStatus, QueueError};
+    use crate::queue::TaskRecord;
+
+    /// outbox だけを持つ TaskStore
+    struct OutboxStore(Mutex<InMemoryOutbox>);
+
+    #[async_trait]
+    impl TaskStore for OutboxStore {
+        async fn claim(
+            &self,
+            _ns: &str,
+            _task_id: TaskId,
+            _worker_id: &str,
+            _lease_ttl: Duration,
+            _now: DateTime<Utc>,
+        ) -> Result<Option<(Lease, TaskEnvelope)>, StoreError> {
+            Ok(None)
+        }
+
+        async fn get_task(
+            &self,
+            _ns: &str,
+            _task_id: TaskId,
+        ) -> Result<Option<TaskRecord>, StoreError> {
+            Ok(None)
+        }
+
+        async fn complete(
+            &self,
+            _ns: &str,
+            lease: Lease,
+            _outcome: Outcome,
+            _decision: Option<Decision>,
+            _now: DateTime<Utc>,
+        ) -> Result<CompleteResult, StoreError> {
+            Err(StoreError::LeaseNotHeld(lease.task_id))
+        }
+
+        async fn pull_outbox(
+            &self,
+            ns: &str,
+            now: DateTime<Utc>,
+            limit: usize,
+        ) -> Result<Vec<OutboxEvent>, StoreError> {
+            Ok(self.0.lock().unwrap().pull(ns, now, limit))
+        }
+
+        async fn ack_outbox(
+            &self,
+            ns: &str,
+            event_id: EventId,
+            now: DateTime<Utc>,
+        ) -> Result<(), StoreError> {
+            self.0.lock().unwrap().ack_batch(ns, &[event_id], now)
+        }
+
+        async fn fail_outbox(
+            &self,
+            ns: &str,
+            event_id: EventId,
+            error: String,
+            now: DateTime<Utc>,
+        ) -> Result<(), StoreError> {
+            self.0
+                .lock()
+                .unwrap()
+                .fail_batch(ns, vec![(event_id, error)], now)
+        }
+
+        async fn compact_outbox(
+            &self,
+            ns: &str,
+            sent_before: DateTime<Utc>,
+            limit: usize,
+        ) -> Result<usize, StoreError> {
+            Ok(self.0.lock().unwrap().compact(ns, sent_before, limit))
+        }
+    }
+
+    /// `refused` の task_id だけ push に失敗する DeliveryQueue
+    struct RefusingDelivery {
+        inner: InMemoryDeliveryQueue,
+        refused: TaskId,
+    }
+
+    #[async_trait]
+    impl DeliveryQueue for RefusingDelivery {
+        async fn push(&self, ns: &str, task_id: TaskId) -> Result<(), QueueError> {
+            if task_id == self.refused {
+                return Err(QueueError::OperationFailed("connection reset".to_string()));
+            }
+            self.inner.push(ns, task_id).await
+        }
+
+        async fn pop(&self, ns: &str, timeout: Duration) -> Result<Option<TaskId>, QueueError> {
+            self.inner.pop(ns, timeout).await
+        }
+
+        async fn len(&self, ns: &str) -> Result<Option<usize>, QueueError> {
+            self.inner.len(ns).await
+        }
+    }
+
+    fn t(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    /// 作成順に並ぶ id（`Ulid::new()` は同じミリ秒内で順序が決まらない）
+    fn ulid(n: u64) -> Ulid {
+        Ulid::from_parts(n, 0)
+    }
+
+    #[tokio::test]
+    async fn pushes_a_batch_acks_the_sent_and_backs_off_the_failed() {
+        let refused = TaskId::from_ulid(ulid(2));
+        let mut outbox = InMemoryOutbox::new(OutboxRetryPolicy::default());
+        let event_ids: Vec<EventId> = (1..=3)
+            .map(|n| {
+                let event_id = EventId::from_ulid(ulid(n));
+                outbox.append("default", event_id, TaskId::from_ulid(ulid(n)), None, t(0))
+            })
+            .collect();
+        let store = Arc::new(OutboxStore(Mutex::new(outbox)));
+        let delivery = Arc::new(RefusingDelivery {
+            inner: InMemoryDeliveryQueue::new(),
+            refused,
+        });
+        let events = Arc::new(BufferedVecEventSink::new());
+        let publisher = PublisherLoop::new(store.clone(), delivery.clone())
+            .with_clock(Arc::new(FixedClock::new(t(0))))
+            .with_event_sink(events.clone())
+            .with_config(PublisherConfig {
+                batch_size: 2,
+                ..PublisherConfig::default()
+            });
+
+        let report = publisher.publish_once().await.unwrap();
+        assert_eq!(
+            report,
+            PublishReport {
+                pulled: 2,
+                sent: 1,
+                failed: 1
+            }
+        );
+        assert_eq!(delivery.len("default").await.unwrap(), Some(1));
+        assert!(matches!(
+            events.events()[..],
+            [DomainEvent::OutboxDeliveryFailed { failed: 1, .. }]
+        ));
+        {
+            let outbox = store.0.lock().unwrap();
+            assert_eq!(
+                outbox.get("default", event_ids[0]).unwrap().status,
+                OutboxStatus::Sent
+            );
+            let failed = outbox.get("default", event_ids[1]).unwrap();
+            assert_eq!(failed.status, OutboxStatus::Pending);
+            assert_eq!(failed.attempts, 1);
+            assert!(failed.available_at > t(0));
+        }
+
+        // The failed event waits out its backoff; only the third event is left
+        let report = publisher.publish_once().await.unwrap();
+        assert_eq!((report.pulled, report.sent), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn compaction_keeps_recently_sent_events() {
+        let mut outbox = InMemoryOutbox::default();
+        let old = outbox.append(
+            "default",
+            EventId::from_ulid(ulid(1)),
+            TaskId::from_ulid(ulid(1)),
+            None,
+            t(0),
+        );
+        let recent = outbox.append(
+            "default",
+            EventId::from_ulid(ulid(2)),
+            TaskId::from_ulid(ulid(2)),
+            None,
+            t(0),
+        );
+        outbox.ack_batch("default", &[old], t(0)).unwrap();
+        outbox.ack_batch("default", &[recent], t(3500)).unwrap();
+        let store = Arc::new(OutboxStore(Mutex::new(outbox)));
+        let publisher = PublisherLoop::new(store.clone(), Arc::new(InMemoryDeliveryQueue::new()))
+            .with_clock(Arc::new(FixedClock::new(t(3700))));
+
+        assert_eq!(publisher.compact_once().await.unwrap(), 1);
+        let outbox = store.0.lock().unwrap();
+        assert!(outbox.get("default", old).is_none());
+        assert!(outbox.get("default", recent).is_some());
+    }
 }
@@ -1,20 +1,241 @@
 //! ReaperLoop - Lease 期限切れの回収
 //!
-//! # 実装予定
-//! - **PR-11**: reap_expired_leases→再評価→outbox
+//! # 学習ポイント
+//! - worker が落ちても、lease の期限が切れれば正本（TaskStore）が実行権を取り戻す
+//! - ready に戻すか dead にするかは TaskStore の方針（attempt の上限）で決まる。
+//!   ReaperLoop は「いつ回収するか」だけを受け持つ
+//! - 回収は運用上の異常のサインなので、イベントと指標の両方に出す
+
+#![allow(deprecated)]
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::domain::events::DomainEvent;
+use crate::ports::{
+    Clock, EventSink, MetricSample, MetricsSink, NoopEventSink, NoopMetricsSink, ReapedLeases,
+    StoreError, SystemClock, TaskStore,
+};
+
+/// ReaperConfig は ReaperLoop の設定
+#[derive(Debug, Clone)]
+pub struct ReaperConfig {
+    /// 回収する namespace（デフォルト: "default"）
+    pub ns: String,
+    /// 回収の間隔（デフォルト: 5 秒）
+    pub interval: Duration,
+    /// 1 回の回収で扱う件数の上限（デフォルト: 100）
+    pub batch_size: usize,
+}
+
+impl Default for ReaperConfig {
+    fn default() -> Self {
+        Self {
+            ns: "default".to_string(),
+            interval: Duration::from_secs(5),
+            batch_size: 100,
+        }
+    }
+}
+
+/// ReaperStats は起動してからの回収の累計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReaperStats {
+    /// reap_expired_leases を呼んだ回数（失敗を除く）
+    pub passes: u64,
+    /// ready に戻したタスク数
+    pub requeued: u64,
+    /// dead にしたタスク数
+    pub dead: u64,
+}
 
 /// ReaperLoop は lease が期限切れになったタスクを回収して再配送
 ///
 /// # フロー
-/// 1. TaskStore::reap_expired_leases() で期限切れを取得
-/// 2. running → pending/ready へ遷移
-/// 3. ready になったら outbox に dispatch_task を追加
+/// 1. TaskStore::reap_expired_leases() で期限切れを回収
+///    （running → ready + outbox に dispatch_task、または dead。同一 TX）
+/// 2. 回収があれば `DomainEvent::LeasesReaped` を EventSink に送る
+/// 3. 累計を `weaver_leases_reaped{namespace, result}` として MetricsSink に送る
+///
+/// # 使用例
+/// ```ignore
+/// let reaper = ReaperLoop::new(store)
+///     .with_event_sink(sink)
+///     .with_config(ReaperConfig { interval: Duration::from_secs(1), ..ReaperConfig::default() });
+/// tokio::spawn(async move { reaper.run(shutdown_rx).await });
+/// ```
 pub struct ReaperLoop {
-    // TODO(PR-11): フィールド定義
+    store: Arc<dyn TaskStore>,
+    events: Arc<dyn EventSink>,
+    metrics: Arc<dyn MetricsSink>,
+    clock: Arc<dyn Clock>,
+    config: ReaperConfig,
+    stats: Mutex<ReaperStats>,
 }
 
 impl ReaperLoop {
-    // TODO(PR-11): メソッド実装
-    // - new()
-    // - run()
+    /// TaskStore を指定して作成（設定はデフォルト、イベント・指標は捨てる）
+    pub fn new(store: Arc<dyn TaskStore>) -> Self {
+        Self {
+            store,
+            events: Arc::new(NoopEventSink),
+            metrics: Arc::new(NoopMetricsSink),
+            clock: Arc::new(SystemClock),
+            config: ReaperConfig::default(),
+            stats: Mutex::new(ReaperStats::default()),
+        }
+    }
+
+    /// 設定を差し替える
+    pub fn with_config(mut self, config: ReaperConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Clock を差し替える（デフォルト: SystemClock）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 回収のイベントの送り先（デフォルト: NoopEventSink）
+    pub fn with_event_sink(mut self, events: Arc<dyn EventSink>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// 回収数の送り先（デフォルト: NoopMetricsSink）
+    pub fn with_metrics_sink(mut self, metrics: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn config(&self) -> &ReaperConfig {
+        &self.config
+    }
+
+    /// 起動してからの回収の累計
+    pub fn stats(&self) -> ReaperStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// 1 回分を回収する
+    ///
+    /// イベント・指標の送信の失敗は回収の結果を変えない（ログに出すだけ）。
+    pub async fn reap_once(&self) -> Result<ReapedLeases, StoreError> {
+        let ns = &self.config.ns;
+        let reaped = self
+            .store
+            .reap_expired_leases(ns, self.clock.now(), self.config.batch_size)
+            .await?;
+
+        let stats = {
+            let mut stats = self.stats.lock().unwrap();
+            stats.passes += 1;
+            stats.requeued += reaped.requeued.len() as u64;
+            stats.dead += reaped.dead.len() as u64;
+            *stats
+        };
+
+        if !reaped.is_empty() {
+            let event = DomainEvent::LeasesReaped {
+                ns: ns.clone(),
+                requeued: reaped.requeued.clone(),
+                dead: reaped.dead.clone(),
+            };
+            if let Err(e) = self.events.emit(event).await {
+                eprintln!("[reaper] {e}");
+            }
+        }
+        if let Err(e) = self.metrics.emit(&self.samples(stats)).await {
+            eprintln!("[reaper] {e}");
+        }
+        Ok(reaped)
+    }
+
+    fn samples(&self, stats: ReaperStats) -> Vec<MetricSample> {
+        [("requeued", stats.requeued), ("dead", stats.dead)]
+            .into_iter()
+            .map(|(result, count)| MetricSample {
+                name: "weaver_leases_reaped",
+                help: "Tasks whose expired lease was reclaimed, by result",
+                labels: vec![
+                    ("namespace", self.config.ns.clone()),
+                    ("result", result.to_string()),
+                ],
+                value: count as f64,
+            })
+            .collect()
+    }
+
+    /// shutdown が通知されるまで `interval` ごとに回収する
+    ///
+    /// 回収が `batch_size` に達したら、残りがありうるので待たずに続ける。
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            let full = match self.reap_once().await {
+                Ok(reaped) => reaped.len() >= self.config.batch_size,
+                Err(e) => {
+                    eprintln!("[reaper] {e}");
+                    false
+                }
+            };
+            if !full {
+                tokio::select! {
+                    _ = tokio::time::sleep(self.config.interval) => {}
+                    _ = shutdown.changed() => break,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::JobSpec;
+    use crate::impls::{BufferedVecEventSink, QueueAsTaskStore};
+    use crate::queue::{InMemoryQueue, Queue, RetryPolicy};
+
+    #[tokio::test]
+    async fn expired_leases_are_requeued_then_dead_and_reported() {
+        let queue = Arc::new(
+            InMemoryQueue::new(RetryPolicy::default_v1()).with_lease_ttl(Duration::from_millis(20)),
+        );
+        let events = Arc::new(BufferedVecEventSink::new());
+        let reaper = ReaperLoop::new(Arc::new(QueueAsTaskStore::new(queue.clone())))
+            .with_event_sink(events.clone());
+        let job = JobSpec::builder()
+            .task("slow")
+            .max_attempts(2)
+            .build()
+            .unwrap();
+        queue.submit_job(job).await.unwrap();
+
+        assert!(reaper.reap_once().await.unwrap().is_empty());
+
+        // Leased and never heard from again, twice
+        let mut reaped = Vec::new();
+        for _ in 0..2 {
+            let _stale = queue.lease().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(40)).await;
+            reaped.push(reaper.reap_once().await.unwrap());
+        }
+        assert_eq!((reaped[0].requeued.len(), reaped[0].dead.len()), (1, 0));
+        assert_eq!((reaped[1].requeued.len(), reaped[1].dead.len()), (0, 1));
+        assert_eq!(queue.counts_by_state().await.unwrap().dead, 1);
+        assert_eq!(
+            reaper.stats(),
+            ReaperStats {
+                passes: 3,
+                requeued: 1,
+                dead: 1
+            }
+        );
+
+        let kinds: Vec<_> = events.events().iter().map(DomainEvent::kind).collect();
+        assert_eq!(kinds, ["leases_reaped", "leases_reaped"]);
+    }
 }
@@ -1,17 +1,246 @@
 //! Runtime - 型付き Task API の表面
 //!
-//! 既存の runtime.rs を統合する予定
+//! # 学習ポイント
+//! - 呼び出し側は Task の型だけを渡す。task_type は `T::TYPE` から決まるので、
+//!   文字列の typo が起きない
+//! - payload は `PayloadCodec` で JSON にする（Handler 側の decode と対になる）。
+//!   `with_format` で形式を、`with_compression` で大きな payload の圧縮を選べる
+//! - 結果は Handler が返した `T::Output`（`Artifact::Output`）を `T` の型で読み戻す
+//!
+//! 実行側（handler の登録・ディスパッチ）は既存の runtime.rs を統合する予定
+
+use std::sync::Arc;
+
+use crate::domain::{OutcomeKind, PayloadSchema, SchemaError, TaskEnvelope, TaskId, TaskType};
+use crate::error::WeaverError;
+use crate::ports::Compressor;
+use crate::queue::Queue;
+use crate::typed::{
+    CodecError, JSON_CONTENT_TYPE, JsonFormat, PayloadCodec, PayloadFormat, Task, compress_payload,
+    encode_payload,
+};
 
 /// Runtime は型付き Task API を提供
 ///
-/// # 使用例（予定）
+/// # 使用例
 /// ```ignore
-/// runtime.enqueue_typed(MyTask { ... }).await?;
+/// let runtime = Runtime::new(queue.clone());
+/// runtime.submit(SendEmail { to: "a@example.com".into() }).await?;
+/// let receipt: Option<EmailReceipt> = runtime.result::<SendEmail>(task_id).await?;
 /// ```
 pub struct Runtime {
-    // TODO(Phase 2): 既存 runtime.rs から移行
+    queue: Arc<dyn Queue>,
+    /// submit() で payload を書く形式（既定は JSON）
+    format: Arc<dyn PayloadFormat>,
+    /// submit() で大きな payload を圧縮する Compressor としきい値（バイト）
+    compression: Option<(Arc<dyn Compressor>, usize)>,
+}
+
+/// RuntimeError は submit / result のエラー
+#[derive(Debug, thiserror::Error)]
+pub enum RuntimeError {
+    #[error(transparent)]
+    Codec(#[from] CodecError),
+
+    #[error("Queue operation failed: {0}")]
+    Queue(#[from] WeaverError),
+
+    /// `Task::payload_schema()` が使えない（キーワードの誤りなど）
+    #[error("Invalid payload schema for {task_type}: {error}")]
+    InvalidSchema {
+        task_type: &'static str,
+        error: SchemaError,
+    },
+
+    /// payload が `Task::payload_schema()` に合わない
+    #[error("Invalid payload for {task_type}: {error}")]
+    InvalidPayload {
+        task_type: &'static str,
+        error: SchemaError,
+    },
 }
 
 impl Runtime {
-    // TODO(Phase 2): メソッド実装
+    /// `queue` にタスクを投入する Runtime
+    pub fn new(queue: Arc<dyn Queue>) -> Self {
+        Self {
+            queue,
+            format: Arc::new(JsonFormat),
+            compression: None,
+        }
+    }
+
+    /// submit() の payload を `format` で書く（例: `MessagePackFormat`）
+    ///
+    /// 受け取る側は `TypedRegistry::register_format` で同じ形式を登録しておく。
+    pub fn with_format(mut self, format: Arc<dyn PayloadFormat>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// submit() の payload が `threshold` バイト以上なら `compressor` で圧縮する
+    ///
+    /// 受け取る側は `TypedRegistry::register_compressor` で同じ Compressor を登録しておく。
+    pub fn with_compression(mut self, compressor: Arc<dyn Compressor>, threshold: usize) -> Self {
+        self.compression = Some((compressor, threshold));
+        self
+    }
+
+    /// `task` を `T::TYPE` のタスクとして投入する
+    ///
+    /// `Queue::enqueue` は id を返さないため、戻り値はない（id は Queue が振る）。
+    pub async fn submit<T: Task>(&self, task: T) -> Result<(), RuntimeError> {
+        let payload = PayloadCodec::encode(&task)?;
+        if let Some(schema) = T::payload_schema() {
+            let schema =
+                PayloadSchema::new(schema).map_err(|error| RuntimeError::InvalidSchema {
+                    task_type: T::TYPE,
+                    error,
+                })?;
+            schema
+                .validate(&payload)
+                .map_err(|error| RuntimeError::InvalidPayload {
+                    task_type: T::TYPE,
+                    error,
+                })?;
+        }
+        let payload = encode_payload(self.format.as_ref(), payload)?;
+        // 仮の id（Queue が振り直す）
+        let mut envelope = TaskEnvelope::new(TaskId::new(0), TaskType::new(T::TYPE), payload);
+        if self.format.content_type() != JSON_CONTENT_TYPE {
+            envelope = envelope.with_content_type(self.format.content_type());
+        }
+        if let Some((compressor, threshold)) = &self.compression {
+            envelope = compress_payload(envelope, compressor.as_ref(), *threshold)?;
+        }
+        self.queue.enqueue(envelope).await?;
+        Ok(())
+    }
+
+    /// `task_id` の Handler が返した結果を `T::Output` として取り出す
+    ///
+    /// 最後の attempt が成功していなければ（未実行・失敗・リトライ待ち）`None`。
+    pub async fn result<T: Task>(
+        &self,
+        task_id: TaskId,
+    ) -> Result<Option<T::Output>, RuntimeError> {
+        let Some(outcome) = self.queue.task_outcome(task_id).await? else {
+            return Ok(None);
+        };
+        if outcome.kind != OutcomeKind::Success {
+            return Ok(None);
+        }
+        let output = outcome.output().cloned().ok_or_else(|| {
+            CodecError::DeserializeFailed(format!("task {task_id} succeeded without an output"))
+        })?;
+        Ok(Some(PayloadCodec::decode_output::<T>(output)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::{InMemoryQueue, RetryPolicy};
+    use crate::typed::task::TestTask;
+
+    #[tokio::test]
+    async fn submit_enqueues_the_encoded_task_under_its_type() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let runtime = Runtime::new(queue.clone());
+
+        runtime.submit(TestTask { value: 42 }).await.unwrap();
+
+        let lease = queue.lease().await.unwrap();
+        let envelope = lease.envelope();
+        assert_eq!(envelope.task_type(), &TaskType::new(TestTask::TYPE));
+        let task: TestTask = PayloadCodec::decode(envelope.payload().clone()).unwrap();
+        assert_eq!(task.value, 42);
+    }
+
+    #[tokio::test]
+    async fn submit_compresses_payloads_over_the_threshold() {
+        use crate::ports::compressor::RunLength;
+        use crate::typed::PayloadFormats;
+
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let runtime = Runtime::new(queue.clone()).with_compression(Arc::new(RunLength), 1);
+
+        runtime.submit(TestTask { value: 42 }).await.unwrap();
+
+        let lease = queue.lease().await.unwrap();
+        assert_eq!(lease.envelope().content_encoding(), Some("rle"));
+        let mut formats = PayloadFormats::new();
+        formats.register_compressor(RunLength);
+        let payload = formats.decode_payload(lease.envelope()).unwrap();
+        let task: TestTask = PayloadCodec::decode(payload).unwrap();
+        assert_eq!(task.value, 42);
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Transfer {
+        amount: i64,
+    }
+
+    impl Task for Transfer {
+        const TYPE: &'static str = "test.bank.transfer.v1";
+        type Output = ();
+
+        fn payload_schema() -> Option<serde_json::Value> {
+            Some(serde_json::json!({
+                "properties": { "amount": { "type": "integer", "exclusiveMinimum": 0 } }
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn submit_rejects_a_payload_that_does_not_match_the_task_schema() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let runtime = Runtime::new(queue.clone());
+
+        let error = runtime.submit(Transfer { amount: -5 }).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid payload for test.bank.transfer.v1: at /amount: -5 is less than or equal to the minimum of 0"
+        );
+        runtime.submit(Transfer { amount: 10 }).await.unwrap();
+        let lease = queue.lease().await.unwrap();
+        assert_eq!(lease.envelope().payload()["amount"], 10);
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, crate::typed::WeaverTask)]
+    #[task(type = "test.math.double.v1", output = i32)]
+    struct Double {
+        value: i32,
+    }
+
+    struct DoubleHandler;
+
+    #[async_trait::async_trait]
+    impl crate::typed::Handler<Double> for DoubleHandler {
+        async fn handle(&self, task: Double) -> Result<i32, crate::domain::WeaverError> {
+            Ok(task.value * 2)
+        }
+    }
+
+    #[tokio::test]
+    async fn result_reads_the_handler_output_back_as_its_type() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let runtime = Runtime::new(queue.clone());
+        let mut registry = crate::typed::TypedRegistry::new();
+        registry.register::<Double, _>(DoubleHandler).unwrap();
+
+        runtime.submit(Double { value: 21 }).await.unwrap();
+        let lease = queue.lease().await.unwrap();
+        let task_id = lease.task_context().await.unwrap().task_id().unwrap();
+        assert_eq!(runtime.result::<Double>(task_id).await.unwrap(), None);
+
+        let handler = registry.get(Double::TYPE).unwrap();
+        let outcome = handler
+            .handle_dyn(lease.envelope().payload().clone())
+            .await
+            .unwrap();
+        lease.succeed(outcome).await.unwrap();
+
+        assert_eq!(runtime.result::<Double>(task_id).await.unwrap(), Some(42));
+    }
 }
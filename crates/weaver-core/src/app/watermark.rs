@@ -0,0 +1,289 @@
+//! Watermark - キューの深さに応じたコールバック（オートスケール・負荷制限用）
+//!
+//! namespace 全体、または task_type ごとのキューの深さ（Queued + RetryScheduled）を
+//! Observer と同じく読み取り専用で poll し、しきい値をまたいだらコールバックを呼びます。
+//!
+//! # ヒステリシス
+//! high と low の 2 つのしきい値を持ち、
+//! - 深さが high 以上になったら `High` を 1 回だけ通知する
+//! - その後 low 以下に下がったら `Low` を 1 回だけ通知する
+//!
+//! low < 深さ < high の間を行き来しても通知しないので、バタつかない。
+
+#![allow(deprecated)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::watch;
+
+use crate::app::observer::ObserverError;
+use crate::domain::TaskType;
+use crate::observability::QueueCounts;
+use crate::ports::TaskStore;
+
+/// DepthScope は深さを数える範囲
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepthScope {
+    /// namespace のすべてのタスク
+    Namespace,
+    /// namespace 内の特定の task_type だけ
+    TaskType(TaskType),
+}
+
+impl DepthScope {
+    fn depth(&self, counts: &QueueCounts) -> usize {
+        match self {
+            Self::Namespace => counts.queued + counts.retry_scheduled,
+            Self::TaskType(task_type) => counts
+                .waiting_by_task_type
+                .get(task_type.as_str())
+                .copied()
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// WatermarkLevel はまたいだしきい値
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatermarkLevel {
+    /// high 以上になった（例: スケールアウト、受付制限）
+    High,
+    /// low 以下に戻った（例: スケールイン、制限解除）
+    Low,
+}
+
+/// WatermarkEvent はコールバックに渡す内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatermarkEvent {
+    pub ns: String,
+    pub scope: DepthScope,
+    pub level: WatermarkLevel,
+    /// 観測した深さ
+    pub depth: usize,
+    pub high: usize,
+    pub low: usize,
+}
+
+/// WatermarkCallback はしきい値をまたいだときに呼ばれる
+///
+/// 例: Kubernetes の Deployment をスケールする、投入側に負荷制限を伝える
+#[async_trait]
+pub trait WatermarkCallback: Send + Sync {
+    async fn crossed(&self, event: &WatermarkEvent);
+}
+
+/// Watermark は 1 組のしきい値とコールバック
+pub struct Watermark {
+    ns: String,
+    scope: DepthScope,
+    high: usize,
+    low: usize,
+    callback: Arc<dyn WatermarkCallback>,
+    /// High を通知済みで、まだ Low に戻っていない
+    above: Mutex<bool>,
+}
+
+impl Watermark {
+    /// namespace 全体の深さを見る
+    ///
+    /// # Panics
+    /// `low >= high` のとき（ヒステリシスの幅がない）
+    pub fn namespace(
+        ns: impl Into<String>,
+        high: usize,
+        low: usize,
+        callback: Arc<dyn WatermarkCallback>,
+    ) -> Self {
+        Self::new(ns.into(), DepthScope::Namespace, high, low, callback)
+    }
+
+    /// task_type ごとの深さを見る
+    ///
+    /// # Panics
+    /// `low >= high` のとき（ヒステリシスの幅がない）
+    pub fn task_type(
+        ns: impl Into<String>,
+        task_type: TaskType,
+        high: usize,
+        low: usize,
+        callback: Arc<dyn WatermarkCallback>,
+    ) -> Self {
+        Self::new(
+            ns.into(),
+            DepthScope::TaskType(task_type),
+            high,
+            low,
+            callback,
+        )
+    }
+
+    fn new(
+        ns: String,
+        scope: DepthScope,
+        high: usize,
+        low: usize,
+        callback: Arc<dyn WatermarkCallback>,
+    ) -> Self {
+        assert!(
+            low < high,
+            "watermark low ({low}) must be below high ({high})"
+        );
+        Self {
+            ns,
+            scope,
+            high,
+            low,
+            callback,
+            above: Mutex::new(false),
+        }
+    }
+
+    /// 深さを反映し、しきい値をまたいだらイベントを返す
+    fn observe(&self, depth: usize) -> Option<WatermarkEvent> {
+        let mut above = self.above.lock().unwrap();
+        let level = if !*above && depth >= self.high {
+            WatermarkLevel::High
+        } else if *above && depth <= self.low {
+            WatermarkLevel::Low
+        } else {
+            return None;
+        };
+        *above = level == WatermarkLevel::High;
+        Some(WatermarkEvent {
+            ns: self.ns.clone(),
+            scope: self.scope.clone(),
+            level,
+            depth,
+            high: self.high,
+            low: self.low,
+        })
+    }
+}
+
+/// WatermarkMonitor は TaskStore の深さを poll して Watermark を評価する
+///
+/// `Observer::watermarks()` で作る。
+///
+/// # 使用例
+/// ```ignore
+/// let monitor = observer
+///     .watermarks()
+///     .with_watermark(Watermark::namespace("default", 1000, 100, scaler));
+/// tokio::spawn(async move { monitor.run(shutdown_rx).await });
+/// ```
+pub struct WatermarkMonitor {
+    store: Arc<dyn TaskStore>,
+    watermarks: Vec<Watermark>,
+    poll_interval: Duration,
+}
+
+impl WatermarkMonitor {
+    pub fn new(store: Arc<dyn TaskStore>, poll_interval: Duration) -> Self {
+        Self {
+            store,
+            watermarks: Vec::new(),
+            poll_interval,
+        }
+    }
+
+    /// 監視する Watermark を追加する
+    pub fn with_watermark(mut self, watermark: Watermark) -> Self {
+        self.watermarks.push(watermark);
+        self
+    }
+
+    /// 1 回分の評価（namespace ごとに counts を 1 回だけ読む）
+    ///
+    /// 呼び出したコールバックの数を返す。
+    pub async fn check(&self) -> Result<usize, ObserverError> {
+        let mut counts: HashMap<&str, QueueCounts> = HashMap::new();
+        let mut events = Vec::new();
+        for watermark in &self.watermarks {
+            let ns = watermark.ns.as_str();
+            if !counts.contains_key(ns) {
+                counts.insert(ns, self.store.counts(ns).await?);
+            }
+            let depth = watermark.scope.depth(&counts[ns]);
+            if let Some(event) = watermark.observe(depth) {
+                events.push((Arc::clone(&watermark.callback), event));
+            }
+        }
+
+        let fired = events.len();
+        for (callback, event) in events {
+            callback.crossed(&event).await;
+        }
+        Ok(fired)
+    }
+
+    /// shutdown まで poll_interval ごとに check() する
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            if let Err(e) = self.check().await {
+                eprintln!("[watermark] check failed: {e}");
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(self.poll_interval) => {}
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{TaskEnvelope, TaskId};
+    use crate::impls::v1_compat::QueueAsTaskStore;
+    use crate::queue::{InMemoryQueue, Queue, RetryPolicy};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(WatermarkLevel, usize)>>);
+
+    #[async_trait]
+    impl WatermarkCallback for Recorder {
+        async fn crossed(&self, event: &WatermarkEvent) {
+            self.0.lock().unwrap().push((event.level, event.depth));
+        }
+    }
+
+    #[tokio::test]
+    async fn watermarks_fire_once_per_crossing() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let store = Arc::new(QueueAsTaskStore::new(queue.clone()));
+        let recorder = Arc::new(Recorder::default());
+        let monitor = WatermarkMonitor::new(store, Duration::from_millis(5)).with_watermark(
+            Watermark::task_type("default", TaskType::new("resize"), 3, 1, recorder.clone()),
+        );
+
+        let mut leases = Vec::new();
+        for depth in [1, 2, 3, 4, 3, 2, 1, 2, 3] {
+            let current = queue.counts_by_state().await.unwrap().queued;
+            for i in current..depth {
+                let env = TaskEnvelope::new(
+                    TaskId::new(i as u128),
+                    TaskType::new("resize"),
+                    serde_json::json!({}),
+                );
+                queue.enqueue(env).await.unwrap();
+            }
+            for _ in depth..current {
+                leases.push(queue.lease().await.unwrap());
+            }
+            monitor.check().await.unwrap();
+        }
+
+        // Moving between low and high does not fire again
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                (WatermarkLevel::High, 3),
+                (WatermarkLevel::Low, 1),
+                (WatermarkLevel::High, 3)
+            ]
+        );
+    }
+}
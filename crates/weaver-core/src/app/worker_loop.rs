@@ -1,23 +1,425 @@
 //! WorkerLoop - タスク実行ループ
 //!
-//! # 実装予定
-//! - **PR-10**: pop→claim→handle→decide→complete
+//! # 学習ポイント
+//! - DeliveryQueue の pop は「候補通知」に過ぎない。実行権は TaskStore::claim が決める
+//! - Handler の失敗は Outcome に変換する（インフラのエラーと業務の失敗を混ぜない）
+//! - Decider は成功時には呼ばない（v1 の `Decision` に成功を表す variant がないため）
+//! - Handler の実行中は別タスクで lease を延ばし続ける（長い Handler が reaper に回収されない）
+//! - v2 の TaskStore はイベントを出さないので、TaskStarted / TaskDead は worker が EventSink に送る
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{oneshot, watch};
+
+use crate::domain::events::DomainEvent;
+use crate::domain::{Outcome, OutcomeKind, TaskEnvelope, TaskId};
+use crate::ports::{
+    Clock, CompleteResult, Decider, DeliveryQueue, EventSink, Lease, NoopEventSink, StoreError,
+    SystemClock, TaskStore,
+};
+use crate::typed::TypedRegistry;
+
+/// WorkerConfig は WorkerLoop の設定
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// 処理する namespace（デフォルト: "default"）
+    pub ns: String,
+    /// claim 時に名乗る worker_id（デフォルト: "worker-0"）
+    pub worker_id: String,
+    /// claim で発行する lease の長さ（デフォルト: 30 秒）
+    ///
+    /// Handler の実行中は `lease_ttl / 3` ごとに延ばすので、Handler の長さの上限ではない。
+    pub lease_ttl: Duration,
+    /// 1 回の pop で待つ時間（デフォルト: 1 秒）。shutdown の反応もこの粒度になる
+    pub pop_timeout: Duration,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            ns: "default".to_string(),
+            worker_id: "worker-0".to_string(),
+            lease_ttl: Duration::from_secs(30),
+            pop_timeout: Duration::from_secs(1),
+        }
+    }
+}
 
 /// WorkerLoop はタスクを実行
 ///
 /// # フロー
 /// 1. DeliveryQueue::pop() で task_id 取得
 /// 2. TaskStore::claim() で lease 発行 + TaskEnvelope 取得
-/// 3. PayloadCodec で deserialize
-/// 4. Handler 実行 → Outcome
-/// 5. Decider 実行 → Decision
+/// 3. TypedRegistry で payload を戻し、Handler を選ぶ
+/// 4. Handler 実行 → Outcome（その間 `lease_ttl / 3` ごとに TaskStore::extend_lease()）
+/// 5. 失敗・分解の場合のみ Decider 実行 → Decision
 /// 6. TaskStore::complete() で状態更新・履歴記録・依存解放・outbox生成
+/// 7. claim したら `TaskStarted`、dead として確定したら `TaskDead` を EventSink に送る
+///
+/// # 使用例
+/// ```ignore
+/// let worker = WorkerLoop::new(store, delivery, Arc::new(registry), decider)
+///     .with_config(WorkerConfig { worker_id: "worker-1".into(), ..WorkerConfig::default() });
+/// tokio::spawn(async move { worker.run(shutdown_rx).await });
+/// ```
 pub struct WorkerLoop {
-    // TODO(PR-10): フィールド定義
+    store: Arc<dyn TaskStore>,
+    delivery: Arc<dyn DeliveryQueue>,
+    registry: Arc<TypedRegistry>,
+    decider: Arc<dyn Decider>,
+    events: Arc<dyn EventSink>,
+    clock: Arc<dyn Clock>,
+    config: WorkerConfig,
 }
 
 impl WorkerLoop {
-    // TODO(PR-10): メソッド実装
-    // - new()
-    // - run()
+    /// ports を指定して作成（設定はデフォルト、Clock は SystemClock）
+    pub fn new(
+        store: Arc<dyn TaskStore>,
+        delivery: Arc<dyn DeliveryQueue>,
+        registry: Arc<TypedRegistry>,
+        decider: Arc<dyn Decider>,
+    ) -> Self {
+        Self {
+            store,
+            delivery,
+            registry,
+            decider,
+            events: Arc::new(NoopEventSink),
+            clock: Arc::new(SystemClock),
+            config: WorkerConfig::default(),
+        }
+    }
+
+    /// 設定を差し替える
+    pub fn with_config(mut self, config: WorkerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Clock を差し替える（デフォルト: SystemClock）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// タスクの開始・dead のイベントの送り先（デフォルト: NoopEventSink）
+    pub fn with_event_sink(mut self, events: Arc<dyn EventSink>) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn config(&self) -> &WorkerConfig {
+        &self.config
+    }
+
+    /// タスクを 1 つ処理する
+    ///
+    /// # Returns
+    /// - `Ok(Some(result))`: タスクを処理し、`result` として確定した
+    /// - `Ok(None)`: timeout まで待っても候補がない、または claim できなかった
+    pub async fn run_once(&self) -> Result<Option<CompleteResult>, StoreError> {
+        match self.pop().await? {
+            Some(task_id) => self.process(task_id).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn pop(&self) -> Result<Option<TaskId>, StoreError> {
+        self.delivery
+            .pop(&self.config.ns, self.config.pop_timeout)
+            .await
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))
+    }
+
+    /// pop した task_id を claim → handle → decide → complete する
+    async fn process(&self, task_id: TaskId) -> Result<Option<CompleteResult>, StoreError> {
+        let ns = &self.config.ns;
+        // pop は候補通知に過ぎない。claim できなければ他の worker に譲る
+        let Some((lease, envelope)) = self
+            .store
+            .claim(
+                ns,
+                task_id,
+                &self.config.worker_id,
+                self.config.lease_ttl,
+                self.clock.now(),
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        self.emit(DomainEvent::TaskStarted {
+            task_id,
+            task_type: envelope.task_type().clone(),
+            attempt: lease.attempt,
+        })
+        .await;
+
+        // 延長の途中で止めない（TaskStore によっては途中で捨てると lease を失う）ので、
+        // abort ではなく stop を落として終わるのを待つ
+        let (stop, stopped) = oneshot::channel::<()>();
+        let heartbeat = tokio::spawn(keep_alive(
+            Arc::clone(&self.store),
+            Arc::clone(&self.clock),
+            self.config.clone(),
+            lease.clone(),
+            stopped,
+        ));
+        let outcome = self.execute(&envelope).await;
+        drop(stop);
+        let _ = heartbeat.await;
+
+        let (job_id, decision) =
+            if outcome.kind == OutcomeKind::Success && outcome.child_tasks.is_none() {
+                (None, None)
+            } else {
+                let record = self
+                    .store
+                    .get_task(ns, task_id)
+                    .await?
+                    .ok_or(StoreError::NotFound(task_id))?;
+                (record.job_id, Some(self.decider.decide(&record, &outcome)))
+            };
+
+        let error = outcome.reason.clone();
+        let result = self
+            .store
+            .complete(ns, lease, outcome, decision, self.clock.now())
+            .await?;
+        if result == CompleteResult::Dead {
+            self.emit(DomainEvent::TaskDead {
+                task_id,
+                task_type: envelope.task_type().clone(),
+                job_id,
+                error: error.unwrap_or_default(),
+            })
+            .await;
+        }
+        Ok(Some(result))
+    }
+
+    async fn emit(&self, event: DomainEvent) {
+        if let Err(e) = self.events.emit(event).await {
+            eprintln!("[{}] {}", self.config.worker_id, e);
+        }
+    }
+
+    /// Handler を選んで実行し、結果を Outcome にする
+    ///
+    /// - Handler がない・payload が読めない: 何度やっても同じなので `permanent_failure`
+    /// - Handler の `Err`: リトライしうる `failure`（Decider が決める）
+    async fn execute(&self, envelope: &TaskEnvelope) -> Outcome {
+        let task_type = envelope.task_type().as_str();
+        let Some(handler) = self.registry.get(task_type) else {
+            return Outcome::permanent_failure(format!(
+                "No handler registered for task type '{task_type}'"
+            ));
+        };
+        let payload = match self.registry.decode_payload(envelope) {
+            Ok(payload) => payload,
+            Err(e) => return Outcome::permanent_failure(e.to_string()),
+        };
+        match handler.handle_dyn(payload).await {
+            Ok(outcome) => outcome,
+            Err(e) => Outcome::failure(e.to_string()),
+        }
+    }
+
+    /// shutdown が通知されるまでタスクを処理し続ける
+    ///
+    /// 処理中のタスクは complete まで終えてから抜ける（lease を宙に浮かせない）。
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            // 待っている間だけ shutdown で中断する
+            let popped = tokio::select! {
+                popped = self.pop() => popped,
+                _ = shutdown.changed() => break,
+            };
+            let result = match popped {
+                Ok(Some(task_id)) => self.process(task_id).await,
+                Ok(None) => continue,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("[{}] {}", self.config.worker_id, e);
+            }
+        }
+    }
+}
+
+/// `stop` が落とされるまで `lease_ttl / 3` ごとに lease を延ばす
+///
+/// 延長に失敗したら諦める（lease を失っていれば complete が `LeaseNotHeld` になる）。
+async fn keep_alive(
+    store: Arc<dyn TaskStore>,
+    clock: Arc<dyn Clock>,
+    config: WorkerConfig,
+    lease: Lease,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let interval = config.lease_ttl / 3;
+    if interval.is_zero() {
+        return;
+    }
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = &mut stop => return,
+        }
+        let extended = store
+            .extend_lease(&config.ns, &lease, config.lease_ttl, clock.now())
+            .await;
+        if let Err(e) = extended {
+            eprintln!(
+                "[{}] stopped extending the lease of {}: {e}",
+                config.worker_id, lease.task_id
+            );
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(deprecated)]
+
+    use super::*;
+    use crate::app::Runtime;
+    use crate::domain::{DefaultDecider, TaskType, WeaverError};
+    use crate::impls::QueueAsTaskStore;
+    use crate::queue::{InMemoryQueue, Queue, RetryPolicy};
+    use crate::typed::Handler;
+
+    #[derive(serde::Serialize, serde::Deserialize, crate::typed::WeaverTask)]
+    #[task(type = "test.math.halve.v1", output = i32)]
+    struct Halve {
+        value: i32,
+    }
+
+    struct HalveHandler;
+
+    #[async_trait::async_trait]
+    impl Handler<Halve> for HalveHandler {
+        async fn handle(&self, task: Halve) -> Result<i32, WeaverError> {
+            if task.value % 2 != 0 {
+                return Err(WeaverError::new(format!("{} is odd", task.value)));
+            }
+            Ok(task.value / 2)
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, crate::typed::WeaverTask)]
+    #[task(type = "test.time.nap.v1", output = ())]
+    struct Nap {
+        millis: u64,
+    }
+
+    struct NapHandler;
+
+    #[async_trait::async_trait]
+    impl Handler<Nap> for NapHandler {
+        async fn handle(&self, task: Nap) -> Result<(), WeaverError> {
+            tokio::time::sleep(Duration::from_millis(task.millis)).await;
+            Ok(())
+        }
+    }
+
+    fn setup() -> (Arc<InMemoryQueue>, WorkerLoop) {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let adapter = Arc::new(QueueAsTaskStore::new(queue.clone()));
+        let mut registry = TypedRegistry::new();
+        registry.register::<Halve, _>(HalveHandler).unwrap();
+        let worker = WorkerLoop::new(
+            adapter.clone(),
+            adapter,
+            Arc::new(registry),
+            Arc::new(DefaultDecider::default_v1()),
+        )
+        .with_config(WorkerConfig {
+            pop_timeout: Duration::from_millis(20),
+            ..WorkerConfig::default()
+        });
+        (queue, worker)
+    }
+
+    #[tokio::test]
+    async fn handler_output_is_recorded_and_failures_go_through_the_decider() {
+        let (queue, worker) = setup();
+        let runtime = Runtime::new(queue.clone());
+
+        runtime.submit(Halve { value: 42 }).await.unwrap();
+        assert_eq!(
+            worker.run_once().await.unwrap(),
+            Some(CompleteResult::Succeeded)
+        );
+        let task_id = queue.get_all_attempts().await[0].task_id;
+        assert_eq!(runtime.result::<Halve>(task_id).await.unwrap(), Some(21));
+
+        runtime.submit(Halve { value: 7 }).await.unwrap();
+        assert_eq!(
+            worker.run_once().await.unwrap(),
+            Some(CompleteResult::RetryScheduled)
+        );
+        assert_eq!(worker.run_once().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn unknown_task_type_goes_dead_without_retries() {
+        let (queue, worker) = setup();
+        queue
+            .enqueue(TaskEnvelope::new(
+                TaskId::new(0),
+                TaskType::new("test.math.unknown.v1"),
+                serde_json::json!({}),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(worker.run_once().await.unwrap(), Some(CompleteResult::Dead));
+        assert_eq!(queue.counts_by_state().await.unwrap().dead, 1);
+    }
+
+    #[tokio::test]
+    async fn lease_is_extended_while_a_handler_outlives_its_ttl() {
+        let ttl = Duration::from_millis(60);
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()).with_lease_ttl(ttl));
+        let adapter = Arc::new(QueueAsTaskStore::new(queue.clone()));
+        let mut registry = TypedRegistry::new();
+        registry.register::<Nap, _>(NapHandler).unwrap();
+        let worker = WorkerLoop::new(
+            adapter.clone(),
+            adapter,
+            Arc::new(registry),
+            Arc::new(DefaultDecider::default_v1()),
+        )
+        .with_config(WorkerConfig {
+            lease_ttl: ttl,
+            pop_timeout: Duration::from_millis(20),
+            ..WorkerConfig::default()
+        });
+        // A reaper that would take the task back as soon as the lease lapses
+        let reaper = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                let mut reaped = 0;
+                for _ in 0..40 {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    reaped += queue.reap_expired_leases().await.unwrap().len();
+                }
+                reaped
+            }
+        });
+
+        Runtime::new(queue.clone())
+            .submit(Nap { millis: 250 })
+            .await
+            .unwrap();
+        assert_eq!(
+            worker.run_once().await.unwrap(),
+            Some(CompleteResult::Succeeded)
+        );
+        assert_eq!(reaper.await.unwrap(), 0);
+    }
 }
@@ -2,9 +2,10 @@
 
 use std::time::Instant;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::ids::{AttemptId, TaskId};
+use super::ids::{AttemptId, JobId, TaskId};
 use super::outcome::{Artifact, Outcome};
 
 /// A single execution attempt of a task.
@@ -31,6 +32,13 @@ pub struct AttemptRecord {
     /// The result of this attempt.
     pub outcome: Outcome,
 
+    /// Version of the app that ran this attempt (see `InMemoryQueue::with_app_version`).
+    ///
+    /// When retries of a task disagree, a different version means a deploy
+    /// happened in between.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_version: Option<String>,
+
     /// When this attempt started (not serialized in v1).
     #[serde(skip_serializing, skip_deserializing, default = "Instant::now")]
     pub started_at: Instant,
@@ -56,6 +64,7 @@ impl AttemptRecord {
             action,
             observation,
             outcome,
+            app_version: None,
             started_at: Instant::now(),
             completed_at: Instant::now(),
         }
@@ -113,3 +122,42 @@ impl DecisionRecord {
         }
     }
 }
+
+/// What an annotation is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationTarget {
+    Task(TaskId),
+    Job(JobId),
+}
+
+/// A free-form note left by an operator.
+///
+/// Annotations are stored next to decisions and never affect scheduling;
+/// they carry context for whoever looks at the task next
+/// (e.g. "retried after fixing API key").
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub target: AnnotationTarget,
+    pub author: String,
+    pub text: String,
+
+    /// Wall-clock time, so the note still makes sense when read later.
+    pub annotated_at: DateTime<Utc>,
+}
+
+impl Annotation {
+    /// Create an annotation timestamped now.
+    pub fn new(
+        target: AnnotationTarget,
+        author: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Self {
+        Self {
+            target,
+            author: author.into(),
+            text: text.into(),
+            annotated_at: Utc::now(),
+        }
+    }
+}
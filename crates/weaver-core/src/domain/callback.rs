@@ -0,0 +1,96 @@
+//! Result callbacks: report a job's / task's final state to the submitter.
+//!
+//! A submitter attaches a `Callback` to a `JobSpec` or `TaskEnvelope`; when the
+//! job/task reaches a terminal state the queue sends a `CallbackPayload`, so
+//! external orchestrators don't need to poll `get_status()`.
+
+use serde::{Deserialize, Serialize};
+
+use super::{JobId, JobStateView, Outcome, TaskId, TaskType};
+use crate::queue::TaskState;
+
+/// Where the final state is reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Callback {
+    /// POST the payload as JSON to `url` (signed when `secret` is set).
+    Webhook {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        secret: Option<String>,
+    },
+
+    /// Enqueue a task of `task_type` with the payload as its payload.
+    Task { task_type: TaskType },
+}
+
+impl Callback {
+    /// Unsigned webhook.
+    pub fn webhook(url: impl Into<String>) -> Self {
+        Self::Webhook {
+            url: url.into(),
+            secret: None,
+        }
+    }
+
+    /// Webhook signed with HMAC-SHA256 over `secret`.
+    pub fn signed_webhook(url: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self::Webhook {
+            url: url.into(),
+            secret: Some(secret.into()),
+        }
+    }
+
+    /// Callback task.
+    pub fn task(task_type: TaskType) -> Self {
+        Self::Task { task_type }
+    }
+}
+
+/// Final state sent to a callback.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "subject", rename_all = "snake_case")]
+pub enum CallbackPayload {
+    /// A task reached Succeeded, Dead or Cancelled.
+    Task {
+        task_id: TaskId,
+        job_id: Option<JobId>,
+        task_type: TaskType,
+        state: TaskState,
+
+        /// Outcome of the last attempt (None: the task never reported one,
+        /// e.g. cancelled or reaped).
+        outcome: Option<Box<Outcome>>,
+
+        /// Last error recorded on the task.
+        error: Option<String>,
+    },
+
+    /// Every task of a job (including decomposed children) is terminal.
+    Job {
+        job_id: JobId,
+        state: JobStateView,
+        succeeded_tasks: usize,
+        failed_tasks: usize,
+        cancelled_tasks: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callback_roundtrip_json() {
+        let callback = Callback::signed_webhook("https://example.com/hook", "s3cret");
+        let json = serde_json::to_value(&callback).unwrap();
+        assert_eq!(json["kind"], "webhook");
+        assert_eq!(serde_json::from_value::<Callback>(json).unwrap(), callback);
+
+        let json = serde_json::json!({"kind": "task", "task_type": "on_done"});
+        assert_eq!(
+            serde_json::from_value::<Callback>(json).unwrap(),
+            Callback::task(TaskType::new("on_done"))
+        );
+    }
+}
@@ -5,7 +5,7 @@
 
 use std::time::Duration;
 
-use super::{Outcome, spec::TaskSpec};
+use super::{Outcome, OutcomeKind, TaskId, spec::TaskSpec};
 use crate::queue::{RetryPolicy, TaskRecord};
 
 /// The next action to take for a task.
@@ -25,6 +25,21 @@ pub enum Decision {
         child_tasks: Vec<TaskSpec>,
         reason: String,
     },
+
+    /// Make `task` wait for `depends_on` before it runs again.
+    ///
+    /// `task` must be the task being decided. The queue rejects the edge
+    /// (and marks the task dead) if it would close a dependency cycle.
+    AddDependency {
+        task: TaskId,
+        depends_on: TaskId,
+        reason: String,
+    },
+
+    /// Park the task until it is unblocked (e.g. it needs missing input).
+    ///
+    /// The attempt does not count against the retry budget.
+    Block { reason: String },
 }
 
 /// Trait for deciding the next action based on task state and outcome.
@@ -33,8 +48,16 @@ pub enum Decision {
 /// they return the next action without side effects.
 ///
 /// Phase 4-1: weaver-core provides DefaultDecider (retry/budget logic)
-/// Future: Users can implement custom Deciders (AI agents, domain logic, etc.)
+///
+/// Custom Deciders (AI agents, domain logic, etc.) are injected with
+/// `InMemoryQueue::with_decider` (failures reported via `fail()`) and
+/// `WorkerGroup::spawn` (outcomes reported via `complete()`).
 pub trait Decider: Send + Sync {
+    /// Name recorded in the context of every DecisionRecord this decider produces.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
     /// Decide the next action for a task based on its current state and outcome.
     ///
     /// # Arguments
@@ -50,7 +73,8 @@ pub trait Decider: Send + Sync {
 ///
 /// Implements attempt-based retry logic with exponential backoff:
 /// - Retry if attempts < max_attempts
-/// - Mark dead if attempts >= max_attempts
+/// - Mark dead if attempts >= max_attempts, or at once if the outcome is not
+///   retryable (`Outcome::permanent_failure`)
 /// - Use RetryPolicy for delay calculation
 ///
 /// This is a pure function implementation - no side effects, no state mutation.
@@ -74,12 +98,39 @@ impl DefaultDecider {
 }
 
 impl Decider for DefaultDecider {
+    fn name(&self) -> &str {
+        "default"
+    }
+
     fn decide(&self, task: &TaskRecord, outcome: &Outcome) -> Decision {
         if let Some(child_tasks) = &outcome.child_tasks {
             Decision::Decompose {
                 child_tasks: child_tasks.clone(),
                 reason: "Decomposing task into child tasks".to_string(),
             }
+        } else if let Some(depends_on) = outcome.blocked_on {
+            Decision::AddDependency {
+                task: task.envelope.task_id(),
+                depends_on,
+                reason: outcome
+                    .reason
+                    .clone()
+                    .unwrap_or_else(|| format!("Blocked on task {}", depends_on)),
+            }
+        } else if outcome.kind == OutcomeKind::Blocked {
+            Decision::Block {
+                reason: outcome
+                    .reason
+                    .clone()
+                    .unwrap_or_else(|| "Blocked".to_string()),
+            }
+        } else if !outcome.is_retryable() {
+            Decision::MarkDead {
+                reason: format!(
+                    "Permanent failure: {}",
+                    outcome.reason.as_deref().unwrap_or("not retryable")
+                ),
+            }
         } else if task.attempts >= task.max_attempts {
             Decision::MarkDead {
                 reason: format!(
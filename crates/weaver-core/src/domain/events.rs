@@ -1,25 +1,172 @@
 //! Events - ドメインイベント
 //!
-//! # 実装予定
-//! - v2 最小: 基本的なイベント定義
-//! - 将来: EventSink への送信
+//! EventSink（ports::event_sink）に送られる。タスクのライフサイクル
+//! （TaskEnqueued → TaskStarted → AttemptFinished → ... → JobCompleted）は
+//! InMemoryQueue が、worker の異常は supervisor が emit する。
+
+use serde::Serialize;
+
+use super::{AttemptId, JobId, JobStateView, OutcomeKind, TaskId, TaskType};
 
 /// DomainEvent はドメインで発生したイベント
 ///
-/// # イベント種類（予定）
-/// - TaskCreated
-/// - TaskClaimed
-/// - TaskCompleted
-/// - TaskFailed
-/// - JobCompleted
-#[derive(Debug, Clone)]
+/// JSON では `{"event": "task_enqueued", ...}` の形になる（`event` は `kind()`）。
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum DomainEvent {
-    // TODO(v2): イベント定義
-    // TaskCreated { ... },
-    // TaskClaimed { ... },
-    // TaskCompleted { ... },
+    /// タスクが投入された（enqueue / submit_job / 子タスク / コールバック）
+    TaskEnqueued {
+        task_id: TaskId,
+        task_type: TaskType,
+        job_id: Option<JobId>,
+    },
+    /// worker がタスクを lease した
+    TaskStarted {
+        task_id: TaskId,
+        task_type: TaskType,
+        /// 何回目の attempt か（1 から）
+        attempt: u32,
+    },
+    /// attempt が終わった（成功・失敗・Block など）
+    AttemptFinished {
+        task_id: TaskId,
+        task_type: TaskType,
+        attempt_id: AttemptId,
+        outcome: OutcomeKind,
+    },
+    /// Decider や operator の判断が記録された
+    DecisionMade {
+        task_id: TaskId,
+        policy: String,
+        decision: String,
+    },
+    /// ジョブの全タスクが終了した（失敗を含む。失敗なら JobFailed も出る）
+    JobCompleted {
+        job_id: JobId,
+        state: JobStateView,
+        succeeded_tasks: usize,
+        failed_tasks: usize,
+        cancelled_tasks: usize,
+    },
+    /// Worker がパニックを繰り返している（supervisor が報告）
+    WorkerCrashLoop {
+        worker_id: usize,
+        /// これまでのクラッシュ回数
+        crashes: u32,
+        /// 直近のパニックメッセージ
+        message: String,
+    },
+    /// Worker が再起動上限に達したため supervisor が再起動を諦めた
+    WorkerGaveUp {
+        worker_id: usize,
+        /// window 内で行った再起動回数
+        restarts: u32,
+        /// 再起動回数を数える時間窓
+        window: std::time::Duration,
+        /// 直近の失敗メッセージ
+        message: String,
+    },
+    /// 結果 webhook の送信をリトライしても届けられなかった
+    WebhookDeliveryFailed {
+        url: String,
+        /// 送信を試みた回数
+        attempts: u32,
+        /// 最後のエラー
+        message: String,
+    },
+    /// タスクが Dead になった（リトライ上限・lease 回収）
+    TaskDead {
+        task_id: TaskId,
+        task_type: TaskType,
+        job_id: Option<JobId>,
+        /// 最後のエラー
+        error: String,
+    },
+    /// ジョブの全タスクが終了し、Dead のタスクがある
+    JobFailed { job_id: JobId, failed_tasks: usize },
+    /// lease の期限が切れたタスクを reaper が回収した（worker が落ちた・詰まった可能性）
+    LeasesReaped {
+        ns: String,
+        /// ready に戻したタスク
+        requeued: Vec<TaskId>,
+        /// attempt の上限で dead にしたタスク
+        dead: Vec<TaskId>,
+    },
+    /// GC が期限切れの artifact を削除した
+    ArtifactsCollected {
+        ns: String,
+        /// 削除した artifact の数
+        deleted: usize,
+        /// 削除した本体の合計バイト数
+        reclaimed_bytes: u64,
+    },
+    /// outbox のイベントを DeliveryQueue に送れなかった（backoff 後に再送される）
+    OutboxDeliveryFailed {
+        ns: String,
+        /// 送れなかったイベントの数
+        failed: usize,
+        /// 最後のエラー
+        message: String,
+    },
+    /// GC のパスが失敗した（期限切れの一覧が取れない・削除に失敗した artifact がある）
+    ArtifactGcFailed {
+        ns: String,
+        /// 削除に失敗した artifact の数（一覧の取得に失敗したなら 0）
+        failed: usize,
+        /// 最後のエラー
+        message: String,
+    },
+    /// SLO を外れた（監視側が emit する）
+    SloBreached {
+        /// SLO の名前（例: `"email.p99_latency"`）
+        slo: String,
+        message: String,
+    },
 }
 
 impl DomainEvent {
-    // TODO(v2): メソッド実装
+    /// イベント種別の名前（ルールのマッチやログ用）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::TaskEnqueued { .. } => "task_enqueued",
+            Self::TaskStarted { .. } => "task_started",
+            Self::AttemptFinished { .. } => "attempt_finished",
+            Self::DecisionMade { .. } => "decision_made",
+            Self::JobCompleted { .. } => "job_completed",
+            Self::WorkerCrashLoop { .. } => "worker_crash_loop",
+            Self::WorkerGaveUp { .. } => "worker_gave_up",
+            Self::WebhookDeliveryFailed { .. } => "webhook_delivery_failed",
+            Self::TaskDead { .. } => "task_dead",
+            Self::JobFailed { .. } => "job_failed",
+            Self::LeasesReaped { .. } => "leases_reaped",
+            Self::ArtifactsCollected { .. } => "artifacts_collected",
+            Self::OutboxDeliveryFailed { .. } => "outbox_delivery_failed",
+            Self::ArtifactGcFailed { .. } => "artifact_gc_failed",
+            Self::SloBreached { .. } => "slo_breached",
+        }
+    }
+
+    /// イベントのタスクの TaskType（タスクのイベントで、型を持つものだけ）
+    pub fn task_type(&self) -> Option<&TaskType> {
+        match self {
+            Self::TaskEnqueued { task_type, .. }
+            | Self::TaskStarted { task_type, .. }
+            | Self::AttemptFinished { task_type, .. }
+            | Self::TaskDead { task_type, .. } => Some(task_type),
+            _ => None,
+        }
+    }
+
+    /// 運用上の問題を表すイベントか（ログの warn など）
+    pub fn is_problem(&self) -> bool {
+        !matches!(
+            self,
+            Self::TaskEnqueued { .. }
+                | Self::TaskStarted { .. }
+                | Self::AttemptFinished { .. }
+                | Self::DecisionMade { .. }
+                | Self::JobCompleted { .. }
+                | Self::ArtifactsCollected { .. }
+        )
+    }
 }
@@ -136,6 +136,36 @@ impl IdMarker for Attempt {
     }
 }
 
+/// Outbox イベントのマーカー型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Event {}
+
+impl IdMarker for Event {
+    fn prefix() -> &'static str {
+        "event-"
+    }
+}
+
+/// Run（プロセスの 1 回の起動）のマーカー型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Run {}
+
+impl IdMarker for Run {
+    fn prefix() -> &'static str {
+        "run-"
+    }
+}
+
+/// Artifact（Blob に置いた巨大データ）のマーカー型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Artifact {}
+
+impl IdMarker for Artifact {
+    fn prefix() -> &'static str {
+        "artifact-"
+    }
+}
+
 // ========================================
 // Type Alias（使いやすさのため）
 // ========================================
@@ -149,6 +179,17 @@ pub type TaskId = Id<Task>;
 /// Identifier of an Attempt (one execution try of a Task).
 pub type AttemptId = Id<Attempt>;
 
+/// Identifier of an outbox event (one delivery instruction).
+pub type EventId = Id<Event>;
+
+/// Identifier of a run (one start of a queue / app process).
+///
+/// ULID-based, so a later run's id sorts after an earlier one's.
+pub type RunId = Id<Run>;
+
+/// Identifier of an artifact (a blob kept in the ArtifactStore).
+pub type ArtifactId = Id<Artifact>;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::queue::TaskState;
 
-use super::attempt::{AttemptRecord, DecisionRecord};
+use super::attempt::{Annotation, AttemptRecord, DecisionRecord};
 use super::ids::{JobId, TaskId};
 use super::spec::JobSpec;
 
@@ -113,12 +113,23 @@ impl JobRecord {
                 )
             }) {
                 JobState::Running
+            } else if task_states
+                .iter()
+                .any(|(_, state)| *state == TaskState::Blocked)
+            {
+                // Nothing can run until someone unblocks a task
+                JobState::Stuck
             } else if task_states.iter().all(|&(_, state)| state.is_terminal())
                 && task_states
                     .iter()
                     .any(|(_, state)| *state == TaskState::Dead)
             {
                 JobState::Failed
+            } else if task_states
+                .iter()
+                .any(|(_, state)| *state == TaskState::Cancelled)
+            {
+                JobState::Cancelled
             } else {
                 JobState::Running // unreachable fallback
             }
@@ -197,6 +208,32 @@ mod tests {
         assert_eq!(job.state, JobState::Failed);
     }
 
+    #[test]
+    fn update_job_state_blocked_without_runnable_tasks_is_stuck() {
+        let spec = JobSpec::new(vec![]);
+        let mut job = JobRecord::new(JobId::new(1), spec);
+        let task_states = vec![
+            (TaskId::new(1), TaskState::Succeeded),
+            (TaskId::new(2), TaskState::Blocked),
+        ];
+
+        job.update_state_from_tasks(&task_states);
+        assert_eq!(job.state, JobState::Stuck);
+    }
+
+    #[test]
+    fn update_job_state_includes_cancelled() {
+        let spec = JobSpec::new(vec![]);
+        let mut job = JobRecord::new(JobId::new(1), spec);
+        let task_states = vec![
+            (TaskId::new(1), TaskState::Succeeded),
+            (TaskId::new(2), TaskState::Cancelled),
+        ];
+
+        job.update_state_from_tasks(&task_states);
+        assert_eq!(job.state, JobState::Cancelled);
+    }
+
     #[test]
     fn update_job_state_with_empty_tasks() {
         let spec = JobSpec::new(vec![]);
@@ -222,6 +259,10 @@ pub struct JobStatus {
     pub completed_tasks: usize,
     pub failed_tasks: usize,
     pub running_tasks: usize,
+
+    /// Operator notes on the job and its tasks, oldest first.
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
 }
 
 /// Serializable view of JobState.
@@ -247,6 +288,18 @@ impl From<JobState> for JobStateView {
     }
 }
 
+impl From<JobStateView> for JobState {
+    fn from(state: JobStateView) -> Self {
+        match state {
+            JobStateView::Running => JobState::Running,
+            JobStateView::Completed => JobState::Completed,
+            JobStateView::Failed => JobState::Failed,
+            JobStateView::Cancelled => JobState::Cancelled,
+            JobStateView::Stuck => JobState::Stuck,
+        }
+    }
+}
+
 /// Job result for API responses (Phase 7.3).
 ///
 /// Contains complete execution history.
@@ -266,4 +319,8 @@ pub struct JobResult {
 
     /// All decision records for tasks in this job.
     pub decisions: Vec<DecisionRecord>,
+
+    /// Operator notes on the job and its tasks, oldest first.
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
 }
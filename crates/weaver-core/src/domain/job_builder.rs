@@ -0,0 +1,311 @@
+//! Fluent construction of a `JobSpec` (`JobSpec::builder()`).
+//!
+//! Methods called after `task()` apply to that task. `build()` checks the
+//! whole job (names, dependencies, cycles, attempt limits), so mistakes
+//! surface where the job is written rather than when it is submitted.
+
+#![allow(deprecated)]
+
+use std::fmt;
+
+use super::{Budget, Callback, JobSpec, RunCondition, RunIf, TaskId, TaskRef, TaskSpec, TaskType};
+use crate::queue::DependencyGraph;
+
+/// Builds a `JobSpec` task by task.
+///
+/// ```ignore
+/// let job = JobSpec::builder()
+///     .task("fetch")
+///     .task_type("http.fetch.v1")
+///     .payload(json!({ "url": "https://example.com" }))
+///     .task("parse")
+///     .depends_on("fetch")
+///     .max_attempts(1)
+///     .budget(Budget { deadline_ms: Some(60_000), ..Budget::default() })
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JobSpecBuilder {
+    tasks: Vec<TaskSpec>,
+    budget: Budget,
+    callback: Option<Callback>,
+
+    /// The first misuse (a task method before any `task()`), reported by `build()`.
+    error: Option<JobSpecError>,
+}
+
+impl JobSpecBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new task named `name` (its `TaskSpec::title`).
+    ///
+    /// The task type defaults to `name` and the payload to `{}`.
+    pub fn task(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        let task_type = TaskType::new(name.clone());
+        self.tasks
+            .push(TaskSpec::new(name, task_type, serde_json::json!({})));
+        self
+    }
+
+    pub fn task_type(self, task_type: impl Into<String>) -> Self {
+        let task_type = TaskType::new(task_type);
+        self.with_task("task_type", |task| task.task_type = task_type)
+    }
+
+    pub fn payload(self, payload: serde_json::Value) -> Self {
+        self.with_task("payload", |task| task.payload = payload)
+    }
+
+    pub fn intent(self, intent: impl Into<String>) -> Self {
+        let intent = intent.into();
+        self.with_task("intent", |task| task.intent = Some(intent))
+    }
+
+    pub fn goal(self, goal: serde_json::Value) -> Self {
+        self.with_task("goal", |task| task.goal = Some(goal))
+    }
+
+    pub fn constraints(self, constraints: serde_json::Value) -> Self {
+        self.with_task("constraints", |task| task.constraints = Some(constraints))
+    }
+
+    /// Wait for another task of the job to succeed (by name or index).
+    pub fn depends_on(self, task: impl Into<TaskRef>) -> Self {
+        let task = task.into();
+        self.with_task("depends_on", |spec| spec.depends_on.push(task))
+    }
+
+    /// Run only if another task of the job went dead (see `RunCondition`).
+    pub fn after_failure_of(self, task: impl Into<TaskRef>) -> Self {
+        self.run_if("after_failure_of", task.into(), RunCondition::OnFailure)
+    }
+
+    /// Run once another task of the job finished, whatever its outcome.
+    pub fn after_any_outcome_of(self, task: impl Into<TaskRef>) -> Self {
+        self.run_if("after_any_outcome_of", task.into(), RunCondition::Always)
+    }
+
+    /// Wait for `tasks` and receive their results (see `TaskSpec::join`).
+    pub fn join_on<R: Into<TaskRef>>(self, tasks: impl IntoIterator<Item = R>) -> Self {
+        let tasks: Vec<TaskRef> = tasks.into_iter().map(Into::into).collect();
+        self.with_task("join_on", |spec| {
+            spec.depends_on.extend(tasks);
+            spec.join = true;
+        })
+    }
+
+    /// Attempts for this task, instead of `Budget::max_attempts_per_task`.
+    pub fn max_attempts(self, max_attempts: u32) -> Self {
+        self.with_task("max_attempts", |task| {
+            task.max_attempts = Some(max_attempts)
+        })
+    }
+
+    /// Budget of the whole job (default: `Budget::default()`).
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Report the job's final state to `callback`.
+    pub fn callback(mut self, callback: Callback) -> Self {
+        self.callback = Some(callback);
+        self
+    }
+
+    /// The job, if it is valid.
+    pub fn build(self) -> Result<JobSpec, JobSpecError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if self.tasks.is_empty() {
+            return Err(JobSpecError::NoTasks);
+        }
+        if self.budget.max_attempts_per_task == 0 {
+            return Err(JobSpecError::ZeroAttempts(None));
+        }
+
+        let name = |index: usize| {
+            self.tasks[index]
+                .title
+                .clone()
+                .unwrap_or_else(|| index.to_string())
+        };
+        let mut graph = DependencyGraph::new();
+        for (index, task) in self.tasks.iter().enumerate() {
+            if task.task_type.as_str().is_empty() {
+                return Err(JobSpecError::EmptyTaskType(name(index)));
+            }
+            if task.max_attempts == Some(0) {
+                return Err(JobSpecError::ZeroAttempts(Some(name(index))));
+            }
+            if self.tasks[..index]
+                .iter()
+                .any(|other| other.title == task.title)
+            {
+                return Err(JobSpecError::DuplicateTask(name(index)));
+            }
+            let run_if = task.run_if.iter().map(|run_if| &run_if.task);
+            for dependency in task.depends_on.iter().chain(run_if) {
+                let target = match dependency {
+                    TaskRef::Index(target) => Some(*target).filter(|&t| t < self.tasks.len()),
+                    TaskRef::Name(target) => self
+                        .tasks
+                        .iter()
+                        .position(|other| other.title.as_deref() == Some(target.as_str())),
+                };
+                let Some(target) = target else {
+                    return Err(JobSpecError::UnknownDependency {
+                        task: name(index),
+                        dependency: dependency.clone(),
+                    });
+                };
+                if target == index {
+                    return Err(JobSpecError::SelfDependency(name(index)));
+                }
+                graph.add_dependency(TaskId::new(index as u128), TaskId::new(target as u128));
+            }
+        }
+        if let Some(cycle) = graph.detect_cycle() {
+            let cycle = cycle
+                .iter()
+                .map(|task_id| name(task_id.as_u64() as usize))
+                .collect();
+            return Err(JobSpecError::Cycle(cycle));
+        }
+
+        Ok(JobSpec {
+            tasks: self.tasks,
+            budget: self.budget,
+            callback: self.callback,
+        })
+    }
+
+    fn run_if(self, method: &'static str, task: TaskRef, when: RunCondition) -> Self {
+        self.with_task(method, |spec| spec.run_if.push(RunIf { task, when }))
+    }
+
+    /// Apply `change` to the task started last.
+    fn with_task(mut self, method: &'static str, change: impl FnOnce(&mut TaskSpec)) -> Self {
+        match self.tasks.last_mut() {
+            Some(task) => change(task),
+            None => {
+                self.error
+                    .get_or_insert(JobSpecError::NoCurrentTask(method));
+            }
+        }
+        self
+    }
+}
+
+/// Why `JobSpecBuilder::build()` rejected a job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobSpecError {
+    /// A task method was called before any `task()`.
+    NoCurrentTask(&'static str),
+    NoTasks,
+    DuplicateTask(String),
+    EmptyTaskType(String),
+    /// `max_attempts(0)` on the named task, or on the budget for None.
+    ZeroAttempts(Option<String>),
+    UnknownDependency {
+        task: String,
+        dependency: TaskRef,
+    },
+    SelfDependency(String),
+    /// Task names along the cycle, each waiting for the next.
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for JobSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCurrentTask(method) => {
+                write!(f, "`{method}()` applies to a task: call `task()` first")
+            }
+            Self::NoTasks => write!(f, "a job needs at least one task"),
+            Self::DuplicateTask(name) => write!(f, "task `{name}` is defined twice"),
+            Self::EmptyTaskType(name) => write!(f, "task `{name}` has an empty task type"),
+            Self::ZeroAttempts(Some(name)) => {
+                write!(f, "task `{name}`: max_attempts must be at least 1")
+            }
+            Self::ZeroAttempts(None) => {
+                write!(f, "budget: max_attempts_per_task must be at least 1")
+            }
+            Self::UnknownDependency { task, dependency } => {
+                write!(
+                    f,
+                    "task `{task}` depends on {dependency}, which is not a task of the job"
+                )
+            }
+            Self::SelfDependency(name) => write!(f, "task `{name}` depends on itself"),
+            Self::Cycle(cycle) => write!(f, "dependency cycle: {}", cycle.join(" -> ")),
+        }
+    }
+}
+
+impl std::error::Error for JobSpecError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn builds_tasks_with_dependencies() {
+        let job = JobSpec::builder()
+            .task("fetch")
+            .task_type("http.fetch.v1")
+            .payload(json!({ "url": "https://example.com" }))
+            .task("parse")
+            .depends_on("fetch")
+            .max_attempts(1)
+            .task("alert")
+            .after_failure_of("parse")
+            .budget(Budget {
+                deadline_ms: Some(60_000),
+                ..Budget::default()
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(job.tasks.len(), 3);
+        assert_eq!(job.tasks[0].task_type.as_str(), "http.fetch.v1");
+        assert_eq!(job.tasks[0].payload["url"], "https://example.com");
+        assert_eq!(job.tasks[1].task_type.as_str(), "parse");
+        assert_eq!(job.tasks[1].depends_on, vec![TaskRef::from("fetch")]);
+        assert_eq!(job.tasks[1].max_attempts, Some(1));
+        assert_eq!(job.tasks[2].run_if[0].when, RunCondition::OnFailure);
+        assert_eq!(job.budget.deadline_ms, Some(60_000));
+    }
+
+    #[test]
+    fn build_rejects_invalid_jobs() {
+        let error = |builder: JobSpecBuilder| builder.build().unwrap_err().to_string();
+        assert_eq!(
+            error(JobSpec::builder().payload(json!({})).task("a")),
+            "`payload()` applies to a task: call `task()` first"
+        );
+        assert_eq!(
+            error(JobSpec::builder().task("a").depends_on("b")),
+            "task `a` depends on \"b\", which is not a task of the job"
+        );
+        assert_eq!(
+            error(JobSpec::builder().task("a").task("a")),
+            "task `a` is defined twice"
+        );
+        assert_eq!(
+            error(
+                JobSpec::builder()
+                    .task("a")
+                    .depends_on("b")
+                    .task("b")
+                    .depends_on(0)
+            ),
+            "dependency cycle: a -> b -> a"
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Lifecycle - 外部システムが読むタスクのライフサイクルイベント
+//!
+//! `TaskStore::read_events` が返す。イベントは増え続ける `EventCursor` を持つので、
+//! 外部システムは cursor の後ろを読み、処理し終えたら cursor を commit する。
+//! v1 の InMemoryQueue（`with_event_log`）も同じ型で記録する。
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{JobId, RunId, TaskId, TaskType};
+
+/// LifecycleOp はタスクに対する queue の操作
+///
+/// v1 の journal（`queue::JournalOp`）と同じもの。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LifecycleOp {
+    Enqueue,
+    Lease,
+    Ack,
+    Complete,
+    Fail,
+    /// 予定の時刻が来て ready に移った
+    Promote,
+    /// 期限切れの lease を reaper が回収した
+    Reap,
+    Cancel,
+    /// 管理操作の purge で消された
+    Purge,
+    /// 管理操作の requeue で ready に戻された
+    Requeue,
+    /// drain 中の worker が終わらなかった lease を返した
+    Release,
+}
+
+/// EventCursor はイベントログ上の位置（最後に読んだイベントの通し番号）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct EventCursor(pub u64);
+
+impl fmt::Display for EventCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// LifecycleEvent はタスクのライフサイクルイベント 1 件（タスクに対する queue の操作）
+///
+/// 操作の結果どの状態になったかはタスクを読む（`TaskStore::get_task` など）。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LifecycleEvent {
+    /// 処理し終えたらこの cursor を commit する
+    pub cursor: EventCursor,
+    pub at: DateTime<Utc>,
+    pub op: LifecycleOp,
+    pub task_id: TaskId,
+    pub task_type: TaskType,
+    pub job_id: Option<JobId>,
+    /// イベントを記録した queue の run
+    pub run_id: RunId,
+}
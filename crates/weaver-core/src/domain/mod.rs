@@ -1,8 +1,8 @@
 //! Domain model (IDs, specs, outcomes, records, ...).
 //!
 //! v2 モジュール構成への移行中:
-//! - 新規: task_type, envelope, budget, state, errors, events
-//! - 既存（v1互換）: attempt, decision, ids, job, outcome, spec, task
+//! - 新規: task_type, envelope, budget, state, errors, events, lifecycle
+//! - 既存（v1互換）: attempt, callback, decision, ids, job, outcome, schedule, spec, task, template, trace
 
 // v2 の新しいモジュール
 pub mod task_type;
@@ -11,29 +11,43 @@ pub mod budget;
 pub mod state;
 pub mod errors;
 pub mod events;
+pub mod lifecycle;
 
 // v1 の既存モジュール（段階的に移行予定）
 pub mod attempt;
+pub mod callback;
 pub mod decision;
 pub mod ids;
 pub mod job;
+pub mod job_builder;
 pub mod outcome;
+pub mod schedule;
+pub mod schema;
 pub mod spec;
 pub mod task;
+pub mod template;
+pub mod trace;
 
 // v2 の型を再エクスポート
-pub use self::task_type::TaskType as TaskTypeV2;
+pub use self::task_type::{TaskType as TaskTypeV2, TaskTypeError};
 pub use self::envelope::TaskEnvelope as TaskEnvelopeV2;
 pub use self::budget::Budget as BudgetV2;
 pub use self::state::{TaskState, JobState as JobStateV2, WaitingReason};
 pub use self::errors::{ErrorKind, WeaverError};
 pub use self::events::DomainEvent;
+pub use self::lifecycle::{EventCursor, LifecycleEvent, LifecycleOp};
 
 // v1 の型を再エクスポート（互換性維持）
-pub use attempt::{AttemptRecord, DecisionRecord};
+pub use attempt::{Annotation, AnnotationTarget, AttemptRecord, DecisionRecord};
+pub use callback::{Callback, CallbackPayload};
 pub use decision::{Decision, Decider, DefaultDecider};
-pub use ids::{AttemptId, JobId, TaskId};
+pub use ids::{ArtifactId, AttemptId, EventId, JobId, RunId, TaskId};
 pub use job::{JobRecord, JobResult, JobState, JobStateView, JobStatus};
+pub use job_builder::{JobSpecBuilder, JobSpecError};
 pub use outcome::{Artifact, Outcome, OutcomeKind};
-pub use spec::{Budget, JobSpec, TaskSpec};
+pub use schedule::{Period, RecurringSchedule};
+pub use schema::{PayloadSchema, SchemaError};
+pub use spec::{Budget, JobSpec, RunCondition, RunIf, TaskRef, TaskSpec};
 pub use task::{TaskEnvelope, TaskType};
+pub use template::{JobTemplate, TemplateError, TemplateParam, TemplateRegistry};
+pub use trace::TraceContext;
@@ -6,6 +6,7 @@
 
 use serde::{Deserialize, Serialize};
 
+use super::ids::TaskId;
 use super::spec::TaskSpec;
 
 /// A unified classification of an attempt result.
@@ -41,6 +42,9 @@ pub enum Artifact {
 
     /// Arbitrary JSON payload (structured observation/output).
     Json(serde_json::Value),
+
+    /// The value a typed handler returned (its task's `Task::Output`).
+    Output(serde_json::Value),
 }
 
 /// A common result format for an attempt.
@@ -70,6 +74,10 @@ pub struct Outcome {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub child_tasks: Option<Vec<TaskSpec>>,
+
+    /// Prerequisite task this attempt is blocked on (see `Outcome::blocked_on`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_on: Option<TaskId>,
 }
 
 impl Outcome {
@@ -81,6 +89,7 @@ impl Outcome {
             retry_hint: None,
             alternatives: Vec::new(),
             child_tasks: None,
+            blocked_on: None,
         }
     }
 
@@ -92,9 +101,26 @@ impl Outcome {
             retry_hint: None,
             alternatives: Vec::new(),
             child_tasks: None,
+            blocked_on: None,
         }
     }
 
+    /// A failure retrying cannot fix (e.g. the request was rejected as
+    /// invalid). The default decider marks the task dead at once.
+    pub fn permanent_failure(reason: impl Into<String>) -> Self {
+        Self::failure(reason).with_retry_hint(serde_json::json!({ "retryable": false }))
+    }
+
+    /// False if the handler said retrying is pointless (`"retryable": false`
+    /// in the retry hint, see `Outcome::permanent_failure`).
+    pub fn is_retryable(&self) -> bool {
+        self.retry_hint
+            .as_ref()
+            .and_then(|hint| hint.get("retryable"))
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(true)
+    }
+
     pub fn blocked(reason: impl Into<String>) -> Self {
         Self {
             kind: OutcomeKind::Blocked,
@@ -103,6 +129,17 @@ impl Outcome {
             retry_hint: None,
             alternatives: Vec::new(),
             child_tasks: None,
+            blocked_on: None,
+        }
+    }
+
+    /// Blocked until `depends_on` succeeds (e.g. a prerequisite the handler
+    /// just created). The default decider turns this into
+    /// `Decision::AddDependency`, so the task runs again after `depends_on`.
+    pub fn blocked_on(depends_on: TaskId, reason: impl Into<String>) -> Self {
+        Self {
+            blocked_on: Some(depends_on),
+            ..Self::blocked(reason)
         }
     }
 
@@ -111,6 +148,14 @@ impl Outcome {
         self
     }
 
+    /// The handler's return value, if it left one (`Artifact::Output`).
+    pub fn output(&self) -> Option<&serde_json::Value> {
+        self.artifacts.iter().find_map(|artifact| match artifact {
+            Artifact::Output(value) => Some(value),
+            _ => None,
+        })
+    }
+
     pub fn with_retry_hint(mut self, hint: serde_json::Value) -> Self {
         self.retry_hint = Some(hint);
         self
@@ -0,0 +1,211 @@
+//! Recurring schedules: a job template submitted once per period.
+//!
+//! Only the definition and period arithmetic live here; submitting the jobs
+//! is done by `queue::Scheduler` (as periods close) and `queue::Backfill`
+//! (missed periods).
+
+use chrono::{DateTime, Datelike, DurationRound, Months, NaiveDate, TimeDelta, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{JobSpec, JobTemplate, TemplateError};
+
+/// How often a schedule runs. Periods are aligned to UTC calendar boundaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Hourly,
+    Daily,
+
+    /// Weeks start on Monday.
+    Weekly,
+    Monthly,
+}
+
+impl Period {
+    /// Start of the period containing `at`.
+    pub fn floor(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let date = at.date_naive();
+        let start = match self {
+            Period::Hourly => {
+                return at
+                    .duration_trunc(TimeDelta::hours(1))
+                    .expect("an hour fits in any timestamp");
+            }
+            Period::Daily => date,
+            Period::Weekly => {
+                date - TimeDelta::days(i64::from(date.weekday().num_days_from_monday()))
+            }
+            Period::Monthly => date.with_day(1).expect("day 1 exists in every month"),
+        };
+        midnight(start)
+    }
+
+    /// Start of the period after the one starting at `start`.
+    pub fn next(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Period::Hourly => start + TimeDelta::hours(1),
+            Period::Daily => start + TimeDelta::days(1),
+            Period::Weekly => start + TimeDelta::weeks(1),
+            Period::Monthly => start + Months::new(1),
+        }
+    }
+
+    /// Periods `[start, end)` whose start falls in `[from, to)`.
+    ///
+    /// `from` is rounded down to its period, so a partial first period is included.
+    pub fn periods(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let mut periods = Vec::new();
+        let mut start = self.floor(from);
+        while start < to {
+            let end = self.next(start);
+            periods.push((start, end));
+            start = end;
+        }
+        periods
+    }
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight exists"))
+}
+
+/// A job submitted once per period.
+///
+/// String values in the task payloads may contain placeholders that are
+/// filled in per period:
+/// - `{period_start}` / `{period_end}`: RFC 3339 timestamps
+/// - `{period_date}`: start date as `YYYY-MM-DD`
+///
+/// ```ignore
+/// {
+///   "name": "daily-export",
+///   "period": "daily",
+///   "job": { "tasks": [{ "task_type": "export", "payload": { "day": "{period_date}" }, ... }] }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringSchedule {
+    pub name: String,
+    pub period: Period,
+    pub job: JobSpec,
+}
+
+impl RecurringSchedule {
+    pub fn new(name: impl Into<String>, period: Period, job: JobSpec) -> Self {
+        Self {
+            name: name.into(),
+            period,
+            job,
+        }
+    }
+
+    /// A schedule that submits a registered template's job.
+    ///
+    /// `params` are filled in once, here; the period placeholders are filled
+    /// in per period as usual.
+    pub fn from_template(
+        name: impl Into<String>,
+        period: Period,
+        template: &JobTemplate,
+        params: &serde_json::Value,
+    ) -> Result<Self, TemplateError> {
+        Ok(Self::new(name, period, template.instantiate(params)?))
+    }
+
+    /// The job for the period `[start, end)`, with placeholders filled in.
+    pub fn job_for(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> JobSpec {
+        let vars = [
+            ("{period_start}", start.to_rfc3339()),
+            ("{period_end}", end.to_rfc3339()),
+            ("{period_date}", start.format("%Y-%m-%d").to_string()),
+        ];
+        let mut job = self.job.clone();
+        for task in &mut job.tasks {
+            fill_placeholders(&mut task.payload, &vars);
+        }
+        job
+    }
+}
+
+fn fill_placeholders(value: &mut serde_json::Value, vars: &[(&str, String)]) {
+    match value {
+        serde_json::Value::String(s) => {
+            for (placeholder, replacement) in vars {
+                if s.contains(placeholder) {
+                    *s = s.replace(placeholder, replacement);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                fill_placeholders(item, vars);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values_mut() {
+                fill_placeholders(field, vars);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{TaskSpec, TaskType};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn periods_are_aligned_and_half_open() {
+        let days = Period::Daily.periods(utc("2024-01-30T12:00:00Z"), utc("2024-02-01T00:00:00Z"));
+        assert_eq!(
+            days,
+            vec![
+                (utc("2024-01-30T00:00:00Z"), utc("2024-01-31T00:00:00Z")),
+                (utc("2024-01-31T00:00:00Z"), utc("2024-02-01T00:00:00Z")),
+            ]
+        );
+
+        let months =
+            Period::Monthly.periods(utc("2024-01-31T00:00:00Z"), utc("2024-03-01T00:00:00Z"));
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[1].0, utc("2024-02-01T00:00:00Z"));
+
+        // 2024-01-03 is a Wednesday
+        let week = Period::Weekly.floor(utc("2024-01-03T08:00:00Z"));
+        assert_eq!(week, utc("2024-01-01T00:00:00Z"));
+        let hour = Period::Hourly.floor(utc("2024-01-03T08:59:59.5Z"));
+        assert_eq!(hour, utc("2024-01-03T08:00:00Z"));
+    }
+
+    #[test]
+    fn job_for_fills_placeholders_in_payloads() {
+        let schedule = RecurringSchedule::new(
+            "export",
+            Period::Daily,
+            JobSpec::new(vec![TaskSpec::new(
+                "export",
+                TaskType::new("export"),
+                serde_json::json!({"day": "{period_date}", "range": ["{period_start}", "{period_end}"], "n": 1}),
+            )]),
+        );
+
+        let job = schedule.job_for(utc("2024-01-05T00:00:00Z"), utc("2024-01-06T00:00:00Z"));
+        assert_eq!(
+            job.tasks[0].payload,
+            serde_json::json!({
+                "day": "2024-01-05",
+                "range": ["2024-01-05T00:00:00+00:00", "2024-01-06T00:00:00+00:00"],
+                "n": 1,
+            })
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! JSON Schemas for task payloads, checked when a task is enqueued.
+//!
+//! A malformed payload is rejected by `enqueue()`/`submit_job()` with the
+//! offending path, instead of failing to deserialize in the handler on
+//! every retry (`InMemoryQueue::with_payload_schema`, `Task::payload_schema`).
+//!
+//! Validation is done by the `jsonschema` crate, so every keyword of the
+//! schema's draft (2020-12 unless `$schema` says otherwise) is checked,
+//! including `pattern`, `$ref`/`$defs` and `format`. A schema that is not
+//! valid against its meta-schema is rejected when it is created. Only local
+//! `$ref`s resolve; remote ones are rejected rather than fetched.
+
+use std::fmt;
+use std::sync::Arc;
+
+use jsonschema::{ValidationError, Validator};
+use serde_json::Value;
+
+/// A validated JSON Schema for the payloads of one task type.
+#[derive(Clone)]
+pub struct PayloadSchema {
+    schema: Value,
+    validator: Arc<Validator>,
+}
+
+/// Why a schema could not be created, or why a payload does not match it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// JSON pointer to the offending value (`""` for the payload itself).
+    pub path: String,
+    pub message: String,
+}
+
+impl From<ValidationError<'_>> for SchemaError {
+    fn from(error: ValidationError<'_>) -> Self {
+        Self {
+            path: error.instance_path.to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "at {}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl PayloadSchema {
+    /// A schema, if it is valid JSON Schema (the error's path points into the schema).
+    pub fn new(schema: Value) -> Result<Self, SchemaError> {
+        let validator = jsonschema::options()
+            .should_validate_formats(true)
+            .build(&schema)?;
+        Ok(Self {
+            schema,
+            validator: Arc::new(validator),
+        })
+    }
+
+    pub fn as_value(&self) -> &Value {
+        &self.schema
+    }
+
+    /// Check `payload`; the error names the first value that does not match.
+    pub fn validate(&self, payload: &Value) -> Result<(), SchemaError> {
+        Ok(self.validator.validate(payload)?)
+    }
+}
+
+impl fmt::Debug for PayloadSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PayloadSchema")
+            .field("schema", &self.schema)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for PayloadSchema {
+    fn eq(&self, other: &Self) -> bool {
+        self.schema == other.schema
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn order_schema() -> PayloadSchema {
+        PayloadSchema::new(json!({
+            "title": "Order",
+            "type": "object",
+            "required": ["order_id", "lines"],
+            "additionalProperties": false,
+            "properties": {
+                "order_id": { "type": "integer", "minimum": 1 },
+                "currency": { "enum": ["JPY", "USD"] },
+                "lines": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "required": ["sku"],
+                        "properties": { "sku": { "type": "string", "minLength": 1 } }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn payloads_are_checked_against_the_schema() {
+        let schema = order_schema();
+        let valid = json!({ "order_id": 7, "currency": "JPY", "lines": [{ "sku": "A-1" }] });
+        assert_eq!(schema.validate(&valid), Ok(()));
+
+        let error = |payload: Value| schema.validate(&payload).unwrap_err().to_string();
+        assert_eq!(
+            error(json!({ "order_id": "7", "lines": [{ "sku": "A" }] })),
+            "at /order_id: \"7\" is not of type \"integer\""
+        );
+        assert_eq!(
+            error(json!({ "order_id": 7 })),
+            "\"lines\" is a required property"
+        );
+        assert_eq!(
+            error(json!({ "order_id": 7, "lines": [{ "sku": "" }] })),
+            "at /lines/0/sku: \"\" is shorter than 1 character"
+        );
+        assert_eq!(
+            error(json!({ "order_id": 0, "lines": [{ "sku": "A" }] })),
+            "at /order_id: 0 is less than the minimum of 1"
+        );
+        assert_eq!(
+            error(json!({ "order_id": 7, "lines": [{ "sku": "A" }], "note": 1 })),
+            "Additional properties are not allowed ('note' was unexpected)"
+        );
+    }
+
+    #[test]
+    fn invalid_schemas_are_rejected_up_front() {
+        let error = PayloadSchema::new(json!({
+            "properties": { "email": { "type": "text" } }
+        }))
+        .unwrap_err();
+        assert_eq!(error.path, "/properties/email/type");
+        assert!(PayloadSchema::new(json!({ "minimum": "1" })).is_err());
+        // remote references are never fetched
+        assert!(PayloadSchema::new(json!({ "$ref": "https://example.com/order.json" })).is_err());
+        assert!(PayloadSchema::new(json!({ "anyOf": [{ "type": "string" }, true] })).is_ok());
+    }
+
+    #[test]
+    fn pattern_format_and_local_refs_are_checked() {
+        let schema = PayloadSchema::new(json!({
+            "$defs": { "email": { "type": "string", "format": "email", "pattern": "@example\\.com$" } },
+            "properties": { "to": { "$ref": "#/$defs/email" } }
+        }))
+        .unwrap();
+        assert_eq!(schema.validate(&json!({ "to": "a@example.com" })), Ok(()));
+        assert_eq!(
+            schema
+                .validate(&json!({ "to": "a@example.org" }))
+                .unwrap_err()
+                .path,
+            "/to"
+        );
+        assert!(schema.validate(&json!({ "to": "not an address" })).is_err());
+    }
+}
@@ -5,7 +5,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use super::TaskType;
+use super::{Callback, JobSpecBuilder, TaskType};
 
 /// A Job is the unit of submission / cancellation / status / result.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +15,10 @@ pub struct JobSpec {
     /// Budget that applies to the whole job (optional / partial in v1).
     #[serde(default)]
     pub budget: Budget,
+
+    /// Reported once every task of the job is terminal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback: Option<Callback>,
 }
 
 impl JobSpec {
@@ -22,8 +26,20 @@ impl JobSpec {
         Self {
             tasks,
             budget: Budget::default(),
+            callback: None,
         }
     }
+
+    /// Build a job task by task, checked at `build()` (see `JobSpecBuilder`).
+    pub fn builder() -> JobSpecBuilder {
+        JobSpecBuilder::new()
+    }
+
+    /// Report the job's final state to `callback`.
+    pub fn with_callback(mut self, callback: Callback) -> Self {
+        self.callback = Some(callback);
+        self
+    }
 }
 
 /// A trackable unit inside a job.
@@ -54,7 +70,115 @@ pub struct TaskSpec {
 
     /// Optional initial dependencies (TaskIds may not be known at creation time;
     /// for v1 we keep this flexible as JSON).
+    ///
+    /// For tasks of a job this is an array of indices of sibling tasks,
+    /// e.g. `[0, 1]`; `submit_job` rejects dependency cycles. Children added
+    /// by decomposition may only reference earlier siblings.
+    ///
+    /// Prefer `depends_on`; both are honored.
     pub dependencies_hint: Option<serde_json::Value>,
+
+    /// Sibling tasks this task waits for, by index or by title
+    /// (resolved to TaskIds when the job is submitted).
+    ///
+    /// JSON: `"depends_on": [0, "extract"]`. Same rules as `dependencies_hint`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<TaskRef>,
+
+    /// Join (fan-in): once every task of `depends_on` succeeded, their
+    /// results are added to this task's payload as `joined`, in `depends_on`
+    /// order: `[{"task_id", "title", "output", "artifacts"}, ...]`.
+    ///
+    /// A payload that is not a JSON object is wrapped as
+    /// `{"payload": ..., "joined": [...]}`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub join: bool,
+
+    /// Dependencies that do not wait for success: run this task when a
+    /// sibling failed (compensation, cleanup) or whatever its outcome.
+    ///
+    /// JSON: `"run_if": [{"task": "charge", "when": "on_failure"}]`. A sibling
+    /// listed here need not also be in `depends_on`; if it is, `run_if` wins.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub run_if: Vec<RunIf>,
+
+    /// Overrides `Budget::max_attempts_per_task` for this task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+}
+
+/// A dependency with a run condition (see `TaskSpec::run_if`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunIf {
+    pub task: TaskRef,
+    pub when: RunCondition,
+}
+
+/// Which outcome of a dependency lets the dependent run.
+///
+/// - `OnSuccess`: the dependency succeeded (what `depends_on` means). A dead
+///   dependency skips the dependent, so its job still finishes.
+/// - `OnFailure`: the dependency went dead.
+/// - `Always`: the dependency succeeded, went dead or was skipped.
+///
+/// A dependent whose condition can no longer hold is skipped: it is
+/// cancelled without running, which in turn releases its own `Always`
+/// dependents and skips the others.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunCondition {
+    #[default]
+    OnSuccess,
+    OnFailure,
+    Always,
+}
+
+impl std::fmt::Display for RunCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunCondition::OnSuccess => write!(f, "on_success"),
+            RunCondition::OnFailure => write!(f, "on_failure"),
+            RunCondition::Always => write!(f, "always"),
+        }
+    }
+}
+
+/// A reference to another task of the same job (see `TaskSpec::depends_on`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TaskRef {
+    /// Position in `JobSpec::tasks` (or in the list of child tasks).
+    Index(usize),
+
+    /// The sibling's `title`; must match exactly one task.
+    Name(String),
+}
+
+impl From<usize> for TaskRef {
+    fn from(index: usize) -> Self {
+        TaskRef::Index(index)
+    }
+}
+
+impl From<&str> for TaskRef {
+    fn from(name: &str) -> Self {
+        TaskRef::Name(name.to_string())
+    }
+}
+
+impl From<String> for TaskRef {
+    fn from(name: String) -> Self {
+        TaskRef::Name(name)
+    }
+}
+
+impl std::fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskRef::Index(index) => write!(f, "{index}"),
+            TaskRef::Name(name) => write!(f, "{name:?}"),
+        }
+    }
 }
 
 impl TaskSpec {
@@ -73,8 +197,51 @@ impl TaskSpec {
             constraints: None,
             seed_action_hint: None,
             dependencies_hint: None,
+            depends_on: Vec::new(),
+            join: false,
+            run_if: Vec::new(),
+            max_attempts: None,
         }
     }
+
+    /// Wait for a sibling task, e.g. `.after("extract")` or `.after(0)`.
+    pub fn after(mut self, task: impl Into<TaskRef>) -> Self {
+        self.depends_on.push(task.into());
+        self
+    }
+
+    /// Run only if a sibling went dead, e.g. a refund after `"charge"` failed.
+    pub fn after_failure_of(mut self, task: impl Into<TaskRef>) -> Self {
+        self.run_if.push(RunIf {
+            task: task.into(),
+            when: RunCondition::OnFailure,
+        });
+        self
+    }
+
+    /// Run once a sibling is finished, whether it succeeded or not.
+    pub fn after_any_outcome_of(mut self, task: impl Into<TaskRef>) -> Self {
+        self.run_if.push(RunIf {
+            task: task.into(),
+            when: RunCondition::Always,
+        });
+        self
+    }
+
+    /// Make this a join over `tasks`: it runs after all of them succeeded,
+    /// with their results in its payload (see `join`).
+    ///
+    /// ```ignore
+    /// let mut tasks: Vec<TaskSpec> = shards.iter().map(|shard| map_task(shard)).collect();
+    /// let reduce = TaskSpec::new("reduce", TaskType::new("sum"), json!({}))
+    ///     .join_on(0..tasks.len());
+    /// tasks.push(reduce);
+    /// ```
+    pub fn join_on<R: Into<TaskRef>>(mut self, tasks: impl IntoIterator<Item = R>) -> Self {
+        self.depends_on.extend(tasks.into_iter().map(Into::into));
+        self.join = true;
+        self
+    }
 }
 
 /// Execution budgets / stop conditions.
@@ -127,6 +294,7 @@ mod tests {
                 serde_json::json!({}),
             )],
             budget: Budget::default(),
+            callback: None,
         };
 
         let s = serde_json::to_string(&job).expect("serialize");
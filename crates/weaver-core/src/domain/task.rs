@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-use super::TaskId;
+use super::{Callback, TaskId, TraceContext};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TaskType(String);
@@ -14,6 +14,11 @@ impl TaskType {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// 命名規約 `{namespace}.{domain}.{action}.v{major}` の namespace（`.` がなければ `None`）
+    pub fn namespace(&self) -> Option<&str> {
+        self.0.split_once('.').map(|(namespace, _)| namespace)
+    }
 }
 
 impl fmt::Display for TaskType {
@@ -28,6 +33,38 @@ pub struct TaskEnvelope {
     task_id: TaskId,
     task_type: TaskType,
     payload: serde_json::Value,
+
+    /// 同一 task_type 内で重複とみなすためのキー（debounce 用、任意）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dedupe_key: Option<String>,
+
+    /// 終了状態（Succeeded / Dead / Cancelled）の通知先（任意）
+    ///
+    /// envelope は lease のたびに clone されるため、めったに使わない値は Box で持つ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    callback: Option<Box<Callback>>,
+
+    /// 試行回数の上限（任意）。`enqueue()` で個別に投入するタスク用
+    ///
+    /// 未設定なら `Budget::default().max_attempts_per_task`。job のタスクは job の Budget に従う
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_attempts: Option<u32>,
+
+    /// payload の形式（未設定なら JSON。それ以外は bytes の base64、`typed::PayloadFormat` 参照）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+
+    /// payload を圧縮した Compressor の名前（例: `zstd`。未設定なら圧縮していない）
+    ///
+    /// 圧縮した payload は bytes の base64（`typed::compress_payload` 参照）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_encoding: Option<String>,
+
+    /// 投入時の trace（W3C `traceparent`）。各 attempt の span はこの子になる
+    ///
+    /// 未設定なら queue が enqueue 時に新しい trace を割り当てる
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    traceparent: Option<TraceContext>,
 }
 
 impl TaskEnvelope {
@@ -36,9 +73,53 @@ impl TaskEnvelope {
             task_id,
             task_type,
             payload,
+            dedupe_key: None,
+            callback: None,
+            max_attempts: None,
+            content_type: None,
+            content_encoding: None,
+            traceparent: None,
         }
     }
 
+    /// dedupe key を設定する（例: `"entity:42"`）
+    pub fn with_dedupe_key(mut self, key: impl Into<String>) -> Self {
+        self.dedupe_key = Some(key.into());
+        self
+    }
+
+    /// 終了時に最終状態を `callback` へ通知する
+    pub fn with_callback(mut self, callback: Callback) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// 試行回数の上限を設定する（リトライを含む）
+    ///
+    /// 0 は一度も実行されずに dead になるため、enqueue 時に拒否される。
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// payload の形式を宣言する（`typed::encode_payload` で作った payload と組にする）
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// payload を圧縮した Compressor を宣言する（`typed::compress_payload` が設定する）
+    pub fn with_content_encoding(mut self, content_encoding: impl Into<String>) -> Self {
+        self.content_encoding = Some(content_encoding.into());
+        self
+    }
+
+    /// 既存の trace に参加する（例: HTTP リクエストの `traceparent` から）
+    pub fn with_trace(mut self, trace: TraceContext) -> Self {
+        self.traceparent = Some(trace);
+        self
+    }
+
     pub fn task_id(&self) -> TaskId {
         self.task_id
     }
@@ -50,4 +131,48 @@ impl TaskEnvelope {
     pub fn payload(&self) -> &serde_json::Value {
         &self.payload
     }
+
+    /// payload を書き換える（queue が unblock 時に解決内容を反映する）
+    pub(crate) fn payload_mut(&mut self) -> &mut serde_json::Value {
+        &mut self.payload
+    }
+
+    pub fn dedupe_key(&self) -> Option<&str> {
+        self.dedupe_key.as_deref()
+    }
+
+    pub fn callback(&self) -> Option<&Callback> {
+        self.callback.as_deref()
+    }
+
+    pub fn max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+
+    /// 投入時の span（queue に入る前は未設定のことがある）
+    pub fn trace(&self) -> Option<TraceContext> {
+        self.traceparent
+    }
+
+    /// trace が未設定なら新しい trace を割り当てる（queue が enqueue 時に呼ぶ）
+    pub(crate) fn ensure_trace(&mut self) {
+        self.traceparent.get_or_insert_with(TraceContext::new_root);
+    }
+
+    /// payload の形式（未設定なら `application/json`）
+    pub fn content_type(&self) -> &str {
+        self.content_type
+            .as_deref()
+            .unwrap_or(crate::typed::JSON_CONTENT_TYPE)
+    }
+
+    /// payload を圧縮した Compressor の名前（圧縮していなければ `None`）
+    pub fn content_encoding(&self) -> Option<&str> {
+        self.content_encoding.as_deref()
+    }
+
+    /// payload がそのまま読める JSON か（形式が JSON で、圧縮もしていない）
+    pub fn has_json_payload(&self) -> bool {
+        self.content_type() == crate::typed::JSON_CONTENT_TYPE && self.content_encoding.is_none()
+    }
 }
@@ -1,25 +1,141 @@
 //! TaskType - task_type 命名規約のサポート
 //!
-//! # 実装予定
-//! - v2 後半: バリデータ、命名規約チェック
+//! # 学習ポイント
+//! - 検証済みの値だけを持つ newtype（作れた時点で規約に従っている）
+//! - `&str` を返す accessor（毎回パースし直すが、文字列が短いので十分速い）
+//!
+//! `#[derive(WeaverTask)]`（weaver-macros）も同じ規約をコンパイル時に検査する。
+//! proc-macro crate は weaver-core に依存できないため、規則は両方に書いてある。
+
+use std::fmt;
 
-/// TaskType は task_type の命名規約をサポート
+/// TaskType は命名規約に従っている task_type
 ///
 /// # 命名規約
-/// - `{namespace}.{domain}.{action}.v{major}`
+/// - `{namespace}.{domain}.{action}.v{major}`（action は省略可: `{namespace}.{domain}.v{major}`）
 /// - 例: `acme.billing.charge.v1`
+/// - 各セグメントは英小文字で始まり、英小文字・数字・`_` だけからなる
 ///
-/// # 将来の拡張
-/// - バリデータ: 命名規約に従っているかチェック
-/// - パーサー: namespace, domain, action, version を抽出
+/// # 使用例
+/// ```ignore
+/// let task_type = TaskType::new("acme.billing.charge.v1")?;
+/// assert_eq!(task_type.namespace(), "acme");
+/// assert_eq!(task_type.version(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TaskType {
-    // TODO(v2後半): フィールド定義
-    // value: String
+    value: String,
+}
+
+/// TaskTypeError は命名規約の違反
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid task type '{value}': {reason}")]
+pub struct TaskTypeError {
+    pub value: String,
+    pub reason: &'static str,
 }
 
 impl TaskType {
-    // TODO(v2後半): メソッド実装
-    // - new(value: String) -> Result<Self, ValidationError>
-    // - validate(value: &str) -> Result<(), ValidationError>
-    // - parse(value: &str) -> Result<ParsedTaskType, ParseError>
+    /// 規約に従っていれば TaskType を作る
+    pub fn new(value: impl Into<String>) -> Result<Self, TaskTypeError> {
+        let value = value.into();
+        Self::validate(&value)?;
+        Ok(Self { value })
+    }
+
+    /// `value` が命名規約に従っているかチェック
+    pub fn validate(value: &str) -> Result<(), TaskTypeError> {
+        let invalid = |reason| TaskTypeError {
+            value: value.to_string(),
+            reason,
+        };
+        let segments: Vec<&str> = value.split('.').collect();
+        if !(3..=4).contains(&segments.len()) {
+            return Err(invalid(
+                "expected `namespace.domain.action.v1` (action is optional)",
+            ));
+        }
+        let (version, names) = segments.split_last().expect("at least 3 segments");
+        for name in names {
+            let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            if !valid {
+                return Err(invalid(
+                    "segments must be lowercase `a-z`, `0-9` or `_`, starting with a letter",
+                ));
+            }
+        }
+        match version.strip_prefix('v') {
+            Some(major) if !major.is_empty() && major.parse::<u32>().is_ok() => Ok(()),
+            _ => Err(invalid("must end with a major version like `.v1`")),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    fn segments(&self) -> Vec<&str> {
+        self.value.split('.').collect()
+    }
+
+    /// 例: `acme.billing.charge.v1` → `acme`
+    pub fn namespace(&self) -> &str {
+        self.segments()[0]
+    }
+
+    /// 例: `acme.billing.charge.v1` → `billing`
+    pub fn domain(&self) -> &str {
+        self.segments()[1]
+    }
+
+    /// 例: `acme.billing.charge.v1` → `Some("charge")`（3 セグメントなら `None`）
+    pub fn action(&self) -> Option<&str> {
+        let segments = self.segments();
+        (segments.len() == 4).then(|| segments[2])
+    }
+
+    /// 例: `acme.billing.charge.v1` → `1`
+    pub fn version(&self) -> u32 {
+        let version = self.value.rsplit('.').next().expect("validated");
+        version[1..].parse().expect("validated")
+    }
+}
+
+impl fmt::Display for TaskType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_types_are_parsed_into_their_parts() {
+        let charge = TaskType::new("acme.billing.charge.v12").unwrap();
+        assert_eq!(charge.namespace(), "acme");
+        assert_eq!(charge.domain(), "billing");
+        assert_eq!(charge.action(), Some("charge"));
+        assert_eq!(charge.version(), 12);
+
+        let greet = TaskType::new("prelude.greet.v1").unwrap();
+        assert_eq!(greet.action(), None);
+        assert_eq!(greet.version(), 1);
+
+        for invalid in [
+            "hello",
+            "acme.v1",
+            "acme.billing.charge",
+            "acme.billing.charge.vx",
+            "Acme.billing.charge.v1",
+            "acme.billing-api.charge.v1",
+            "acme.billing.charge.extra.v1",
+        ] {
+            assert!(TaskType::new(invalid).is_err(), "{invalid}");
+        }
+    }
 }
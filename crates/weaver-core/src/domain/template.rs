@@ -0,0 +1,285 @@
+//! Job templates: named, parameterised jobs registered once at startup.
+//!
+//! Callers submit a template by name with parameter values instead of
+//! building the `JobSpec` themselves, so a pipeline's definition lives in
+//! one place (`InMemoryQueue::submit_template`, `weaver submit`).
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::JobSpec;
+
+/// A parameter of a template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateParam {
+    pub name: String,
+
+    /// Used when the caller does not pass the parameter; `None` makes it required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+}
+
+impl TemplateParam {
+    pub fn required(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            default: None,
+        }
+    }
+
+    pub fn optional(name: impl Into<String>, default: serde_json::Value) -> Self {
+        Self {
+            name: name.into(),
+            default: Some(default),
+        }
+    }
+}
+
+/// A named job whose task payloads contain `{param}` placeholders.
+///
+/// A string that is exactly one placeholder is replaced by the value itself
+/// (so `"{limit}"` can become a number); elsewhere the value is spliced into
+/// the string. Other `{...}` placeholders are left as they are, so a
+/// template can also be the job of a `RecurringSchedule` (`{period_date}`).
+///
+/// ```ignore
+/// {
+///   "name": "nightly_etl",
+///   "params": [{ "name": "source" }, { "name": "limit", "default": 1000 }],
+///   "job": { "tasks": [{ "task_type": "etl.extract.v1", "payload": { "from": "{source}", "limit": "{limit}" }, ... }] }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub params: Vec<TemplateParam>,
+    pub job: JobSpec,
+}
+
+impl JobTemplate {
+    pub fn new(name: impl Into<String>, params: Vec<TemplateParam>, job: JobSpec) -> Self {
+        Self {
+            name: name.into(),
+            params,
+            job,
+        }
+    }
+
+    /// The job with `params` (a JSON object, or null for none) filled in.
+    pub fn instantiate(&self, params: &serde_json::Value) -> Result<JobSpec, TemplateError> {
+        let given = match params {
+            serde_json::Value::Null => &serde_json::Map::new(),
+            serde_json::Value::Object(given) => given,
+            _ => return Err(TemplateError::ParamsNotAnObject(self.name.clone())),
+        };
+        if let Some(unknown) = given
+            .keys()
+            .find(|name| !self.params.iter().any(|param| &param.name == *name))
+        {
+            return Err(TemplateError::UnknownParam {
+                template: self.name.clone(),
+                param: unknown.clone(),
+            });
+        }
+
+        let mut vars = Vec::with_capacity(self.params.len());
+        for param in &self.params {
+            let value = given
+                .get(&param.name)
+                .or(param.default.as_ref())
+                .ok_or_else(|| TemplateError::MissingParam {
+                    template: self.name.clone(),
+                    param: param.name.clone(),
+                })?;
+            vars.push((format!("{{{}}}", param.name), value.clone()));
+        }
+        let mut job = self.job.clone();
+        for task in &mut job.tasks {
+            fill_params(&mut task.payload, &vars);
+        }
+        Ok(job)
+    }
+}
+
+fn fill_params(value: &mut serde_json::Value, vars: &[(String, serde_json::Value)]) {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Some((_, replacement)) = vars.iter().find(|(placeholder, _)| placeholder == s) {
+                *value = replacement.clone();
+                return;
+            }
+            for (placeholder, replacement) in vars {
+                if s.contains(placeholder.as_str()) {
+                    let replacement = match replacement {
+                        serde_json::Value::String(r) => r.clone(),
+                        other => other.to_string(),
+                    };
+                    *s = s.replace(placeholder.as_str(), &replacement);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                fill_params(item, vars);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values_mut() {
+                fill_params(field, vars);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Why a template could not be registered or instantiated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    Duplicate(String),
+    Unknown(String),
+    ParamsNotAnObject(String),
+    MissingParam { template: String, param: String },
+    UnknownParam { template: String, param: String },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate(name) => write!(f, "template `{name}` is already registered"),
+            Self::Unknown(name) => write!(f, "no template named `{name}`"),
+            Self::ParamsNotAnObject(name) => {
+                write!(f, "template `{name}`: parameters must be a JSON object")
+            }
+            Self::MissingParam { template, param } => {
+                write!(f, "template `{template}`: missing parameter `{param}`")
+            }
+            Self::UnknownParam { template, param } => {
+                write!(f, "template `{template}` has no parameter `{param}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Templates by name, registered at startup.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateRegistry {
+    templates: BTreeMap<String, JobTemplate>,
+}
+
+impl TemplateRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a template; names are unique.
+    pub fn register(&mut self, template: JobTemplate) -> Result<(), TemplateError> {
+        if self.templates.contains_key(&template.name) {
+            return Err(TemplateError::Duplicate(template.name));
+        }
+        self.templates.insert(template.name.clone(), template);
+        Ok(())
+    }
+
+    /// A registry holding `templates` (e.g. read from a JSON file).
+    pub fn from_templates(
+        templates: impl IntoIterator<Item = JobTemplate>,
+    ) -> Result<Self, TemplateError> {
+        let mut registry = Self::new();
+        for template in templates {
+            registry.register(template)?;
+        }
+        Ok(registry)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&JobTemplate> {
+        self.templates.get(name)
+    }
+
+    /// Registered names, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.templates.keys().map(String::as_str)
+    }
+
+    /// The job of template `name` with `params` filled in.
+    pub fn instantiate(
+        &self,
+        name: &str,
+        params: &serde_json::Value,
+    ) -> Result<JobSpec, TemplateError> {
+        self.get(name)
+            .ok_or_else(|| TemplateError::Unknown(name.to_string()))?
+            .instantiate(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{TaskSpec, TaskType};
+
+    fn nightly_etl() -> JobTemplate {
+        JobTemplate::new(
+            "nightly_etl",
+            vec![
+                TemplateParam::required("source"),
+                TemplateParam::optional("limit", serde_json::json!(1000)),
+            ],
+            JobSpec::new(vec![TaskSpec::new(
+                "extract",
+                TaskType::new("etl.extract.v1"),
+                serde_json::json!({
+                    "from": "{source}",
+                    "path": "s3://{source}/{period_date}",
+                    "limit": "{limit}",
+                }),
+            )]),
+        )
+    }
+
+    #[test]
+    fn instantiate_fills_params_and_defaults() {
+        let mut registry = TemplateRegistry::from_templates([nightly_etl()]).unwrap();
+
+        let job = registry
+            .instantiate("nightly_etl", &serde_json::json!({ "source": "orders" }))
+            .unwrap();
+        assert_eq!(
+            job.tasks[0].payload,
+            serde_json::json!({
+                "from": "orders",
+                "path": "s3://orders/{period_date}",
+                "limit": 1000,
+            })
+        );
+
+        assert_eq!(
+            registry
+                .instantiate("nightly_etl", &serde_json::Value::Null)
+                .unwrap_err(),
+            TemplateError::MissingParam {
+                template: "nightly_etl".to_string(),
+                param: "source".to_string(),
+            }
+        );
+        assert!(matches!(
+            registry.instantiate(
+                "nightly_etl",
+                &serde_json::json!({ "source": "a", "lmit": 1 })
+            ),
+            Err(TemplateError::UnknownParam { .. })
+        ));
+        assert!(matches!(
+            registry.instantiate("hourly_etl", &serde_json::Value::Null),
+            Err(TemplateError::Unknown(_))
+        ));
+        assert_eq!(
+            registry.register(nightly_etl()),
+            Err(TemplateError::Duplicate("nightly_etl".to_string()))
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! AesGcmCipher - AES-256-GCM の EnvelopeCipher（鍵のローテーション対応、feature `aes-gcm`）
+//!
+//! # 学習ポイント
+//! - 暗号そのものは RustCrypto の `aes-gcm` に任せる（定数時間の実装で、
+//!   AES-NI / CLMUL があれば使う）。ここで持つのは key_id ごとの鍵と nonce の生成だけ
+//! - nonce は 96 ビットの乱数。同じ鍵で nonce が重なると安全性が崩れるので、
+//!   1 つの鍵で封をする数は 2^32 程度までにしてローテーションする
+//! - 鍵（展開済みの鍵を含む）は捨てるときに zeroize される
+//!
+//! # ローテーション
+//! 1. `rotate("2024-07", key)` で新しい鍵を current にする（古い鍵は復号用に残る）
+//! 2. `InMemoryQueue::reseal()` で保存済みの payload を新しい鍵で封をし直す
+//! 3. `retire("2024-06")` で古い鍵を捨てる
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+use crate::ports::{CipherError, EnvelopeCipher, SealedPayload};
+
+const NONCE_LEN: usize = 12;
+
+/// AesGcmCipher は key_id ごとの AES-256 鍵を持ち、current の鍵で封をする
+///
+/// # 使用例
+/// ```ignore
+/// let cipher = Arc::new(AesGcmCipher::new("2024-06", key));
+/// let queue = InMemoryQueue::new(policy).with_cipher(cipher.clone());
+/// // 後日
+/// cipher.rotate("2024-07", new_key);
+/// queue.reseal().await;
+/// cipher.retire("2024-06");
+/// ```
+pub struct AesGcmCipher {
+    keys: RwLock<KeyRing>,
+}
+
+struct KeyRing {
+    current: String,
+    keys: HashMap<String, Aes256Gcm>,
+}
+
+impl fmt::Debug for AesGcmCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 鍵そのものは出さない
+        let ring = self.keys.read().expect("key ring lock poisoned");
+        let mut key_ids: Vec<&str> = ring.keys.keys().map(String::as_str).collect();
+        key_ids.sort_unstable();
+        f.debug_struct("AesGcmCipher")
+            .field("current", &ring.current)
+            .field("key_ids", &key_ids)
+            .finish()
+    }
+}
+
+impl AesGcmCipher {
+    /// `key_id` の鍵で封をする cipher
+    pub fn new(key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        let key_id = key_id.into();
+        let keys = HashMap::from([(key_id.clone(), Aes256Gcm::new(&key.into()))]);
+        Self {
+            keys: RwLock::new(KeyRing {
+                current: key_id,
+                keys,
+            }),
+        }
+    }
+
+    /// 復号だけに使う古い鍵を足す（再起動後もローテーション前の payload を開ける）
+    pub fn with_key(self, key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        self.write()
+            .keys
+            .insert(key_id.into(), Aes256Gcm::new(&key.into()));
+        self
+    }
+
+    /// `key_id` を新しい current にする（それまでの鍵は復号用に残る）
+    pub fn rotate(&self, key_id: impl Into<String>, key: [u8; 32]) {
+        let key_id = key_id.into();
+        let mut ring = self.write();
+        ring.keys
+            .insert(key_id.clone(), Aes256Gcm::new(&key.into()));
+        ring.current = key_id;
+    }
+
+    /// 古い鍵を捨てる（current は捨てられない）。捨てたら true
+    pub fn retire(&self, key_id: &str) -> bool {
+        let mut ring = self.write();
+        ring.current != key_id && ring.keys.remove(key_id).is_some()
+    }
+
+    pub fn current_key_id(&self) -> String {
+        self.keys
+            .read()
+            .expect("key ring lock poisoned")
+            .current
+            .clone()
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, KeyRing> {
+        self.keys.write().expect("key ring lock poisoned")
+    }
+}
+
+impl EnvelopeCipher for AesGcmCipher {
+    fn seal(&self, aad: &[u8], plaintext: &[u8]) -> SealedPayload {
+        let ring = self.keys.read().expect("key ring lock poisoned");
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        SealedPayload {
+            key_id: ring.current.clone(),
+            nonce: nonce.to_vec(),
+            ciphertext: seal(&ring.keys[&ring.current], &nonce, aad, plaintext),
+        }
+    }
+
+    fn open(&self, aad: &[u8], sealed: &SealedPayload) -> Result<Vec<u8>, CipherError> {
+        let ring = self.keys.read().expect("key ring lock poisoned");
+        let key = ring
+            .keys
+            .get(&sealed.key_id)
+            .ok_or_else(|| CipherError::UnknownKey(sealed.key_id.clone()))?;
+        let nonce: &[u8; NONCE_LEN] = sealed
+            .nonce
+            .as_slice()
+            .try_into()
+            .map_err(|_| CipherError::Malformed("nonce must be 12 bytes".to_string()))?;
+        open(key, nonce, aad, &sealed.ciphertext)
+    }
+}
+
+/// `plaintext` を暗号化する（戻り値は暗号文 + 16 バイトの認証タグ）
+fn seal(key: &Aes256Gcm, nonce: &[u8; NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let payload = Payload {
+        msg: plaintext,
+        aad,
+    };
+    key.encrypt(Nonce::from_slice(nonce), payload)
+        .expect("AES-GCM encrypts any payload that fits in memory")
+}
+
+/// 認証タグを確かめてから復号する
+fn open(
+    key: &Aes256Gcm,
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, CipherError> {
+    let payload = Payload { msg: sealed, aad };
+    key.decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| CipherError::AuthenticationFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn matches_the_gcm_test_vectors() {
+        // "The Galois/Counter Mode of Operation", test cases 14 and 16
+        let zero = Aes256Gcm::new(&[0; 32].into());
+        let sealed = seal(&zero, &[0; 12], &[], &[0; 16]);
+        assert_eq!(
+            sealed,
+            hex("cea7403d4d606b6e074ec5d3baf39d18 d0d1c8a799996bf0265b98b5d48ab919")
+        );
+
+        let key = hex("feffe9928665731c6d6a8f9467308308 feffe9928665731c6d6a8f9467308308");
+        let aes = Aes256Gcm::new_from_slice(&key).unwrap();
+        let nonce = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeef abaddad2");
+        let plaintext = hex(
+            "d9313225f88406e5a55909c5aff5269a 86a7a9531534f7da2e4c303d8a318a72
+             1c3c0c95956809532fcf0e2449a6b525 b16aedf5aa0de657ba637b39",
+        );
+        let sealed = seal(&aes, &nonce, &aad, &plaintext);
+        assert_eq!(
+            sealed,
+            hex(
+                "522dc1f099567d07f47f37a32a84427d 643a8cdcbfe5c0c97598a2bd2555d1aa
+                 8cb08e48590dbb3da7b08b1056828838 c5f61e6393ba7a0abcc9f662
+                 76fc6ece0f4e1768cddf8853bb2d551b"
+            )
+        );
+        assert_eq!(open(&aes, &nonce, &aad, &sealed).unwrap(), plaintext);
+        assert_eq!(
+            open(&aes, &nonce, b"other aad", &sealed),
+            Err(CipherError::AuthenticationFailed)
+        );
+    }
+
+    #[test]
+    fn rotated_keys_still_open_older_payloads_until_retired() {
+        let cipher = AesGcmCipher::new("k1", [1; 32]);
+        let old = cipher.seal(b"aad", b"secret");
+        assert_eq!(old.key_id, "k1");
+
+        cipher.rotate("k2", [2; 32]);
+        let new = cipher.seal(b"aad", b"secret");
+        assert_eq!(new.key_id, "k2");
+        assert_ne!(old.ciphertext, new.ciphertext);
+        assert_eq!(cipher.open(b"aad", &old).unwrap(), b"secret");
+
+        assert!(!cipher.retire("k2"));
+        assert!(cipher.retire("k1"));
+        assert_eq!(
+            cipher.open(b"aad", &old),
+            Err(CipherError::UnknownKey("k1".to_string()))
+        );
+        assert!(!format!("{cipher:?}").contains("[2, 2"));
+    }
+}
@@ -0,0 +1,296 @@
+//! CborFormat - payload を CBOR（RFC 8949）で書く PayloadFormat（feature `cbor`）
+//!
+//! # 学習ポイント
+//! - 先頭 1 バイトの上位 3 ビットが major type、下位 5 ビットが引数（24〜27 なら後ろに 1/2/4/8 バイト）
+//! - 負の整数は `-1 - n` で持つので、i64 に入らない値もある（その場合は f64 にする）
+//! - JSON の値だけを扱うので、byte string は数値の配列として読み、tag は読み飛ばす
+//! - 不定長（引数 31）は書かないし読まない
+//! - 読むときは配列 / map / tag の入れ子を `MAX_DEPTH` 段までに制限する
+
+use serde_json::{Map, Number, Value};
+
+use crate::typed::{CodecError, PayloadFormat};
+
+/// CborFormat は `application/cbor`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborFormat;
+
+impl PayloadFormat for CborFormat {
+    fn content_type(&self) -> &'static str {
+        "application/cbor"
+    }
+
+    fn encode(&self, value: &Value) -> Result<Vec<u8>, CodecError> {
+        let mut out = Vec::new();
+        write_value(&mut out, value);
+        Ok(out)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CodecError> {
+        let mut reader = Reader {
+            bytes,
+            pos: 0,
+            depth: 0,
+        };
+        let value = reader.value()?;
+        if reader.pos != bytes.len() {
+            return Err(error("trailing bytes"));
+        }
+        Ok(value)
+    }
+}
+
+/// 配列 / map / tag の入れ子の上限
+const MAX_DEPTH: usize = 128;
+
+fn error(message: &str) -> CodecError {
+    CodecError::DeserializeFailed(format!("cbor: {message}"))
+}
+
+/// major type と引数を最小の形で書く
+fn write_head(out: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    if arg < 24 {
+        out.push(major | arg as u8);
+    } else if let Ok(arg) = u8::try_from(arg) {
+        out.extend_from_slice(&[major | 24, arg]);
+    } else if let Ok(arg) = u16::try_from(arg) {
+        out.push(major | 25);
+        out.extend_from_slice(&arg.to_be_bytes());
+    } else if let Ok(arg) = u32::try_from(arg) {
+        out.push(major | 26);
+        out.extend_from_slice(&arg.to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&arg.to_be_bytes());
+    }
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(b) => out.push(if *b { 0xf5 } else { 0xf4 }),
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                write_head(out, 0, u);
+            } else if let Some(i) = n.as_i64() {
+                // as_u64 が None なので負: -1 - n
+                write_head(out, 1, !(i as u64));
+            } else {
+                out.push(0xfb);
+                out.extend_from_slice(&n.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        }
+        Value::String(s) => {
+            write_head(out, 3, s.len() as u64);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            write_head(out, 4, items.len() as u64);
+            for item in items {
+                write_value(out, item);
+            }
+        }
+        Value::Object(fields) => {
+            write_head(out, 5, fields.len() as u64);
+            for (key, value) in fields {
+                write_head(out, 3, key.len() as u64);
+                out.extend_from_slice(key.as_bytes());
+                write_value(out, value);
+            }
+        }
+    }
+}
+
+/// 半精度（major 7, 引数 25）を f64 に
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f64::from(bits & 0x3ff);
+    match exponent {
+        0 => sign * mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => sign * f64::INFINITY,
+        31 => f64::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// いま読んでいる配列 / map / tag の入れ子の深さ
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], CodecError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| error("unexpected end of input"))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn uint(&mut self, size: usize) -> Result<u64, CodecError> {
+        Ok(self
+            .take(size)?
+            .iter()
+            .fold(0, |n, &b| (n << 8) | u64::from(b)))
+    }
+
+    /// 下位 5 ビットの引数（24〜27 なら続くバイトから読む）
+    fn arg(&mut self, info: u8) -> Result<u64, CodecError> {
+        match info {
+            0..=23 => Ok(u64::from(info)),
+            24..=27 => self.uint(1 << (info - 24)),
+            31 => Err(error("indefinite-length items are not supported")),
+            _ => Err(error("reserved additional information")),
+        }
+    }
+
+    fn len(&mut self, info: u8) -> Result<usize, CodecError> {
+        usize::try_from(self.arg(info)?).map_err(|_| error("length does not fit in memory"))
+    }
+
+    fn float(f: f64) -> Result<Value, CodecError> {
+        Number::from_f64(f)
+            .map(Value::Number)
+            .ok_or_else(|| error("NaN and infinity are not valid JSON"))
+    }
+
+    fn value(&mut self) -> Result<Value, CodecError> {
+        let initial = self.bytes.get(self.pos).copied();
+        // 配列 / map / tag なら中に入る（深すぎたらエラー）
+        let nested = initial.is_some_and(|b| matches!(b >> 5, 4..=6));
+        if nested {
+            if self.depth == MAX_DEPTH {
+                return Err(error("nested too deeply"));
+            }
+            self.depth += 1;
+        }
+        let value = self.item();
+        if nested {
+            self.depth -= 1;
+        }
+        value
+    }
+
+    fn item(&mut self) -> Result<Value, CodecError> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        match major {
+            0 => Ok(Value::from(self.arg(info)?)),
+            1 => {
+                let n = self.arg(info)?;
+                match i64::try_from(n) {
+                    Ok(n) => Ok(Value::from(-1 - n)),
+                    Err(_) => Self::float(-1.0 - n as f64),
+                }
+            }
+            2 => {
+                let len = self.len(info)?;
+                let bytes = self.take(len)?;
+                Ok(Value::Array(
+                    bytes.iter().map(|&b| Value::from(b)).collect(),
+                ))
+            }
+            3 => {
+                let len = self.len(info)?;
+                let s = std::str::from_utf8(self.take(len)?)
+                    .map_err(|_| error("text string is not UTF-8"))?;
+                Ok(Value::String(s.to_string()))
+            }
+            4 => {
+                let len = self.len(info)?;
+                let mut items = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    items.push(self.value()?);
+                }
+                Ok(Value::Array(items))
+            }
+            5 => {
+                let len = self.len(info)?;
+                let mut fields = Map::new();
+                for _ in 0..len {
+                    let Value::String(key) = self.value()? else {
+                        return Err(error("map keys must be text strings"));
+                    };
+                    fields.insert(key, self.value()?);
+                }
+                Ok(Value::Object(fields))
+            }
+            6 => {
+                // tag（日時など）は意味を付けずに中身だけ読む
+                self.arg(info)?;
+                self.value()
+            }
+            _ => match info {
+                20 => Ok(Value::Bool(false)),
+                21 => Ok(Value::Bool(true)),
+                22 | 23 => Ok(Value::Null),
+                25 => Self::float(f16_to_f64(self.uint(2)? as u16)),
+                26 => Self::float(f64::from(f32::from_bits(self.uint(4)? as u32))),
+                27 => Self::float(f64::from_bits(self.uint(8)?)),
+                _ => Err(error("unsupported simple value")),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values_roundtrip_and_match_the_rfc_examples() {
+        let value = json!({
+            "small": 1,
+            "negative": -500,
+            "big": u64::MAX,
+            "min": i64::MIN,
+            "pi": 3.5,
+            "text": "x".repeat(40),
+            "list": [null, true, false, [], {}],
+        });
+        let bytes = CborFormat.encode(&value).unwrap();
+        assert_eq!(CborFormat.decode(&bytes).unwrap(), value);
+
+        // RFC 8949 Appendix A
+        assert_eq!(CborFormat.encode(&json!(100)).unwrap(), [0x18, 0x64]);
+        assert_eq!(
+            CborFormat.encode(&json!(-1000)).unwrap(),
+            [0x39, 0x03, 0xe7]
+        );
+        assert_eq!(
+            CborFormat.encode(&json!({ "a": [2] })).unwrap(),
+            [0xa1, 0x61, b'a', 0x81, 0x02]
+        );
+        assert_eq!(CborFormat.decode(&[0xf9, 0x3e, 0x00]).unwrap(), json!(1.5));
+        // tag 1（epoch 時刻）は中身の数値になる
+        assert_eq!(
+            CborFormat
+                .decode(&[0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0])
+                .unwrap(),
+            json!(1363896240)
+        );
+        assert!(CborFormat.decode(&[0x9f, 0x01, 0xff]).is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected_without_overflowing_the_stack() {
+        for marker in [0x81, 0xc1] {
+            let deep = vec![marker; 200_000];
+            let err = CborFormat.decode(&deep).unwrap_err();
+            assert!(err.to_string().contains("nested too deeply"), "{err}");
+        }
+
+        // MAX_DEPTH 段ちょうどは読める
+        let mut ok = vec![0x81; MAX_DEPTH];
+        ok.push(0xf6);
+        assert!(CborFormat.decode(&ok).is_ok());
+    }
+}
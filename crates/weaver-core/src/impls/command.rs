@@ -0,0 +1,232 @@
+//! CommandHandler - 外部コマンドを実行する組み込み TaskHandler
+//!
+//! payload の例:
+//! ```json
+//! { "cmd": "ffmpeg", "args": ["-i", "in.mov", "out.mp4"], "env": { "LANG": "C" }, "timeout_ms": 60000 }
+//! ```
+//!
+//! # 学習ポイント
+//! - シェルを通さない（`cmd` はプログラム名、`args` はそのまま渡す）ので、
+//!   payload の文字列がシェルに解釈されることはない
+//! - `kill_on_drop(true)` により、待っている future を drop すれば子プロセスが kill される。
+//!   timeout と `TaskContext` のキャンセルはどちらもこれで止める
+//! - payload で任意のコマンドを実行できるので、信頼できない入力を受けるキューでは
+//!   `with_allowed_commands` で実行できるコマンドを絞ること
+
+#![allow(deprecated)]
+
+use std::collections::{BTreeMap, HashSet};
+use std::process::Stdio;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::domain::{Artifact, Outcome, TaskEnvelope};
+use crate::error::WeaverError;
+use crate::runtime::{TaskContext, TaskHandler};
+
+/// CommandHandler の payload
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandSpec {
+    pub cmd: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// 子プロセスに追加する環境変数（プロセスの環境は引き継ぐ）
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// これを過ぎたら kill して失敗にする（`None` は無制限）
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// CommandHandler は payload のコマンドを実行し、stdout / stderr を Artifact にする
+///
+/// - 終了コード 0: 成功（`Artifact::Stdout` / `Artifact::Stderr`、空なら付けない）
+/// - 0 以外・timeout・キャンセル: 失敗（Decider がリトライを決める）
+/// - payload が不正・コマンドを起動できない: `Err`
+///
+/// # 使用例
+/// ```ignore
+/// registry.register(
+///     TaskType::new("ops.command.v1"),
+///     Arc::new(CommandHandler::new().with_allowed_commands(["pg_dump", "gzip"])),
+/// )?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CommandHandler {
+    /// 実行を許すコマンド（`None` はすべて）
+    allowed: Option<HashSet<String>>,
+}
+
+impl CommandHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `commands` 以外の `cmd` を失敗にする
+    pub fn with_allowed_commands(
+        mut self,
+        commands: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.allowed = Some(commands.into_iter().map(Into::into).collect());
+        self
+    }
+
+    async fn run(&self, spec: CommandSpec, ctx: &TaskContext) -> Result<Outcome, WeaverError> {
+        if let Some(allowed) = &self.allowed
+            && !allowed.contains(&spec.cmd)
+        {
+            return Ok(Outcome::failure(format!(
+                "command {} is not allowed",
+                spec.cmd
+            )));
+        }
+
+        let child = Command::new(&spec.cmd)
+            .args(&spec.args)
+            .envs(&spec.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| WeaverError::Other(format!("spawn {}: {e}", spec.cmd)))?;
+
+        let timeout = async {
+            match spec.timeout_ms {
+                Some(ms) => tokio::time::sleep(Duration::from_millis(ms)).await,
+                None => std::future::pending().await,
+            }
+        };
+        // 早く終わった分岐以外は drop され、子プロセスは kill される
+        let output = tokio::select! {
+            output = child.wait_with_output() => {
+                output.map_err(|e| WeaverError::Other(format!("wait {}: {e}", spec.cmd)))?
+            }
+            _ = timeout => {
+                return Ok(Outcome::failure(format!(
+                    "{} timed out after {}ms",
+                    spec.cmd,
+                    spec.timeout_ms.unwrap_or_default()
+                )));
+            }
+            _ = ctx.cancellation().cancelled() => {
+                return Ok(Outcome::failure(format!("{} killed: task cancelled", spec.cmd)));
+            }
+        };
+
+        let mut outcome = if output.status.success() {
+            Outcome::success()
+        } else {
+            Outcome::failure(format!("{} exited with {}", spec.cmd, output.status))
+        };
+        if !output.stdout.is_empty() {
+            let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+            outcome = outcome.with_artifact(Artifact::Stdout(stdout));
+        }
+        if !output.stderr.is_empty() {
+            let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+            outcome = outcome.with_artifact(Artifact::Stderr(stderr));
+        }
+        Ok(outcome)
+    }
+}
+
+#[async_trait]
+impl TaskHandler for CommandHandler {
+    async fn handle(&self, envelope: &TaskEnvelope) -> Result<Outcome, WeaverError> {
+        self.handle_with_context(envelope, &TaskContext::default())
+            .await
+    }
+
+    async fn handle_with_context(
+        &self,
+        envelope: &TaskEnvelope,
+        ctx: &TaskContext,
+    ) -> Result<Outcome, WeaverError> {
+        let spec = CommandSpec::deserialize(envelope.payload())
+            .map_err(|e| WeaverError::Other(format!("invalid command payload: {e}")))?;
+        self.run(spec, ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{OutcomeKind, TaskId, TaskType};
+    use crate::runtime::CancellationToken;
+
+    fn envelope(payload: serde_json::Value) -> TaskEnvelope {
+        TaskEnvelope::new(TaskId::new(1), TaskType::new("command"), payload)
+    }
+
+    #[tokio::test]
+    async fn captures_output_and_fails_on_non_zero_exit() {
+        let handler = CommandHandler::new();
+        let outcome = handler
+            .handle(&envelope(serde_json::json!({
+                "cmd": "sh",
+                "args": ["-c", "echo \"out $GREETING\"; echo err >&2; exit 3"],
+                "env": { "GREETING": "hi" },
+            })))
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.kind, OutcomeKind::Failure);
+        assert!(outcome.reason.unwrap().contains("exit status: 3"));
+        assert_eq!(
+            outcome.artifacts,
+            [
+                Artifact::Stdout("out hi\n".to_string()),
+                Artifact::Stderr("err\n".to_string()),
+            ]
+        );
+
+        let denied = CommandHandler::new().with_allowed_commands(["echo"]);
+        let outcome = denied
+            .handle(&envelope(serde_json::json!({ "cmd": "sh" })))
+            .await
+            .unwrap();
+        assert_eq!(outcome.reason.as_deref(), Some("command sh is not allowed"));
+        assert!(
+            handler
+                .handle(&envelope(serde_json::json!({ "command": "sh" })))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn timeout_and_cancellation_kill_the_child() {
+        let handler = CommandHandler::new();
+        let started = std::time::Instant::now();
+        let outcome = handler
+            .handle(&envelope(serde_json::json!({
+                "cmd": "sleep",
+                "args": ["5"],
+                "timeout_ms": 50,
+            })))
+            .await
+            .unwrap();
+        assert!(outcome.reason.unwrap().contains("timed out"));
+
+        let cancellation = CancellationToken::new();
+        let ctx = TaskContext::default().with_cancellation(cancellation.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancellation.cancel();
+        });
+        let outcome = handler
+            .handle_with_context(
+                &envelope(serde_json::json!({ "cmd": "sleep", "args": ["5"] })),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(outcome.reason.unwrap().contains("task cancelled"));
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+}
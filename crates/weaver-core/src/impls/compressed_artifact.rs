@@ -0,0 +1,134 @@
+//! CompressedArtifactStore - 大きな artifact を圧縮して保存する ArtifactStore
+//!
+//! # 学習ポイント
+//! - どの ArtifactStore でも包める（Blob 側は圧縮を知らなくてよい）
+//! - `threshold` バイト以上のものだけ圧縮する（小さいものは圧縮しても縮まない）
+//! - 圧縮した本体の先頭に Compressor の名前を記録する（`ports::compressor::compress_framed`）。
+//!   `get` はそれを見て展開するので、圧縮を有効にする前の artifact もそのまま読める
+//! - `ArtifactMeta::size_bytes` は保存した（圧縮後の）大きさ。GC が回収した量になる
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::domain::ids::ArtifactId;
+use crate::ports::compressor::{compress_framed, decompress_framed};
+use crate::ports::{ArtifactError, ArtifactMeta, ArtifactStore, Compressor};
+
+/// CompressedArtifactStore は `inner` に圧縮して保存する
+///
+/// # 使用例
+/// ```ignore
+/// let store = CompressedArtifactStore::new(
+///     Arc::new(InMemoryArtifactStore::new()),
+///     Arc::new(Zstd::default()),
+///     16 * 1024,
+/// );
+/// ```
+pub struct CompressedArtifactStore {
+    inner: Arc<dyn ArtifactStore>,
+    compressor: Arc<dyn Compressor>,
+    threshold: usize,
+}
+
+impl CompressedArtifactStore {
+    pub fn new(
+        inner: Arc<dyn ArtifactStore>,
+        compressor: Arc<dyn Compressor>,
+        threshold: usize,
+    ) -> Self {
+        Self {
+            inner,
+            compressor,
+            threshold,
+        }
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for CompressedArtifactStore {
+    async fn put(
+        &self,
+        ns: &str,
+        bytes: Vec<u8>,
+        content_type: Option<&str>,
+        ttl: Option<Duration>,
+        now: DateTime<Utc>,
+    ) -> Result<ArtifactMeta, ArtifactError> {
+        let bytes = if bytes.len() < self.threshold {
+            bytes
+        } else {
+            compress_framed(self.compressor.as_ref(), &bytes)
+                .map_err(|e| ArtifactError::OperationFailed(e.to_string()))?
+        };
+        self.inner.put(ns, bytes, content_type, ttl, now).await
+    }
+
+    async fn get(&self, ns: &str, artifact_id: ArtifactId) -> Result<Vec<u8>, ArtifactError> {
+        let bytes = self.inner.get(ns, artifact_id).await?;
+        match decompress_framed(self.compressor.as_ref(), &bytes) {
+            None => Ok(bytes),
+            Some(decompressed) => {
+                decompressed.map_err(|e| ArtifactError::OperationFailed(e.to_string()))
+            }
+        }
+    }
+
+    async fn delete(&self, ns: &str, artifact_id: ArtifactId) -> Result<(), ArtifactError> {
+        self.inner.delete(ns, artifact_id).await
+    }
+
+    async fn list_expired(
+        &self,
+        ns: &str,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<ArtifactMeta>, ArtifactError> {
+        self.inner.list_expired(ns, now, limit).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::impls::InMemoryArtifactStore;
+    use crate::ports::compressor::RunLength;
+
+    #[tokio::test]
+    async fn large_artifacts_are_stored_compressed_and_read_back() {
+        let inner = Arc::new(InMemoryArtifactStore::new());
+        let store = CompressedArtifactStore::new(inner.clone(), Arc::new(RunLength), 64);
+        let now = Utc::now();
+
+        let large = vec![b'x'; 4096];
+        let meta = store
+            .put("ns", large.clone(), Some("text/plain"), None, now)
+            .await
+            .unwrap();
+        assert!(meta.size_bytes < 100);
+        assert_eq!(meta.content_type.as_deref(), Some("text/plain"));
+        assert!(
+            inner
+                .get("ns", meta.artifact_id)
+                .await
+                .unwrap()
+                .starts_with(b"WVZ1\x03rle")
+        );
+        assert_eq!(store.get("ns", meta.artifact_id).await.unwrap(), large);
+
+        let small = store
+            .put("ns", b"{}".to_vec(), None, None, now)
+            .await
+            .unwrap();
+        assert_eq!(inner.get("ns", small.artifact_id).await.unwrap(), b"{}");
+
+        // 圧縮を有効にする前に保存したもの
+        let old = inner
+            .put("ns", large.clone(), None, None, now)
+            .await
+            .unwrap();
+        assert_eq!(store.get("ns", old.artifact_id).await.unwrap(), large);
+    }
+}
@@ -0,0 +1,406 @@
+//! EventSink の実装 - ログへの出力（TracingEventSink）とテスト用の記録（BufferedVecEventSink）
+//!
+//! # 学習ポイント
+//! - tracing クレートに依存しないため、TracingEventSink は 1 イベント 1 行の
+//!   JSON を書く（`ts` / `level` / `target` と、イベントの中身）。
+//!   ログ収集側（Vector, Fluent Bit など）でそのまま構造化ログとして扱える
+//! - 問題を表すイベント（`DomainEvent::is_problem()`）は `WARN`、それ以外は `INFO`
+//! - キューは 1 本の forwarder タスクから順に `emit()` するので、
+//!   BufferedVecEventSink には発生順に並ぶ
+//! - CompositeEventSink / FilteredEventSink / SamplingEventSink は他の sink を包む。
+//!   組み合わせて「全件は監査ログへ、問題だけ通知へ、1% をデバッグ用へ」のように
+//!   送り先ごとに粒度を変えられる
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use tokio::sync::Notify;
+
+use crate::domain::TaskType;
+use crate::domain::events::DomainEvent;
+use crate::ports::{EventSink, EventSinkError};
+
+/// TracingEventSink はイベントを構造化ログ（JSON Lines）として書く
+///
+/// # 使用例
+/// ```ignore
+/// let queue = InMemoryQueue::new(RetryPolicy::default_v1())
+///     .with_event_sink(Arc::new(TracingEventSink::new()));
+/// // {"event":"task_enqueued","job_id":null,"level":"INFO","target":"weaver::events",...}
+/// ```
+pub struct TracingEventSink {
+    target: String,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl TracingEventSink {
+    /// 標準エラー出力に書く TracingEventSink を作成
+    pub fn new() -> Self {
+        Self::with_writer(io::stderr())
+    }
+
+    /// `out` に書く TracingEventSink を作成（ファイル、テスト用のバッファなど）
+    pub fn with_writer(out: impl Write + Send + 'static) -> Self {
+        Self {
+            target: "weaver::events".to_string(),
+            out: Mutex::new(Box::new(out)),
+        }
+    }
+
+    /// 行の `target`（デフォルト: `weaver::events`）
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = target.into();
+        self
+    }
+}
+
+impl Default for TracingEventSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for TracingEventSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TracingEventSink")
+            .field("target", &self.target)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl EventSink for TracingEventSink {
+    async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError> {
+        let failed = |e: &dyn std::fmt::Display| EventSinkError::EmitFailed(e.to_string());
+        let mut line = serde_json::to_value(&event).map_err(|e| failed(&e))?;
+        let level = if event.is_problem() { "WARN" } else { "INFO" };
+        if let Some(fields) = line.as_object_mut() {
+            fields.insert("ts".into(), chrono::Utc::now().to_rfc3339().into());
+            fields.insert("level".into(), level.into());
+            fields.insert("target".into(), self.target.clone().into());
+        }
+        let mut out = self.out.lock().unwrap();
+        writeln!(out, "{line}").map_err(|e| failed(&e))?;
+        out.flush().map_err(|e| failed(&e))
+    }
+}
+
+/// BufferedVecEventSink は受け取ったイベントをメモリに溜める（テスト用）
+///
+/// # 使用例
+/// ```ignore
+/// let sink = Arc::new(BufferedVecEventSink::new());
+/// let queue = InMemoryQueue::new(RetryPolicy::default_v1()).with_event_sink(sink.clone());
+/// // ...
+/// let events = sink.wait_for(4).await;
+/// ```
+#[derive(Debug, Default)]
+pub struct BufferedVecEventSink {
+    events: Mutex<Vec<DomainEvent>>,
+    changed: Notify,
+}
+
+impl BufferedVecEventSink {
+    /// 新しい BufferedVecEventSink を作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// これまでに受け取ったイベント（受け取った順）
+    pub fn events(&self) -> Vec<DomainEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// 受け取ったイベントを取り出して空にする
+    pub fn take(&self) -> Vec<DomainEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+
+    /// `count` 個以上溜まるまで待ってから `events()` を返す
+    ///
+    /// 届かなければ待ち続けるので、テストでは `tokio::time::timeout` で囲む。
+    pub async fn wait_for(&self, count: usize) -> Vec<DomainEvent> {
+        loop {
+            // 確認より先に登録しておけば、その間の emit を取りこぼさない
+            let changed = self.changed.notified();
+            let events = self.events();
+            if events.len() >= count {
+                return events;
+            }
+            changed.await;
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for BufferedVecEventSink {
+    async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError> {
+        self.events.lock().unwrap().push(event);
+        self.changed.notify_waiters();
+        Ok(())
+    }
+}
+
+/// CompositeEventSink は 1 つのイベントを全ての sink に送る（fan-out）
+///
+/// 1 つの sink が失敗しても残りには送る。エラーは最初のものを返す。
+///
+/// # 使用例
+/// ```ignore
+/// let sink = CompositeEventSink::new()
+///     .with_sink(Arc::new(TracingEventSink::new()))
+///     .with_sink(Arc::new(FilteredEventSink::new(alerts).only_problems()));
+/// ```
+#[derive(Default)]
+pub struct CompositeEventSink {
+    sinks: Vec<Arc<dyn EventSink>>,
+}
+
+impl CompositeEventSink {
+    /// sink のない CompositeEventSink を作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 送り先を追加（追加した順に emit する）
+    pub fn with_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+}
+
+#[async_trait]
+impl EventSink for CompositeEventSink {
+    async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError> {
+        let mut first_error = None;
+        for sink in &self.sinks {
+            if let Err(e) = sink.emit(event.clone()).await {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+/// FilteredEventSink は条件に合うイベントだけを内側の sink に送る
+///
+/// 条件を複数指定したときは全てを満たすものだけを送る。
+///
+/// # 使用例
+/// ```ignore
+/// // email のタスクの attempt の結果と Dead だけ
+/// let sink = FilteredEventSink::new(inner)
+///     .with_kinds(["attempt_finished", "task_dead"])
+///     .with_task_types([TaskType::new("email.send.v1")]);
+/// ```
+pub struct FilteredEventSink {
+    inner: Arc<dyn EventSink>,
+    kinds: Option<Vec<&'static str>>,
+    task_types: Option<Vec<TaskType>>,
+    only_problems: bool,
+}
+
+impl FilteredEventSink {
+    /// 全てのイベントを通す FilteredEventSink を作成
+    pub fn new(inner: Arc<dyn EventSink>) -> Self {
+        Self {
+            inner,
+            kinds: None,
+            task_types: None,
+            only_problems: false,
+        }
+    }
+
+    /// `DomainEvent::kind()` がいずれかに一致するものだけ
+    pub fn with_kinds(mut self, kinds: impl IntoIterator<Item = &'static str>) -> Self {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
+
+    /// いずれかの TaskType のタスクのイベントだけ
+    ///
+    /// TaskType を持たないイベント（ジョブ・worker など）は通さない。
+    pub fn with_task_types(mut self, task_types: impl IntoIterator<Item = TaskType>) -> Self {
+        self.task_types = Some(task_types.into_iter().collect());
+        self
+    }
+
+    /// 問題を表すイベント（`DomainEvent::is_problem()`）だけ
+    pub fn only_problems(mut self) -> Self {
+        self.only_problems = true;
+        self
+    }
+
+    fn matches(&self, event: &DomainEvent) -> bool {
+        if self.only_problems && !event.is_problem() {
+            return false;
+        }
+        if let Some(kinds) = &self.kinds
+            && !kinds.contains(&event.kind())
+        {
+            return false;
+        }
+        match &self.task_types {
+            Some(task_types) => event
+                .task_type()
+                .is_some_and(|task_type| task_types.contains(task_type)),
+            None => true,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for FilteredEventSink {
+    async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError> {
+        if !self.matches(&event) {
+            return Ok(());
+        }
+        self.inner.emit(event).await
+    }
+}
+
+/// SamplingEventSink はイベントを `rate` の割合だけ内側の sink に送る
+///
+/// 問題を表すイベント（`DomainEvent::is_problem()`）は間引かずに全て送る。
+/// 量の多いライフサイクルイベントをデバッグ用の sink に少しだけ流すときに使う。
+///
+/// # 使用例
+/// ```ignore
+/// // 1% だけ
+/// let sink = SamplingEventSink::new(Arc::new(TracingEventSink::new()), 0.01);
+/// ```
+pub struct SamplingEventSink {
+    inner: Arc<dyn EventSink>,
+    rate: f64,
+}
+
+impl SamplingEventSink {
+    /// `rate` は 0.0（何も送らない）〜 1.0（全て送る）。範囲外は丸める
+    pub fn new(inner: Arc<dyn EventSink>, rate: f64) -> Self {
+        Self {
+            inner,
+            rate: rate.clamp(0.0, 1.0),
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for SamplingEventSink {
+    async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError> {
+        if !event.is_problem() && rand::random::<f64>() >= self.rate {
+            return Ok(());
+        }
+        self.inner.emit(event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{JobId, TaskId, TaskType};
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn tracing_sink_writes_one_json_line_per_event() {
+        let buffer = SharedBuffer::default();
+        let sink = TracingEventSink::with_writer(buffer.clone()).with_target("app::jobs");
+
+        sink.emit(DomainEvent::TaskEnqueued {
+            task_id: TaskId::new(1),
+            task_type: TaskType::new("email"),
+            job_id: None,
+        })
+        .await
+        .unwrap();
+        sink.emit(DomainEvent::JobFailed {
+            job_id: JobId::new(7),
+            failed_tasks: 2,
+        })
+        .await
+        .unwrap();
+
+        let written = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "task_enqueued");
+        assert_eq!(lines[0]["task_type"], "email");
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["target"], "app::jobs");
+        assert_eq!(lines[1]["event"], "job_failed");
+        assert_eq!(lines[1]["failed_tasks"], 2);
+        assert_eq!(lines[1]["level"], "WARN");
+        assert!(lines[1]["ts"].is_string());
+    }
+
+    #[tokio::test]
+    async fn combinators_route_events_by_kind_task_type_and_rate() {
+        let all = Arc::new(BufferedVecEventSink::new());
+        let email = Arc::new(BufferedVecEventSink::new());
+        let problems = Arc::new(BufferedVecEventSink::new());
+        let sampled = Arc::new(BufferedVecEventSink::new());
+        let sink = CompositeEventSink::new()
+            .with_sink(all.clone())
+            .with_sink(Arc::new(
+                FilteredEventSink::new(email.clone())
+                    .with_kinds(["task_enqueued", "task_dead"])
+                    .with_task_types([TaskType::new("email")]),
+            ))
+            .with_sink(Arc::new(
+                FilteredEventSink::new(problems.clone()).only_problems(),
+            ))
+            .with_sink(Arc::new(SamplingEventSink::new(sampled.clone(), 0.0)));
+
+        let enqueued = |task_type: &str| DomainEvent::TaskEnqueued {
+            task_id: TaskId::new(1),
+            task_type: TaskType::new(task_type),
+            job_id: None,
+        };
+        sink.emit(enqueued("email")).await.unwrap();
+        sink.emit(enqueued("render")).await.unwrap();
+        sink.emit(DomainEvent::TaskStarted {
+            task_id: TaskId::new(1),
+            task_type: TaskType::new("email"),
+            attempt: 1,
+        })
+        .await
+        .unwrap();
+        sink.emit(DomainEvent::JobFailed {
+            job_id: JobId::new(7),
+            failed_tasks: 1,
+        })
+        .await
+        .unwrap();
+
+        let kinds = |sink: &BufferedVecEventSink| {
+            sink.events()
+                .iter()
+                .map(|event| (event.kind(), event.task_type().map(|t| t.to_string())))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(all.events().len(), 4);
+        assert_eq!(
+            kinds(&email),
+            [("task_enqueued", Some("email".to_string()))]
+        );
+        assert_eq!(kinds(&problems), [("job_failed", None)]);
+        // 0.0 でも問題を表すイベントは送る
+        assert_eq!(kinds(&sampled), [("job_failed", None)]);
+    }
+}
@@ -0,0 +1,245 @@
+//! FileEventSink - イベントを JSON Lines でファイルに追記する EventSink（監査ログ）
+//!
+//! # 学習ポイント
+//! - 1 イベント 1 行の JSON（`ts` とイベントの中身）。`ts` は Clock port から取るので
+//!   テストでは時刻を固定・進められる
+//! - ファイルは日ごと（UTC）に分かれ、上限サイズを超えると同じ日の次の番号に移る:
+//!   `events-2025-01-31.jsonl` → `events-2025-01-31.1.jsonl` → ...
+//! - 再起動後は同じ日の最後のファイルに追記を続ける
+//! - PG の outbox ができるまでの最小限の監査証跡。書き込みは同期 I/O で、
+//!   キューは 1 本の forwarder タスクから `emit()` するのでワーカーは待たない
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+
+use crate::domain::events::DomainEvent;
+use crate::ports::{Clock, EventSink, EventSinkError, SystemClock};
+
+/// デフォルトの 1 ファイルの上限（100 MiB）
+const DEFAULT_MAX_FILE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// FileEventSink は `dir` にイベントを追記する
+///
+/// # 使用例
+/// ```ignore
+/// let sink = FileEventSink::new("/var/log/weaver")?.with_max_file_bytes(10 * 1024 * 1024);
+/// let queue = InMemoryQueue::new(RetryPolicy::default_v1()).with_event_sink(Arc::new(sink));
+/// // /var/log/weaver/events-2025-01-31.jsonl:
+/// // {"event":"task_enqueued","job_id":null,"task_id":"task-1",...,"ts":"2025-01-31T09:00:00+00:00"}
+/// ```
+pub struct FileEventSink {
+    dir: PathBuf,
+    prefix: String,
+    max_file_bytes: u64,
+    clock: Arc<dyn Clock>,
+    current: Mutex<Option<OpenFile>>,
+}
+
+/// 書き込み中のファイル
+struct OpenFile {
+    file: File,
+    path: PathBuf,
+    day: NaiveDate,
+    index: u32,
+    bytes: u64,
+}
+
+impl FileEventSink {
+    /// `dir`（なければ作る）に書く FileEventSink を作成
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            prefix: "events".to_string(),
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            clock: Arc::new(SystemClock),
+            current: Mutex::new(None),
+        })
+    }
+
+    /// ファイル名の先頭（デフォルト: `events`）
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// 1 ファイルの上限バイト数（デフォルト: 100 MiB）
+    ///
+    /// 1 行はファイルをまたがないので、上限を少し超えることがある。
+    pub fn with_max_file_bytes(mut self, max_file_bytes: u64) -> Self {
+        self.max_file_bytes = max_file_bytes.max(1);
+        self
+    }
+
+    /// Clock を差し替える（デフォルト: SystemClock）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// いま書いているファイル（まだ何も書いていなければ None）
+    pub fn current_path(&self) -> Option<PathBuf> {
+        let current = self.current.lock().unwrap();
+        current.as_ref().map(|open| open.path.clone())
+    }
+
+    fn path_for(&self, day: NaiveDate, index: u32) -> PathBuf {
+        let name = match index {
+            0 => format!("{}-{}.jsonl", self.prefix, day.format("%Y-%m-%d")),
+            n => format!("{}-{}.{}.jsonl", self.prefix, day.format("%Y-%m-%d"), n),
+        };
+        self.dir.join(name)
+    }
+
+    /// `day` のファイルのうち、`index` 以降で上限に達していない最初のものを開く
+    fn open(&self, day: NaiveDate, mut index: u32) -> io::Result<OpenFile> {
+        loop {
+            let path = self.path_for(day, index);
+            let bytes = file_len(&path)?;
+            if bytes < self.max_file_bytes {
+                let file = OpenOptions::new().create(true).append(true).open(&path)?;
+                return Ok(OpenFile {
+                    file,
+                    path,
+                    day,
+                    index,
+                    bytes,
+                });
+            }
+            index += 1;
+        }
+    }
+
+    fn write_line(&self, line: &str, day: NaiveDate) -> io::Result<()> {
+        let mut current = self.current.lock().unwrap();
+        let rotate_to = match current.as_ref() {
+            None => Some(0),
+            Some(open) if open.day != day => Some(0),
+            Some(open) if open.bytes >= self.max_file_bytes => Some(open.index + 1),
+            Some(_) => None,
+        };
+        if let Some(index) = rotate_to {
+            *current = Some(self.open(day, index)?);
+        }
+        let open = current.as_mut().expect("file opened above");
+        open.file.write_all(line.as_bytes())?;
+        open.file.flush()?;
+        open.bytes += line.len() as u64;
+        Ok(())
+    }
+}
+
+fn file_len(path: &Path) -> io::Result<u64> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+impl std::fmt::Debug for FileEventSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileEventSink")
+            .field("dir", &self.dir)
+            .field("prefix", &self.prefix)
+            .field("max_file_bytes", &self.max_file_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl EventSink for FileEventSink {
+    async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError> {
+        let failed = |e: &dyn std::fmt::Display| EventSinkError::EmitFailed(e.to_string());
+        let now = self.clock.now();
+        let mut line = serde_json::to_value(&event).map_err(|e| failed(&e))?;
+        if let Some(fields) = line.as_object_mut() {
+            fields.insert("ts".into(), now.to_rfc3339().into());
+        }
+        self.write_line(&format!("{line}\n"), now.date_naive())
+            .map_err(|e| failed(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{TaskId, TaskType};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    /// 進められる Clock
+    struct SteppingClock(Mutex<DateTime<Utc>>);
+
+    impl Clock for SteppingClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn enqueued(id: u128) -> DomainEvent {
+        DomainEvent::TaskEnqueued {
+            task_id: TaskId::new(id),
+            task_type: TaskType::new("email"),
+            job_id: None,
+        }
+    }
+
+    fn read_lines(path: &Path) -> Vec<serde_json::Value> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rotates_by_size_and_by_day() {
+        let dir = std::env::temp_dir().join(format!("weaver-file-sink-{}", ulid::Ulid::new()));
+        let start = Utc.with_ymd_and_hms(2025, 1, 31, 23, 0, 0).unwrap();
+        let clock = Arc::new(SteppingClock(Mutex::new(start)));
+        let line_len = {
+            let probe = serde_json::to_value(enqueued(1)).unwrap();
+            // `ts` を入れた後の長さ（+ 改行）
+            probe.to_string().len() + r#","ts":"2025-01-31T23:00:00+00:00""#.len() + 1
+        };
+        let sink = FileEventSink::new(&dir)
+            .unwrap()
+            .with_max_file_bytes(2 * line_len as u64)
+            .with_clock(clock.clone());
+
+        for id in 1..=3 {
+            sink.emit(enqueued(id)).await.unwrap();
+        }
+        let first = read_lines(&dir.join("events-2025-01-31.jsonl"));
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0]["event"], "task_enqueued");
+        assert_eq!(first[0]["ts"], "2025-01-31T23:00:00+00:00");
+        assert_eq!(read_lines(&dir.join("events-2025-01-31.1.jsonl")).len(), 1);
+
+        *clock.0.lock().unwrap() = start + Duration::hours(2);
+        sink.emit(enqueued(4)).await.unwrap();
+        let next_day = dir.join("events-2025-02-01.jsonl");
+        assert_eq!(sink.current_path(), Some(next_day.clone()));
+        assert_eq!(
+            read_lines(&next_day)[0]["task_id"],
+            serde_json::to_value(TaskId::new(4)).unwrap()
+        );
+
+        // 再起動後は同じ日の続きに追記する
+        drop(sink);
+        let sink = FileEventSink::new(&dir)
+            .unwrap()
+            .with_max_file_bytes(2 * line_len as u64)
+            .with_clock(clock);
+        sink.emit(enqueued(5)).await.unwrap();
+        assert_eq!(read_lines(&next_day).len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+//! Gzip - gzip（RFC 1952）で圧縮する Compressor（feature `gzip`）
+//!
+//! # 学習ポイント
+//! - zstd より遅く大きいが、どの言語・ツールでも展開できる（`gunzip` で読める）
+//! - レベルは 0（圧縮しない）〜 9（小さい）。既定は 6
+
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::ports::{CompressionError, Compressor};
+
+/// Gzip は `"gzip"` という名前で記録される
+#[derive(Debug, Clone, Copy)]
+pub struct Gzip {
+    level: u32,
+}
+
+impl Gzip {
+    pub fn new(level: u32) -> Self {
+        Self {
+            level: level.min(9),
+        }
+    }
+}
+
+impl Default for Gzip {
+    fn default() -> Self {
+        Self::new(6)
+    }
+}
+
+impl Compressor for Gzip {
+    fn name(&self) -> &str {
+        "gzip"
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::new(self.level));
+        encoder
+            .write_all(bytes)
+            .and_then(|()| encoder.finish())
+            .map_err(|e| CompressionError::Compress(e.to_string()))
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        let mut out = Vec::new();
+        GzDecoder::new(bytes)
+            .read_to_end(&mut out)
+            .map_err(|e| CompressionError::Decompress(e.to_string()))?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_roundtrips_and_shrinks() {
+        let json =
+            serde_json::to_vec(&vec![serde_json::json!({ "sku": "A-1", "qty": 1 }); 200]).unwrap();
+        let compressed = Gzip::default().compress(&json).unwrap();
+        assert!(compressed.starts_with(&[0x1f, 0x8b]));
+        assert!(compressed.len() < json.len() / 10);
+        assert_eq!(Gzip::new(9).decompress(&compressed).unwrap(), json);
+        assert!(Gzip::default().decompress(b"not gzip").is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! HttpRequestHandler - payload に書かれた HTTP リクエストを送る組み込み TaskHandler
+//!
+//! payload の例（webhook 配送）:
+//! ```json
+//! {
+//!   "method": "POST",
+//!   "url": "https://hooks.example.com/orders",
+//!   "headers": { "Authorization": "Bearer ..." },
+//!   "body": { "order_id": 42 },
+//!   "timeout_ms": 10000
+//! }
+//! ```
+//!
+//! # 学習ポイント
+//! - 送信は `HttpClient` port に任せる（weaver-core は HTTP クライアントに依存しない）
+//! - リトライするかはステータスで分ける:
+//!   - 2xx: 成功
+//!   - 408 / 429 / 5xx・接続失敗・timeout: 失敗（Decider がリトライを決める）
+//!   - それ以外（4xx など）: `Outcome::permanent_failure`（送り直しても受け付けられない）
+//! - レスポンスはステータスとボディを `Artifact::Json` にして残す
+
+#![allow(deprecated)]
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::domain::{Artifact, Outcome, TaskEnvelope};
+use crate::error::WeaverError;
+use crate::ports::{HttpClient, HttpRequest, HttpResponse};
+use crate::runtime::{TaskContext, TaskHandler};
+
+/// HttpRequestHandler の payload
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpRequestSpec {
+    /// 省略時は `POST`
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// JSON として送るボディ（`Content-Type` を指定しなければ `application/json`）
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+    /// これを過ぎたら失敗にする（`None` は HttpClient に任せる）
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+/// HttpRequestHandler は payload のリクエストを `HttpClient` で送る
+///
+/// # 使用例
+/// ```ignore
+/// registry.register(
+///     TaskType::new("ops.http.v1"),
+///     Arc::new(HttpRequestHandler::new(Arc::new(ReqwestClient::default()))),
+/// )?;
+/// ```
+#[derive(Clone)]
+pub struct HttpRequestHandler {
+    client: Arc<dyn HttpClient>,
+}
+
+impl HttpRequestHandler {
+    pub fn new(client: Arc<dyn HttpClient>) -> Self {
+        Self { client }
+    }
+
+    async fn run(&self, spec: HttpRequestSpec, ctx: &TaskContext) -> Outcome {
+        let request = build_request(&spec);
+        let timeout = async {
+            match spec.timeout_ms {
+                Some(ms) => tokio::time::sleep(Duration::from_millis(ms)).await,
+                None => std::future::pending().await,
+            }
+        };
+        let response = tokio::select! {
+            response = self.client.send(&request) => response,
+            _ = timeout => {
+                return Outcome::failure(format!(
+                    "{} {} timed out after {}ms",
+                    request.method,
+                    request.url,
+                    spec.timeout_ms.unwrap_or_default()
+                ));
+            }
+            _ = ctx.cancellation().cancelled() => {
+                return Outcome::failure(format!("{} {} aborted: task cancelled", request.method, request.url));
+            }
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => return Outcome::failure(format!("{} {}: {e}", request.method, request.url)),
+        };
+
+        let status = response.status;
+        let outcome = match status {
+            200..=299 => Outcome::success(),
+            408 | 429 | 500.. => Outcome::failure(format!(
+                "{} {} returned HTTP {status}",
+                request.method, request.url
+            )),
+            _ => Outcome::permanent_failure(format!(
+                "{} {} rejected with HTTP {status}",
+                request.method, request.url
+            )),
+        };
+        outcome.with_artifact(response_artifact(&response))
+    }
+}
+
+fn build_request(spec: &HttpRequestSpec) -> HttpRequest {
+    let mut headers: Vec<(String, String)> = spec
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    let body = match &spec.body {
+        Some(body) => {
+            if !headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            {
+                headers.push(("Content-Type".to_string(), "application/json".to_string()));
+            }
+            body.to_string().into_bytes()
+        }
+        None => Vec::new(),
+    };
+    HttpRequest {
+        method: spec.method.to_ascii_uppercase(),
+        url: spec.url.clone(),
+        headers,
+        body,
+    }
+}
+
+/// `{"status": 201, "body": ...}`（ボディは JSON として読めればそのまま、だめなら文字列）
+fn response_artifact(response: &HttpResponse) -> Artifact {
+    let body = serde_json::from_slice(&response.body).unwrap_or_else(|_| {
+        serde_json::Value::String(String::from_utf8_lossy(&response.body).into_owned())
+    });
+    Artifact::Json(serde_json::json!({ "status": response.status, "body": body }))
+}
+
+#[async_trait]
+impl TaskHandler for HttpRequestHandler {
+    async fn handle(&self, envelope: &TaskEnvelope) -> Result<Outcome, WeaverError> {
+        self.handle_with_context(envelope, &TaskContext::default())
+            .await
+    }
+
+    async fn handle_with_context(
+        &self,
+        envelope: &TaskEnvelope,
+        ctx: &TaskContext,
+    ) -> Result<Outcome, WeaverError> {
+        let spec = HttpRequestSpec::deserialize(envelope.payload())
+            .map_err(|e| WeaverError::Other(format!("invalid http request payload: {e}")))?;
+        Ok(self.run(spec, ctx).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::domain::{Decider, Decision, DefaultDecider, OutcomeKind, TaskId, TaskType};
+    use crate::ports::HttpError;
+    use crate::queue::TaskRecord;
+
+    /// 決まったステータスとボディを返し、受け取ったリクエストを記録する
+    struct ScriptedClient {
+        status: u16,
+        body: &'static str,
+        sent: Mutex<Vec<HttpRequest>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for ScriptedClient {
+        async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+            self.sent.lock().unwrap().push(request.clone());
+            Ok(HttpResponse {
+                status: self.status,
+                headers: Vec::new(),
+                body: self.body.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    fn handler(status: u16, body: &'static str) -> (HttpRequestHandler, Arc<ScriptedClient>) {
+        let client = Arc::new(ScriptedClient {
+            status,
+            body,
+            sent: Mutex::new(Vec::new()),
+        });
+        (HttpRequestHandler::new(client.clone()), client)
+    }
+
+    fn envelope(payload: serde_json::Value) -> TaskEnvelope {
+        TaskEnvelope::new(TaskId::new(1), TaskType::new("http"), payload)
+    }
+
+    #[tokio::test]
+    async fn sends_the_payload_request_and_keeps_the_response() {
+        let (handler, client) = handler(201, r#"{"id":7}"#);
+        let outcome = handler
+            .handle(&envelope(serde_json::json!({
+                "url": "https://hooks.example.com/orders",
+                "headers": { "Authorization": "Bearer t" },
+                "body": { "order_id": 42 },
+            })))
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.kind, OutcomeKind::Success);
+        assert_eq!(
+            outcome.artifacts,
+            [Artifact::Json(
+                serde_json::json!({ "status": 201, "body": { "id": 7 } })
+            )]
+        );
+        let sent = client.sent.lock().unwrap();
+        assert_eq!(sent[0].method, "POST");
+        assert_eq!(sent[0].header("content-type"), Some("application/json"));
+        assert_eq!(sent[0].header("authorization"), Some("Bearer t"));
+        assert_eq!(sent[0].body, br#"{"order_id":42}"#);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_and_client_errors_are_not() {
+        let decider = DefaultDecider::default_v1();
+        let record = TaskRecord::new(envelope(serde_json::json!({})), 5);
+        let payload = serde_json::json!({ "method": "put", "url": "https://api.example.com/x" });
+
+        let (unavailable, _) = handler(503, "try later");
+        let outcome = unavailable
+            .handle(&envelope(payload.clone()))
+            .await
+            .unwrap();
+        assert!(outcome.is_retryable());
+        assert!(matches!(
+            decider.decide(&record, &outcome),
+            Decision::Retry { .. }
+        ));
+
+        let (rejected, _) = handler(422, "bad order");
+        let outcome = rejected.handle(&envelope(payload)).await.unwrap();
+        assert_eq!(
+            outcome.reason.as_deref(),
+            Some("PUT https://api.example.com/x rejected with HTTP 422")
+        );
+        assert_eq!(
+            outcome.artifacts,
+            [Artifact::Json(
+                serde_json::json!({ "status": 422, "body": "bad order" })
+            )]
+        );
+        assert!(matches!(
+            decider.decide(&record, &outcome),
+            Decision::MarkDead { .. }
+        ));
+    }
+}
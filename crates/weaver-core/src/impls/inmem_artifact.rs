@@ -0,0 +1,121 @@
+//! InMemoryArtifactStore - 開発用の ArtifactStore
+//!
+//! # 学習ポイント
+//! - 期限は保存時に `now + ttl` として記録し、判定は呼び出し側の `now` で行う
+//!   （FixedClock で GC を決定的にテストできる）
+//! - メタ情報と本体を 1 つの Mutex に置くので、delete は常に両方を一度に消す
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use ulid::Ulid;
+
+use crate::domain::ids::ArtifactId;
+use crate::ports::{ArtifactError, ArtifactMeta, ArtifactStore};
+
+/// namespace 内の artifact_id → (メタ情報, 本体)
+type Artifacts = BTreeMap<ArtifactId, (ArtifactMeta, Vec<u8>)>;
+
+/// InMemoryArtifactStore は開発用の ArtifactStore
+#[derive(Debug, Default)]
+pub struct InMemoryArtifactStore {
+    /// namespace ごとの artifact
+    artifacts: Mutex<HashMap<String, Artifacts>>,
+}
+
+impl InMemoryArtifactStore {
+    /// 新しい InMemoryArtifactStore を作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// namespace に残っている artifact の数（削除済みを含まない）
+    pub fn len(&self, ns: &str) -> usize {
+        self.artifacts
+            .lock()
+            .unwrap()
+            .get(ns)
+            .map_or(0, BTreeMap::len)
+    }
+
+    pub fn is_empty(&self, ns: &str) -> bool {
+        self.len(ns) == 0
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for InMemoryArtifactStore {
+    async fn put(
+        &self,
+        ns: &str,
+        bytes: Vec<u8>,
+        content_type: Option<&str>,
+        ttl: Option<Duration>,
+        now: DateTime<Utc>,
+    ) -> Result<ArtifactMeta, ArtifactError> {
+        let artifact_id = ArtifactId::from_ulid(Ulid::from_parts(
+            now.timestamp_millis() as u64,
+            rand::random(),
+        ));
+        let expires_at = ttl.map(|ttl| {
+            now.checked_add_signed(TimeDelta::from_std(ttl).unwrap_or(TimeDelta::MAX))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC)
+        });
+        let meta = ArtifactMeta {
+            artifact_id,
+            size_bytes: bytes.len() as u64,
+            content_type: content_type.map(str::to_string),
+            created_at: now,
+            expires_at,
+        };
+        self.artifacts
+            .lock()
+            .unwrap()
+            .entry(ns.to_string())
+            .or_default()
+            .insert(artifact_id, (meta.clone(), bytes));
+        Ok(meta)
+    }
+
+    async fn get(&self, ns: &str, artifact_id: ArtifactId) -> Result<Vec<u8>, ArtifactError> {
+        self.artifacts
+            .lock()
+            .unwrap()
+            .get(ns)
+            .and_then(|artifacts| artifacts.get(&artifact_id))
+            .map(|(_, bytes)| bytes.clone())
+            .ok_or(ArtifactError::NotFound(artifact_id))
+    }
+
+    async fn delete(&self, ns: &str, artifact_id: ArtifactId) -> Result<(), ArtifactError> {
+        self.artifacts
+            .lock()
+            .unwrap()
+            .get_mut(ns)
+            .and_then(|artifacts| artifacts.remove(&artifact_id))
+            .map(|_| ())
+            .ok_or(ArtifactError::NotFound(artifact_id))
+    }
+
+    async fn list_expired(
+        &self,
+        ns: &str,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<ArtifactMeta>, ArtifactError> {
+        let artifacts = self.artifacts.lock().unwrap();
+        let Some(artifacts) = artifacts.get(ns) else {
+            return Ok(Vec::new());
+        };
+        let mut expired: Vec<&ArtifactMeta> = artifacts
+            .values()
+            .map(|(meta, _)| meta)
+            .filter(|meta| meta.expires_at.is_some_and(|at| at <= now))
+            .collect();
+        expired.sort_by_key(|meta| (meta.expires_at, meta.artifact_id));
+        Ok(expired.into_iter().take(limit).cloned().collect())
+    }
+}
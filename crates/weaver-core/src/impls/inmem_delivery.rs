@@ -105,6 +105,12 @@ impl DeliveryQueue for InMemoryDeliveryQueue {
         .await
         .map_err(|e| QueueError::OperationFailed(format!("Pop failed: {}", e)))?
     }
+
+    async fn len(&self, ns: &str) -> Result<Option<usize>, QueueError> {
+        // 短時間しか保持しないので spawn_blocking は使わない
+        let queues = self.queues.lock().unwrap();
+        Ok(Some(queues.get(ns).map_or(0, VecDeque::len)))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,119 @@
+//! InMemoryKvStore - 開発用の KvStore
+//!
+//! # 学習ポイント
+//! - TTL は期限の Instant を保存し、読み出し時に判定する（掃除用タイマー不要）
+//! - BTreeMap によるキー順の prefix 列挙
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::ports::{KvError, KvStore};
+
+/// InMemoryKvStore は開発用の KvStore
+///
+/// 同じインスタンスを `Arc` で共有すれば、プロセス内の複数 worker で membership を共有できる。
+#[derive(Debug, Default)]
+pub struct InMemoryKvStore {
+    /// key → (value, 期限)
+    entries: Mutex<BTreeMap<String, (String, Option<Instant>)>>,
+}
+
+impl InMemoryKvStore {
+    /// 新しい InMemoryKvStore を作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn is_live(expires_at: &Option<Instant>, now: Instant) -> bool {
+    expires_at.is_none_or(|at| at > now)
+}
+
+#[async_trait]
+impl KvStore for InMemoryKvStore {
+    async fn put(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<(), KvError> {
+        let expires_at = ttl.and_then(|ttl| Instant::now().checked_add(ttl));
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (value, expires_at));
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, KvError> {
+        let now = Instant::now();
+        let entries = self.entries.lock().unwrap();
+        Ok(entries
+            .get(key)
+            .filter(|(_, expires_at)| is_live(expires_at, now))
+            .map(|(value, _)| value.clone()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), KvError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn list_prefix(&self, prefix: &str) -> Result<Vec<(String, String)>, KvError> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        // 期限切れはついでに掃除する
+        entries.retain(|_, (_, expires_at)| is_live(expires_at, now));
+        Ok(entries
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, (value, _))| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn put_get_delete() {
+        let kv = InMemoryKvStore::new();
+        kv.put("a", "1".into(), None).await.unwrap();
+        assert_eq!(kv.get("a").await.unwrap().as_deref(), Some("1"));
+
+        kv.delete("a").await.unwrap();
+        assert_eq!(kv.get("a").await.unwrap(), None);
+        kv.delete("a").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn expired_keys_disappear() {
+        let kv = InMemoryKvStore::new();
+        kv.put("short", "1".into(), Some(Duration::from_millis(10)))
+            .await
+            .unwrap();
+        kv.put("long", "2".into(), None).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(kv.get("short").await.unwrap(), None);
+        let listed = kv.list_prefix("").await.unwrap();
+        assert_eq!(listed, vec![("long".to_string(), "2".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn list_prefix_only_returns_matching_keys_in_order() {
+        let kv = InMemoryKvStore::new();
+        for key in ["workers/b", "workers/a", "jobs/x", "workersx"] {
+            kv.put(key, key.to_uppercase(), None).await.unwrap();
+        }
+
+        let keys: Vec<String> = kv
+            .list_prefix("workers/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec!["workers/a", "workers/b"]);
+    }
+}
@@ -0,0 +1,85 @@
+//! InMemoryLock - 開発用の DistributedLock
+//!
+//! # 学習ポイント
+//! - TTL は期限の Instant を保存し、取得時に判定する（InMemoryKvStore と同じ）
+//! - owner を比べてから消すことで、他人のロックを解放しない
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::ports::{DistributedLock, LockError};
+
+/// InMemoryLock は開発用の DistributedLock
+///
+/// 同じインスタンスを `Arc` で共有すれば、プロセス内の複数 worker で排他できる。
+#[derive(Debug, Default)]
+pub struct InMemoryLock {
+    /// resource → (owner, 期限)
+    held: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl InMemoryLock {
+    /// 新しい InMemoryLock を作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DistributedLock for InMemoryLock {
+    async fn try_acquire(
+        &self,
+        resource: &str,
+        owner: &str,
+        ttl: Duration,
+    ) -> Result<bool, LockError> {
+        let now = Instant::now();
+        let mut held = self.held.lock().unwrap();
+        if let Some((holder, expires_at)) = held.get(resource)
+            && holder != owner
+            && *expires_at > now
+        {
+            return Ok(false);
+        }
+        held.insert(resource.to_string(), (owner.to_string(), now + ttl));
+        Ok(true)
+    }
+
+    async fn release(&self, resource: &str, owner: &str) -> Result<(), LockError> {
+        let mut held = self.held.lock().unwrap();
+        if held
+            .get(resource)
+            .is_some_and(|(holder, _)| holder == owner)
+        {
+            held.remove(resource);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_the_owner_holds_and_releases_until_ttl() {
+        let lock = InMemoryLock::new();
+        let ttl = Duration::from_millis(30);
+        assert!(lock.try_acquire("repo", "a", ttl).await.unwrap());
+        assert!(lock.try_acquire("repo", "a", ttl).await.unwrap());
+        assert!(!lock.try_acquire("repo", "b", ttl).await.unwrap());
+
+        // 他の owner は解放できない
+        lock.release("repo", "b").await.unwrap();
+        assert!(!lock.try_acquire("repo", "b", ttl).await.unwrap());
+
+        // TTL が切れたら取れる
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(lock.try_acquire("repo", "b", ttl).await.unwrap());
+        lock.release("repo", "b").await.unwrap();
+        assert!(lock.try_acquire("repo", "a", ttl).await.unwrap());
+    }
+}
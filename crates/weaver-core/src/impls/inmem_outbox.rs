@@ -0,0 +1,348 @@
+//! InMemoryOutbox - 開発用の outbox テーブル
+//!
+//! InMemoryTaskStore（将来）が自分のロックの内側に埋め込み、
+//! 状態遷移と outbox 生成を「同一トランザクション」として扱うための部品です。
+//! そのため自身はロックを持たず、すべての操作は `&mut self` で行います。
+//!
+//! # 学習ポイント
+//! - ack / fail のバッチ化（1 回のロック取得でまとめて反映）
+//! - sent イベントの compaction（テーブルの肥大化防止）
+//! - fail 時の exponential backoff と dead への遷移
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::domain::ids::{EventId, TaskId};
+use crate::ports::{OutboxEvent, OutboxEventType, OutboxStatus, StoreError};
+
+/// OutboxRetryPolicy は配送失敗時の再試行方針
+#[derive(Debug, Clone)]
+pub struct OutboxRetryPolicy {
+    /// 1 回目の失敗後の待ち時間（以降は倍々）
+    pub base_delay: Duration,
+    /// 待ち時間の上限
+    pub max_delay: Duration,
+    /// この回数失敗したら dead
+    pub max_attempts: u32,
+}
+
+impl Default for OutboxRetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            max_attempts: 10,
+        }
+    }
+}
+
+impl OutboxRetryPolicy {
+    /// `attempts` 回失敗した後の待ち時間
+    fn delay(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// InMemoryOutbox は namespace ごとの outbox_events
+///
+/// # 実装詳細
+/// - `BTreeMap<EventId, OutboxEvent>`（ULID 順 = 作成順）
+/// - バッチ操作は全件を検証してから反映する（途中で失敗しても部分適用しない）
+#[derive(Debug, Default)]
+pub struct InMemoryOutbox {
+    events: HashMap<String, BTreeMap<EventId, OutboxEvent>>,
+    policy: OutboxRetryPolicy,
+}
+
+impl InMemoryOutbox {
+    /// 新しい InMemoryOutbox を作成
+    pub fn new(policy: OutboxRetryPolicy) -> Self {
+        Self {
+            events: HashMap::new(),
+            policy,
+        }
+    }
+
+    /// `dispatch_task` イベントを積む
+    ///
+    /// 同じ `dedupe_key` の pending イベントがあれば、新しく積まずにその ID を返す。
+    pub fn append(
+        &mut self,
+        ns: &str,
+        event_id: EventId,
+        task_id: TaskId,
+        dedupe_key: Option<String>,
+        now: DateTime<Utc>,
+    ) -> EventId {
+        let events = self.events.entry(ns.to_string()).or_default();
+        if let Some(key) = &dedupe_key
+            && let Some(existing) = events
+                .values()
+                .find(|e| e.status == OutboxStatus::Pending && e.dedupe_key.as_ref() == Some(key))
+        {
+            return existing.event_id;
+        }
+
+        events.insert(
+            event_id,
+            OutboxEvent {
+                event_id,
+                event_type: OutboxEventType::DispatchTask,
+                task_id,
+                available_at: now,
+                status: OutboxStatus::Pending,
+                attempts: 0,
+                last_error: None,
+                created_at: now,
+                sent_at: None,
+                dedupe_key,
+            },
+        );
+        event_id
+    }
+
+    /// 配送可能な pending イベントを `available_at` の古い順に最大 `limit` 件
+    pub fn pull(&self, ns: &str, now: DateTime<Utc>, limit: usize) -> Vec<OutboxEvent> {
+        let Some(events) = self.events.get(ns) else {
+            return Vec::new();
+        };
+        let mut ready: Vec<&OutboxEvent> = events
+            .values()
+            .filter(|e| e.status == OutboxStatus::Pending && e.available_at <= now)
+            .collect();
+        ready.sort_by_key(|e| (e.available_at, e.event_id));
+        ready.into_iter().take(limit).cloned().collect()
+    }
+
+    /// イベントを 1 件取得
+    pub fn get(&self, ns: &str, event_id: EventId) -> Option<&OutboxEvent> {
+        self.events.get(ns)?.get(&event_id)
+    }
+
+    /// namespace 内のイベント数（status を問わない）
+    pub fn len(&self, ns: &str) -> usize {
+        self.events.get(ns).map_or(0, BTreeMap::len)
+    }
+
+    /// namespace にイベントがないか
+    pub fn is_empty(&self, ns: &str) -> bool {
+        self.len(ns) == 0
+    }
+
+    /// まとめて sent にする（1 件でも存在しなければ何も変更しない）
+    pub fn ack_batch(
+        &mut self,
+        ns: &str,
+        event_ids: &[EventId],
+        now: DateTime<Utc>,
+    ) -> Result<(), StoreError> {
+        let events = self.events_mut(ns, event_ids.iter().copied())?;
+        for event_id in event_ids {
+            if let Some(event) = events.get_mut(event_id) {
+                event.status = OutboxStatus::Sent;
+                event.sent_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    /// まとめて失敗を記録する（1 件でも存在しなければ何も変更しない）
+    ///
+    /// 失敗回数が `max_attempts` に達したイベントは dead になる。
+    pub fn fail_batch(
+        &mut self,
+        ns: &str,
+        failures: Vec<(EventId, String)>,
+        now: DateTime<Utc>,
+    ) -> Result<(), StoreError> {
+        let policy = self.policy.clone();
+        let events = self.events_mut(ns, failures.iter().map(|(id, _)| *id))?;
+        for (event_id, error) in failures {
+            let Some(event) = events.get_mut(&event_id) else {
+                continue;
+            };
+            event.attempts += 1;
+            event.last_error = Some(error);
+            if event.attempts >= policy.max_attempts {
+                event.status = OutboxStatus::Dead;
+            } else {
+                let delay =
+                    TimeDelta::from_std(policy.delay(event.attempts)).unwrap_or(TimeDelta::MAX);
+                event.available_at = now + delay;
+            }
+        }
+        Ok(())
+    }
+
+    /// `sent_before` より前に sent になったイベントを作成順に最大 `limit` 件削除する
+    ///
+    /// # Returns
+    /// 削除した件数
+    pub fn compact(&mut self, ns: &str, sent_before: DateTime<Utc>, limit: usize) -> usize {
+        let Some(events) = self.events.get_mut(ns) else {
+            return 0;
+        };
+        let expired: Vec<EventId> = events
+            .values()
+            .filter(|e| {
+                e.status == OutboxStatus::Sent && e.sent_at.is_some_and(|t| t < sent_before)
+            })
+            .map(|e| e.event_id)
+            .take(limit)
+            .collect();
+        for event_id in &expired {
+            events.remove(event_id);
+        }
+        expired.len()
+    }
+
+    /// 全 ID の存在を確認してから namespace のイベントを返す
+    fn events_mut(
+        &mut self,
+        ns: &str,
+        mut event_ids: impl Iterator<Item = EventId>,
+    ) -> Result<&mut BTreeMap<EventId, OutboxEvent>, StoreError> {
+        let events = self.events.entry(ns.to_string()).or_default();
+        if let Some(missing) = event_ids.find(|id| !events.contains_key(id)) {
+            return Err(StoreError::EventNotFound(missing));
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use ulid::Ulid;
+
+    fn t(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn event_id() -> EventId {
+        EventId::from_ulid(Ulid::new())
+    }
+
+    fn task_id() -> TaskId {
+        TaskId::from_ulid(Ulid::new())
+    }
+
+    #[test]
+    fn pull_returns_available_pending_events_up_to_limit() {
+        let mut outbox = InMemoryOutbox::default();
+        for _ in 0..3 {
+            outbox.append("default", event_id(), task_id(), None, t(0));
+        }
+        outbox.append("other", event_id(), task_id(), None, t(0));
+
+        assert_eq!(outbox.pull("default", t(0), 2).len(), 2);
+        assert_eq!(outbox.pull("default", t(0), 10).len(), 3);
+        assert_eq!(outbox.pull("other", t(0), 10).len(), 1);
+    }
+
+    #[test]
+    fn ack_batch_marks_all_events_sent() {
+        let mut outbox = InMemoryOutbox::default();
+        let ids: Vec<EventId> = (0..3)
+            .map(|_| outbox.append("default", event_id(), task_id(), None, t(0)))
+            .collect();
+
+        outbox.ack_batch("default", &ids[..2], t(1)).unwrap();
+
+        let pending = outbox.pull("default", t(1), 10);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].event_id, ids[2]);
+        let sent = outbox.get("default", ids[0]).unwrap();
+        assert_eq!(sent.status, OutboxStatus::Sent);
+        assert_eq!(sent.sent_at, Some(t(1)));
+    }
+
+    #[test]
+    fn ack_batch_with_unknown_event_changes_nothing() {
+        let mut outbox = InMemoryOutbox::default();
+        let known = outbox.append("default", event_id(), task_id(), None, t(0));
+
+        let result = outbox.ack_batch("default", &[known, event_id()], t(1));
+
+        assert!(matches!(result, Err(StoreError::EventNotFound(_))));
+        assert_eq!(
+            outbox.get("default", known).unwrap().status,
+            OutboxStatus::Pending
+        );
+    }
+
+    #[test]
+    fn fail_batch_backs_off_then_marks_dead() {
+        let mut outbox = InMemoryOutbox::new(OutboxRetryPolicy {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts: 3,
+        });
+        let id = outbox.append("default", event_id(), task_id(), None, t(0));
+
+        outbox
+            .fail_batch("default", vec![(id, "redis down".into())], t(0))
+            .unwrap();
+        assert!(outbox.pull("default", t(0), 10).is_empty());
+        assert_eq!(outbox.pull("default", t(1), 10).len(), 1);
+
+        outbox
+            .fail_batch("default", vec![(id, "redis down".into())], t(1))
+            .unwrap();
+        assert_eq!(outbox.get("default", id).unwrap().available_at, t(3));
+
+        outbox
+            .fail_batch("default", vec![(id, "redis down".into())], t(3))
+            .unwrap();
+        let event = outbox.get("default", id).unwrap();
+        assert_eq!(event.status, OutboxStatus::Dead);
+        assert_eq!(event.attempts, 3);
+        assert_eq!(event.last_error.as_deref(), Some("redis down"));
+        assert!(outbox.pull("default", t(100), 10).is_empty());
+    }
+
+    #[test]
+    fn compact_removes_only_old_sent_events() {
+        let mut outbox = InMemoryOutbox::default();
+        let old = outbox.append("default", event_id(), task_id(), None, t(0));
+        let recent = outbox.append("default", event_id(), task_id(), None, t(0));
+        let pending = outbox.append("default", event_id(), task_id(), None, t(0));
+        outbox.ack_batch("default", &[old], t(1)).unwrap();
+        outbox.ack_batch("default", &[recent], t(10)).unwrap();
+
+        assert_eq!(outbox.compact("default", t(5), 100), 1);
+
+        assert!(outbox.get("default", old).is_none());
+        assert!(outbox.get("default", recent).is_some());
+        assert!(outbox.get("default", pending).is_some());
+        assert_eq!(outbox.len("default"), 2);
+    }
+
+    #[test]
+    fn compact_respects_limit() {
+        let mut outbox = InMemoryOutbox::default();
+        let ids: Vec<EventId> = (0..5)
+            .map(|_| outbox.append("default", event_id(), task_id(), None, t(0)))
+            .collect();
+        outbox.ack_batch("default", &ids, t(1)).unwrap();
+
+        assert_eq!(outbox.compact("default", t(2), 2), 2);
+        assert_eq!(outbox.compact("default", t(2), 10), 3);
+        assert!(outbox.is_empty("default"));
+    }
+
+    #[test]
+    fn pending_dedupe_key_is_not_appended_twice() {
+        let mut outbox = InMemoryOutbox::default();
+        let task = task_id();
+        let first = outbox.append("default", event_id(), task, Some("k".into()), t(0));
+        let second = outbox.append("default", event_id(), task, Some("k".into()), t(0));
+
+        assert_eq!(first, second);
+        assert_eq!(outbox.len("default"), 1);
+    }
+}
@@ -0,0 +1,266 @@
+//! MetricsSink の実装 - Prometheus（scrape 用に保持）と StatsD（UDP で push）
+//!
+//! # 学習ポイント
+//! - Prometheus は pull 型: sink は最新の値を持っておき、scrape のたびに
+//!   `render()` でテキストにする
+//! - StatsD は push 型: emit のたびに UDP で投げっぱなしにする（届かなくても
+//!   次の emit で最新値が送られる gauge なので問題ない）
+//! - StatsD にはラベルがないため、ラベルの値を名前に `.` でつなぐ。
+//!   DogStatsD（Datadog）ならタグ（`|#namespace:default`）で送れる
+
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::app::metrics::render_samples;
+use crate::ports::{MetricSample, MetricsError, MetricsSink};
+
+/// PrometheusSink は最後に受け取った sample を namespace ごとに保持する
+///
+/// # 使用例
+/// ```ignore
+/// let sink = Arc::new(PrometheusSink::new());
+/// let app = AppBuilder::new().with_metrics_sink(sink.clone()).build()?;
+/// // /metrics のハンドラで
+/// let body = sink.render();
+/// ```
+#[derive(Debug, Default)]
+pub struct PrometheusSink {
+    samples: Mutex<Vec<MetricSample>>,
+}
+
+impl PrometheusSink {
+    /// 新しい PrometheusSink を作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 保持している sample を Prometheus のテキスト形式で
+    pub fn render(&self) -> String {
+        render_samples(&self.samples.lock().unwrap())
+    }
+}
+
+fn namespace_of(sample: &MetricSample) -> Option<&str> {
+    sample
+        .labels
+        .iter()
+        .find(|(name, _)| *name == "namespace")
+        .map(|(_, value)| value.as_str())
+}
+
+#[async_trait]
+impl MetricsSink for PrometheusSink {
+    /// 受け取った namespace の sample を丸ごと置き換える（消えた時系列を残さない）
+    async fn emit(&self, samples: &[MetricSample]) -> Result<(), MetricsError> {
+        let namespaces: Vec<&str> = samples.iter().filter_map(namespace_of).collect();
+        let mut current = self.samples.lock().unwrap();
+        current.retain(|sample| !namespace_of(sample).is_some_and(|ns| namespaces.contains(&ns)));
+        current.extend_from_slice(samples);
+        // 同じ指標を続けて並べる（安定ソートなので指標内の順は保たれる）
+        current.sort_by_key(|sample| sample.name);
+        Ok(())
+    }
+}
+
+/// 1 データグラムの上限（イーサネットの MTU に収まる大きさ）
+const MAX_DATAGRAM: usize = 1432;
+
+/// StatsdSink は sample を StatsD の gauge（`name:value|g`）として UDP で送る
+///
+/// # 使用例
+/// ```ignore
+/// let sink = StatsdSink::new("127.0.0.1:8125")?.with_prefix("myapp.").with_tags();
+/// let app = AppBuilder::new().with_metrics_sink(Arc::new(sink)).build()?;
+/// ```
+#[derive(Debug)]
+pub struct StatsdSink {
+    socket: UdpSocket,
+    prefix: String,
+    tags: bool,
+}
+
+impl StatsdSink {
+    /// `addr` の StatsD に送る sink（ラベルは名前につなぐ）
+    pub fn new(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no address to send metrics to")
+        })?;
+        let local = if addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            prefix: String::new(),
+            tags: false,
+        })
+    }
+
+    /// 指標名の前につける文字列（例: `"myapp."`）
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// ラベルを DogStatsD のタグ（`|#name:value`）で送る
+    pub fn with_tags(mut self) -> Self {
+        self.tags = true;
+        self
+    }
+
+    fn line(&self, sample: &MetricSample) -> String {
+        if self.tags {
+            let tags: Vec<String> = sample
+                .labels
+                .iter()
+                .map(|(name, value)| format!("{name}:{}", sanitize_tag(value)))
+                .collect();
+            format!(
+                "{}{}:{}|g|#{}",
+                self.prefix,
+                sample.name,
+                sample.value,
+                tags.join(",")
+            )
+        } else {
+            let mut name = format!("{}{}", self.prefix, sample.name);
+            for (_, value) in &sample.labels {
+                name.push('.');
+                name.push_str(&sanitize(value));
+            }
+            format!("{name}:{}|g", sample.value)
+        }
+    }
+
+    fn send(&self, datagram: &str) -> Result<(), MetricsError> {
+        self.socket
+            .send(datagram.as_bytes())
+            .map(|_| ())
+            .map_err(|e| MetricsError::EmitFailed(format!("statsd: {e}")))
+    }
+}
+
+/// 名前に入れるラベル値: StatsD の区切り文字（`.` `:` `|` など）を `_` にする
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// DogStatsD のタグの値: 区切り（`|` `,` `#`）と空白を `_` にする（`.` はそのまま）
+fn sanitize_tag(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if matches!(c, '|' | ',' | '#') || c.is_whitespace() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[async_trait]
+impl MetricsSink for StatsdSink {
+    /// 改行区切りでまとめ、`MAX_DATAGRAM` を超えないように分けて送る
+    async fn emit(&self, samples: &[MetricSample]) -> Result<(), MetricsError> {
+        let mut datagram = String::new();
+        for sample in samples {
+            let line = self.line(sample);
+            if !datagram.is_empty() && datagram.len() + 1 + line.len() > MAX_DATAGRAM {
+                self.send(&datagram)?;
+                datagram.clear();
+            }
+            if !datagram.is_empty() {
+                datagram.push('\n');
+            }
+            datagram.push_str(&line);
+        }
+        if !datagram.is_empty() {
+            self.send(&datagram)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(name: &'static str, ns: &str, task_type: &str, value: f64) -> MetricSample {
+        MetricSample {
+            name,
+            help: "Test.",
+            labels: vec![
+                ("namespace", ns.to_string()),
+                ("task_type", task_type.to_string()),
+            ],
+            value,
+        }
+    }
+
+    #[tokio::test]
+    async fn prometheus_sink_keeps_the_latest_samples_per_namespace() {
+        let sink = PrometheusSink::new();
+        sink.emit(&[
+            sample("depth", "a", "x", 1.0),
+            sample("depth", "a", "y", 2.0),
+        ])
+        .await
+        .unwrap();
+        sink.emit(&[sample("depth", "b", "x", 5.0)]).await.unwrap();
+        sink.emit(&[sample("depth", "a", "x", 3.0)]).await.unwrap();
+
+        assert_eq!(
+            sink.render(),
+            "# HELP depth Test.\n\
+             # TYPE depth gauge\n\
+             depth{namespace=\"b\",task_type=\"x\"} 5\n\
+             depth{namespace=\"a\",task_type=\"x\"} 3\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn statsd_sink_sends_gauges_with_labels_in_the_name_or_as_tags() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let mut buf = [0; MAX_DATAGRAM];
+        let samples = [sample(
+            "weaver_queue_depth",
+            "default",
+            "acme.render.v1",
+            9.0,
+        )];
+
+        let plain = StatsdSink::new(addr).unwrap().with_prefix("app.");
+        plain.emit(&samples).await.unwrap();
+        let n = server.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..n]).unwrap(),
+            "app.weaver_queue_depth.default.acme_render_v1:9|g"
+        );
+
+        let tagged = StatsdSink::new(addr).unwrap().with_tags();
+        tagged.emit(&samples).await.unwrap();
+        let n = server.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..n]).unwrap(),
+            "weaver_queue_depth:9|g|#namespace:default,task_type:acme.render.v1"
+        );
+    }
+}
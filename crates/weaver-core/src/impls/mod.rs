@@ -5,6 +5,28 @@
 //! # 含まれる実装
 //! - **InMemoryDeliveryQueue**: 開発用の配送キュー
 //! - **DirectDispatch**: v2 デフォルトの DispatchStrategy
+//! - **InMemoryKvStore**: 開発用の KvStore（membership の共有）
+//! - **InMemoryLock**: 開発用の DistributedLock（handler 側リソースの排他）
+//! - **InMemoryOutbox**: 開発用の outbox（InMemoryTaskStore の部品）
+//! - **InMemoryArtifactStore**: 開発用の ArtifactStore（GCLoop のテストにも使う）
+//! - **CompressedArtifactStore**: 大きな artifact を圧縮して保存する ArtifactStore（任意の store を包む）
+//! - **SlackChannel / EmailChannel**: NotificationChannel（送信は port 経由）
+//! - **PrometheusSink / StatsdSink**: MetricsSink（scrape 用の保持 / UDP で push）
+//! - **TokenBucketRateLimiter**: プロセス内の RateLimiter
+//! - **QueueAsTaskStore / RuntimeAsWorkerLoop**: v1 → v2 移行用アダプタ
+//! - **CommandHandler**: 外部コマンドを実行する組み込み TaskHandler
+//! - **SubprocessHandler**: 外部プロセスの worker（任意の言語）に task を渡す組み込み TaskHandler
+//! - **HttpRequestHandler**: HTTP リクエストを送る組み込み TaskHandler（feature `http-handler`）
+//! - **WasmHandler**: task を WASM モジュールの中で実行する DynHandler（feature `wasm`）
+//! - **WasmtimeEngine**: wasmtime で .wasm を動かす WasmEngine（import なし、fuel・メモリ上限付き、feature `wasm`）
+//! - **AesGcmCipher**: payload を保存時に暗号化する EnvelopeCipher（AES-256-GCM、feature `aes-gcm`）
+//! - **ReqwestClient**: reqwest で送る HttpClient（webhook・Slack・OTLP・HttpRequestHandler 用、feature `reqwest`）
+//! - **TracingEventSink / BufferedVecEventSink**: EventSink（構造化ログ / テスト用の記録）
+//! - **CompositeEventSink / FilteredEventSink / SamplingEventSink**: EventSink を包む（fan-out / 絞り込み / 間引き）
+//! - **FileEventSink**: EventSink（JSON Lines の監査ログ。日ごと・サイズでローテーション）
+//! - **OtlpHttpExporter**: SpanExporter（OTLP/HTTP の JSON で OpenTelemetry Collector へ）
+//! - **MessagePackFormat / CborFormat**: payload の PayloadFormat（feature `msgpack` / `cbor`）
+//! - **Zstd / Gzip**: payload・artifact・snapshot の Compressor（feature `zstd` / `gzip`）
 //! - （将来）InMemoryTaskStore: テスト用の正本
 //!
 //! # 本番用実装
@@ -14,8 +36,75 @@
 //! - `weaver-blob`: MinIO/S3/LocalArtifactStore
 
 pub mod inmem_delivery;
+pub mod inmem_outbox;
+pub mod inmem_artifact;
+pub mod compressed_artifact;
+pub mod inmem_kv;
+pub mod inmem_lock;
 pub mod dispatch;
+pub mod token_bucket;
+pub mod metrics_sink;
+pub mod notification;
+pub mod v1_compat;
+pub mod command;
+pub mod subprocess;
+#[cfg(feature = "aes-gcm")]
+pub mod aes_gcm;
+pub mod event_sink;
+pub mod file_event_sink;
+pub mod otlp;
+#[cfg(feature = "http-handler")]
+pub mod http_request;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "wasm")]
+pub mod wasmtime_engine;
+#[cfg(feature = "reqwest")]
+pub mod reqwest_client;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "zstd")]
+pub mod zstd;
+#[cfg(feature = "gzip")]
+pub mod gzip;
 
 // 主要な型を再エクスポート
 pub use self::inmem_delivery::InMemoryDeliveryQueue;
+pub use self::inmem_kv::InMemoryKvStore;
+pub use self::inmem_lock::InMemoryLock;
+pub use self::inmem_outbox::{InMemoryOutbox, OutboxRetryPolicy};
+pub use self::inmem_artifact::InMemoryArtifactStore;
+pub use self::compressed_artifact::CompressedArtifactStore;
 pub use self::dispatch::DirectDispatch;
+pub use self::notification::{EmailChannel, SlackChannel};
+pub use self::token_bucket::{RateLimit, TokenBucketRateLimiter};
+pub use self::metrics_sink::{PrometheusSink, StatsdSink};
+pub use self::v1_compat::{QueueAsTaskStore, RuntimeAsWorkerLoop};
+pub use self::command::{CommandHandler, CommandSpec};
+pub use self::subprocess::SubprocessHandler;
+#[cfg(feature = "aes-gcm")]
+pub use self::aes_gcm::AesGcmCipher;
+pub use self::event_sink::{
+    BufferedVecEventSink, CompositeEventSink, FilteredEventSink, SamplingEventSink,
+    TracingEventSink,
+};
+pub use self::file_event_sink::FileEventSink;
+pub use self::otlp::OtlpHttpExporter;
+#[cfg(feature = "http-handler")]
+pub use self::http_request::{HttpRequestHandler, HttpRequestSpec};
+#[cfg(feature = "wasm")]
+pub use self::wasm::WasmHandler;
+#[cfg(feature = "wasm")]
+pub use self::wasmtime_engine::WasmtimeEngine;
+#[cfg(feature = "reqwest")]
+pub use self::reqwest_client::ReqwestClient;
+#[cfg(feature = "msgpack")]
+pub use self::msgpack::MessagePackFormat;
+#[cfg(feature = "cbor")]
+pub use self::cbor::CborFormat;
+#[cfg(feature = "zstd")]
+pub use self::zstd::Zstd;
+#[cfg(feature = "gzip")]
+pub use self::gzip::Gzip;
@@ -0,0 +1,296 @@
+//! MessagePackFormat - payload を MessagePack で書く PayloadFormat（feature `msgpack`）
+//!
+//! # 学習ポイント
+//! - 先頭 1 バイトで型と（短ければ）長さを表す。長いものは後ろにビッグエンディアンの長さ
+//! - JSON の値だけを扱うので、bin / ext は読むだけ（bin は数値の配列、ext はエラー）
+//! - 整数は入る最小の形で書く（`1` は 1 バイト）
+//! - 読むときは入れ子を `MAX_DEPTH` 段までに制限する（`0x91` が続くだけの入力でスタックを使い切らない）
+//!
+//! 仕様: <https://github.com/msgpack/msgpack/blob/master/spec.md>
+
+use serde_json::{Map, Number, Value};
+
+use crate::typed::{CodecError, PayloadFormat};
+
+/// MessagePackFormat は `application/msgpack`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackFormat;
+
+impl PayloadFormat for MessagePackFormat {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn encode(&self, value: &Value) -> Result<Vec<u8>, CodecError> {
+        let mut out = Vec::new();
+        write_value(&mut out, value);
+        Ok(out)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CodecError> {
+        let mut reader = Reader {
+            bytes,
+            pos: 0,
+            depth: 0,
+        };
+        let value = reader.value()?;
+        if reader.pos != bytes.len() {
+            return Err(error("trailing bytes"));
+        }
+        Ok(value)
+    }
+}
+
+/// 配列 / map の入れ子の上限
+const MAX_DEPTH: usize = 128;
+
+fn error(message: &str) -> CodecError {
+    CodecError::DeserializeFailed(format!("msgpack: {message}"))
+}
+
+/// 長さの前置き: fix 形式に入らなければ 16 / 32 ビット
+fn write_len(out: &mut Vec<u8>, len: usize, fix: u8, fix_max: usize, markers: [u8; 2]) {
+    if len <= fix_max {
+        out.push(fix | len as u8);
+    } else if let Ok(len) = u16::try_from(len) {
+        out.push(markers[0]);
+        out.extend_from_slice(&len.to_be_bytes());
+    } else {
+        out.push(markers[1]);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                match u {
+                    0..=0x7f => out.push(u as u8),
+                    0x80..=0xff => out.extend_from_slice(&[0xcc, u as u8]),
+                    0x100..=0xffff => {
+                        out.push(0xcd);
+                        out.extend_from_slice(&(u as u16).to_be_bytes());
+                    }
+                    0x1_0000..=0xffff_ffff => {
+                        out.push(0xce);
+                        out.extend_from_slice(&(u as u32).to_be_bytes());
+                    }
+                    _ => {
+                        out.push(0xcf);
+                        out.extend_from_slice(&u.to_be_bytes());
+                    }
+                }
+            } else if let Some(i) = n.as_i64() {
+                // as_u64 が None なので負
+                if i >= -32 {
+                    out.push(i as u8);
+                } else if let Ok(i) = i8::try_from(i) {
+                    out.extend_from_slice(&[0xd0, i as u8]);
+                } else if let Ok(i) = i16::try_from(i) {
+                    out.push(0xd1);
+                    out.extend_from_slice(&i.to_be_bytes());
+                } else if let Ok(i) = i32::try_from(i) {
+                    out.push(0xd2);
+                    out.extend_from_slice(&i.to_be_bytes());
+                } else {
+                    out.push(0xd3);
+                    out.extend_from_slice(&i.to_be_bytes());
+                }
+            } else {
+                out.push(0xcb);
+                out.extend_from_slice(&n.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        }
+        Value::String(s) => {
+            if s.len() <= 31 {
+                out.push(0xa0 | s.len() as u8);
+            } else if let Ok(len) = u8::try_from(s.len()) {
+                out.extend_from_slice(&[0xd9, len]);
+            } else {
+                write_len(out, s.len(), 0xa0, 31, [0xda, 0xdb]);
+            }
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            write_len(out, items.len(), 0x90, 15, [0xdc, 0xdd]);
+            for item in items {
+                write_value(out, item);
+            }
+        }
+        Value::Object(fields) => {
+            write_len(out, fields.len(), 0x80, 15, [0xde, 0xdf]);
+            for (key, value) in fields {
+                write_value(out, &Value::String(key.clone()));
+                write_value(out, value);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// いま読んでいる配列 / map の入れ子の深さ
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], CodecError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| error("unexpected end of input"))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CodecError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn uint(&mut self, size: usize) -> Result<u64, CodecError> {
+        Ok(self
+            .take(size)?
+            .iter()
+            .fold(0, |n, &b| (n << 8) | u64::from(b)))
+    }
+
+    fn str(&mut self, len: usize) -> Result<Value, CodecError> {
+        let bytes = self.take(len)?;
+        let s = std::str::from_utf8(bytes).map_err(|_| error("string is not UTF-8"))?;
+        Ok(Value::String(s.to_string()))
+    }
+
+    /// 配列 / map の中に入る（深すぎたらエラー）
+    fn enter(&mut self) -> Result<(), CodecError> {
+        if self.depth == MAX_DEPTH {
+            return Err(error("nested too deeply"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn items(&mut self, len: usize) -> Result<Value, CodecError> {
+        self.enter()?;
+        let mut items = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            items.push(self.value()?);
+        }
+        self.depth -= 1;
+        Ok(Value::Array(items))
+    }
+
+    fn fields(&mut self, len: usize) -> Result<Value, CodecError> {
+        self.enter()?;
+        let mut fields = Map::new();
+        for _ in 0..len {
+            let Value::String(key) = self.value()? else {
+                return Err(error("map keys must be strings"));
+            };
+            fields.insert(key, self.value()?);
+        }
+        self.depth -= 1;
+        Ok(Value::Object(fields))
+    }
+
+    fn bin(&mut self, len: usize) -> Result<Value, CodecError> {
+        Ok(Value::Array(
+            self.take(len)?.iter().map(|&b| Value::from(b)).collect(),
+        ))
+    }
+
+    fn float(f: f64) -> Result<Value, CodecError> {
+        Number::from_f64(f)
+            .map(Value::Number)
+            .ok_or_else(|| error("NaN and infinity are not valid JSON"))
+    }
+
+    fn value(&mut self) -> Result<Value, CodecError> {
+        let marker = self.take(1)?[0];
+        match marker {
+            0x00..=0x7f => Ok(Value::from(marker)),
+            0x80..=0x8f => self.fields(usize::from(marker & 0x0f)),
+            0x90..=0x9f => self.items(usize::from(marker & 0x0f)),
+            0xa0..=0xbf => self.str(usize::from(marker & 0x1f)),
+            0xc0 => Ok(Value::Null),
+            0xc2 => Ok(Value::Bool(false)),
+            0xc3 => Ok(Value::Bool(true)),
+            0xc4..=0xc6 => {
+                let len = self.uint(1 << (marker - 0xc4))? as usize;
+                self.bin(len)
+            }
+            0xca => Self::float(f64::from(f32::from_be_bytes(self.array()?))),
+            0xcb => Self::float(f64::from_be_bytes(self.array()?)),
+            0xcc..=0xcf => Ok(Value::from(self.uint(1 << (marker - 0xcc))?)),
+            0xd0 => Ok(Value::from(i8::from_be_bytes(self.array()?))),
+            0xd1 => Ok(Value::from(i16::from_be_bytes(self.array()?))),
+            0xd2 => Ok(Value::from(i32::from_be_bytes(self.array()?))),
+            0xd3 => Ok(Value::from(i64::from_be_bytes(self.array()?))),
+            0xd9..=0xdb => {
+                let len = self.uint(1 << (marker - 0xd9))? as usize;
+                self.str(len)
+            }
+            0xdc | 0xdd => {
+                let len = self.uint(if marker == 0xdc { 2 } else { 4 })? as usize;
+                self.items(len)
+            }
+            0xde | 0xdf => {
+                let len = self.uint(if marker == 0xde { 2 } else { 4 })? as usize;
+                self.fields(len)
+            }
+            0xe0..=0xff => Ok(Value::from(marker as i8)),
+            0xc1 | 0xc7..=0xc9 | 0xd4..=0xd8 => Err(error("ext types are not supported")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values_roundtrip_in_their_smallest_encoding() {
+        let value = json!({
+            "small": 1,
+            "negative": -33,
+            "big": u64::MAX,
+            "min": i64::MIN,
+            "pi": 3.5,
+            "text": "x".repeat(40),
+            "list": [null, true, false, [], {}],
+        });
+        let bytes = MessagePackFormat.encode(&value).unwrap();
+        assert_eq!(MessagePackFormat.decode(&bytes).unwrap(), value);
+
+        assert_eq!(MessagePackFormat.encode(&json!(1)).unwrap(), [0x01]);
+        assert_eq!(MessagePackFormat.encode(&json!(-1)).unwrap(), [0xff]);
+        assert_eq!(
+            MessagePackFormat.encode(&json!({ "a": [300] })).unwrap(),
+            [0x81, 0xa1, b'a', 0x91, 0xcd, 0x01, 0x2c]
+        );
+        // bin 8 (e.g. from another producer) becomes an array of bytes
+        assert_eq!(
+            MessagePackFormat.decode(&[0xc4, 0x02, 0x00, 0xff]).unwrap(),
+            json!([0, 255])
+        );
+        assert!(MessagePackFormat.decode(&[0x92, 0x01]).is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected_without_overflowing_the_stack() {
+        let deep = vec![0x91; 200_000];
+        let err = MessagePackFormat.decode(&deep).unwrap_err();
+        assert!(err.to_string().contains("nested too deeply"), "{err}");
+
+        // MAX_DEPTH 段ちょうどは読める
+        let mut ok = vec![0x91; MAX_DEPTH];
+        ok.push(0xc0);
+        assert!(MessagePackFormat.decode(&ok).is_ok());
+    }
+}
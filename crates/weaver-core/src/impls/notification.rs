@@ -0,0 +1,149 @@
+//! SlackChannel / EmailChannel - NotificationChannel の実装
+//!
+//! # 学習ポイント
+//! - HTTP・SMTP の実体は別の port（HttpClient / EmailTransport）に任せ、
+//!   ここでは通知をそれぞれの形式に変換するだけ
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::ports::{
+    Email, EmailTransport, HttpClient, HttpRequest, Notification, NotificationChannel,
+    NotificationError,
+};
+
+/// SlackChannel は Slack の Incoming Webhook に投稿する
+pub struct SlackChannel {
+    client: Arc<dyn HttpClient>,
+    webhook_url: String,
+}
+
+impl SlackChannel {
+    /// `webhook_url` は Slack で発行した Incoming Webhook の URL
+    pub fn new(client: Arc<dyn HttpClient>, webhook_url: impl Into<String>) -> Self {
+        Self {
+            client,
+            webhook_url: webhook_url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SlackChannel {
+    async fn send(&self, notification: &Notification) -> Result<(), NotificationError> {
+        let text = format!("*{}*\n{}", notification.subject, notification.body);
+        let request = HttpRequest {
+            method: "POST".to_string(),
+            url: self.webhook_url.clone(),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: serde_json::to_vec(&serde_json::json!({ "text": text }))
+                .expect("JSON value serializes"),
+        };
+        match self.client.send(&request).await {
+            Ok(response) if (200..300).contains(&response.status) => Ok(()),
+            Ok(response) => Err(NotificationError::SendFailed(format!(
+                "Slack responded with HTTP {}",
+                response.status
+            ))),
+            Err(e) => Err(NotificationError::SendFailed(e.to_string())),
+        }
+    }
+}
+
+/// EmailChannel は決まった宛先にメールを送る
+pub struct EmailChannel {
+    transport: Arc<dyn EmailTransport>,
+    from: String,
+    to: Vec<String>,
+}
+
+impl EmailChannel {
+    pub fn new(
+        transport: Arc<dyn EmailTransport>,
+        from: impl Into<String>,
+        to: Vec<String>,
+    ) -> Self {
+        Self {
+            transport,
+            from: from.into(),
+            to,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    async fn send(&self, notification: &Notification) -> Result<(), NotificationError> {
+        let email = Email {
+            from: self.from.clone(),
+            to: self.to.clone(),
+            subject: notification.subject.clone(),
+            body: notification.body.clone(),
+        };
+        self.transport.send(&email).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::ports::{HttpError, HttpResponse};
+
+    #[derive(Default)]
+    struct Recorder {
+        requests: Mutex<Vec<HttpRequest>>,
+        emails: Mutex<Vec<Email>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for Recorder {
+        async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+            self.requests.lock().unwrap().push(request.clone());
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: Vec::new(),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl EmailTransport for Recorder {
+        async fn send(&self, email: &Email) -> Result<(), NotificationError> {
+            self.emails.lock().unwrap().push(email.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn channels_format_the_notification() {
+        let recorder = Arc::new(Recorder::default());
+        let notification = Notification {
+            subject: "3 tasks died".to_string(),
+            body: "type: email".to_string(),
+        };
+
+        SlackChannel::new(recorder.clone(), "https://hooks.slack.test/x")
+            .send(&notification)
+            .await
+            .unwrap();
+        let request = recorder.requests.lock().unwrap()[0].clone();
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["text"], "*3 tasks died*\ntype: email");
+
+        EmailChannel::new(
+            recorder.clone(),
+            "weaver@example.com",
+            vec!["ops@example.com".into()],
+        )
+        .send(&notification)
+        .await
+        .unwrap();
+        let email = recorder.emails.lock().unwrap()[0].clone();
+        assert_eq!(email.to, vec!["ops@example.com".to_string()]);
+        assert_eq!(email.subject, "3 tasks died");
+    }
+}
@@ -0,0 +1,197 @@
+//! OtlpHttpExporter - span を OTLP/HTTP（JSON）で送る SpanExporter
+//!
+//! # 学習ポイント
+//! - OpenTelemetry Collector（や Jaeger・Tempo など）は `POST /v1/traces` で
+//!   OTLP の JSON を受け付ける。SDK に依存せず、HTTP の実体は HttpClient port に任せる
+//! - OTLP/JSON の trace_id / span_id は hex 文字列、時刻は Unix ナノ秒の文字列
+//! - 1 span ごとに 1 リクエスト（まとめて送りたければ Collector 側の batch processor を使う）
+//!
+//! 仕様: <https://opentelemetry.io/docs/specs/otlp/#json-protobuf-encoding>
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+use crate::ports::{HttpClient, HttpRequest, Span, SpanExportError, SpanExporter, SpanStatus};
+
+/// OTLP の SpanKind: メッセージを受け取って処理する側
+const SPAN_KIND_CONSUMER: u8 = 5;
+
+/// OtlpHttpExporter は `{endpoint}/v1/traces` に span を送る
+///
+/// # 使用例
+/// ```ignore
+/// let exporter = OtlpHttpExporter::new(client, "http://otel-collector:4318")
+///     .with_service_name("billing-worker");
+/// let config = WorkerGroupConfig {
+///     span_exporter: Some(Arc::new(exporter)),
+///     ..WorkerGroupConfig::default()
+/// };
+/// ```
+pub struct OtlpHttpExporter {
+    client: Arc<dyn HttpClient>,
+    url: String,
+    service_name: String,
+    headers: Vec<(String, String)>,
+}
+
+impl OtlpHttpExporter {
+    /// `endpoint` は Collector の OTLP/HTTP のベース URL（例: `http://localhost:4318`）
+    pub fn new(client: Arc<dyn HttpClient>, endpoint: &str) -> Self {
+        Self {
+            client,
+            url: format!("{}/v1/traces", endpoint.trim_end_matches('/')),
+            service_name: "weaver".to_string(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// resource の `service.name`（デフォルト: `weaver`）
+    pub fn with_service_name(mut self, service_name: impl Into<String>) -> Self {
+        self.service_name = service_name.into();
+        self
+    }
+
+    /// 追加のヘッダー（例: SaaS の API キー）
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// `span` を ExportTraceServiceRequest の JSON に
+    fn body(&self, span: &Span) -> Value {
+        let attributes: Vec<Value> = span
+            .attributes
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+            .collect();
+        let status = match &span.status {
+            SpanStatus::Ok => json!({ "code": 1 }),
+            SpanStatus::Error(message) => json!({ "code": 2, "message": message }),
+        };
+        let mut otlp_span = json!({
+            "traceId": span.context.trace_id(),
+            "spanId": span.context.span_id(),
+            "name": span.name,
+            "kind": SPAN_KIND_CONSUMER,
+            "startTimeUnixNano": unix_nanos(span.start),
+            "endTimeUnixNano": unix_nanos(span.end),
+            "attributes": attributes,
+            "status": status,
+        });
+        if let Some(parent) = &span.parent {
+            otlp_span["parentSpanId"] = parent.span_id().into();
+        }
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        { "key": "service.name", "value": { "stringValue": self.service_name } },
+                    ],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "weaver", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": [otlp_span],
+                }],
+            }],
+        })
+    }
+}
+
+/// OTLP/JSON では 64 ビット整数を文字列で書く
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+#[async_trait]
+impl SpanExporter for OtlpHttpExporter {
+    async fn export(&self, span: Span) -> Result<(), SpanExportError> {
+        if !span.context.is_sampled() {
+            return Ok(());
+        }
+        let mut headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+        headers.extend(self.headers.iter().cloned());
+        let request = HttpRequest {
+            method: "POST".to_string(),
+            url: self.url.clone(),
+            headers,
+            body: serde_json::to_vec(&self.body(&span)).expect("JSON value serializes"),
+        };
+        match self.client.send(&request).await {
+            Ok(response) if (200..300).contains(&response.status) => Ok(()),
+            Ok(response) => Err(SpanExportError::ExportFailed(format!(
+                "collector responded with HTTP {}",
+                response.status
+            ))),
+            Err(e) => Err(SpanExportError::ExportFailed(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::TraceContext;
+    use crate::ports::{HttpError, HttpResponse};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct RecordingClient(Mutex<Vec<HttpRequest>>);
+
+    #[async_trait]
+    impl HttpClient for RecordingClient {
+        async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+            self.0.lock().unwrap().push(request.clone());
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: Vec::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn spans_are_posted_as_otlp_json() {
+        let client = Arc::new(RecordingClient::default());
+        let exporter = OtlpHttpExporter::new(client.clone(), "http://collector:4318/")
+            .with_service_name("billing")
+            .with_header("x-api-key", "secret");
+        let parent = TraceContext::new_root();
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let span = Span {
+            context: parent.child(),
+            parent: Some(parent),
+            name: "process email".to_string(),
+            start,
+            end: start + Duration::from_millis(5),
+            attributes: vec![("weaver.attempt".to_string(), "2".to_string())],
+            status: SpanStatus::Error("smtp timeout".to_string()),
+        };
+        exporter.export(span.clone()).await.unwrap();
+
+        let requests = client.0.lock().unwrap();
+        assert_eq!(requests[0].url, "http://collector:4318/v1/traces");
+        assert_eq!(requests[0].header("x-api-key"), Some("secret"));
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "billing"
+        );
+        let otlp_span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(otlp_span["traceId"], parent.trace_id());
+        assert_eq!(otlp_span["spanId"], span.context.span_id());
+        assert_eq!(otlp_span["parentSpanId"], parent.span_id());
+        assert_eq!(otlp_span["startTimeUnixNano"], "1700000000000000000");
+        assert_eq!(otlp_span["endTimeUnixNano"], "1700000000005000000");
+        assert_eq!(otlp_span["attributes"][0]["key"], "weaver.attempt");
+        assert_eq!(otlp_span["status"]["code"], 2);
+        assert_eq!(otlp_span["status"]["message"], "smtp timeout");
+    }
+}
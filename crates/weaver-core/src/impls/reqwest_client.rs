@@ -0,0 +1,169 @@
+//! ReqwestClient - reqwest で送る HttpClient（feature `reqwest`）
+//!
+//! # 学習ポイント
+//! - `reqwest::Client` は接続プールを持つので、1 つ作って `Arc` で使い回す
+//! - TLS は rustls。ルート証明書は OS のものを使う
+//! - リダイレクトは reqwest が追う（最大 10 回）。それ以外の再送はしない
+//! - 4xx / 5xx も `Ok`（ステータスの判定は呼び出し側）
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Method;
+
+use crate::ports::{HttpClient, HttpError, HttpRequest, HttpResponse};
+
+/// ReqwestClient は `reqwest::Client` で 1 回送る
+///
+/// # 使用例
+/// ```ignore
+/// let http: Arc<dyn HttpClient> = Arc::new(ReqwestClient::new(Duration::from_secs(10))?);
+/// let notifier = WebhookNotifier::new(http);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReqwestClient {
+    client: reqwest::Client,
+}
+
+impl ReqwestClient {
+    /// 1 回の送信（接続からレスポンス本文を読み終えるまで）の上限を `timeout` にする
+    pub fn new(timeout: Duration) -> Result<Self, HttpError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| HttpError::Transport(e.to_string()))?;
+        Ok(Self { client })
+    }
+
+    /// プロキシやクライアント証明書など、設定済みの `reqwest::Client` を使う
+    pub fn from_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl HttpClient for ReqwestClient {
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+        let method = Method::from_bytes(request.method.as_bytes())
+            .map_err(|e| HttpError::Transport(format!("{}: {e}", request.method)))?;
+        let mut builder = self.client.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder
+            .body(request.body.clone())
+            .send()
+            .await
+            .map_err(|e| HttpError::Transport(format!("{e:#}")))?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.as_str().to_string(), value)
+            })
+            .collect();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| HttpError::Transport(format!("{e:#}")))?;
+        Ok(HttpResponse {
+            status,
+            headers,
+            body: body.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 1 回だけ受けて、受け取ったリクエストを返す HTTP サーバー
+    async fn serve_once(response: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0; 1024];
+            // ヘッダーと 5 バイトの本文が届くまで読む
+            while !received.ends_with(b"hello") {
+                let n = socket.read(&mut buf).await.unwrap();
+                assert!(n > 0, "connection closed early");
+                received.extend_from_slice(&buf[..n]);
+            }
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(received).unwrap()
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn sends_the_request_and_returns_any_status_as_ok() {
+        let (url, server) = serve_once(
+            "HTTP/1.1 503 Service Unavailable\r\nretry-after: 7\r\ncontent-length: 4\r\nconnection: close\r\n\r\nbusy",
+        )
+        .await;
+        let client = ReqwestClient::new(Duration::from_secs(5)).unwrap();
+
+        let response = client
+            .send(&HttpRequest {
+                method: "POST".to_string(),
+                url,
+                headers: vec![("X-Weaver-Signature".to_string(), "v1=abc".to_string())],
+                body: b"hello".to_vec(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status, 503);
+        assert!(
+            response
+                .headers
+                .contains(&("retry-after".to_string(), "7".to_string()))
+        );
+        assert_eq!(response.body, b"busy");
+
+        let received = server.await.unwrap().to_ascii_lowercase();
+        assert!(
+            received.starts_with("post /hook http/1.1\r\n"),
+            "{received}"
+        );
+        assert!(
+            received.contains("x-weaver-signature: v1=abc\r\n"),
+            "{received}"
+        );
+    }
+
+    #[tokio::test]
+    async fn connection_failures_are_transport_errors() {
+        // 直前まで使っていたポートは、閉じたあと誰も listen していない
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+
+        let client = ReqwestClient::new(Duration::from_secs(5)).unwrap();
+        let request = HttpRequest {
+            method: "GET".to_string(),
+            url,
+            headers: Vec::new(),
+            body: Vec::new(),
+        };
+        assert!(matches!(
+            client.send(&request).await,
+            Err(HttpError::Transport(_))
+        ));
+        let bad_method = HttpRequest {
+            method: "NOT A METHOD".to_string(),
+            ..request
+        };
+        assert!(matches!(
+            client.send(&bad_method).await,
+            Err(HttpError::Transport(_))
+        ));
+    }
+}
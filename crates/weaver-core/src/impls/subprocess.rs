@@ -0,0 +1,377 @@
+//! SubprocessHandler - 別プロセスの worker（任意の言語）に task を渡す組み込み TaskHandler
+//!
+//! # プロトコル（stdin / stdout）
+//! メッセージは「4 バイトの長さ（big endian）+ その長さの JSON」。
+//! worker は stdin から要求を読み、1 つにつき 1 つの応答を stdout に書く。
+//! stderr はそのままホストの stderr に流れる（ログ用）。
+//!
+//! ホスト → worker:
+//! ```json
+//! { "type": "task", "task_id": "task-01J...", "task_type": "ml.embed.v1", "attempt": 1, "payload": { } }
+//! { "type": "ping" }
+//! ```
+//! worker → ホスト:
+//! ```json
+//! { "type": "outcome", "outcome": { "kind": "SUCCESS" } }
+//! { "type": "error", "message": "model not found" }
+//! { "type": "pong" }
+//! ```
+//!
+//! # 学習ポイント
+//! - worker プロセスは使い回す（起動の重い Python のモデルなど向け）。
+//!   空いている worker がなければ新しく起動するので、同時に処理できる数は
+//!   worker の同時実行数に合わせて増える
+//! - 空き worker の置き場はロックで守るが、取り出してから使う（ロック中に await しない、ADR-0003）
+//! - worker が落ちた・timeout・応答が読めない: その worker は kill して捨て、
+//!   次の task で起動し直す（respawn）。task は失敗になり、Decider がリトライを決める
+//! - `health_check()` で ping / pong を確かめられる
+
+#![allow(deprecated)]
+
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+use crate::domain::{Outcome, TaskEnvelope};
+use crate::error::WeaverError;
+use crate::runtime::{TaskContext, TaskHandler};
+
+/// 1 メッセージの上限（これを超える長さを受け取ったら worker を捨てる）
+const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// `health_check()` の応答待ちの上限
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// ホスト → worker
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request<'a> {
+    Task {
+        task_id: String,
+        task_type: &'a str,
+        attempt: u32,
+        payload: &'a serde_json::Value,
+    },
+    Ping,
+}
+
+/// worker → ホスト
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reply {
+    Outcome { outcome: Outcome },
+    Error { message: String },
+    Pong,
+}
+
+/// 起動済みの worker プロセス（drop すると kill される）
+struct WorkerProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+}
+
+impl WorkerProcess {
+    async fn exchange(&mut self, request: &Request<'_>) -> Result<Reply, String> {
+        let body = serde_json::to_vec(request).map_err(|e| e.to_string())?;
+        let len = u32::try_from(body.len()).map_err(|_| "request is too large".to_string())?;
+        self.stdin
+            .write_all(&len.to_be_bytes())
+            .await
+            .map_err(|e| format!("write: {e}"))?;
+        self.stdin
+            .write_all(&body)
+            .await
+            .map_err(|e| format!("write: {e}"))?;
+        self.stdin
+            .flush()
+            .await
+            .map_err(|e| format!("write: {e}"))?;
+
+        let mut len = [0u8; 4];
+        self.stdout
+            .read_exact(&mut len)
+            .await
+            .map_err(|e| format!("read: {e}"))?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_MESSAGE_BYTES {
+            return Err(format!("reply of {len} bytes exceeds the limit"));
+        }
+        let mut body = vec![0u8; len];
+        self.stdout
+            .read_exact(&mut body)
+            .await
+            .map_err(|e| format!("read: {e}"))?;
+        serde_json::from_slice(&body).map_err(|e| format!("invalid reply: {e}"))
+    }
+}
+
+/// SubprocessHandler は task を外部の worker プロセスで実行する
+///
+/// # 使用例
+/// ```ignore
+/// registry.register(
+///     TaskType::new("ml.embed.v1"),
+///     Arc::new(
+///         SubprocessHandler::new("python3")
+///             .with_args(["-m", "embed_worker"])
+///             .with_timeout(Duration::from_secs(60)),
+///     ),
+/// )?;
+/// ```
+pub struct SubprocessHandler {
+    program: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    /// 1 つの task の応答待ちの上限（`None` は無制限）
+    timeout: Option<Duration>,
+    /// 空いている worker
+    idle: Mutex<Vec<WorkerProcess>>,
+}
+
+impl SubprocessHandler {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            timeout: None,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// worker に追加する環境変数（プロセスの環境は引き継ぐ）
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// これを過ぎても応答がなければ worker を kill して失敗にする
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 空いている worker（生きているもの）を取り出す。なければ起動する
+    fn checkout(&self) -> Result<WorkerProcess, WeaverError> {
+        loop {
+            let Some(mut worker) = self.idle.lock().unwrap().pop() else {
+                break;
+            };
+            // 待っている間に終了していたら捨てる
+            if matches!(worker.child.try_wait(), Ok(None)) {
+                return Ok(worker);
+            }
+        }
+        self.spawn()
+    }
+
+    fn checkin(&self, worker: WorkerProcess) {
+        self.idle.lock().unwrap().push(worker);
+    }
+
+    fn spawn(&self) -> Result<WorkerProcess, WeaverError> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| WeaverError::Other(format!("spawn {}: {e}", self.program)))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(WeaverError::Other(format!(
+                "spawn {}: no stdio pipes",
+                self.program
+            )));
+        };
+        Ok(WorkerProcess {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    /// worker に ping を送り、pong が返ることを確かめる
+    ///
+    /// 空いている worker がなければ起動して確かめる（起動できるかの確認にもなる）。
+    pub async fn health_check(&self) -> Result<(), WeaverError> {
+        let mut worker = self.checkout()?;
+        let reply = tokio::time::timeout(HEALTH_CHECK_TIMEOUT, worker.exchange(&Request::Ping))
+            .await
+            .map_err(|_| WeaverError::Other(format!("{}: ping timed out", self.program)))?
+            .map_err(|e| WeaverError::Other(format!("{}: {e}", self.program)))?;
+        match reply {
+            Reply::Pong => {
+                self.checkin(worker);
+                Ok(())
+            }
+            reply => Err(WeaverError::Other(format!(
+                "{}: expected pong, got {reply:?}",
+                self.program
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl TaskHandler for SubprocessHandler {
+    async fn handle(&self, envelope: &TaskEnvelope) -> Result<Outcome, WeaverError> {
+        self.handle_with_context(envelope, &TaskContext::default())
+            .await
+    }
+
+    async fn handle_with_context(
+        &self,
+        envelope: &TaskEnvelope,
+        ctx: &TaskContext,
+    ) -> Result<Outcome, WeaverError> {
+        let request = Request::Task {
+            task_id: envelope.task_id().to_string(),
+            task_type: envelope.task_type().as_str(),
+            attempt: ctx.attempt(),
+            payload: envelope.payload(),
+        };
+        let mut worker = self.checkout()?;
+
+        let timeout = async {
+            match self.timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        // timeout・キャンセル・エラーでは worker を返さない（drop で kill される）
+        let reply = tokio::select! {
+            reply = worker.exchange(&request) => reply,
+            _ = timeout => {
+                return Ok(Outcome::failure(format!(
+                    "{} timed out after {:?}",
+                    self.program,
+                    self.timeout.unwrap_or_default()
+                )));
+            }
+            _ = ctx.cancellation().cancelled() => {
+                return Ok(Outcome::failure(format!("{} killed: task cancelled", self.program)));
+            }
+        };
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(e) => {
+                return Ok(Outcome::failure(format!(
+                    "{} worker crashed: {e}",
+                    self.program
+                )));
+            }
+        };
+        match reply {
+            Reply::Outcome { outcome } => {
+                self.checkin(worker);
+                Ok(outcome)
+            }
+            Reply::Error { message } => {
+                self.checkin(worker);
+                Ok(Outcome::failure(message))
+            }
+            Reply::Pong => Ok(Outcome::failure(format!(
+                "{}: unexpected pong for a task",
+                self.program
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Artifact, OutcomeKind, TaskId, TaskType};
+
+    /// 長さ付き JSON を話す worker（python3）
+    ///
+    /// payload の `"mode"`: `"ok"` は成功、`"error"` は error 応答、`"crash"` は即終了。
+    /// 成功の stdout には worker の pid を入れる（使い回しの確認用）。
+    const WORKER: &str = r#"
+import json, os, struct, sys
+def read():
+    head = sys.stdin.buffer.read(4)
+    if len(head) < 4:
+        sys.exit(0)
+    return json.loads(sys.stdin.buffer.read(struct.unpack(">I", head)[0]))
+def write(msg):
+    body = json.dumps(msg).encode()
+    sys.stdout.buffer.write(struct.pack(">I", len(body)) + body)
+    sys.stdout.buffer.flush()
+while True:
+    msg = read()
+    if msg["type"] == "ping":
+        write({"type": "pong"})
+        continue
+    mode = msg["payload"]["mode"]
+    if mode == "crash":
+        sys.exit(3)
+    if mode == "error":
+        write({"type": "error", "message": "bad input for " + msg["task_type"]})
+        continue
+    write({"type": "outcome", "outcome": {"kind": "SUCCESS", "artifacts": [{"kind": "Stdout", "value": str(os.getpid())}]}})
+"#;
+
+    fn handler() -> SubprocessHandler {
+        SubprocessHandler::new("python3")
+            .with_args(["-c", WORKER])
+            .with_timeout(Duration::from_secs(10))
+    }
+
+    fn envelope(mode: &str) -> TaskEnvelope {
+        TaskEnvelope::new(
+            TaskId::new(1),
+            TaskType::new("ml.embed.v1"),
+            serde_json::json!({ "mode": mode }),
+        )
+    }
+
+    fn worker_pid(outcome: &Outcome) -> String {
+        match &outcome.artifacts[..] {
+            [Artifact::Stdout(pid)] => pid.clone(),
+            artifacts => panic!("unexpected artifacts: {artifacts:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn replies_map_to_outcomes_and_the_worker_is_reused() {
+        let handler = handler();
+        handler.health_check().await.unwrap();
+
+        let first = handler.handle(&envelope("ok")).await.unwrap();
+        assert_eq!(first.kind, OutcomeKind::Success);
+        let error = handler.handle(&envelope("error")).await.unwrap();
+        assert_eq!(error.kind, OutcomeKind::Failure);
+        assert_eq!(error.reason.as_deref(), Some("bad input for ml.embed.v1"));
+        let second = handler.handle(&envelope("ok")).await.unwrap();
+        assert_eq!(worker_pid(&first), worker_pid(&second));
+    }
+
+    #[tokio::test]
+    async fn a_crashed_worker_fails_the_task_and_is_respawned() {
+        let handler = handler();
+        let before = handler.handle(&envelope("ok")).await.unwrap();
+
+        let crashed = handler.handle(&envelope("crash")).await.unwrap();
+        assert_eq!(crashed.kind, OutcomeKind::Failure);
+        assert!(crashed.reason.unwrap().contains("worker crashed"));
+
+        let after = handler.handle(&envelope("ok")).await.unwrap();
+        assert_eq!(after.kind, OutcomeKind::Success);
+        assert_ne!(worker_pid(&before), worker_pid(&after));
+    }
+}
@@ -0,0 +1,149 @@
+//! TokenBucketRateLimiter - プロセス内の token bucket
+//!
+//! # 学習ポイント
+//! - token bucket: 一定レートで token が補充され、`burst` 個まで貯まる
+//! - 補充は「前回からの経過時間 × レート」で遅延計算する（タイマー不要）
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::ports::RateLimiter;
+
+/// RateLimit は 1 つの key に対する制限
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// 1 秒あたりに補充される token 数
+    pub per_second: f64,
+    /// 貯めておける token の上限（瞬間的に許可する実行数）
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// 1 秒あたり `n` 回（burst も `n`）
+    pub fn per_second(n: u32) -> Self {
+        Self {
+            per_second: f64::from(n),
+            burst: n.max(1),
+        }
+    }
+
+    /// burst を設定
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// TokenBucketRateLimiter は key ごとの token bucket
+///
+/// # 使用例
+/// ```ignore
+/// let limiter = TokenBucketRateLimiter::new()
+///     .with_limit("send_email", RateLimit::per_second(5));
+/// let queue = InMemoryQueue::new(policy).with_rate_limiter(Arc::new(limiter));
+/// ```
+#[derive(Debug, Default)]
+pub struct TokenBucketRateLimiter {
+    limits: HashMap<String, RateLimit>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl TokenBucketRateLimiter {
+    /// 制限なしの RateLimiter を作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// key に制限を設定
+    pub fn with_limit(mut self, key: impl Into<String>, limit: RateLimit) -> Self {
+        self.limits.insert(key.into(), limit);
+        self
+    }
+
+    /// `now` 時点で token を 1 つ消費する（テスト用に時刻を注入できる）
+    fn try_acquire_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let Some(limit) = self.limits.get(key) else {
+            return Ok(());
+        };
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key.to_string()).or_insert_with(|| Bucket {
+            tokens: f64::from(limit.burst),
+            refilled_at: now,
+        });
+
+        let elapsed = now
+            .saturating_duration_since(bucket.refilled_at)
+            .as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(f64::from(limit.burst));
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if limit.per_second > 0.0 {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / limit.per_second,
+            ))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+}
+
+impl RateLimiter for TokenBucketRateLimiter {
+    fn try_acquire(&self, key: &str) -> Result<(), Duration> {
+        self.try_acquire_at(key, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_is_allowed_then_limited() {
+        let limiter =
+            TokenBucketRateLimiter::new().with_limit("a", RateLimit::per_second(2).with_burst(3));
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at("a", now).is_ok());
+        }
+        let wait = limiter.try_acquire_at("a", now).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        let limiter = TokenBucketRateLimiter::new().with_limit("a", RateLimit::per_second(10));
+        let start = Instant::now();
+        for _ in 0..10 {
+            limiter.try_acquire_at("a", start).unwrap();
+        }
+        assert!(limiter.try_acquire_at("a", start).is_err());
+
+        let later = start + Duration::from_millis(250);
+        assert!(limiter.try_acquire_at("a", later).is_ok());
+        assert!(limiter.try_acquire_at("a", later).is_ok());
+        assert!(limiter.try_acquire_at("a", later).is_err());
+    }
+
+    #[test]
+    fn keys_without_limit_are_unlimited() {
+        let limiter = TokenBucketRateLimiter::new().with_limit("a", RateLimit::per_second(1));
+        let now = Instant::now();
+
+        for _ in 0..100 {
+            assert!(limiter.try_acquire_at("b", now).is_ok());
+        }
+        assert!(limiter.try_acquire_at("a", now).is_ok());
+        assert!(limiter.try_acquire_at("a", now).is_err());
+    }
+}
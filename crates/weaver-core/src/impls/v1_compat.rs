@@ -0,0 +1,640 @@
+//! v1 → v2 移行用アダプタ
+//!
+//! v1 の `InMemoryQueue` + `WorkerGroup` を使っている既存コードを、
+//! handler を書き換えずに ports/app 構成へ段階的に移すための橋渡しです。
+//!
+//! # 含まれる型
+//! - **QueueAsTaskStore**: v1 `Queue` を `TaskStore` + `DeliveryQueue` として見せる
+//! - **RuntimeAsWorkerLoop**: v1 `Runtime`（登録済み `TaskHandler`）を v2 のフロー
+//!   （pop→claim→handle→decide→complete）で動かす
+//!
+//! # 移行手順
+//! 1. `WorkerGroup::spawn(n, queue, runtime, decider)` を
+//!    `RuntimeAsWorkerLoop::from_v1(queue, runtime, decider)` + `run()` に置き換える
+//!    （handler と Decider はそのまま）
+//! 2. worker 側のコードを ports（`TaskStore` / `DeliveryQueue`）経由に揃える
+//! 3. 正本を v2 の TaskStore 実装（PR-7）に差し替える。`RuntimeAsWorkerLoop::new()` に
+//!    store と delivery を別々に渡せば、v1 の `InMemoryQueue` は不要になる
+//! 4. 最後に handler を typed API（`typed::Handler`）へ移し、`app::WorkerLoop` に切り替える
+//!
+//! ```ignore
+//! // Before (v1)
+//! let workers = WorkerGroup::spawn(4, queue.clone(), runtime, decider);
+//!
+//! // After (移行中)
+//! let worker = RuntimeAsWorkerLoop::from_v1(queue.clone(), runtime, decider);
+//! tokio::spawn(async move { worker.run(shutdown_rx).await });
+//! ```
+//!
+//! # 制約
+//! - v1 の Queue には namespace がないため、`ns` は無視される
+//! - lease の期限は v1 Queue の設定（`with_lease_ttl`）に従い、`lease_ttl` 引数は使わない
+//!   （`extend_lease` も v1 の `heartbeat()` で同じ長さだけ延ばす）
+//! - `get_task` で見えるのは claim 中のタスクだけ
+//! - v1 の Queue は outbox を持たない（ready になったタスクは自分で配送する）ため、
+//!   `pull_outbox` / `ack_outbox` / `fail_outbox` は `StoreError::Unsupported`。
+//!   PublisherLoop はこれを受けると止まる（v1 では配送するものがない）
+//! - `read_events` / cursor は v1 Queue の event log を使う（`InMemoryQueue::with_event_log`）
+//! - `reap_expired_leases` は v1 Queue の時計で期限を判定し、`now` と `limit` は使わない
+
+#![allow(deprecated)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::watch;
+
+use crate::domain::ids::{EventId, RunId, TaskId};
+use crate::domain::{
+    Decider, Decision, EventCursor, LifecycleEvent, Outcome, OutcomeKind, TaskEnvelope,
+};
+use crate::ports::{
+    Clock, CompleteResult, DeliveryQueue, Lease, OutboxEvent, QueueError, ReapedLeases, StoreError,
+    SystemClock, TaskStore,
+};
+use crate::observability::QueueCounts;
+use crate::queue::{Queue, TaskLease, TaskRecord};
+use crate::runtime::Runtime;
+
+/// QueueAsTaskStore は v1 `Queue` を v2 の `TaskStore` / `DeliveryQueue` として公開する
+///
+/// # 実装詳細
+/// - `pop()` で v1 の lease を取得して保留し、task_id だけを返す
+/// - `claim()` で保留中の lease を引き渡し済みに移す
+/// - `complete()` で v1 の `ack()` / `complete()` を呼ぶ
+///
+/// v1 の Queue は ready なタスクを自分で配送するため、`push()` は何もしない。
+pub struct QueueAsTaskStore {
+    queue: Arc<dyn Queue>,
+    /// pop 済みで claim 前の lease
+    popped: Mutex<HashMap<TaskId, Box<dyn TaskLease>>>,
+    /// claim 済みの lease
+    claimed: Mutex<HashMap<TaskId, Box<dyn TaskLease>>>,
+}
+
+impl QueueAsTaskStore {
+    /// v1 Queue を包む
+    pub fn new(queue: Arc<dyn Queue>) -> Self {
+        Self {
+            queue,
+            popped: Mutex::new(HashMap::new()),
+            claimed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn take_claimed(&self, task_id: TaskId) -> Result<Box<dyn TaskLease>, StoreError> {
+        self.claimed
+            .lock()
+            .unwrap()
+            .remove(&task_id)
+            .ok_or(StoreError::LeaseNotHeld(task_id))
+    }
+}
+
+#[async_trait]
+impl DeliveryQueue for QueueAsTaskStore {
+    async fn push(&self, _ns: &str, _task_id: TaskId) -> Result<(), QueueError> {
+        Ok(())
+    }
+
+    async fn pop(&self, _ns: &str, timeout: Duration) -> Result<Option<TaskId>, QueueError> {
+        let Ok(Some(lease)) = tokio::time::timeout(timeout, self.queue.lease()).await else {
+            return Ok(None);
+        };
+        let task_id = lease.envelope().task_id();
+        self.popped.lock().unwrap().insert(task_id, lease);
+        Ok(Some(task_id))
+    }
+}
+
+#[async_trait]
+impl TaskStore for QueueAsTaskStore {
+    async fn claim(
+        &self,
+        _ns: &str,
+        task_id: TaskId,
+        worker_id: &str,
+        _lease_ttl: Duration,
+        _now: DateTime<Utc>,
+    ) -> Result<Option<(Lease, TaskEnvelope)>, StoreError> {
+        let Some(lease) = self.popped.lock().unwrap().remove(&task_id) else {
+            return Ok(None);
+        };
+        let record = lease
+            .get_task_record()
+            .await
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))?;
+        let envelope = lease.envelope().clone();
+        let run_id = lease.run_id();
+        self.claimed.lock().unwrap().insert(task_id, lease);
+
+        let lease = Lease {
+            task_id,
+            attempt: record.attempts,
+            worker_id: worker_id.to_string(),
+            expires_at: None,
+            run_id,
+        };
+        Ok(Some((lease, envelope)))
+    }
+
+    async fn extend_lease(
+        &self,
+        _ns: &str,
+        lease: &Lease,
+        _lease_ttl: Duration,
+        _now: DateTime<Utc>,
+    ) -> Result<(), StoreError> {
+        let task_id = lease.task_id;
+        // ロックを保持したまま await しない（ADR-0003）
+        let v1_lease = self.take_claimed(task_id)?;
+        let result = v1_lease.heartbeat().await;
+        self.claimed.lock().unwrap().insert(task_id, v1_lease);
+        // v1 の heartbeat が失敗するのは lease が回収されたときだけ
+        result.map_err(|_| StoreError::LeaseNotHeld(task_id))
+    }
+
+    async fn get_task(&self, _ns: &str, task_id: TaskId) -> Result<Option<TaskRecord>, StoreError> {
+        // ロックを保持したまま await しない（ADR-0003）
+        let Some(lease) = self.claimed.lock().unwrap().remove(&task_id) else {
+            return Ok(None);
+        };
+        let record = lease.get_task_record().await;
+        self.claimed.lock().unwrap().insert(task_id, lease);
+        record
+            .map(Some)
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))
+    }
+
+    async fn complete(
+        &self,
+        _ns: &str,
+        lease: Lease,
+        outcome: Outcome,
+        decision: Option<Decision>,
+        _now: DateTime<Utc>,
+    ) -> Result<CompleteResult, StoreError> {
+        let v1_lease = self.take_claimed(lease.task_id)?;
+        let result = CompleteResult::from_decision(decision.as_ref());
+        match decision {
+            None => v1_lease.succeed(outcome).await,
+            Some(decision) => v1_lease.complete(outcome, decision).await,
+        }
+        .map_err(|e| StoreError::OperationFailed(e.to_string()))?;
+        Ok(result)
+    }
+
+    async fn pull_outbox(
+        &self,
+        _ns: &str,
+        _now: DateTime<Utc>,
+        _limit: usize,
+    ) -> Result<Vec<OutboxEvent>, StoreError> {
+        Err(StoreError::Unsupported("pull_outbox"))
+    }
+
+    async fn counts(&self, _ns: &str) -> Result<QueueCounts, StoreError> {
+        self.queue
+            .counts_by_state()
+            .await
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))
+    }
+
+    async fn read_events(
+        &self,
+        _ns: &str,
+        after: Option<EventCursor>,
+        limit: usize,
+    ) -> Result<Vec<LifecycleEvent>, StoreError> {
+        self.queue
+            .read_events(after, limit)
+            .await
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))
+    }
+
+    async fn load_cursor(
+        &self,
+        _ns: &str,
+        consumer: &str,
+    ) -> Result<Option<EventCursor>, StoreError> {
+        self.queue
+            .event_cursor(consumer)
+            .await
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))
+    }
+
+    async fn find_tasks_by_field(
+        &self,
+        _ns: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<Vec<TaskId>, StoreError> {
+        self.queue
+            .find_tasks_by_field(field, value)
+            .await
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))
+    }
+
+    async fn run_id(&self, _ns: &str) -> Result<Option<RunId>, StoreError> {
+        Ok(self.queue.run_id().await)
+    }
+
+    async fn reap_expired_leases(
+        &self,
+        _ns: &str,
+        _now: DateTime<Utc>,
+        _limit: usize,
+    ) -> Result<ReapedLeases, StoreError> {
+        self.queue
+            .reap_expired_leases()
+            .await
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))
+    }
+
+    async fn commit_cursor(
+        &self,
+        _ns: &str,
+        consumer: &str,
+        cursor: EventCursor,
+    ) -> Result<(), StoreError> {
+        self.queue
+            .commit_event_cursor(consumer, cursor)
+            .await
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))
+    }
+
+    async fn ack_outbox(
+        &self,
+        _ns: &str,
+        _event_id: EventId,
+        _now: DateTime<Utc>,
+    ) -> Result<(), StoreError> {
+        Err(StoreError::Unsupported("ack_outbox"))
+    }
+
+    async fn fail_outbox(
+        &self,
+        _ns: &str,
+        _event_id: EventId,
+        _error: String,
+        _now: DateTime<Utc>,
+    ) -> Result<(), StoreError> {
+        Err(StoreError::Unsupported("fail_outbox"))
+    }
+}
+
+/// RuntimeAsWorkerLoop は v1 の Runtime を v2 のフローで動かす worker
+///
+/// # フロー
+/// 1. DeliveryQueue::pop() で task_id 取得
+/// 2. TaskStore::claim() で lease 発行 + TaskEnvelope 取得
+/// 3. v1 Runtime で handler 実行 → Outcome
+/// 4. 失敗・分解の場合のみ Decider 実行 → Decision
+/// 5. TaskStore::complete() で確定
+pub struct RuntimeAsWorkerLoop {
+    store: Arc<dyn TaskStore>,
+    delivery: Arc<dyn DeliveryQueue>,
+    runtime: Arc<Runtime>,
+    decider: Arc<dyn Decider>,
+    clock: Arc<dyn Clock>,
+    ns: String,
+    worker_id: String,
+    lease_ttl: Duration,
+    pop_timeout: Duration,
+}
+
+impl RuntimeAsWorkerLoop {
+    /// ports を指定して作成
+    pub fn new(
+        store: Arc<dyn TaskStore>,
+        delivery: Arc<dyn DeliveryQueue>,
+        runtime: Arc<Runtime>,
+        decider: Arc<dyn Decider>,
+    ) -> Self {
+        Self {
+            store,
+            delivery,
+            runtime,
+            decider,
+            clock: Arc::new(SystemClock),
+            ns: "default".to_string(),
+            worker_id: "worker-0".to_string(),
+            lease_ttl: Duration::from_secs(30),
+            pop_timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// v1 の `WorkerGroup::spawn` と同じ引数から作成
+    ///
+    /// queue は `QueueAsTaskStore` で包み、store と delivery の両方に使う。
+    pub fn from_v1(
+        queue: Arc<dyn Queue>,
+        runtime: Arc<Runtime>,
+        decider: Arc<dyn Decider>,
+    ) -> Self {
+        let adapter = Arc::new(QueueAsTaskStore::new(queue));
+        Self::new(adapter.clone(), adapter, runtime, decider)
+    }
+
+    /// namespace を設定（デフォルト: "default"）
+    pub fn with_namespace(mut self, ns: impl Into<String>) -> Self {
+        self.ns = ns.into();
+        self
+    }
+
+    /// worker_id を設定（デフォルト: "worker-0"）
+    pub fn with_worker_id(mut self, worker_id: impl Into<String>) -> Self {
+        self.worker_id = worker_id.into();
+        self
+    }
+
+    /// claim 時の lease_ttl を設定（デフォルト: 30 秒）
+    pub fn with_lease_ttl(mut self, lease_ttl: Duration) -> Self {
+        self.lease_ttl = lease_ttl;
+        self
+    }
+
+    /// Clock を差し替える（デフォルト: SystemClock）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// タスクを 1 つ処理する
+    ///
+    /// # Returns
+    /// - `Ok(true)`: タスクを処理した
+    /// - `Ok(false)`: timeout まで待っても候補がない、または claim できなかった
+    pub async fn run_once(&self) -> Result<bool, StoreError> {
+        let Some(task_id) = self
+            .delivery
+            .pop(&self.ns, self.pop_timeout)
+            .await
+            .map_err(|e| StoreError::OperationFailed(e.to_string()))?
+        else {
+            return Ok(false);
+        };
+
+        // pop は候補通知に過ぎない。claim できなければ他の worker に譲る
+        let Some((lease, envelope)) = self
+            .store
+            .claim(
+                &self.ns,
+                task_id,
+                &self.worker_id,
+                self.lease_ttl,
+                self.clock.now(),
+            )
+            .await?
+        else {
+            return Ok(false);
+        };
+
+        let outcome = match self.runtime.execute(&envelope).await {
+            Ok(outcome) => outcome,
+            // インフラ起因のエラーは業務上の失敗として扱う（v1 worker と同じ）
+            Err(e) => Outcome::failure(e.to_string()),
+        };
+
+        let decision = if outcome.kind == OutcomeKind::Success && outcome.child_tasks.is_none() {
+            None
+        } else {
+            let record = self
+                .store
+                .get_task(&self.ns, task_id)
+                .await?
+                .ok_or(StoreError::NotFound(task_id))?;
+            Some(self.decider.decide(&record, &outcome))
+        };
+
+        self.store
+            .complete(&self.ns, lease, outcome, decision, self.clock.now())
+            .await?;
+        Ok(true)
+    }
+
+    /// shutdown が通知されるまでタスクを処理し続ける
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            tokio::select! {
+                result = self.run_once() => {
+                    if let Err(e) = result {
+                        eprintln!("[{}] {}", self.worker_id, e);
+                    }
+                }
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{DefaultDecider, TaskType};
+    use crate::queue::{InMemoryQueue, JournalOp, RetryPolicy};
+    use crate::runtime::{HandlerRegistry, TaskHandler};
+
+    struct EchoHandler;
+
+    #[async_trait]
+    impl TaskHandler for EchoHandler {
+        async fn handle(
+            &self,
+            envelope: &TaskEnvelope,
+        ) -> Result<Outcome, crate::error::WeaverError> {
+            if envelope.payload()["fail"].as_bool() == Some(true) {
+                return Ok(Outcome::failure("asked to fail"));
+            }
+            Ok(Outcome::success())
+        }
+    }
+
+    fn setup() -> (Arc<InMemoryQueue>, RuntimeAsWorkerLoop) {
+        setup_with(InMemoryQueue::new(RetryPolicy::default_v1()))
+    }
+
+    fn setup_with(queue: InMemoryQueue) -> (Arc<InMemoryQueue>, RuntimeAsWorkerLoop) {
+        let queue = Arc::new(queue);
+        let mut registry = HandlerRegistry::new();
+        registry
+            .register(TaskType::new("echo"), Arc::new(EchoHandler))
+            .unwrap();
+        let runtime = Arc::new(Runtime::new(Arc::new(registry)));
+        let decider = Arc::new(DefaultDecider::default_v1());
+        let worker = RuntimeAsWorkerLoop::from_v1(queue.clone(), runtime, decider);
+        (queue, worker)
+    }
+
+    #[tokio::test]
+    async fn v1_handler_runs_through_v2_flow() {
+        let (queue, worker) = setup();
+        queue
+            .enqueue(TaskEnvelope::new(
+                TaskId::new(1),
+                TaskType::new("echo"),
+                serde_json::json!({}),
+            ))
+            .await
+            .unwrap();
+
+        assert!(worker.run_once().await.unwrap());
+
+        let counts = queue.counts_by_state().await.unwrap();
+        assert_eq!(counts.succeeded, 1);
+        assert_eq!(counts.running, 0);
+    }
+
+    #[tokio::test]
+    async fn failure_goes_through_decider() {
+        let (queue, worker) = setup();
+        queue
+            .enqueue(TaskEnvelope::new(
+                TaskId::new(1),
+                TaskType::new("echo"),
+                serde_json::json!({ "fail": true }),
+            ))
+            .await
+            .unwrap();
+
+        assert!(worker.run_once().await.unwrap());
+
+        let counts = queue.counts_by_state().await.unwrap();
+        assert_eq!(counts.retry_scheduled, 1);
+        assert_eq!(queue.get_all_attempts().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn consumer_resumes_from_its_committed_cursor() {
+        let (queue, worker) =
+            setup_with(InMemoryQueue::new(RetryPolicy::default_v1()).with_event_log());
+        let store = QueueAsTaskStore::new(queue.clone());
+        queue
+            .enqueue(TaskEnvelope::new(
+                TaskId::new(1),
+                TaskType::new("echo"),
+                serde_json::json!({}),
+            ))
+            .await
+            .unwrap();
+        assert!(worker.run_once().await.unwrap());
+
+        let after = store.load_cursor("default", "audit").await.unwrap();
+        assert_eq!(after, None);
+        let events = store.read_events("default", after, 2).await.unwrap();
+        let ops: Vec<_> = events.iter().map(|event| event.op).collect();
+        assert_eq!(ops, [JournalOp::Enqueue, JournalOp::Lease]);
+        store
+            .commit_cursor("default", "audit", events[1].cursor)
+            .await
+            .unwrap();
+
+        // A restarted consumer picks up where it committed
+        let after = store.load_cursor("default", "audit").await.unwrap();
+        let events = store.read_events("default", after, 10).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].op, JournalOp::Ack);
+        assert_eq!(events[0].cursor, EventCursor(3));
+    }
+
+    #[tokio::test]
+    async fn leases_and_events_carry_the_queue_run() {
+        let run_id = RunId::from_ulid(ulid::Ulid::new());
+        let queue = Arc::new(
+            InMemoryQueue::new(RetryPolicy::default_v1())
+                .with_event_log()
+                .with_run_id(run_id),
+        );
+        let store = QueueAsTaskStore::new(queue.clone());
+        queue
+            .enqueue(TaskEnvelope::new(
+                TaskId::new(1),
+                TaskType::new("echo"),
+                serde_json::json!({}),
+            ))
+            .await
+            .unwrap();
+
+        let task_id = store
+            .pop("default", Duration::from_millis(20))
+            .await
+            .unwrap()
+            .unwrap();
+        let (lease, _) = store
+            .claim("default", task_id, "w", Duration::from_secs(1), Utc::now())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lease.run_id, Some(run_id));
+        assert_eq!(store.run_id("default").await.unwrap(), Some(run_id));
+        let events = store.read_events("default", None, 10).await.unwrap();
+        assert!(events.iter().all(|event| event.run_id == run_id));
+    }
+
+    #[tokio::test]
+    async fn outbox_is_reported_as_unsupported_and_the_publisher_stops() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let store = Arc::new(QueueAsTaskStore::new(queue));
+
+        assert!(matches!(
+            store.pull_outbox("default", Utc::now(), 10).await,
+            Err(StoreError::Unsupported("pull_outbox"))
+        ));
+        let publisher = crate::app::PublisherLoop::new(store.clone(), store);
+        let (_shutdown, rx) = watch::channel(false);
+        tokio::time::timeout(Duration::from_secs(1), publisher.run(rx))
+            .await
+            .expect("the publisher should stop without an outbox");
+    }
+
+    #[tokio::test]
+    async fn claim_without_pop_returns_none() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let store = QueueAsTaskStore::new(queue);
+
+        let claimed = store
+            .claim(
+                "default",
+                TaskId::new(1),
+                "w",
+                Duration::from_secs(1),
+                Utc::now(),
+            )
+            .await
+            .unwrap();
+        assert!(claimed.is_none());
+    }
+
+    #[tokio::test]
+    async fn complete_without_claim_is_rejected() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let store = QueueAsTaskStore::new(queue);
+        let lease = Lease {
+            task_id: TaskId::new(1),
+            attempt: 1,
+            worker_id: "w".to_string(),
+            expires_at: None,
+            run_id: None,
+        };
+
+        let result = store
+            .complete("default", lease, Outcome::success(), None, Utc::now())
+            .await;
+        assert!(matches!(result, Err(StoreError::LeaseNotHeld(_))));
+    }
+
+    #[tokio::test]
+    async fn pop_times_out_when_empty() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let store = QueueAsTaskStore::new(queue);
+
+        let popped = store
+            .pop("default", Duration::from_millis(20))
+            .await
+            .unwrap();
+        assert!(popped.is_none());
+    }
+}
@@ -0,0 +1,128 @@
+//! WasmHandler - task_type ごとの .wasm モジュールで task を実行する DynHandler
+//!
+//! # 学習ポイント
+//! - handler のコードは sandbox（WASM）の中で動くので、ホストのファイルや
+//!   ネットワークには、`WasmEngine` の実装が渡したものしか触れない
+//! - モジュールは起動時に 1 度だけ読み込む（`AppBuilder::register_wasm`）。
+//!   読めなければ build() が失敗する（Fail-fast）
+//! - 結果の判定:
+//!   - モジュールが返した `Outcome` の JSON: そのまま返す
+//!   - trap（panic・上限超過）: 失敗（Decider がリトライを決める）
+//!   - 出力が `Outcome` として読めない: `Err`（モジュールの不具合）
+
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::domain::Outcome;
+use crate::domain::errors::WeaverError;
+use crate::ports::{WasmEngine, WasmError, WasmModule};
+use crate::typed::DynHandler;
+
+/// モジュールが export する関数名のデフォルト
+pub const DEFAULT_WASM_FUNCTION: &str = "handle";
+
+/// WasmHandler は 1 つの task_type を 1 つの WASM モジュールで処理する
+pub struct WasmHandler {
+    task_type: String,
+    module: Arc<dyn WasmModule>,
+    function: String,
+}
+
+impl WasmHandler {
+    /// 読み込み済みの `module` の `handle` 関数で `task_type` を処理する
+    pub fn new(task_type: impl Into<String>, module: Arc<dyn WasmModule>) -> Self {
+        Self {
+            task_type: task_type.into(),
+            module,
+            function: DEFAULT_WASM_FUNCTION.to_string(),
+        }
+    }
+
+    /// `path` のモジュールを `engine` で読み込む
+    pub fn load(
+        engine: &dyn WasmEngine,
+        task_type: impl Into<String>,
+        path: &Path,
+    ) -> Result<Self, WasmError> {
+        Ok(Self::new(task_type, engine.load(path)?))
+    }
+
+    /// `handle` 以外の export を呼ぶ
+    pub fn with_function(mut self, function: impl Into<String>) -> Self {
+        self.function = function.into();
+        self
+    }
+}
+
+#[async_trait]
+impl DynHandler for WasmHandler {
+    async fn handle_dyn(&self, payload: serde_json::Value) -> Result<Outcome, WeaverError> {
+        let input = payload.to_string().into_bytes();
+        let output = match self.module.call(&self.function, &input).await {
+            Ok(output) => output,
+            Err(e) => return Ok(Outcome::failure(format!("{}: {e}", self.task_type))),
+        };
+        serde_json::from_slice(&output).map_err(|e| {
+            WeaverError::new(format!(
+                "{}: wasm output is not an Outcome: {e}",
+                self.task_type
+            ))
+        })
+    }
+
+    fn task_type(&self) -> &str {
+        &self.task_type
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::OutcomeKind;
+
+    /// payload の `"mode"` で振る舞いを変えるモジュール
+    struct ScriptedModule;
+
+    #[async_trait]
+    impl WasmModule for ScriptedModule {
+        async fn call(&self, function: &str, input: &[u8]) -> Result<Vec<u8>, WasmError> {
+            assert_eq!(function, "handle");
+            let payload: serde_json::Value = serde_json::from_slice(input).unwrap();
+            match payload["mode"].as_str() {
+                Some("ok") => Ok(serde_json::to_vec(&Outcome::success()).unwrap()),
+                Some("trap") => Err(WasmError::Trap("unreachable executed".to_string())),
+                _ => Ok(b"not json".to_vec()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn outcome_comes_from_the_module_and_traps_fail_the_attempt() {
+        let handler = WasmHandler::new("plugins.resize.v1", Arc::new(ScriptedModule));
+
+        let outcome = handler
+            .handle_dyn(serde_json::json!({ "mode": "ok" }))
+            .await
+            .unwrap();
+        assert_eq!(outcome.kind, OutcomeKind::Success);
+
+        let outcome = handler
+            .handle_dyn(serde_json::json!({ "mode": "trap" }))
+            .await
+            .unwrap();
+        assert_eq!(outcome.kind, OutcomeKind::Failure);
+        assert_eq!(
+            outcome.reason.as_deref(),
+            Some("plugins.resize.v1: Wasm trapped: unreachable executed")
+        );
+
+        assert!(
+            handler
+                .handle_dyn(serde_json::json!({ "mode": "garbage" }))
+                .await
+                .is_err()
+        );
+    }
+}
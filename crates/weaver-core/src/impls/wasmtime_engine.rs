@@ -0,0 +1,226 @@
+//! WasmtimeEngine - wasmtime で .wasm を動かす WasmEngine（feature `wasm`）
+//!
+//! # 学習ポイント
+//! - import を 1 つも渡さない（WASI もなし）。モジュールはホストのファイル・ネットワーク・
+//!   時計に触れず、受け取った payload から Outcome を計算することしかできない
+//! - 呼び出しごとに新しい Store / インスタンスを作る（前の task の状態が残らない）
+//! - CPU は fuel、メモリは線形メモリの上限で止める。超えたら trap（`WasmError::Trap`）
+//! - 実行は CPU を使い続けるので `spawn_blocking` で動かす（tokio の worker を塞がない）
+//!
+//! # ABI
+//! モジュールは次を export する:
+//! - `memory`: 線形メモリ
+//! - `alloc(len: i32) -> i32`: 入力を書く `len` バイトの領域を確保して先頭を返す
+//! - `handle(ptr: i32, len: i32) -> i64`（名前は `WasmHandler::with_function` で変えられる）:
+//!   入力（payload の JSON）を読み、出力（`Outcome` の JSON）の位置を
+//!   `(ptr << 32) | len` にして返す
+
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::ports::{WasmEngine, WasmError, WasmModule};
+
+/// WasmtimeEngine は .wasm を wasmtime でコンパイルする
+#[derive(Clone)]
+pub struct WasmtimeEngine {
+    engine: Engine,
+    limits: Limits,
+}
+
+/// 1 回の呼び出しの上限
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    fuel: u64,
+    max_memory_bytes: usize,
+}
+
+impl WasmtimeEngine {
+    /// 既定の上限: fuel 10 億（おおよそ命令数）、メモリ 64 MiB
+    pub fn new() -> Result<Self, WasmError> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| WasmError::Load(e.to_string()))?;
+        Ok(Self {
+            engine,
+            limits: Limits {
+                fuel: 1_000_000_000,
+                max_memory_bytes: 64 << 20,
+            },
+        })
+    }
+
+    /// 1 回の呼び出しで使える fuel
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.limits.fuel = fuel;
+        self
+    }
+
+    /// 線形メモリの上限（バイト）
+    pub fn with_max_memory_bytes(mut self, max_memory_bytes: usize) -> Self {
+        self.limits.max_memory_bytes = max_memory_bytes;
+        self
+    }
+
+    /// .wasm（または wasmtime が読めるバイト列）からモジュールを作る
+    pub fn load_bytes(&self, bytes: &[u8]) -> Result<Arc<dyn WasmModule>, WasmError> {
+        let module =
+            Module::new(&self.engine, bytes).map_err(|e| WasmError::Load(format!("{e:#}")))?;
+        self.check(module)
+    }
+
+    /// import がなく、ABI の export があるか
+    fn check(&self, module: Module) -> Result<Arc<dyn WasmModule>, WasmError> {
+        if let Some(import) = module.imports().next() {
+            return Err(WasmError::Load(format!(
+                "imports are not provided (module imports {}::{})",
+                import.module(),
+                import.name()
+            )));
+        }
+        for export in ["memory", "alloc"] {
+            if module.get_export(export).is_none() {
+                return Err(WasmError::Load(format!("missing export `{export}`")));
+            }
+        }
+        Ok(Arc::new(WasmtimeModule {
+            module,
+            limits: self.limits,
+        }))
+    }
+}
+
+impl WasmEngine for WasmtimeEngine {
+    fn load(&self, path: &Path) -> Result<Arc<dyn WasmModule>, WasmError> {
+        let module = Module::from_file(&self.engine, path)
+            .map_err(|e| WasmError::Load(format!("{}: {e:#}", path.display())))?;
+        self.check(module)
+    }
+}
+
+/// WasmtimeModule はコンパイル済みの 1 モジュール
+struct WasmtimeModule {
+    module: Module,
+    limits: Limits,
+}
+
+impl WasmtimeModule {
+    fn call_blocking(&self, function: &str, input: &[u8]) -> Result<Vec<u8>, WasmError> {
+        let trap = |e: wasmtime::Error| WasmError::Trap(format!("{e:#}"));
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory_bytes)
+            .build();
+        let mut store: Store<StoreLimits> = Store::new(self.module.engine(), limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.limits.fuel).map_err(trap)?;
+
+        let instance = Instance::new(&mut store, &self.module, &[]).map_err(trap)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| WasmError::Load("`memory` is not a memory".to_string()))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(|e| WasmError::Load(format!("`alloc`: {e:#}")))?;
+        let handle = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, function)
+            .map_err(|e| WasmError::Load(format!("`{function}`: {e:#}")))?;
+
+        let len = i32::try_from(input.len())
+            .map_err(|_| WasmError::Trap("input does not fit in wasm32".to_string()))?;
+        let ptr = alloc.call(&mut store, len).map_err(trap)?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|e| WasmError::Trap(format!("`alloc` returned a bad pointer: {e}")))?;
+        let packed = handle.call(&mut store, (ptr, len)).map_err(trap)? as u64;
+
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let mut output = vec![0; out_len];
+        memory
+            .read(&store, out_ptr, &mut output)
+            .map_err(|e| WasmError::Trap(format!("`{function}` returned a bad range: {e}")))?;
+        Ok(output)
+    }
+}
+
+#[async_trait]
+impl WasmModule for WasmtimeModule {
+    async fn call(&self, function: &str, input: &[u8]) -> Result<Vec<u8>, WasmError> {
+        let module = Self {
+            module: self.module.clone(),
+            limits: self.limits,
+        };
+        let (function, input) = (function.to_string(), input.to_vec());
+        tokio::task::spawn_blocking(move || module.call_blocking(&function, &input))
+            .await
+            .map_err(|e| WasmError::Trap(e.to_string()))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::OutcomeKind;
+    use crate::impls::WasmHandler;
+    use crate::typed::DynHandler;
+
+    /// payload が `{}`（2 バイト以下）なら失敗、それ以外は成功の Outcome を返す。`spin` は無限ループ
+    const MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 0) "{\"kind\":\"SUCCESS\"}")
+          (data (i32.const 64) "{\"kind\":\"FAILURE\",\"reason\":\"empty\"}")
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "handle") (param $ptr i32) (param $len i32) (result i64)
+            (if (result i64) (i32.le_u (local.get $len) (i32.const 2))
+              (then (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 35)))
+              (else (i64.const 18))))
+          (func (export "spin") (param i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))
+    "#;
+
+    #[tokio::test]
+    async fn runs_a_real_module_and_stops_it_when_it_runs_out_of_fuel() {
+        let engine = WasmtimeEngine::new().unwrap().with_fuel(1_000_000);
+        let module = engine.load_bytes(&wat::parse_str(MODULE).unwrap()).unwrap();
+
+        let handler = WasmHandler::new("test.wasm.v1", module.clone());
+        let outcome = handler
+            .handle_dyn(serde_json::json!({ "to": "a@example.com" }))
+            .await
+            .unwrap();
+        assert_eq!(outcome.kind, OutcomeKind::Success);
+        let outcome = handler.handle_dyn(serde_json::json!({})).await.unwrap();
+        assert_eq!(outcome.kind, OutcomeKind::Failure);
+
+        let error = module.call("spin", b"{}").await.unwrap_err();
+        assert!(matches!(error, WasmError::Trap(_)), "{error}");
+        assert!(error.to_string().contains("fuel"), "{error}");
+    }
+
+    #[test]
+    fn modules_with_imports_or_without_the_abi_are_rejected() {
+        let engine = WasmtimeEngine::new().unwrap();
+        let with_import = wat::parse_str(
+            r#"(module (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32))))"#,
+        )
+        .unwrap();
+        let error = engine.load_bytes(&with_import).err().unwrap();
+        assert!(error.to_string().contains("fd_write"), "{error}");
+
+        let no_alloc = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+        let error = engine.load_bytes(&no_alloc).err().unwrap();
+        assert!(
+            error.to_string().contains("missing export `alloc`"),
+            "{error}"
+        );
+        assert!(engine.load(Path::new("/nonexistent.wasm")).is_err());
+    }
+}
@@ -8,6 +8,10 @@
 //! - **app**: アプリケーションロジック（builder, runtime, worker_loop, publisher_loop, など）
 //! - **typed**: 型付き Task API（Task trait, Handler trait, TypedRegistry, PayloadCodec）
 //! - **impls**: 実装（InMemoryDeliveryQueue など開発用）
+//! - **workflow**: 定義ファイル（JSON / YAML）から JobSpec を作る
+//!
+//! # 公開 API
+//! - **prelude**: ユーザー向けの安定した入口（`use weaver_core::prelude::*;`）
 //!
 //! # v1 互換モジュール（deprecated）
 //! - queue: Queue trait + in-memory implementation → ports/delivery_queue + impls/inmem_delivery に移行
@@ -15,6 +19,11 @@
 //! - worker: worker 実行ロジック → app/worker_loop に移行
 //! - observability: status views → app/status に移行
 //! - error: エラー型 → domain/errors に移行
+//!
+//! v1 互換モジュールは `#[doc(hidden)]`（ドキュメントに出さない）。既存ユーザー向けに公開は維持する。
+
+// derive(WeaverTask) が生成する `::weaver_core::...` を crate 内でも解決させる
+extern crate self as weaver_core;
 
 // v2 の新しいモジュール
 pub mod domain;
@@ -22,28 +31,37 @@ pub mod ports;
 pub mod app;
 pub mod typed;
 pub mod impls;
+pub mod workflow;
+
+// ユーザー向けの入口
+pub mod prelude;
 
 // v1 の既存モジュール（deprecated、互換性維持）
+#[doc(hidden)]
 #[deprecated(
     note = "Use `domain::errors` instead. This module will be removed in a future version."
 )]
 pub mod error;
 
+#[doc(hidden)]
 #[deprecated(
     note = "Use `app::worker_loop` instead. This module will be removed in a future version."
 )]
 pub mod worker;
 
+#[doc(hidden)]
 #[deprecated(
     note = "Use `ports::delivery_queue` and `impls::inmem_delivery` instead. This module will be removed in a future version."
 )]
 pub mod queue;
 
+#[doc(hidden)]
 #[deprecated(
     note = "Use `app::runtime` instead. This module will be removed in a future version."
 )]
 pub mod runtime;
 
+#[doc(hidden)]
 #[deprecated(
     note = "Use `app::status` instead. This module will be removed in a future version."
 )]
@@ -1,5 +1,17 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::domain::{
+    AttemptId, AttemptRecord, DecisionRecord, JobId, JobStateView, OutcomeKind, RunCondition,
+    TaskId, TaskType,
+};
+use crate::queue::{DrawnEdge, DrawnNode, EdgeStyle, TaskRecord, TaskState, render_dot, render_mermaid};
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QueueCounts {
     pub queued: usize,
@@ -8,4 +20,912 @@ pub struct QueueCounts {
     pub retry_scheduled: usize,
     pub dead: usize,
     pub decomposed: usize,
+    pub cancelled: usize,
+    pub blocked: usize,
+
+    /// Queue depth (Queued + RetryScheduled) per task type.
+    #[serde(default)]
+    pub waiting_by_task_type: BTreeMap<String, usize>,
+
+    /// Whole queue is paused (lease() hands out nothing).
+    pub paused: bool,
+
+    /// Task types paused individually (sorted).
+    pub paused_task_types: Vec<String>,
+
+    /// Namespaces paused as a whole (sorted).
+    #[serde(default)]
+    pub paused_namespaces: Vec<String>,
+}
+
+/// Moved to the v2 port (`ports::task_store`); re-exported for v1 users.
+pub use crate::ports::task_store::ReapedLeases;
+
+/// Task counts by state for a slice of the queue (a task type or a job).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateCounts {
+    pub queued: usize,
+    pub running: usize,
+    pub succeeded: usize,
+    pub retry_scheduled: usize,
+    pub dead: usize,
+    pub decomposed: usize,
+    pub cancelled: usize,
+    pub blocked: usize,
+}
+
+impl StateCounts {
+    /// Count one task in `state`.
+    pub fn add(&mut self, state: TaskState) {
+        match state {
+            TaskState::Queued => self.queued += 1,
+            TaskState::Running => self.running += 1,
+            TaskState::Succeeded => self.succeeded += 1,
+            TaskState::RetryScheduled => self.retry_scheduled += 1,
+            TaskState::Dead => self.dead += 1,
+            TaskState::Decomposed => self.decomposed += 1,
+            TaskState::Cancelled => self.cancelled += 1,
+            TaskState::Blocked => self.blocked += 1,
+        }
+    }
+
+    /// Tasks waiting for a worker (Queued + RetryScheduled): the backlog.
+    pub fn waiting(&self) -> usize {
+        self.queued + self.retry_scheduled
+    }
+
+    pub fn total(&self) -> usize {
+        self.queued
+            + self.running
+            + self.succeeded
+            + self.retry_scheduled
+            + self.dead
+            + self.decomposed
+            + self.cancelled
+            + self.blocked
+    }
+}
+
+/// How far tasks finishing within this window count as "recent" for the ETA.
+const PROGRESS_RATE_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// How far a job has got (see `Queue::job_progress`), e.g. for a progress bar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub job_id: JobId,
+    pub state: JobStateView,
+
+    /// Tasks that will not run again (succeeded, dead, decomposed, cancelled).
+    pub completed: usize,
+    pub total: usize,
+    pub by_state: StateCounts,
+
+    /// 0.0 to 100.0 (100.0 for a job without tasks).
+    pub percent_complete: f64,
+
+    /// Since the job was submitted (until it finished, for a finished job).
+    pub elapsed_ms: u64,
+
+    /// Time left at the rate the job's tasks finished over the last 5 minutes
+    /// (naive: assumes the remaining tasks take as long as the recent ones).
+    /// None while nothing has finished recently; 0 once every task is done.
+    pub eta_ms: Option<u64>,
+}
+
+impl JobProgress {
+    /// `finished_at` holds when each of the job's terminal tasks finished.
+    pub(crate) fn build(
+        job_id: JobId,
+        state: JobStateView,
+        by_state: StateCounts,
+        finished_at: &[Instant],
+        elapsed: Duration,
+        now: Instant,
+    ) -> Self {
+        let total = by_state.total();
+        let pending =
+            by_state.queued + by_state.running + by_state.retry_scheduled + by_state.blocked;
+        let completed = total - pending;
+        let percent_complete = if total == 0 {
+            100.0
+        } else {
+            completed as f64 * 100.0 / total as f64
+        };
+
+        let window = PROGRESS_RATE_WINDOW.min(elapsed);
+        let recent = finished_at
+            .iter()
+            .filter(|at| now.saturating_duration_since(**at) <= window)
+            .count();
+        let eta_ms = if pending == 0 {
+            Some(0)
+        } else if recent == 0 {
+            None
+        } else {
+            Some((window.as_millis() as u64).saturating_mul(pending as u64) / recent as u64)
+        };
+
+        Self {
+            job_id,
+            state,
+            completed,
+            total,
+            by_state,
+            percent_complete,
+            elapsed_ms: elapsed.as_millis() as u64,
+            eta_ms,
+        }
+    }
+}
+
+/// The tasks of a job and how they depend on each other (see
+/// `Queue::job_graph`), to see at a glance why a job is stuck.
+///
+/// Unlike `DependencyGraph::to_dot()`, resolved dependencies are kept (drawn
+/// thin; the ones still waited on are bold) and every node is coloured by
+/// its state. A decomposed task points at its children with a dotted edge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobGraph {
+    pub job_id: JobId,
+    pub nodes: Vec<JobGraphNode>,
+    pub edges: Vec<JobGraphEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobGraphNode {
+    pub task_id: TaskId,
+    pub title: Option<String>,
+    pub task_type: TaskType,
+    pub state: TaskState,
+}
+
+/// `from` is the dependency (or the decomposed parent), `to` the task after it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobGraphEdge {
+    pub from: TaskId,
+    pub to: TaskId,
+    #[serde(flatten)]
+    pub kind: JobGraphEdgeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobGraphEdgeKind {
+    Dependency {
+        condition: RunCondition,
+        /// `to` is still waiting for `from`.
+        waiting: bool,
+    },
+    Child,
+}
+
+impl JobGraph {
+    /// `tasks` are the job's records, in the order the job lists them.
+    pub(crate) fn build(job_id: JobId, tasks: &[(TaskId, &TaskRecord)]) -> Self {
+        let in_job = |task_id: &TaskId| tasks.iter().any(|(id, _)| id == task_id);
+        let mut nodes = Vec::with_capacity(tasks.len());
+        let mut edges = Vec::new();
+        for &(task_id, record) in tasks {
+            nodes.push(JobGraphNode {
+                task_id,
+                title: record.title.clone(),
+                task_type: record.envelope.task_type().clone(),
+                state: record.state,
+            });
+            if let Some(parent) = record.parent_task_id.filter(in_job) {
+                edges.push(JobGraphEdge {
+                    from: parent,
+                    to: task_id,
+                    kind: JobGraphEdgeKind::Child,
+                });
+            }
+            for &dependency in &record.declared_dependencies {
+                edges.push(JobGraphEdge {
+                    from: dependency,
+                    to: task_id,
+                    kind: JobGraphEdgeKind::Dependency {
+                        condition: record.run_condition(dependency),
+                        waiting: record.depends_on.contains(&dependency),
+                    },
+                });
+            }
+        }
+        Self {
+            job_id,
+            nodes,
+            edges,
+        }
+    }
+
+    /// Graphviz DOT source (`dot -Tsvg`).
+    pub fn to_dot(&self) -> String {
+        let (nodes, edges) = self.drawing();
+        render_dot(&nodes, &edges)
+    }
+
+    /// A Mermaid flowchart, for Markdown (```` ```mermaid ````).
+    pub fn to_mermaid(&self) -> String {
+        let (nodes, edges) = self.drawing();
+        render_mermaid(&nodes, &edges)
+    }
+
+    fn drawing(&self) -> (Vec<DrawnNode>, Vec<DrawnEdge>) {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let name = node
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| node.task_id.to_string());
+                DrawnNode {
+                    id: node.task_id,
+                    label: format!("{name}\n{}\n{:?}", node.task_type, node.state),
+                    fill: Some(state_fill(node.state)),
+                }
+            })
+            .collect();
+        let edges = self
+            .edges
+            .iter()
+            .map(|edge| {
+                let (label, style) = match edge.kind {
+                    JobGraphEdgeKind::Child => (Some("child".to_string()), EdgeStyle::Dotted),
+                    JobGraphEdgeKind::Dependency { condition, waiting } => {
+                        let label =
+                            (condition != RunCondition::OnSuccess).then(|| condition.to_string());
+                        let style = if waiting {
+                            EdgeStyle::Bold
+                        } else {
+                            EdgeStyle::Normal
+                        };
+                        (label, style)
+                    }
+                };
+                DrawnEdge {
+                    from: edge.from,
+                    to: edge.to,
+                    label,
+                    style,
+                }
+            })
+            .collect();
+        (nodes, edges)
+    }
+}
+
+/// Node colour per state: grey waiting, blue running, green done, red dead.
+fn state_fill(state: TaskState) -> &'static str {
+    match state {
+        TaskState::Queued => "#eeeeee",
+        TaskState::Running => "#9ecae1",
+        TaskState::RetryScheduled => "#fdd0a2",
+        TaskState::Blocked => "#ffe08a",
+        TaskState::Succeeded => "#a1d99b",
+        TaskState::Dead => "#fc9272",
+        TaskState::Decomposed => "#dadaeb",
+        TaskState::Cancelled => "#bdbdbd",
+    }
+}
+
+/// A task moved to another state (see `Queue::subscribe`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskStateChanged {
+    pub task_id: TaskId,
+    pub task_type: TaskType,
+    pub job_id: Option<JobId>,
+    /// None for a task the queue had not reported yet (e.g. just enqueued).
+    pub from: Option<TaskState>,
+    pub to: TaskState,
+    pub at: DateTime<Utc>,
+}
+
+/// An upcoming entry of the scheduled heap (retry or debounced task).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTaskView {
+    pub task_id: TaskId,
+    pub task_type: String,
+    pub state: TaskState,
+
+    /// Time until the task becomes ready (0 if already due).
+    pub fires_in_ms: u64,
+}
+
+/// Latency, duration and success rate of one task type over one window.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindowStats {
+    /// Attempts started in the window.
+    pub started: usize,
+    /// Enqueue → start of first attempts started in the window.
+    pub mean_latency_ms: Option<u64>,
+    pub max_latency_ms: Option<u64>,
+
+    /// Attempts finished in the window (with an outcome).
+    pub finished: usize,
+    pub succeeded: usize,
+    /// `succeeded / finished` (None when nothing finished).
+    pub success_rate: Option<f64>,
+    /// Lease → outcome of attempts finished in the window.
+    pub mean_duration_ms: Option<u64>,
+    pub max_duration_ms: Option<u64>,
+
+    /// Finished attempts per minute.
+    pub throughput_per_min: f64,
+}
+
+/// Windows of one task type (see `QueueStats`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskTypeStats {
+    pub last_1m: WindowStats,
+    pub last_5m: WindowStats,
+    pub last_1h: WindowStats,
+}
+
+/// Latency and throughput per task type over sliding windows.
+///
+/// Windows are made of 5-second buckets, so a window can include up to 5s
+/// more than its length.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub by_task_type: BTreeMap<String, TaskTypeStats>,
+}
+
+const STATS_BUCKET: Duration = Duration::from_secs(5);
+const STATS_WINDOWS: [Duration; 3] = [
+    Duration::from_secs(60),
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(60 * 60),
+];
+
+/// Totals of one 5-second bucket.
+#[derive(Debug, Clone, Default)]
+struct StatsBucket {
+    started: usize,
+    latency_count: usize,
+    latency_sum: Duration,
+    latency_max: Duration,
+    finished: usize,
+    succeeded: usize,
+    duration_sum: Duration,
+    duration_max: Duration,
+}
+
+/// Maintains `QueueStats` incrementally: each event adds to the current
+/// bucket of its task type, buckets older than the longest window are dropped.
+///
+/// Only active buckets are kept, so an idle task type costs nothing.
+#[derive(Debug)]
+pub(crate) struct QueueStatsRecorder {
+    origin: Instant,
+    /// Buckets per task type, oldest first, keyed by bucket number since `origin`.
+    buckets: HashMap<String, VecDeque<(u64, StatsBucket)>>,
+}
+
+impl Default for QueueStatsRecorder {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl QueueStatsRecorder {
+    pub(crate) fn new(origin: Instant) -> Self {
+        Self {
+            origin,
+            buckets: HashMap::new(),
+        }
+    }
+
+    fn bucket_number(&self, now: Instant) -> u64 {
+        (now.saturating_duration_since(self.origin).as_millis() / STATS_BUCKET.as_millis()) as u64
+    }
+
+    fn bucket(&mut self, task_type: &str, now: Instant) -> &mut StatsBucket {
+        let number = self.bucket_number(now);
+        let oldest = number.saturating_sub(Self::bucket_span(STATS_WINDOWS[2]));
+        let buckets = self.buckets.entry(task_type.to_string()).or_default();
+        while buckets.front().is_some_and(|(n, _)| *n < oldest) {
+            buckets.pop_front();
+        }
+        if buckets.back().is_none_or(|(n, _)| *n != number) {
+            buckets.push_back((number, StatsBucket::default()));
+        }
+        &mut buckets.back_mut().expect("bucket was just pushed").1
+    }
+
+    fn bucket_span(window: Duration) -> u64 {
+        (window.as_millis() / STATS_BUCKET.as_millis()) as u64
+    }
+
+    /// An attempt started; `latency` is enqueue → start for first attempts.
+    pub(crate) fn record_start(
+        &mut self,
+        task_type: &str,
+        latency: Option<Duration>,
+        now: Instant,
+    ) {
+        let bucket = self.bucket(task_type, now);
+        bucket.started += 1;
+        if let Some(latency) = latency {
+            bucket.latency_count += 1;
+            bucket.latency_sum += latency;
+            bucket.latency_max = bucket.latency_max.max(latency);
+        }
+    }
+
+    /// An attempt finished after running for `duration`.
+    pub(crate) fn record_finish(
+        &mut self,
+        task_type: &str,
+        duration: Duration,
+        succeeded: bool,
+        now: Instant,
+    ) {
+        let bucket = self.bucket(task_type, now);
+        bucket.finished += 1;
+        bucket.succeeded += usize::from(succeeded);
+        bucket.duration_sum += duration;
+        bucket.duration_max = bucket.duration_max.max(duration);
+    }
+
+    pub(crate) fn stats(&self, now: Instant) -> QueueStats {
+        let current = self.bucket_number(now);
+        let by_task_type = self
+            .buckets
+            .iter()
+            .map(|(task_type, buckets)| {
+                let [last_1m, last_5m, last_1h] = STATS_WINDOWS.map(|window| {
+                    let oldest = current.saturating_sub(Self::bucket_span(window));
+                    let in_window = buckets.iter().filter(|(n, _)| *n >= oldest);
+                    window_stats(in_window.map(|(_, bucket)| bucket), window)
+                });
+                let stats = TaskTypeStats {
+                    last_1m,
+                    last_5m,
+                    last_1h,
+                };
+                (task_type.clone(), stats)
+            })
+            .filter(|(_, stats)| stats.last_1h.started + stats.last_1h.finished > 0)
+            .collect();
+        QueueStats { by_task_type }
+    }
+}
+
+fn window_stats<'a>(
+    buckets: impl Iterator<Item = &'a StatsBucket>,
+    window: Duration,
+) -> WindowStats {
+    let total = buckets.fold(StatsBucket::default(), |mut total, bucket| {
+        total.started += bucket.started;
+        total.latency_count += bucket.latency_count;
+        total.latency_sum += bucket.latency_sum;
+        total.latency_max = total.latency_max.max(bucket.latency_max);
+        total.finished += bucket.finished;
+        total.succeeded += bucket.succeeded;
+        total.duration_sum += bucket.duration_sum;
+        total.duration_max = total.duration_max.max(bucket.duration_max);
+        total
+    });
+    let mean_ms =
+        |sum: Duration, count: usize| (count > 0).then(|| (sum.as_millis() / count as u128) as u64);
+    let has_latency = total.latency_count > 0;
+    let has_finished = total.finished > 0;
+    WindowStats {
+        started: total.started,
+        mean_latency_ms: mean_ms(total.latency_sum, total.latency_count),
+        max_latency_ms: has_latency.then_some(total.latency_max.as_millis() as u64),
+        finished: total.finished,
+        succeeded: total.succeeded,
+        success_rate: has_finished.then(|| total.succeeded as f64 / total.finished as f64),
+        mean_duration_ms: mean_ms(total.duration_sum, total.finished),
+        max_duration_ms: has_finished.then_some(total.duration_max.as_millis() as u64),
+        throughput_per_min: total.finished as f64 / (window.as_secs_f64() / 60.0),
+    }
+}
+
+/// Why a task is where it is: its attempts and the decisions taken on them,
+/// in the order they happened (see `Queue::explain`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplanationReport {
+    pub task_id: TaskId,
+    pub task_type: TaskType,
+    pub job_id: Option<JobId>,
+    pub state: TaskState,
+    pub attempts: u32,
+    pub max_attempts: u32,
+    pub last_error: Option<String>,
+
+    /// Oldest first.
+    pub entries: Vec<ExplanationEntry>,
+}
+
+/// One step of an `ExplanationReport`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExplanationEntry {
+    /// Time since the task was enqueued.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub step: ExplanationStep,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum ExplanationStep {
+    Enqueued,
+
+    /// An attempt finished; `number` counts the task's attempts from 1.
+    Attempt {
+        attempt_id: AttemptId,
+        number: u32,
+        outcome: OutcomeKind,
+        reason: Option<String>,
+        app_version: Option<String>,
+    },
+
+    /// The retry policy scheduled another attempt.
+    RetryScheduled {
+        delay_secs: Option<u64>,
+    },
+
+    /// Any other decision (mark dead, decompose, cancel, cleanup, ...).
+    Decision {
+        policy: String,
+        decision: String,
+        context: Option<serde_json::Value>,
+    },
+}
+
+impl ExplanationReport {
+    /// Assemble the report for `record` from its attempts and decisions
+    /// (in any order; they are sorted by when they happened).
+    pub(crate) fn build(
+        record: &TaskRecord,
+        attempts: Vec<&AttemptRecord>,
+        decisions: Vec<&DecisionRecord>,
+    ) -> Self {
+        let since_enqueue =
+            |at: Instant| at.saturating_duration_since(record.created_at).as_millis() as u64;
+
+        let mut attempts = attempts;
+        attempts.sort_by_key(|attempt| attempt.attempt_id);
+        let mut timed: Vec<(Instant, ExplanationStep)> = Vec::new();
+        for (number, attempt) in (1..).zip(attempts) {
+            timed.push((
+                attempt.completed_at,
+                ExplanationStep::Attempt {
+                    attempt_id: attempt.attempt_id,
+                    number,
+                    outcome: attempt.outcome.kind,
+                    reason: attempt.outcome.reason.clone(),
+                    app_version: attempt.app_version.clone(),
+                },
+            ));
+        }
+        for decision in decisions {
+            let step = if decision.decision == "schedule_retry" {
+                ExplanationStep::RetryScheduled {
+                    delay_secs: decision
+                        .context
+                        .as_ref()
+                        .and_then(|context| context["delay_secs"].as_u64()),
+                }
+            } else {
+                ExplanationStep::Decision {
+                    policy: decision.policy.clone(),
+                    decision: decision.decision.clone(),
+                    context: decision.context.clone(),
+                }
+            };
+            timed.push((decision.decided_at, step));
+        }
+        // Stable: an attempt stays ahead of the decision taken on it
+        timed.sort_by_key(|(at, _)| *at);
+
+        let mut entries = vec![ExplanationEntry {
+            at_ms: 0,
+            step: ExplanationStep::Enqueued,
+        }];
+        entries.extend(timed.into_iter().map(|(at, step)| ExplanationEntry {
+            at_ms: since_enqueue(at),
+            step,
+        }));
+
+        Self {
+            task_id: record.envelope.task_id(),
+            task_type: record.envelope.task_type().clone(),
+            job_id: record.job_id,
+            state: record.state,
+            attempts: record.attempts,
+            max_attempts: record.max_attempts,
+            last_error: record.last_error.clone(),
+            entries,
+        }
+    }
+
+    /// Human-readable rendering, one line per entry (same as `Display`).
+    pub fn render_text(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for ExplanationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): {:?} after {}/{} attempts",
+            self.task_id, self.task_type, self.state, self.attempts, self.max_attempts
+        )?;
+        if let Some(job_id) = self.job_id {
+            write!(f, " [{}]", job_id)?;
+        }
+        writeln!(f)?;
+        if let Some(error) = &self.last_error {
+            writeln!(f, "last error: {}", error)?;
+        }
+        for entry in &self.entries {
+            write!(f, "  +{}ms ", entry.at_ms)?;
+            match &entry.step {
+                ExplanationStep::Enqueued => writeln!(f, "enqueued")?,
+                ExplanationStep::Attempt {
+                    attempt_id,
+                    number,
+                    outcome,
+                    reason,
+                    app_version,
+                } => {
+                    write!(f, "attempt #{} ({}): {:?}", number, attempt_id, outcome)?;
+                    if let Some(reason) = reason {
+                        write!(f, ": {}", reason)?;
+                    }
+                    if let Some(version) = app_version {
+                        write!(f, " [app {}]", version)?;
+                    }
+                    writeln!(f)?;
+                }
+                ExplanationStep::RetryScheduled {
+                    delay_secs: Some(delay),
+                } => writeln!(f, "retry scheduled in {}s", delay)?,
+                ExplanationStep::RetryScheduled { delay_secs: None } => {
+                    writeln!(f, "retry scheduled")?
+                }
+                ExplanationStep::Decision {
+                    policy, decision, ..
+                } => writeln!(f, "{}: {}", policy, decision)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Severity of a task log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// One line a handler (or the worker) logged while running a task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskLogLine {
+    /// Attempt that logged the line (1-based; 0 if unknown).
+    pub attempt: u32,
+    pub level: LogLevel,
+    pub message: String,
+
+    /// Structured context, e.g. `{"http_status": 503}`.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    pub at: DateTime<Utc>,
+}
+
+/// Per-task store of structured log lines, kept for debugging failed tasks.
+///
+/// Cheap to clone (shared). Each task keeps its latest `max_lines_per_task`
+/// lines; older ones are dropped. Like intents, the log is not part of
+/// snapshots.
+#[derive(Debug, Clone)]
+pub struct TaskLog {
+    lines: Arc<Mutex<HashMap<TaskId, VecDeque<TaskLogLine>>>>,
+    max_lines_per_task: usize,
+}
+
+impl Default for TaskLog {
+    fn default() -> Self {
+        Self {
+            lines: Arc::default(),
+            max_lines_per_task: 500,
+        }
+    }
+}
+
+impl TaskLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at most `max` lines per task (at least 1).
+    pub fn with_max_lines_per_task(mut self, max: usize) -> Self {
+        self.max_lines_per_task = max.max(1);
+        self
+    }
+
+    /// Logger for one attempt of `task_id`, handed to its handler.
+    pub fn logger(&self, task_id: TaskId, attempt: u32) -> TaskLogger {
+        TaskLogger {
+            log: self.clone(),
+            task_id,
+            attempt,
+        }
+    }
+
+    pub fn record(&self, task_id: TaskId, line: TaskLogLine) {
+        let mut lines = self.lines.lock().unwrap();
+        let task_lines = lines.entry(task_id).or_default();
+        if task_lines.len() == self.max_lines_per_task {
+            task_lines.pop_front();
+        }
+        task_lines.push_back(line);
+    }
+
+    /// Lines logged for `task_id`, oldest first, across all its attempts.
+    pub fn lines(&self, task_id: TaskId) -> Vec<TaskLogLine> {
+        self.lines
+            .lock()
+            .unwrap()
+            .get(&task_id)
+            .map(|lines| lines.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drop the lines of a task that is gone (e.g. purged).
+    pub fn forget(&self, task_id: TaskId) {
+        self.lines.lock().unwrap().remove(&task_id);
+    }
+}
+
+/// Writes log lines for one attempt of a task (see `TaskContext::logger`).
+#[derive(Debug, Clone)]
+pub struct TaskLogger {
+    log: TaskLog,
+    task_id: TaskId,
+    attempt: u32,
+}
+
+impl TaskLogger {
+    /// Log `message` with structured `fields` (an object; any other value is
+    /// kept under `"value"`).
+    pub fn log(&self, level: LogLevel, message: impl fmt::Display, fields: serde_json::Value) {
+        let fields = match fields {
+            serde_json::Value::Object(fields) => fields,
+            serde_json::Value::Null => serde_json::Map::new(),
+            value => serde_json::Map::from_iter([("value".to_string(), value)]),
+        };
+        self.log.record(
+            self.task_id,
+            TaskLogLine {
+                attempt: self.attempt,
+                level,
+                message: message.to_string(),
+                fields,
+                at: Utc::now(),
+            },
+        );
+    }
+
+    pub fn debug(&self, message: impl fmt::Display) {
+        self.log(LogLevel::Debug, message, serde_json::Value::Null);
+    }
+
+    pub fn info(&self, message: impl fmt::Display) {
+        self.log(LogLevel::Info, message, serde_json::Value::Null);
+    }
+
+    pub fn warn(&self, message: impl fmt::Display) {
+        self.log(LogLevel::Warn, message, serde_json::Value::Null);
+    }
+
+    pub fn error(&self, message: impl fmt::Display) {
+        self.log(LogLevel::Error, message, serde_json::Value::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_progress_eta_follows_recent_throughput() {
+        let origin = Instant::now();
+        let at = |secs: u64| origin + Duration::from_secs(secs);
+        let by_state = StateCounts {
+            succeeded: 3,
+            dead: 1,
+            queued: 4,
+            ..StateCounts::default()
+        };
+        // Two tasks finished in the last 5 minutes, two long before
+        let finished_at = [at(0), at(10), at(900), at(1000)];
+        let progress = JobProgress::build(
+            JobId::new(1),
+            JobStateView::Running,
+            by_state,
+            &finished_at,
+            Duration::from_secs(1100),
+            at(1100),
+        );
+        assert_eq!((progress.completed, progress.total), (4, 8));
+        assert_eq!(progress.percent_complete, 50.0);
+        assert_eq!(progress.elapsed_ms, 1_100_000);
+        // 2 tasks per 300s, 4 left
+        assert_eq!(progress.eta_ms, Some(600_000));
+
+        let done = StateCounts {
+            succeeded: 2,
+            ..StateCounts::default()
+        };
+        let progress = JobProgress::build(
+            JobId::new(1),
+            JobStateView::Completed,
+            done,
+            &[],
+            Duration::from_secs(5),
+            at(5),
+        );
+        assert_eq!(progress.eta_ms, Some(0));
+    }
+
+    #[test]
+    fn stats_slide_out_of_their_windows() {
+        let origin = Instant::now();
+        let at = |secs: u64| origin + Duration::from_secs(secs);
+        let mut recorder = QueueStatsRecorder::new(origin);
+        recorder.record_start("render", Some(Duration::from_millis(200)), at(0));
+        recorder.record_finish("render", Duration::from_millis(900), false, at(1));
+        recorder.record_start("render", None, at(200));
+        recorder.record_finish("render", Duration::from_millis(300), true, at(201));
+        recorder.record_start("render", Some(Duration::from_millis(400)), at(230));
+
+        let stats = recorder.stats(at(240));
+        let render = &stats.by_task_type["render"];
+        // Only the retry and the new task are in the last minute
+        assert_eq!((render.last_1m.started, render.last_1m.finished), (2, 1));
+        assert_eq!(render.last_1m.success_rate, Some(1.0));
+        assert_eq!(render.last_1m.mean_latency_ms, Some(400));
+        assert_eq!(render.last_5m.started, 3);
+        assert_eq!(render.last_5m.success_rate, Some(0.5));
+        assert_eq!(render.last_5m.mean_duration_ms, Some(600));
+        assert_eq!(render.last_5m.max_duration_ms, Some(900));
+        assert_eq!(render.last_5m.mean_latency_ms, Some(300));
+        assert_eq!(render.last_5m.throughput_per_min, 0.4);
+
+        // An hour later everything has slid out
+        assert!(recorder.stats(at(3_900)).by_task_type.is_empty());
+    }
+
+    #[test]
+    fn task_log_keeps_the_latest_lines_per_task() {
+        let log = TaskLog::new().with_max_lines_per_task(2);
+        let first = log.logger(TaskId::new(1), 1);
+        first.info("fetching");
+        first.log(
+            LogLevel::Warn,
+            "upstream slow",
+            serde_json::json!({ "ms": 900 }),
+        );
+        log.logger(TaskId::new(1), 2).error("upstream down");
+        log.logger(TaskId::new(2), 1).debug("other task");
+
+        let lines = log.lines(TaskId::new(1));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].message, "upstream slow");
+        assert_eq!(lines[0].fields["ms"], 900);
+        assert_eq!((lines[1].attempt, lines[1].level), (2, LogLevel::Error));
+
+        log.forget(TaskId::new(1));
+        assert!(log.lines(TaskId::new(1)).is_empty());
+        assert_eq!(log.lines(TaskId::new(2)).len(), 1);
+    }
 }
@@ -1,19 +1,72 @@
 //! ArtifactStore port - Blob ストレージ（MinIO/S3/Local）
 //!
 //! ArtifactStore は巨大データ（payload, context）を保存します。
+//! 本体（bytes）は Blob に、メタ情報（大きさ・期限）は PG の artifacts テーブルに置く。
 //!
-//! # 実装予定
-//! - **PR-12**: MinIOArtifactStore, LocalArtifactStore
+//! # 実装
+//! - **impls::InMemoryArtifactStore**: 開発用（メタ情報と本体を同じ Mutex に置く）
+//! - 将来（`weaver-blob`）: MinIOArtifactStore, LocalArtifactStore
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::domain::ids::ArtifactId;
 
 /// ArtifactStore は巨大データを Blob に保存
 ///
 /// # 設計原則
 /// - TTL（expires_at）をサポート
 /// - PG の artifacts テーブルにメタ情報を記録
-/// - GC ループで期限切れを削除
-pub trait ArtifactStore {
-    // TODO(PR-12): メソッド定義
-    // - async fn put(&self, ns: &str, bytes: Bytes, content_type: Option<&str>, ttl: Option<Duration>) -> Result<ArtifactHandle, ArtifactError>
-    // - async fn get(&self, ns: &str, artifact: ArtifactId) -> Result<Bytes, ArtifactError>
-    // - async fn delete(&self, ns: &str, artifact: ArtifactId) -> Result<(), ArtifactError>
+/// - GC ループ（`app::GCLoop`）で期限切れを削除
+#[async_trait]
+pub trait ArtifactStore: Send + Sync {
+    /// `bytes` を保存する（`ttl` が `None` なら期限なし）
+    async fn put(
+        &self,
+        ns: &str,
+        bytes: Vec<u8>,
+        content_type: Option<&str>,
+        ttl: Option<Duration>,
+        now: DateTime<Utc>,
+    ) -> Result<ArtifactMeta, ArtifactError>;
+
+    /// 本体を読む（期限切れでも GC が消すまでは読める）
+    async fn get(&self, ns: &str, artifact_id: ArtifactId) -> Result<Vec<u8>, ArtifactError>;
+
+    /// メタ情報と本体を削除する
+    ///
+    /// メタ情報を先に消す（`deleted_at` を立てる）ので、本体の削除に失敗しても
+    /// 読めない artifact が残るだけで、メタ情報だけが残って本体がない状態にはならない。
+    async fn delete(&self, ns: &str, artifact_id: ArtifactId) -> Result<(), ArtifactError>;
+
+    /// `expires_at <= now` の artifact を期限の古い順に最大 `limit` 件
+    async fn list_expired(
+        &self,
+        ns: &str,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<ArtifactMeta>, ArtifactError>;
+}
+
+/// ArtifactMeta は artifacts テーブルの 1 行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactMeta {
+    pub artifact_id: ArtifactId,
+    /// 本体の大きさ（GC が回収した量として報告する）
+    pub size_bytes: u64,
+    pub content_type: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// この時刻を過ぎたら GC の対象（`None` は期限なし）
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// ArtifactError は ArtifactStore の操作エラー
+#[derive(Debug, thiserror::Error)]
+pub enum ArtifactError {
+    #[error("Artifact not found: {0}")]
+    NotFound(ArtifactId),
+    #[error("Artifact operation failed: {0}")]
+    OperationFailed(String),
 }
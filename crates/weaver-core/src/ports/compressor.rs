@@ -0,0 +1,153 @@
+//! Compressor port - 保存するバイト列の圧縮の抽象化
+//!
+//! 大きな JSON（payload・artifact・snapshot）を圧縮して、保存量と転送量を減らすための port。
+//! 圧縮したデータには必ず Compressor の名前を残し、読むときにその名前で選ぶ:
+//! - payload: `Runtime::with_compression` が envelope の `content_encoding` に記録する。
+//!   受け取り側は `TypedRegistry::register_compressor` で同じ Compressor を登録しておく
+//! - artifact: `impls::CompressedArtifactStore` が本体の先頭に記録する（`compress_framed`）
+//! - snapshot: `queue::CompressedCodec` が同じ形で記録する
+//!
+//! # 実装
+//! - `impls::Zstd`（feature `zstd`）
+//! - `impls::Gzip`（feature `gzip`）
+
+/// 圧縮したバイト列の先頭（続いて名前の長さ 1 バイト、名前、圧縮したバイト列）
+pub const COMPRESSED_MAGIC: &[u8; 4] = b"WVZ1";
+
+/// Compressor はバイト列を圧縮・展開する
+///
+/// # 設計原則
+/// - `name` は圧縮したデータに記録されるので変えない（例: `"zstd"`）
+/// - 同期的に呼ばれる（キューのロック中にも呼ばれうるので、I/O をしない）
+pub trait Compressor: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError>;
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError>;
+}
+
+/// CompressionError は圧縮・展開のエラー
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CompressionError {
+    #[error("Compression failed: {0}")]
+    Compress(String),
+
+    /// 壊れている・途中で切れている
+    #[error("Decompression failed: {0}")]
+    Decompress(String),
+
+    /// 別の Compressor で圧縮されている
+    #[error("Data is compressed with {found}, not {expected}")]
+    Mismatch { found: String, expected: String },
+}
+
+/// `bytes` を圧縮し、`COMPRESSED_MAGIC` と名前を前に付ける
+pub fn compress_framed(
+    compressor: &dyn Compressor,
+    bytes: &[u8],
+) -> Result<Vec<u8>, CompressionError> {
+    let name = compressor.name().as_bytes();
+    let name_len = u8::try_from(name.len())
+        .map_err(|_| CompressionError::Compress("compressor name is too long".to_string()))?;
+    let mut framed = COMPRESSED_MAGIC.to_vec();
+    framed.push(name_len);
+    framed.extend_from_slice(name);
+    framed.extend(compressor.compress(bytes)?);
+    Ok(framed)
+}
+
+/// `compress_framed` の逆。`COMPRESSED_MAGIC` で始まらなければ `None`（圧縮していない）
+pub fn decompress_framed(
+    compressor: &dyn Compressor,
+    bytes: &[u8],
+) -> Option<Result<Vec<u8>, CompressionError>> {
+    let rest = bytes.strip_prefix(COMPRESSED_MAGIC)?;
+    let truncated = || CompressionError::Decompress("truncated header".to_string());
+    let unframe = || {
+        let (&name_len, rest) = rest.split_first().ok_or_else(truncated)?;
+        let (name, compressed) = rest
+            .split_at_checked(name_len as usize)
+            .ok_or_else(truncated)?;
+        if name != compressor.name().as_bytes() {
+            return Err(CompressionError::Mismatch {
+                found: String::from_utf8_lossy(name).into_owned(),
+                expected: compressor.name().to_string(),
+            });
+        }
+        compressor.decompress(compressed)
+    };
+    Some(unframe())
+}
+
+/// テスト用: ランレングス符号化（同じバイトが続くと小さくなる）
+#[cfg(test)]
+pub(crate) struct RunLength;
+
+#[cfg(test)]
+impl Compressor for RunLength {
+    fn name(&self) -> &str {
+        "rle"
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        let mut out = Vec::new();
+        for chunk in bytes.chunk_by(|a, b| a == b) {
+            for run in chunk.chunks(u8::MAX as usize) {
+                out.extend([run.len() as u8, run[0]]);
+            }
+        }
+        Ok(out)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        if !bytes.len().is_multiple_of(2) {
+            return Err(CompressionError::Decompress("odd length".to_string()));
+        }
+        Ok(bytes
+            .chunks(2)
+            .flat_map(|pair| std::iter::repeat_n(pair[1], pair[0] as usize))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framed_data_names_its_compressor() {
+        let bytes = vec![b' '; 1000];
+        let framed = compress_framed(&RunLength, &bytes).unwrap();
+        assert!(framed.starts_with(b"WVZ1\x03rle"));
+        assert!(framed.len() < 100);
+        assert_eq!(decompress_framed(&RunLength, &framed), Some(Ok(bytes)));
+
+        // 圧縮していないデータ
+        assert_eq!(decompress_framed(&RunLength, b"{}"), None);
+
+        struct Other;
+        impl Compressor for Other {
+            fn name(&self) -> &str {
+                "zstd"
+            }
+            fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError> {
+                Ok(bytes.to_vec())
+            }
+            fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError> {
+                Ok(bytes.to_vec())
+            }
+        }
+        assert_eq!(
+            decompress_framed(&Other, &framed),
+            Some(Err(CompressionError::Mismatch {
+                found: "rle".to_string(),
+                expected: "zstd".to_string(),
+            }))
+        );
+        assert!(matches!(
+            decompress_framed(&RunLength, b"WVZ1\x09rl"),
+            Some(Err(CompressionError::Decompress(_)))
+        ));
+    }
+}
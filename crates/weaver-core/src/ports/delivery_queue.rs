@@ -40,6 +40,13 @@ pub trait DeliveryQueue: Send + Sync {
     /// - `Ok(None)`: timeout まで待っても要素なし
     /// - `Err(QueueError)`: エラー
     async fn pop(&self, ns: &str, timeout: Duration) -> Result<Option<TaskId>, QueueError>;
+
+    /// 配送待ちの task_id 数（取り出さずに数える）
+    ///
+    /// デフォルトは `Ok(None)`（数えられない実装）。
+    async fn len(&self, _ns: &str) -> Result<Option<usize>, QueueError> {
+        Ok(None)
+    }
 }
 
 /// QueueError は DeliveryQueue の操作エラー
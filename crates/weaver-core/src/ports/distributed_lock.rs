@@ -0,0 +1,51 @@
+//! DistributedLock port - handler が使う外部リソースの排他
+//!
+//! 複数の worker プロセスにまたがって、同じ外部リソース（例: 同じリポジトリへの push、
+//! 同じアカウントへの課金）を同時に触らないようにするためのロックです。
+//! タスクの lease とは独立で、handler が `TaskContext` 経由で取得します。
+//!
+//! # 実装
+//! - **InMemoryLock**: 開発用（単一プロセス内で共有）
+//! - 将来: Redis（`SET NX PX` + owner を確認して消す Lua）、
+//!   PostgreSQL（owner と期限を持つロック行）。本番用実装は別クレートに置く
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+/// DistributedLock は owner 付き・TTL 付きのロック
+///
+/// # 設計原則
+/// - ロックは TTL で必ず切れる（worker プロセスが落ちても永久に残らない）
+/// - 解放は取得した owner だけが行える（TTL 切れ後に他の owner が取ったロックを消さない）
+/// - 同じ owner による再取得は成功し、期限を延ばす
+#[async_trait]
+pub trait DistributedLock: Send + Sync {
+    /// `resource` のロックを `owner` として取る
+    ///
+    /// # Returns
+    /// - `Ok(true)`: 取得した（または同じ owner が持っていて期限を延ばした）
+    /// - `Ok(false)`: 他の owner が持っている
+    async fn try_acquire(
+        &self,
+        resource: &str,
+        owner: &str,
+        ttl: Duration,
+    ) -> Result<bool, LockError>;
+
+    /// `owner` が持っていれば `resource` のロックを解放する（持っていなくてもエラーにしない）
+    async fn release(&self, resource: &str, owner: &str) -> Result<(), LockError>;
+}
+
+/// LockError は DistributedLock の操作エラー
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("lock operation failed: {0}")]
+    OperationFailed(String),
+
+    #[error("timed out waiting for lock on {0}")]
+    Timeout(String),
+
+    #[error("no DistributedLock is configured for this worker")]
+    NotConfigured,
+}
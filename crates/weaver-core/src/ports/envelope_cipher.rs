@@ -0,0 +1,134 @@
+//! EnvelopeCipher port - payload の保存時暗号化の抽象化
+//!
+//! PII を含む payload を、どのストア（メモリ上の snapshot・PostgreSQL・Redis）にも
+//! 平文で残さないための port。キューは保存するときに `seal_envelope()` し、
+//! worker に lease するときに `open_envelope()` する。
+//!
+//! 封をした payload は次の形の JSON になる（task_type を AAD にするので、
+//! 別の task_type の envelope に付け替えると開けない）:
+//! ```text
+//! { "$sealed": { "key_id": "2024-06", "nonce": "<base64>", "ciphertext": "<base64>" } }
+//! ```
+//!
+//! # 実装
+//! - `impls::AesGcmCipher`: AES-256-GCM（鍵のローテーション対応、feature `aes-gcm`）
+//! - 将来: KMS のデータ鍵を使う実装（別クレート）
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{TaskEnvelope, TaskType};
+use crate::typed::format::{base64_decode, base64_encode};
+
+/// 封をした payload を表す JSON のキー
+pub const SEALED_KEY: &str = "$sealed";
+
+/// SealedPayload は暗号化された payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedPayload {
+    /// 暗号化に使った鍵（ローテーション後も古い鍵で開けるように記録する）
+    pub key_id: String,
+    pub nonce: Vec<u8>,
+    /// 暗号文（認証タグを含む）
+    pub ciphertext: Vec<u8>,
+}
+
+/// EnvelopeCipher は payload を暗号化・復号する
+///
+/// `seal()` は失敗しない: キューはロックを持ったまま同期的に呼ぶので、
+/// 鍵はあらかじめ手元に用意しておく（KMS ならデータ鍵を取得してから渡す）。
+///
+/// # Thread Safety
+/// - `Send + Sync` を要求（キューと lease から共有される）
+pub trait EnvelopeCipher: Send + Sync {
+    /// 現在の鍵で `plaintext` を暗号化する（`aad` は認証されるが暗号化されない）
+    fn seal(&self, aad: &[u8], plaintext: &[u8]) -> SealedPayload;
+
+    /// `sealed.key_id` の鍵で復号する
+    fn open(&self, aad: &[u8], sealed: &SealedPayload) -> Result<Vec<u8>, CipherError>;
+}
+
+/// CipherError は復号のエラー
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CipherError {
+    #[error("Unknown encryption key: {0}")]
+    UnknownKey(String),
+
+    /// 鍵・AAD が違う、または暗号文が改ざんされている
+    #[error("Payload authentication failed")]
+    AuthenticationFailed,
+
+    #[error("Malformed sealed payload: {0}")]
+    Malformed(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct SealedJson {
+    key_id: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// payload が封をされているか
+pub fn is_sealed(payload: &serde_json::Value) -> bool {
+    payload
+        .as_object()
+        .is_some_and(|fields| fields.len() == 1 && fields.contains_key(SEALED_KEY))
+}
+
+/// `task_type` の payload に封をする（封をしたものはそのまま）
+pub fn seal_payload(
+    cipher: &dyn EnvelopeCipher,
+    task_type: &TaskType,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    if is_sealed(&payload) {
+        return payload;
+    }
+    let plaintext = serde_json::to_vec(&payload).expect("Value serializes");
+    let sealed = cipher.seal(task_type.as_str().as_bytes(), &plaintext);
+    let sealed = SealedJson {
+        key_id: sealed.key_id,
+        nonce: base64_encode(&sealed.nonce),
+        ciphertext: base64_encode(&sealed.ciphertext),
+    };
+    serde_json::json!({ SEALED_KEY: sealed })
+}
+
+/// `task_type` の payload を開く（封をされていなければそのまま）
+pub fn open_payload(
+    cipher: &dyn EnvelopeCipher,
+    task_type: &TaskType,
+    payload: &serde_json::Value,
+) -> Result<serde_json::Value, CipherError> {
+    if !is_sealed(payload) {
+        return Ok(payload.clone());
+    }
+    let malformed = |e: &dyn std::fmt::Display| CipherError::Malformed(e.to_string());
+    let sealed: SealedJson =
+        serde_json::from_value(payload[SEALED_KEY].clone()).map_err(|e| malformed(&e))?;
+    let sealed = SealedPayload {
+        key_id: sealed.key_id,
+        nonce: base64_decode(&sealed.nonce).map_err(|e| malformed(&e))?,
+        ciphertext: base64_decode(&sealed.ciphertext).map_err(|e| malformed(&e))?,
+    };
+    let plaintext = cipher.open(task_type.as_str().as_bytes(), &sealed)?;
+    serde_json::from_slice(&plaintext).map_err(|e| malformed(&e))
+}
+
+/// `envelope` の payload に封をする
+pub fn seal_envelope(cipher: &dyn EnvelopeCipher, mut envelope: TaskEnvelope) -> TaskEnvelope {
+    let payload = std::mem::take(envelope.payload_mut());
+    *envelope.payload_mut() = seal_payload(cipher, envelope.task_type(), payload);
+    envelope
+}
+
+/// `envelope` の payload を開いた複製
+pub fn open_envelope(
+    cipher: &dyn EnvelopeCipher,
+    envelope: &TaskEnvelope,
+) -> Result<TaskEnvelope, CipherError> {
+    let payload = open_payload(cipher, envelope.task_type(), envelope.payload())?;
+    let mut envelope = envelope.clone();
+    *envelope.payload_mut() = payload;
+    Ok(envelope)
+}
@@ -1,9 +1,17 @@
 //! EventSink port - イベント記録の抽象化
 //!
-//! # 実装予定
-//! - v2 最小: NoopEventSink（何もしない）
+//! # 実装
+//! - **NoopEventSink**: 何もしない（デフォルト）
+//! - **impls::TracingEventSink**: 構造化ログ（JSON Lines）として書く
+//! - **impls::FileEventSink**: ファイルに追記する（監査ログ）
+//! - **impls::BufferedVecEventSink**: メモリに溜める（テスト用）
+//! - **impls::CompositeEventSink / FilteredEventSink / SamplingEventSink**: 他の sink への振り分け
 //! - 将来: Kafka, CloudWatch Logs などへの送信
 
+use async_trait::async_trait;
+
+use crate::domain::events::DomainEvent;
+
 /// EventSink はドメインイベントを記録
 ///
 /// # v2 最小実装
@@ -12,7 +20,31 @@
 /// # 将来の拡張
 /// - Kafka へのイベント送信
 /// - CloudWatch Logs への記録
-pub trait EventSink {
-    // TODO(v2後半): メソッド定義
-    // - async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError>
+///
+/// # Thread Safety
+/// - `Send + Sync` を要求（Worker の supervisor などから共有される）
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// イベントを記録する
+    ///
+    /// 呼び出し側はロックを保持したまま `emit()` を await してはならない（ADR-0003）。
+    async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError>;
+}
+
+/// EventSinkError は EventSink の操作エラー
+#[derive(Debug, thiserror::Error)]
+pub enum EventSinkError {
+    #[error("Event emit failed: {0}")]
+    EmitFailed(String),
+}
+
+/// NoopEventSink はイベントを捨てる EventSink 実装
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopEventSink;
+
+#[async_trait]
+impl EventSink for NoopEventSink {
+    async fn emit(&self, _event: DomainEvent) -> Result<(), EventSinkError> {
+        Ok(())
+    }
 }
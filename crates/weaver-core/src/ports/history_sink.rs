@@ -0,0 +1,38 @@
+//! HistorySink port - 実行履歴（attempt / decision）の書き出し先の抽象化
+//!
+//! 履歴は状態遷移より量が多く、1 件ずつ永続ストアに書くと書き込みが
+//! タスク数に比例して増える。`queue::HistoryWriter` がまとめてから
+//! `write_batch()` を呼ぶので、実装は 1 回の書き込み（1 トランザクション・
+//! 1 回の multi-row INSERT など）で受け取れる。
+//!
+//! # 実装
+//! - 将来: PostgreSQL の attempts / decisions テーブル（`weaver-pg`）
+//! - テスト: 受け取ったバッチを記録するだけの sink
+
+use async_trait::async_trait;
+
+use crate::domain::{AttemptRecord, DecisionRecord};
+
+/// HistoryRecord は履歴の 1 件
+#[derive(Debug, Clone)]
+pub enum HistoryRecord {
+    Attempt(AttemptRecord),
+    Decision(DecisionRecord),
+}
+
+/// HistorySink は履歴をまとめて書き出す
+///
+/// # Thread Safety
+/// - `Send + Sync` を要求（書き出しは HistoryWriter のタスクから行う）
+#[async_trait]
+pub trait HistorySink: Send + Sync {
+    /// `records` を記録された順に書く
+    async fn write_batch(&self, records: Vec<HistoryRecord>) -> Result<(), HistorySinkError>;
+}
+
+/// HistorySinkError は HistorySink の操作エラー
+#[derive(Debug, thiserror::Error)]
+pub enum HistorySinkError {
+    #[error("History write failed: {0}")]
+    WriteFailed(String),
+}
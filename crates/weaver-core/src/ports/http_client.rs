@@ -0,0 +1,59 @@
+//! HttpClient port - HTTP リクエスト送信の抽象化
+//!
+//! 送信だけを port に切り出し、呼び出し側は HTTP クライアントの crate を知りません。
+//! HTTP を話すものはすべてこの port を使います: 結果 webhook（`queue::WebhookNotifier`）、
+//! `SlackChannel`、`OtlpSpanExporter`、`HttpRequestHandler`。
+//! 署名・リトライ・ステータスの判定は呼び出し側が行います。
+//!
+//! # 実装
+//! - `impls::ReqwestClient`（feature `reqwest`）
+//! - テスト: 決まったレスポンスを返すだけの実装
+
+use async_trait::async_trait;
+
+/// HttpRequest は送信する 1 回分のリクエスト
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// `GET` / `POST` など（大文字）
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// ヘッダーの値（名前は大文字小文字を区別しない）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// HttpResponse は受け取ったレスポンス
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// HttpClient は HTTP リクエストを 1 回送る
+///
+/// # 設計原則
+/// - 1 回の呼び出しは 1 回の送信（リトライもリダイレクト以外の再送もしない）
+/// - どのステータスでも `Ok`（成否の判定は呼び出し側）
+/// - 接続失敗・TLS エラーなどは `HttpError::Transport`
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, HttpError>;
+}
+
+/// HttpError は HTTP 送信のエラー
+#[derive(Debug, thiserror::Error)]
+pub enum HttpError {
+    /// 接続できなかった・レスポンスを読めなかった
+    #[error("HTTP transport failed: {0}")]
+    Transport(String),
+}
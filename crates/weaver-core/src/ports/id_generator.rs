@@ -6,7 +6,7 @@
 //! # 実装
 //! - **UlidGenerator**: ULID ベース（本番用）
 
-use crate::domain::ids::{AttemptId, JobId, TaskId};
+use crate::domain::ids::{ArtifactId, AttemptId, EventId, JobId, TaskId};
 use crate::ports::Clock;
 use ulid::Ulid;
 
@@ -28,6 +28,12 @@ pub trait IdGenerator: Send + Sync {
 
     /// Attempt ID を生成
     fn generate_attempt_id(&self) -> AttemptId;
+
+    /// Outbox Event ID を生成
+    fn generate_event_id(&self) -> EventId;
+
+    /// Artifact ID を生成
+    fn generate_artifact_id(&self) -> ArtifactId;
 }
 
 /// UlidGenerator は ULID ベースの ID 生成器
@@ -63,6 +69,18 @@ impl<C: Clock> IdGenerator for UlidGenerator<C> {
         let ulid = Ulid::from_parts(timestamp_ms, rand::random());
         AttemptId::from(ulid)
     }
+
+    fn generate_event_id(&self) -> EventId {
+        let timestamp_ms = self.clock.now().timestamp_millis() as u64;
+        let ulid = Ulid::from_parts(timestamp_ms, rand::random());
+        EventId::from(ulid)
+    }
+
+    fn generate_artifact_id(&self) -> ArtifactId {
+        let timestamp_ms = self.clock.now().timestamp_millis() as u64;
+        let ulid = Ulid::from_parts(timestamp_ms, rand::random());
+        ArtifactId::from(ulid)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,39 @@
+//! KvStore port - 小さな共有状態（membership など）の保存先
+//!
+//! 複数の worker プロセスが協調するための最小限の key-value ストアです。
+//! 大きなデータや正本の状態は置かない（それは TaskStore / ArtifactStore の役割）。
+//!
+//! # 実装
+//! - **InMemoryKvStore**: 開発用（単一プロセス内で共有）
+//! - 将来: Redis（SET EX / SCAN）、etcd など
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+/// KvStore は TTL 付きの key-value ストア
+///
+/// # 設計原則
+/// - TTL を過ぎたキーは読み出しから消える（heartbeat で生存を表現できる）
+/// - 値は小さな文字列に限る
+#[async_trait]
+pub trait KvStore: Send + Sync {
+    /// 値を書き込む（`ttl` が `None` なら期限なし）
+    async fn put(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<(), KvError>;
+
+    /// 値を読む（存在しない・期限切れなら `None`）
+    async fn get(&self, key: &str) -> Result<Option<String>, KvError>;
+
+    /// キーを削除する（存在しなくてもエラーにしない）
+    async fn delete(&self, key: &str) -> Result<(), KvError>;
+
+    /// `prefix` で始まる有効なキーと値をキー順に列挙する
+    async fn list_prefix(&self, prefix: &str) -> Result<Vec<(String, String)>, KvError>;
+}
+
+/// KvError は KvStore の操作エラー
+#[derive(Debug, thiserror::Error)]
+pub enum KvError {
+    #[error("KV operation failed: {0}")]
+    OperationFailed(String),
+}
@@ -0,0 +1,53 @@
+//! MetricsSink port - 指標の送り先の抽象化
+//!
+//! Prometheus の scrape に限らず、StatsD / Datadog などの push 型の
+//! パイプラインにも指標を流せるようにするためのものです。
+//! 指標の中身（何をどのラベルで出すか）は `app::metrics::metric_samples()` が決め、
+//! sink は送り方だけを受け持ちます。
+//!
+//! # 実装
+//! - **NoopMetricsSink**: 何もしない（デフォルト）
+//! - **PrometheusSink**: 最後に受け取った指標をテキスト形式で保持（scrape 用）
+//! - **StatsdSink**: UDP で StatsD / DogStatsD に送る
+
+use async_trait::async_trait;
+
+/// MetricSample は 1 つの時系列の現在値（gauge）
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSample {
+    /// 指標名（例: `weaver_queue_depth`）
+    pub name: &'static str,
+    /// 指標の説明（Prometheus の `# HELP`）
+    pub help: &'static str,
+    /// ラベル（名前, 値）。値はエスケープしない
+    pub labels: Vec<(&'static str, String)>,
+    pub value: f64,
+}
+
+/// MetricsSink は指標を受け取って外部に出す
+///
+/// # Thread Safety
+/// - `Send + Sync` を要求（App と Observer から共有される）
+#[async_trait]
+pub trait MetricsSink: Send + Sync {
+    /// 同じ名前の sample は続けて並んでいる
+    async fn emit(&self, samples: &[MetricSample]) -> Result<(), MetricsError>;
+}
+
+/// MetricsError は MetricsSink の操作エラー
+#[derive(Debug, thiserror::Error)]
+pub enum MetricsError {
+    #[error("Metrics emit failed: {0}")]
+    EmitFailed(String),
+}
+
+/// NoopMetricsSink は指標を捨てる MetricsSink 実装
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetricsSink;
+
+#[async_trait]
+impl MetricsSink for NoopMetricsSink {
+    async fn emit(&self, _samples: &[MetricSample]) -> Result<(), MetricsError> {
+        Ok(())
+    }
+}
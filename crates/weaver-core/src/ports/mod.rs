@@ -18,14 +18,41 @@ pub mod repair_hint;
 pub mod clock;
 pub mod id_generator;
 pub mod event_sink;
+pub mod rate_limiter;
+pub mod kv_store;
+pub mod http_client;
+pub mod wasm;
+pub mod notification;
+pub mod distributed_lock;
+pub mod metrics_sink;
+pub mod history_sink;
+pub mod envelope_cipher;
+pub mod compressor;
+pub mod span_exporter;
 
 // 主要な trait を再エクスポート
-pub use self::task_store::TaskStore;
+pub use self::task_store::{
+    CompleteResult, Lease, OutboxEvent, OutboxEventType, OutboxStatus, ReapedLeases, StoreError,
+    TaskStore,
+};
 pub use self::delivery_queue::{DeliveryQueue, QueueError};
-pub use self::artifact_store::ArtifactStore;
+pub use self::artifact_store::{ArtifactError, ArtifactMeta, ArtifactStore};
 pub use self::decider::Decider;
 pub use self::dispatch::DispatchStrategy;
 pub use self::repair_hint::RepairHintGenerator;
 pub use self::clock::{Clock, SystemClock, FixedClock};
 pub use self::id_generator::{IdGenerator, UlidGenerator};
-pub use self::event_sink::EventSink;
+pub use self::event_sink::{EventSink, EventSinkError, NoopEventSink};
+pub use self::metrics_sink::{MetricSample, MetricsError, MetricsSink, NoopMetricsSink};
+pub use self::history_sink::{HistoryRecord, HistorySink, HistorySinkError};
+pub use self::envelope_cipher::{CipherError, EnvelopeCipher, SealedPayload};
+pub use self::compressor::{CompressionError, Compressor};
+pub use self::span_exporter::{Span, SpanExportError, SpanExporter, SpanStatus};
+pub use self::rate_limiter::RateLimiter;
+pub use self::kv_store::{KvError, KvStore};
+pub use self::distributed_lock::{DistributedLock, LockError};
+pub use self::http_client::{HttpClient, HttpError, HttpRequest, HttpResponse};
+pub use self::wasm::{WasmEngine, WasmError, WasmModule};
+pub use self::notification::{
+    Email, EmailTransport, Notification, NotificationChannel, NotificationError,
+};
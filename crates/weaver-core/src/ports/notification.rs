@@ -0,0 +1,51 @@
+//! Notification port - 人間向け通知（Slack / email）の送り先
+//!
+//! EventSink がイベントを機械的に記録するのに対し、NotificationChannel は
+//! ルールエンジン（`app::notification_rules`）が組み立てた文面を人に届けます。
+//!
+//! # 実装
+//! - **SlackChannel**: Slack の Incoming Webhook（`HttpClient` 経由）
+//! - **EmailChannel**: `EmailTransport` 経由のメール
+//! - 将来: SMTP / SES による EmailTransport（別クレート）
+
+use async_trait::async_trait;
+
+/// Notification は 1 件の通知
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// 件名（Slack では 1 行目の太字）
+    pub subject: String,
+    pub body: String,
+}
+
+/// NotificationChannel は通知を送る
+///
+/// # 設計原則
+/// - 1 回の呼び出しで 1 件を送る（まとめ・間引きはルールエンジン側）
+/// - 呼び出し側はロックを保持したまま `send()` を await しない（ADR-0003）
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    async fn send(&self, notification: &Notification) -> Result<(), NotificationError>;
+}
+
+/// Email は送信する 1 通のメール
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+/// EmailTransport はメールを送信する（SMTP / SES などの差し替え点）
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), NotificationError>;
+}
+
+/// NotificationError は通知の送信エラー
+#[derive(Debug, thiserror::Error)]
+pub enum NotificationError {
+    #[error("Notification send failed: {0}")]
+    SendFailed(String),
+}
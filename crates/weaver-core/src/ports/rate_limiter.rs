@@ -0,0 +1,28 @@
+//! RateLimiter port - task_type ごとの実行レート制限
+//!
+//! 同時実行数の上限とは別に、「1 秒あたり N 回まで」を制限します。
+//!
+//! # 実装
+//! - **TokenBucketRateLimiter**: プロセス内の token bucket（開発用・単一プロセス用）
+//! - 将来: Redis ベースの分散実装
+
+use std::time::Duration;
+
+/// RateLimiter は key（task_type）ごとに実行権（token）を払い出す
+///
+/// # 設計原則
+/// - lease 経路（キューのロック内）から呼ばれるため **同期** メソッド
+///   （ロックを保持したまま await しない、ADR-0003）
+/// - 分散実装は token をまとめてローカルに予約し、lease 経路でネットワーク I/O を待たないこと
+/// - 制限が設定されていない key は常に許可する
+///
+/// # Thread Safety
+/// - `Send + Sync` を要求（キューと worker から共有される）
+pub trait RateLimiter: Send + Sync {
+    /// key の token を 1 つ消費する
+    ///
+    /// # Returns
+    /// - `Ok(())`: 実行してよい（token を消費した）
+    /// - `Err(wait)`: token がない。`wait` 後に次の token が補充される
+    fn try_acquire(&self, key: &str) -> Result<(), Duration>;
+}
@@ -0,0 +1,54 @@
+//! SpanExporter port - 分散トレースの span の送り先の抽象化
+//!
+//! task は投入時に trace（`TaskEnvelope::trace()`、W3C `traceparent`）を持ち、
+//! worker は attempt ごとにその子 span を開いて、終わったら `export()` する。
+//! リトライも同じ投入 span の子になるので、トレース上では
+//! 「投入 → attempt 1（失敗）→ attempt 2（成功）」が 1 本にまとまる。
+//!
+//! # 実装
+//! - `impls::OtlpHttpExporter`: OTLP/HTTP（JSON）で OpenTelemetry Collector へ送る
+//! - テスト: 受け取った span を記録するだけの実装
+
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+
+use crate::domain::TraceContext;
+
+/// Span は終わった 1 区間（worker では 1 attempt）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    /// この span（trace_id は親と同じ）
+    pub context: TraceContext,
+    /// 親 span（attempt なら投入時の span）
+    pub parent: Option<TraceContext>,
+    pub name: String,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    /// 例: `("weaver.task_type", "email.send.v1")`
+    pub attributes: Vec<(String, String)>,
+    pub status: SpanStatus,
+}
+
+/// SpanStatus は span の結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpanStatus {
+    Ok,
+    Error(String),
+}
+
+/// SpanExporter は終わった span を送る
+///
+/// # Thread Safety
+/// - `Send + Sync` を要求（worker ごとのタスクから呼ばれる）
+#[async_trait]
+pub trait SpanExporter: Send + Sync {
+    async fn export(&self, span: Span) -> Result<(), SpanExportError>;
+}
+
+/// SpanExportError は SpanExporter の操作エラー
+#[derive(Debug, thiserror::Error)]
+pub enum SpanExportError {
+    #[error("Span export failed: {0}")]
+    ExportFailed(String),
+}
@@ -9,6 +9,28 @@
 //! # 実装予定
 //! - **PR-7**: `weaver-pg` クレートで PostgreSQL 実装
 //! - テスト用に InMemory 実装も検討
+//!
+//! # 現状
+//! - claim / extend_lease / get_task / complete（WorkerLoop が使う最小セット）
+//! - outbox の pull / ack / fail（単発とバッチ）と compaction（PublisherLoop が使う）
+//! - ライフサイクルイベントの読み出しと consumer ごとの cursor（外部システムが使う）
+//! - 実行中の run（起動ごとの id）の問い合わせ（metrics が使う）
+//! - 期限切れ lease の回収（ReaperLoop が使う）
+//! - payload のフィールドによるタスク検索（サポート対応が使う）
+//! - v1 の Queue を包む `impls::v1_compat::QueueAsTaskStore` が唯一の実装
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::ids::{EventId, RunId, TaskId};
+use crate::domain::{Decision, EventCursor, LifecycleEvent, Outcome, TaskEnvelope};
+#[allow(deprecated)]
+use crate::observability::QueueCounts;
+#[allow(deprecated)]
+use crate::queue::TaskRecord;
 
 /// TaskStore は状態・履歴・依存・outbox の正本（source of truth）
 ///
@@ -16,13 +38,315 @@
 /// - 状態遷移（claim/complete/reap）と outbox 生成は同一トランザクション内
 /// - Lease の権威はここにある（Redis の pop は候補通知に過ぎない）
 /// - すべての状態は PostgreSQL から再構築可能
-pub trait TaskStore {
+#[async_trait]
+pub trait TaskStore: Send + Sync {
+    /// 仕事を引き受ける（正本で lease を発行）
+    ///
+    /// # Returns
+    /// - `Ok(Some((lease, envelope)))`: lease を取得
+    /// - `Ok(None)`: 他の worker が取得済み、または実行可能でない
+    async fn claim(
+        &self,
+        ns: &str,
+        task_id: TaskId,
+        worker_id: &str,
+        lease_ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Option<(Lease, TaskEnvelope)>, StoreError>;
+
+    /// lease の期限を `now + lease_ttl` まで延ばす（Handler の実行中に WorkerLoop が呼ぶ）
+    ///
+    /// 既に reaper に回収された lease なら `LeaseNotHeld`。
+    /// デフォルトは未対応（`Unsupported`）。
+    async fn extend_lease(
+        &self,
+        _ns: &str,
+        _lease: &Lease,
+        _lease_ttl: Duration,
+        _now: DateTime<Utc>,
+    ) -> Result<(), StoreError> {
+        Err(StoreError::Unsupported("extend_lease"))
+    }
+
+    /// Decider に渡す最新の TaskRecord を取得
+    async fn get_task(&self, ns: &str, task_id: TaskId) -> Result<Option<TaskRecord>, StoreError>;
+
+    /// 結果を確定（状態・履歴・依存解放・outbox生成まで同一TX）
+    ///
+    /// `decision` が `None` の場合は成功として確定する
+    /// （v1 の `Decision` には成功を表す variant がないため）。
+    async fn complete(
+        &self,
+        ns: &str,
+        lease: Lease,
+        outcome: Outcome,
+        decision: Option<Decision>,
+        now: DateTime<Utc>,
+    ) -> Result<CompleteResult, StoreError>;
+
+    /// 配送可能な pending イベントを取得（`available_at <= now`、古い順に最大 `limit` 件）
+    async fn pull_outbox(
+        &self,
+        ns: &str,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<OutboxEvent>, StoreError>;
+
+    /// 配送に成功したイベントを sent にする
+    async fn ack_outbox(
+        &self,
+        ns: &str,
+        event_id: EventId,
+        now: DateTime<Utc>,
+    ) -> Result<(), StoreError>;
+
+    /// 配送に失敗したイベントを記録する（backoff 後に再配送、上限で dead）
+    async fn fail_outbox(
+        &self,
+        ns: &str,
+        event_id: EventId,
+        error: String,
+        now: DateTime<Utc>,
+    ) -> Result<(), StoreError>;
+
+    /// 複数イベントをまとめて sent にする
+    ///
+    /// デフォルトは `ack_outbox()` を 1 件ずつ呼ぶ。
+    /// 永続化ストアは 1 回の往復（`UPDATE ... WHERE event_id = ANY($1)`）で上書きすること。
+    async fn ack_outbox_batch(
+        &self,
+        ns: &str,
+        event_ids: &[EventId],
+        now: DateTime<Utc>,
+    ) -> Result<(), StoreError> {
+        for &event_id in event_ids {
+            self.ack_outbox(ns, event_id, now).await?;
+        }
+        Ok(())
+    }
+
+    /// 複数イベントの失敗をまとめて記録する
+    ///
+    /// デフォルトは `fail_outbox()` を 1 件ずつ呼ぶ。
+    async fn fail_outbox_batch(
+        &self,
+        ns: &str,
+        failures: Vec<(EventId, String)>,
+        now: DateTime<Utc>,
+    ) -> Result<(), StoreError> {
+        for (event_id, error) in failures {
+            self.fail_outbox(ns, event_id, error, now).await?;
+        }
+        Ok(())
+    }
+
+    /// `sent_before` より前に sent になったイベントを最大 `limit` 件削除する
+    ///
+    /// PublisherLoop が定期的に呼び、outbox の肥大化を防ぐ。
+    /// pending / dead は削除しない（dead は調査用に残す）。
+    ///
+    /// # Returns
+    /// 削除した件数（outbox を持たない実装は常に 0）
+    async fn compact_outbox(
+        &self,
+        _ns: &str,
+        _sent_before: DateTime<Utc>,
+        _limit: usize,
+    ) -> Result<usize, StoreError> {
+        Ok(0)
+    }
+
+    /// namespace 内のタスク数を状態ごとに数える（読み取り専用、Observer が使う）
+    ///
+    /// デフォルトは未対応（`Unsupported`）。
+    async fn counts(&self, _ns: &str) -> Result<QueueCounts, StoreError> {
+        Err(StoreError::Unsupported("counts"))
+    }
+
+    /// `after` より後のライフサイクルイベントを古い順に最大 `limit` 件読む（`None` は先頭から）
+    ///
+    /// 外部システムは `load_cursor()` から読み、処理し終えたら `commit_cursor()` する。
+    /// commit 前に落ちた分は再び読まれる（at-least-once）。
+    /// デフォルトは未対応（`Unsupported`）。
+    async fn read_events(
+        &self,
+        _ns: &str,
+        _after: Option<EventCursor>,
+        _limit: usize,
+    ) -> Result<Vec<LifecycleEvent>, StoreError> {
+        Err(StoreError::Unsupported("read_events"))
+    }
+
+    /// `consumer` が最後に commit した cursor（`None` はまだ commit していない）
+    async fn load_cursor(
+        &self,
+        _ns: &str,
+        _consumer: &str,
+    ) -> Result<Option<EventCursor>, StoreError> {
+        Err(StoreError::Unsupported("load_cursor"))
+    }
+
+    /// `consumer` が `cursor` までのイベントを処理したことを記録する（cursor は戻らない）
+    async fn commit_cursor(
+        &self,
+        _ns: &str,
+        _consumer: &str,
+        _cursor: EventCursor,
+    ) -> Result<(), StoreError> {
+        Err(StoreError::Unsupported("commit_cursor"))
+    }
+
+    /// 索引を張った payload のフィールド `field` が `value` のタスク（id 順）
+    ///
+    /// 例: `find_tasks_by_field(ns, "customer_id", "42")` で顧客 42 のタスクすべて。
+    /// デフォルトは未対応（`Unsupported`）。
+    async fn find_tasks_by_field(
+        &self,
+        _ns: &str,
+        _field: &str,
+        _value: &str,
+    ) -> Result<Vec<TaskId>, StoreError> {
+        Err(StoreError::Unsupported("find_tasks_by_field"))
+    }
+
+    /// いま lease を発行している run（起動ごとの id）
+    ///
+    /// 再起動・デプロイの前後どちらで処理されたかを見分けるためのもの。
+    /// デフォルトは `None`（run を区別しない実装）。
+    async fn run_id(&self, _ns: &str) -> Result<Option<RunId>, StoreError> {
+        Ok(None)
+    }
+
+    /// lease の期限（`Lease::expires_at`）が `now` より前の running を最大 `limit` 件回収する
+    ///
+    /// attempt が残っていれば ready に戻して outbox に dispatch_task を積み、
+    /// 上限に達していれば dead にする（期限切れの attempt も 1 回と数える）。
+    /// 回収された lease での `complete()` は `LeaseNotHeld` になる。
+    /// デフォルトは未対応（`Unsupported`）。
+    async fn reap_expired_leases(
+        &self,
+        _ns: &str,
+        _now: DateTime<Utc>,
+        _limit: usize,
+    ) -> Result<ReapedLeases, StoreError> {
+        Err(StoreError::Unsupported("reap_expired_leases"))
+    }
+
     // TODO(PR-7): メソッド定義
     // - create_job / create_task / add_dependency
-    // - claim (lease 発行)
-    // - complete (状態更新・履歴記録・依存解放・outbox生成)
     // - evaluate_readiness (ready 再評価)
-    // - reap_expired_leases (期限切れ回収)
     // - update_payload (repair 用)
-    // - pull_outbox / ack_outbox / fail_outbox
+}
+
+/// Lease は claim で発行される実行権
+///
+/// visibility timeout（`expires_at`）を過ぎると reaper に回収されうる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub task_id: TaskId,
+    /// 何回目の実行か（1 始まり）
+    pub attempt: u32,
+    /// lease を保持している worker
+    pub worker_id: String,
+    /// lease の期限（`None` は期限なし）
+    pub expires_at: Option<DateTime<Utc>>,
+    /// lease を発行した run（これと違う run の lease は前の起動の残り）
+    pub run_id: Option<RunId>,
+}
+
+/// CompleteResult は complete で確定した結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompleteResult {
+    /// 成功として確定
+    Succeeded,
+    /// 再実行を予約
+    RetryScheduled,
+    /// dead として確定
+    Dead,
+    /// 子タスクに分解
+    Decomposed,
+    /// ブロック（依存タスクの完了待ち、または unblock 待ち）
+    Blocked,
+}
+
+impl CompleteResult {
+    /// Decision（`None` は成功）から結果を決める
+    pub fn from_decision(decision: Option<&Decision>) -> Self {
+        match decision {
+            None => Self::Succeeded,
+            Some(Decision::Retry { .. }) => Self::RetryScheduled,
+            Some(Decision::MarkDead { .. }) => Self::Dead,
+            Some(Decision::Decompose { .. }) => Self::Decomposed,
+            Some(Decision::AddDependency { .. } | Decision::Block { .. }) => Self::Blocked,
+        }
+    }
+}
+
+/// ReapedLeases は reaper 1 回分で回収した lease のタスク
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReapedLeases {
+    /// attempt が残っていたので ready に戻した（期限切れの attempt も 1 回と数える）
+    pub requeued: Vec<TaskId>,
+    /// attempt の上限に達したので dead にした
+    pub dead: Vec<TaskId>,
+}
+
+impl ReapedLeases {
+    pub fn len(&self) -> usize {
+        self.requeued.len() + self.dead.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// OutboxEvent は outbox_events テーブルの 1 行（配送指示）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxEvent {
+    pub event_id: EventId,
+    pub event_type: OutboxEventType,
+    pub task_id: TaskId,
+    /// この時刻以降に配送可能（fail 後は backoff 分だけ後ろにずれる）
+    pub available_at: DateTime<Utc>,
+    pub status: OutboxStatus,
+    /// 配送に失敗した回数
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    /// 重複配送を無害化するためのキー
+    pub dedupe_key: Option<String>,
+}
+
+/// OutboxEventType は outbox イベント種
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxEventType {
+    /// task_id を DeliveryQueue に push
+    DispatchTask,
+}
+
+/// OutboxStatus は outbox イベントの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    /// 配送失敗が上限に達した
+    Dead,
+}
+
+/// StoreError は TaskStore の操作エラー
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("Task not found: {0}")]
+    NotFound(TaskId),
+    #[error("Outbox event not found: {0}")]
+    EventNotFound(EventId),
+    #[error("Lease not held: {0}")]
+    LeaseNotHeld(TaskId),
+    #[error("Store operation failed: {0}")]
+    OperationFailed(String),
+    /// この実装が持たない機能（リトライしても結果は変わらない）
+    #[error("{0} is not supported by this store")]
+    Unsupported(&'static str),
 }
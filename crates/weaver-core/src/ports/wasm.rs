@@ -0,0 +1,45 @@
+//! WasmEngine port - WebAssembly モジュールの読み込みと呼び出しの抽象化
+//!
+//! 信頼できない handler のコードを sandbox で動かすためのものです。
+//! 読み込みと呼び出しだけを port に切り出し、handler 側は WASM ランタイムを知りません。
+//! `WasmHandler`（feature `wasm`）が使います。
+//!
+//! # 呼び出しの約束
+//! - 入力は task の payload（JSON のバイト列）
+//! - 出力は `Outcome` の JSON のバイト列
+//! - 線形メモリへの受け渡し（alloc / ptr・len）は実装側の ABI に任せる
+//! - CPU（fuel）・メモリの上限も実装側で掛ける
+//!
+//! # 実装
+//! - `impls::WasmtimeEngine`（feature `wasm`）
+//! - テスト: Rust のクロージャで模したモジュール
+
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+/// WasmEngine は .wasm ファイルを読み込んでモジュールにする
+pub trait WasmEngine: Send + Sync {
+    /// コンパイル済みのモジュール（呼び出しごとに新しいインスタンスを作る想定）
+    fn load(&self, path: &Path) -> Result<Arc<dyn WasmModule>, WasmError>;
+}
+
+/// WasmModule は読み込み済みの 1 モジュール
+#[async_trait]
+pub trait WasmModule: Send + Sync {
+    /// export された `function` を `input` で呼び、出力を返す
+    async fn call(&self, function: &str, input: &[u8]) -> Result<Vec<u8>, WasmError>;
+}
+
+/// WasmError は WASM の読み込み・実行のエラー
+#[derive(Debug, thiserror::Error)]
+pub enum WasmError {
+    /// ファイルを読めない・不正なモジュール・必要な export がない
+    #[error("Failed to load wasm module: {0}")]
+    Load(String),
+
+    /// 実行中に trap した（上限超過を含む）
+    #[error("Wasm trapped: {0}")]
+    Trap(String),
+}
@@ -0,0 +1,52 @@
+//! Prelude - ユーザー向け API の入口
+//!
+//! ```ignore
+//! use weaver_core::prelude::*;
+//! ```
+//!
+//! # 方針
+//! - ここに載っている名前が「意図した公開 API」（v2 の表面）
+//! - v1/v2 の内部モジュールは移行中のため、直接 import すると将来壊れる可能性がある
+//! - 内部の統合が進んでも、prelude の名前は維持する
+//!
+//! # 名前の対応
+//! - `Queue` は v2 の配送キュー（`ports::DeliveryQueue`）。v1 の `queue::Queue` ではない
+
+pub use crate::app::builder::{App, BuildError};
+pub use crate::app::{AppBuilder, Runtime};
+pub use crate::domain::{ErrorKind, Outcome, OutcomeKind, WeaverError};
+pub use crate::ports::delivery_queue::{DeliveryQueue as Queue, QueueError};
+pub use crate::typed::{Handler, RegistryError, Task, WeaverTask};
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, WeaverTask)]
+    #[task(type = "prelude.greet.v1")]
+    struct GreetTask {
+        name: String,
+    }
+
+    struct GreetHandler;
+
+    #[async_trait]
+    impl Handler<GreetTask> for GreetHandler {
+        async fn handle(&self, _task: GreetTask) -> Result<(), WeaverError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn prelude_is_enough_to_build_an_app() {
+        let app = AppBuilder::new()
+            .register::<GreetTask, _>(GreetHandler)
+            .unwrap()
+            .expect_tasks(&[GreetTask::TYPE])
+            .build();
+        assert!(app.is_ok());
+    }
+}
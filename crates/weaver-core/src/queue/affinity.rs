@@ -0,0 +1,71 @@
+//! Task type affinity: which task types a worker pool may lease.
+//!
+//! Lets heavy task types run on a dedicated pool (e.g. `render` on machines
+//! with a GPU) while another pool on the same queue takes everything else.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::domain::TaskType;
+
+/// Task types a worker pool accepts.
+///
+/// v1: Passed by `WorkerGroup` to `Queue::lease_many_filtered` (see
+/// `WorkerGroupConfig::task_types`). Tasks outside the filter stay queued
+/// for another pool.
+///
+/// ```ignore
+/// let render = TaskTypeFilter::only([TaskType::new("render")]);
+/// let light = TaskTypeFilter::except([TaskType::new("render")]);
+/// ```
+#[derive(Clone, Default)]
+pub enum TaskTypeFilter {
+    /// Every task type.
+    #[default]
+    Any,
+    /// Only these task types.
+    Only(HashSet<TaskType>),
+    /// Every task type except these.
+    Except(HashSet<TaskType>),
+    /// Task types the predicate accepts.
+    Matching(Arc<dyn Fn(&TaskType) -> bool + Send + Sync>),
+}
+
+impl TaskTypeFilter {
+    pub fn only(task_types: impl IntoIterator<Item = TaskType>) -> Self {
+        Self::Only(task_types.into_iter().collect())
+    }
+
+    pub fn except(task_types: impl IntoIterator<Item = TaskType>) -> Self {
+        Self::Except(task_types.into_iter().collect())
+    }
+
+    pub fn matching(predicate: impl Fn(&TaskType) -> bool + Send + Sync + 'static) -> Self {
+        Self::Matching(Arc::new(predicate))
+    }
+
+    pub fn is_any(&self) -> bool {
+        matches!(self, Self::Any)
+    }
+
+    pub fn matches(&self, task_type: &TaskType) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Only(task_types) => task_types.contains(task_type),
+            Self::Except(task_types) => !task_types.contains(task_type),
+            Self::Matching(predicate) => predicate(task_type),
+        }
+    }
+}
+
+impl fmt::Debug for TaskTypeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("Any"),
+            Self::Only(task_types) => f.debug_tuple("Only").field(task_types).finish(),
+            Self::Except(task_types) => f.debug_tuple("Except").field(task_types).finish(),
+            Self::Matching(_) => f.write_str("Matching(<fn>)"),
+        }
+    }
+}
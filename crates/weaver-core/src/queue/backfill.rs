@@ -0,0 +1,158 @@
+//! Backfill: submit a recurring schedule's job for every period in a range.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use super::InMemoryQueue;
+use crate::domain::{JobId, RecurringSchedule};
+use crate::error::WeaverError;
+
+/// A job submitted for one period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackfilledJob {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub job_id: JobId,
+}
+
+/// Submits one job per period, oldest first, with at most
+/// `max_concurrent_jobs` of them unfinished at a time.
+///
+/// ```ignore
+/// let jobs = Backfill::new()
+///     .with_max_concurrent_jobs(2)
+///     .run(&queue, &schedule, from, to)
+///     .await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Backfill {
+    max_concurrent_jobs: usize,
+    poll_interval: Duration,
+}
+
+impl Default for Backfill {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backfill {
+    /// Defaults: 4 concurrent jobs, job status polled every 100ms.
+    pub fn new() -> Self {
+        Self {
+            max_concurrent_jobs: 4,
+            poll_interval: Duration::from_millis(100),
+        }
+    }
+
+    /// Cap on submitted jobs that still have unfinished tasks (at least 1).
+    pub fn with_max_concurrent_jobs(mut self, max_concurrent_jobs: usize) -> Self {
+        self.max_concurrent_jobs = max_concurrent_jobs.max(1);
+        self
+    }
+
+    /// How often to check whether a slot has freed up.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Submit the jobs for every period starting in `[from, to)`.
+    ///
+    /// Returns once the last job is submitted (it may still be running).
+    pub async fn run(
+        &self,
+        queue: &InMemoryQueue,
+        schedule: &RecurringSchedule,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<BackfilledJob>, WeaverError> {
+        let mut submitted = Vec::new();
+        let mut in_flight: Vec<JobId> = Vec::new();
+
+        for (period_start, period_end) in schedule.period.periods(from, to) {
+            loop {
+                let mut still_running = Vec::with_capacity(in_flight.len());
+                for job_id in in_flight {
+                    if queue.get_status(job_id).await?.running_tasks > 0 {
+                        still_running.push(job_id);
+                    }
+                }
+                in_flight = still_running;
+                if in_flight.len() < self.max_concurrent_jobs {
+                    break;
+                }
+                tokio::time::sleep(self.poll_interval).await;
+            }
+
+            let job_id = queue
+                .submit_job(schedule.job_for(period_start, period_end))
+                .await?;
+            in_flight.push(job_id);
+            submitted.push(BackfilledJob {
+                period_start,
+                period_end,
+                job_id,
+            });
+        }
+        Ok(submitted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{JobSpec, Period, TaskSpec, TaskType};
+    use crate::queue::{Queue, RetryPolicy};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[tokio::test]
+    async fn backfill_waits_for_a_free_slot_before_submitting() {
+        let queue = std::sync::Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let schedule = RecurringSchedule::new(
+            "export",
+            Period::Daily,
+            JobSpec::new(vec![TaskSpec::new(
+                "export",
+                TaskType::new("export"),
+                serde_json::json!({"day": "{period_date}"}),
+            )]),
+        );
+
+        let backfill = {
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                Backfill::new()
+                    .with_max_concurrent_jobs(2)
+                    .with_poll_interval(Duration::from_millis(5))
+                    .run(
+                        &queue,
+                        &schedule,
+                        utc("2024-01-01T00:00:00Z"),
+                        utc("2024-01-04T00:00:00Z"),
+                    )
+                    .await
+            })
+        };
+
+        // Two jobs are submitted right away; the third waits for one to finish
+        let first = queue.lease().await.unwrap();
+        let second = queue.lease().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(queue.counts_by_state().await.unwrap().queued, 0);
+        assert_eq!(first.envelope().payload()["day"], "2024-01-01");
+        assert_eq!(second.envelope().payload()["day"], "2024-01-02");
+
+        first.ack().await.unwrap();
+        let third = queue.lease().await.unwrap();
+        assert_eq!(third.envelope().payload()["day"], "2024-01-03");
+
+        let jobs = backfill.await.unwrap().unwrap();
+        assert_eq!(jobs.len(), 3);
+        assert_eq!(jobs[2].period_end, utc("2024-01-04T00:00:00Z"));
+    }
+}
@@ -0,0 +1,170 @@
+//! Cleanup hooks: per-task-type side effects run once a task is terminal.
+//!
+//! Typical uses: delete temp files, release external locks held for the task.
+//!
+//! Hooks run after the queue releases its lock (ADR-0003), each on its own
+//! tokio task, with retry. The result is recorded as a decision of the task
+//! (policy `cleanup_hook`), so `get_result()` shows whether cleanup happened.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::{RetryPolicy, TaskState};
+use crate::domain::{Outcome, TaskEnvelope, TaskId, TaskType};
+use crate::error::WeaverError;
+
+/// The task a cleanup hook runs for.
+#[derive(Debug, Clone)]
+pub struct FinishedTask {
+    pub task_id: TaskId,
+    pub envelope: TaskEnvelope,
+
+    /// Succeeded, Dead or Cancelled.
+    pub state: TaskState,
+
+    /// Outcome of the last attempt (None if the task never completed one).
+    pub outcome: Option<Outcome>,
+    pub error: Option<String>,
+}
+
+/// A cleanup action for one task type.
+///
+/// Hooks may run more than once (retry), so they should be idempotent.
+#[async_trait]
+pub trait CleanupHook: Send + Sync {
+    async fn cleanup(&self, task: &FinishedTask) -> Result<(), WeaverError>;
+}
+
+/// Cleanup hooks by task type, with their retry settings.
+///
+/// Attach them to a queue with `InMemoryQueue::with_cleanup_hooks()`:
+///
+/// ```ignore
+/// let hooks = CleanupHooks::new()
+///     .with_hook(TaskType::new("render"), Arc::new(RemoveScratchDir))
+///     .with_max_attempts(3);
+/// let queue = InMemoryQueue::new(RetryPolicy::default_v1()).with_cleanup_hooks(Arc::new(hooks));
+/// ```
+pub struct CleanupHooks {
+    hooks: HashMap<TaskType, Arc<dyn CleanupHook>>,
+    retry_policy: RetryPolicy,
+    max_attempts: u32,
+}
+
+impl Default for CleanupHooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CleanupHooks {
+    /// Defaults: 5 attempts, 1s exponential backoff capped at 1 minute.
+    pub fn new() -> Self {
+        Self {
+            hooks: HashMap::new(),
+            retry_policy: RetryPolicy {
+                base_delay: Duration::from_secs(1),
+                ..RetryPolicy::default_v1()
+            }
+            .with_max_delay(Duration::from_secs(60)),
+            max_attempts: 5,
+        }
+    }
+
+    /// Run `hook` when a task of `task_type` is terminal (last registration wins).
+    pub fn with_hook(mut self, task_type: TaskType, hook: Arc<dyn CleanupHook>) -> Self {
+        self.hooks.insert(task_type, hook);
+        self
+    }
+
+    /// Backoff between attempts.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Give up after this many attempts (at least 1).
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub(crate) fn get(&self, task_type: &TaskType) -> Option<Arc<dyn CleanupHook>> {
+        self.hooks.get(task_type).cloned()
+    }
+
+    /// Run `hook` for `task`, retrying failures.
+    ///
+    /// Returns the number of attempts it took.
+    pub async fn run(
+        &self,
+        hook: &dyn CleanupHook,
+        task: &FinishedTask,
+    ) -> Result<u32, (u32, WeaverError)> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match hook.cleanup(task).await {
+                Ok(()) => return Ok(attempt),
+                Err(e) if attempt >= self.max_attempts => return Err((attempt, e)),
+                Err(_) => tokio::time::sleep(self.retry_policy.next_delay(attempt)).await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    /// Fails the first `failures` calls.
+    struct Flaky {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl CleanupHook for Flaky {
+        async fn cleanup(&self, _task: &FinishedTask) -> Result<(), WeaverError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(WeaverError::Other("lock server unavailable".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn run_retries_until_the_hook_succeeds_or_attempts_run_out() {
+        let hooks = CleanupHooks::new()
+            .with_retry_policy(RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                ..RetryPolicy::default_v1()
+            })
+            .with_max_attempts(3);
+        let task = FinishedTask {
+            task_id: TaskId::new(1),
+            envelope: TaskEnvelope::new(TaskId::new(1), TaskType::new("t"), serde_json::json!({})),
+            state: TaskState::Succeeded,
+            outcome: Some(Outcome::success()),
+            error: None,
+        };
+
+        let flaky = Flaky {
+            failures: 2,
+            calls: AtomicU32::new(0),
+        };
+        assert_eq!(hooks.run(&flaky, &task).await.unwrap(), 3);
+
+        let broken = Flaky {
+            failures: u32::MAX,
+            calls: AtomicU32::new(0),
+        };
+        let (attempts, _) = hooks.run(&broken, &task).await.unwrap_err();
+        assert_eq!(attempts, 3);
+    }
+}
@@ -0,0 +1,89 @@
+//! Completion handles: await a submitted task instead of polling its state.
+//!
+//! The queue keeps one sender per waiter and fires it when the task reaches a
+//! terminal state (`TaskState::is_terminal`).
+
+use std::collections::HashMap;
+
+use tokio::sync::oneshot;
+
+use super::state::TaskState;
+use crate::domain::{Outcome, TaskId};
+use crate::error::WeaverError;
+
+/// How a task ended.
+#[derive(Debug, Clone)]
+pub struct TaskCompletion {
+    pub task_id: TaskId,
+    pub state: TaskState,
+
+    /// Outcome of the attempt that ended the task (`None` if it ended
+    /// without one, e.g. cancelled or reaped).
+    pub outcome: Option<Outcome>,
+}
+
+/// A submitted task that can be awaited (see `InMemoryQueue::submit_with_handle`).
+#[derive(Debug)]
+pub struct TaskHandle {
+    task_id: TaskId,
+    completion: oneshot::Receiver<TaskCompletion>,
+}
+
+impl TaskHandle {
+    /// The queue's id for the task.
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+
+    /// Wait until the task is terminal.
+    ///
+    /// Errors if the task is purged before it finishes.
+    pub async fn await_outcome(self) -> Result<TaskCompletion, WeaverError> {
+        self.completion.await.map_err(|_| {
+            WeaverError::Other(format!(
+                "task {} was removed before it finished",
+                self.task_id
+            ))
+        })
+    }
+}
+
+/// Waiters per task, fired once.
+#[derive(Debug, Default)]
+pub(crate) struct Completions {
+    waiters: HashMap<TaskId, Vec<oneshot::Sender<TaskCompletion>>>,
+}
+
+impl Completions {
+    pub(crate) fn handle(&mut self, task_id: TaskId) -> TaskHandle {
+        let (tx, rx) = oneshot::channel();
+        self.waiters.entry(task_id).or_default().push(tx);
+        TaskHandle {
+            task_id,
+            completion: rx,
+        }
+    }
+
+    /// A handle that is already resolved (the task finished before anyone waited).
+    pub(crate) fn resolved(completion: TaskCompletion) -> TaskHandle {
+        let (tx, rx) = oneshot::channel();
+        let task_id = completion.task_id;
+        let _ = tx.send(completion);
+        TaskHandle {
+            task_id,
+            completion: rx,
+        }
+    }
+
+    pub(crate) fn complete(&mut self, completion: TaskCompletion) {
+        for waiter in self.waiters.remove(&completion.task_id).unwrap_or_default() {
+            // The waiter may have dropped its handle
+            let _ = waiter.send(completion.clone());
+        }
+    }
+
+    /// Drop the waiters of a removed task (their handles report an error).
+    pub(crate) fn forget(&mut self, task_id: TaskId) {
+        self.waiters.remove(&task_id);
+    }
+}
@@ -5,10 +5,11 @@
 //! - Reverse edges: task -> tasks that depend on it (waiting tasks)
 //! - Invariant: edges and reverse_edges must be kept in sync
 
+use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
 
 use crate::domain::TaskId;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 
 /// Dependency graph for tracking task dependencies.
 ///
@@ -101,6 +102,40 @@ impl DependencyGraph {
             .map(|deps| deps.iter().copied().collect())
             .unwrap_or_default()
     }
+
+    /// The cycle that `add_dependency(task, depends_on)` would close, if any.
+    ///
+    /// The edge closes a cycle iff `task` is already reachable from `depends_on`.
+    /// Returns the cycle as `[task, depends_on, ..., task]`.
+    pub fn cycle_if_added(&self, task: TaskId, depends_on: TaskId) -> Option<Vec<TaskId>> {
+        if task == depends_on {
+            return Some(vec![task, task]);
+        }
+        let mut prev = HashMap::new();
+        let mut visited = HashSet::from([depends_on]);
+        let mut stack = vec![depends_on];
+        while let Some(node) = stack.pop() {
+            for dep in self.get_dependencies(node) {
+                if !visited.insert(dep) {
+                    continue;
+                }
+                prev.insert(dep, node);
+                if dep == task {
+                    let mut path = vec![task];
+                    let mut current = task;
+                    while let Some(&p) = prev.get(&current) {
+                        path.push(p);
+                        current = p;
+                    }
+                    path.push(task);
+                    path.reverse();
+                    return Some(path);
+                }
+                stack.push(dep);
+            }
+        }
+        None
+    }
 }
 
 impl Default for DependencyGraph {
@@ -109,107 +144,286 @@ impl Default for DependencyGraph {
     }
 }
 
-// TODO(human): Implement cycle detection
-//
-// Hints:
-// 1. Create a HashMap<TaskId, Color> to track node states
-// 2. Create a Vec<TaskId> to track the current DFS path
-// 3. For each unvisited node, call dfs_cycle()
-// 4. dfs_cycle() should:
-//    - Mark node as Gray
-//    - Push to path
-//    - For each dependency:
-//      - If Gray: cycle found! Extract cycle from path
-//      - If White: recurse
-//      - If Black: skip (already explored)
-//    - Mark node as Black
-//    - Pop from path
-//
-// Example implementation structure:
-//
 impl DependencyGraph {
-    /// Detect a cycle in the dependency graph.
-    ///
-    /// Returns the first cycle found, or None if the graph is acyclic (DAG).
-    ///
-    /// # Current Implementation (v1)
-    ///
-    /// This implementation uses iterative DFS with visited tracking.
-    /// It works correctly for most cases due to `visited.clone()` creating
-    /// independent searches from each start point.
+    /// Every task in the graph, each after all the tasks it waits for
+    /// (Kahn's algorithm, O(V + E)).
     ///
-    /// ## Known Limitations:
-    /// - **Efficiency**: O(V * E) in worst case due to visited.clone()
-    /// - **Design**: visited.clone() is accidental correctness, not intentional
-    /// - May have edge cases where false positives occur (though none found in testing)
+    /// Ties are broken by TaskId, so the order is deterministic. Tasks outside
+    /// the graph (no dependencies, no dependents) are not included.
     ///
-    /// ## Future Improvement (v2):
-    /// Replace with Kahn's algorithm (topological sort):
-    /// - O(V + E) guaranteed
-    /// - More explicit and maintainable
-    /// - Clearer separation: has_cycle() check, then find_cycle() if needed
-    ///
-    /// For v1, this implementation is sufficient and passes all known test cases.
-    pub fn detect_cycle(&self) -> Option<Vec<TaskId>> {
-        let start_points: Vec<TaskId> = self
-            .reverse_edges
+    /// Returns `Err(cycle)` (see `detect_cycle()`) if the graph is not a DAG.
+    pub fn topological_order(&self) -> Result<Vec<TaskId>, Vec<TaskId>> {
+        // Remaining unresolved dependencies per task
+        let mut pending: HashMap<TaskId, usize> = self
+            .edges
+            .keys()
+            .chain(self.reverse_edges.keys())
+            .map(|&task| (task, self.edges.get(&task).map_or(0, HashSet::len)))
+            .collect();
+        let mut ready: BinaryHeap<Reverse<TaskId>> = pending
             .iter()
-            .filter(|(_, v)| !v.is_empty())
-            .map(|(k, _)| k.clone())
+            .filter(|(_, count)| **count == 0)
+            .map(|(&task, _)| Reverse(task))
             .collect();
-        let visited: HashSet<TaskId> = HashSet::new();
-        for start in start_points {
-            if let Some(cycle) = self.detect_cycle_from(start, &mut visited.clone()) {
-                return Some(cycle);
+
+        let mut order = Vec::with_capacity(pending.len());
+        while let Some(Reverse(task)) = ready.pop() {
+            order.push(task);
+            for waiting in self.get_waiting_tasks(task) {
+                let count = pending.get_mut(&waiting).expect("every node is counted");
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(Reverse(waiting));
+                }
             }
         }
-        None
-    }
-
-    pub fn detect_cycle_from(
-        &self,
-        start: TaskId,
-        visited: &mut HashSet<TaskId>,
-    ) -> Option<Vec<TaskId>> {
-        let mut stack = Vec::new();
-        stack.push(start);
-        visited.insert(start);
+        if order.len() == pending.len() {
+            return Ok(order);
+        }
 
-        let mut prev = HashMap::new();
-        while let Some(node) = stack.pop() {
-            for dep in self.get_dependencies(node) {
-                prev.insert(dep, node);
-                if visited.contains(&dep) {
-                    return Some(self.follow_cycle(dep, &prev));
-                }
-                visited.insert(dep);
-                stack.push(dep);
+        // Every task left still waits for another task left, so following
+        // those edges from any of them must run into a cycle
+        let resolved: HashSet<TaskId> = order.into_iter().collect();
+        let unresolved_dependency = |task: TaskId| {
+            self.edges[&task]
+                .iter()
+                .copied()
+                .filter(|dep| !resolved.contains(dep))
+                .min()
+                .expect("an unresolved task waits for an unresolved task")
+        };
+        let start = pending
+            .keys()
+            .copied()
+            .filter(|task| !resolved.contains(task))
+            .min()
+            .expect("some task is unresolved");
+        let mut path = vec![start];
+        let mut position = HashMap::from([(start, 0)]);
+        loop {
+            let next = unresolved_dependency(*path.last().expect("path is never empty"));
+            if let Some(&at) = position.get(&next) {
+                let mut cycle = path.split_off(at);
+                cycle.push(next);
+                return Err(cycle);
             }
+            position.insert(next, path.len());
+            path.push(next);
         }
-        None
     }
 
-    pub fn follow_cycle(&self, join_point: TaskId, prev: &HashMap<TaskId, TaskId>) -> Vec<TaskId> {
-        let mut cycle = Vec::new();
-        let mut current = join_point;
-        cycle.push(current);
-        while let Some(&p) = prev.get(&current) {
-            cycle.push(p);
-            if p == join_point {
-                break;
-            }
-            current = p;
+    /// Detect a cycle in the dependency graph.
+    ///
+    /// Returns a cycle as `[a, b, ..., a]`, each task waiting for the next
+    /// (like `cycle_if_added()`), or None if the graph is acyclic (DAG).
+    pub fn detect_cycle(&self) -> Option<Vec<TaskId>> {
+        self.topological_order().err()
+    }
+}
+
+impl DependencyGraph {
+    /// Graphviz DOT source with one edge per dependency still waited on,
+    /// pointing from the dependency to the waiting task (`dot -Tsvg`).
+    ///
+    /// Resolved dependencies are removed from the graph, so this shows what
+    /// is still blocked; see `Queue::job_graph` for a whole job with states.
+    pub fn to_dot(&self) -> String {
+        let (nodes, edges) = self.drawing();
+        render_dot(&nodes, &edges)
+    }
+
+    /// The same graph as a Mermaid flowchart, for Markdown (```` ```mermaid ````).
+    pub fn to_mermaid(&self) -> String {
+        let (nodes, edges) = self.drawing();
+        render_mermaid(&nodes, &edges)
+    }
+
+    fn drawing(&self) -> (Vec<DrawnNode>, Vec<DrawnEdge>) {
+        let mut ids: Vec<TaskId> = self
+            .edges
+            .keys()
+            .chain(self.reverse_edges.keys())
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        ids.sort_by_key(|id| id.as_u64());
+        let mut edges: Vec<DrawnEdge> = self
+            .edges
+            .iter()
+            .flat_map(|(&task, deps)| {
+                deps.iter().map(move |&dep| DrawnEdge {
+                    from: dep,
+                    to: task,
+                    label: None,
+                    style: EdgeStyle::Normal,
+                })
+            })
+            .collect();
+        edges.sort_by_key(|edge| (edge.from.as_u64(), edge.to.as_u64()));
+        let nodes = ids
+            .into_iter()
+            .map(|id| DrawnNode {
+                id,
+                label: id.to_string(),
+                fill: None,
+            })
+            .collect();
+        (nodes, edges)
+    }
+}
+
+/// A node to render with `render_dot()` / `render_mermaid()`.
+pub(crate) struct DrawnNode {
+    pub id: TaskId,
+    /// Lines separated by `\n`.
+    pub label: String,
+    /// Fill colour, e.g. `#a1d99b`.
+    pub fill: Option<&'static str>,
+}
+
+/// An edge from a dependency (`from`) to the task that waits for it (`to`).
+pub(crate) struct DrawnEdge {
+    pub from: TaskId,
+    pub to: TaskId,
+    pub label: Option<String>,
+    pub style: EdgeStyle,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EdgeStyle {
+    Normal,
+    Bold,
+    Dotted,
+}
+
+pub(crate) fn render_dot(nodes: &[DrawnNode], edges: &[DrawnEdge]) -> String {
+    let quote = |text: &str| {
+        let escaped = text.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("\"{}\"", escaped.replace('\n', "\\n"))
+    };
+    let mut out = String::from("digraph {\n    rankdir=LR;\n    node [shape=box];\n");
+    for node in nodes {
+        let mut attrs = format!("label={}", quote(&node.label));
+        if let Some(fill) = node.fill {
+            attrs.push_str(&format!(", style=filled, fillcolor={}", quote(fill)));
+        }
+        out.push_str(&format!("    t{} [{attrs}];\n", node.id.as_u64()));
+    }
+    for edge in edges {
+        let mut attrs = Vec::new();
+        if let Some(label) = &edge.label {
+            attrs.push(format!("label={}", quote(label)));
         }
+        match edge.style {
+            EdgeStyle::Normal => {}
+            EdgeStyle::Bold => attrs.push("style=bold".to_string()),
+            EdgeStyle::Dotted => attrs.push("style=dotted".to_string()),
+        }
+        let attrs = if attrs.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", attrs.join(", "))
+        };
+        out.push_str(&format!(
+            "    t{} -> t{}{attrs};\n",
+            edge.from.as_u64(),
+            edge.to.as_u64()
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
 
-        cycle.reverse();
-        cycle
+pub(crate) fn render_mermaid(nodes: &[DrawnNode], edges: &[DrawnEdge]) -> String {
+    let quote = |text: &str| text.replace('"', "#quot;").replace('\n', "<br/>");
+    let mut out = String::from("flowchart LR\n");
+    for node in nodes {
+        out.push_str(&format!(
+            "    t{}[\"{}\"]\n",
+            node.id.as_u64(),
+            quote(&node.label)
+        ));
+    }
+    for edge in edges {
+        let arrow = match edge.style {
+            EdgeStyle::Normal => "-->",
+            EdgeStyle::Bold => "==>",
+            EdgeStyle::Dotted => "-.->",
+        };
+        let label = match &edge.label {
+            Some(label) => format!("|\"{}\"|", quote(label)),
+            None => String::new(),
+        };
+        out.push_str(&format!(
+            "    t{} {arrow}{label} t{}\n",
+            edge.from.as_u64(),
+            edge.to.as_u64()
+        ));
+    }
+    for node in nodes {
+        if let Some(fill) = node.fill {
+            out.push_str(&format!("    style t{} fill:{fill}\n", node.id.as_u64()));
+        }
     }
+    out
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn renders_waiting_edges_as_dot_and_mermaid() {
+        let mut graph = DependencyGraph::new();
+        let (a, b, c) = (TaskId::new(1), TaskId::new(2), TaskId::new(3));
+        graph.add_dependency(c, a);
+        graph.add_dependency(c, b);
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph {\n"), "{dot}");
+        assert!(dot.contains(&format!("    t3 [label=\"{c}\"];\n")), "{dot}");
+        assert!(dot.ends_with("    t1 -> t3;\n    t2 -> t3;\n}\n"), "{dot}");
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n"), "{mermaid}");
+        assert!(
+            mermaid.ends_with("    t1 --> t3\n    t2 --> t3\n"),
+            "{mermaid}"
+        );
+    }
+
+    #[test]
+    fn cycle_if_added_reports_the_closing_path() {
+        let mut graph = DependencyGraph::new();
+        let (a, b, c) = (TaskId::new(1), TaskId::new(2), TaskId::new(3));
+        graph.add_dependency(b, a); // B waits for A
+        graph.add_dependency(c, b); // C waits for B
+
+        assert_eq!(graph.cycle_if_added(a, c), Some(vec![a, c, b, a]));
+        assert_eq!(graph.cycle_if_added(a, a), Some(vec![a, a]));
+        // Diamonds are not cycles
+        graph.add_dependency(c, a);
+        assert_eq!(graph.cycle_if_added(c, a), None);
+    }
+
+    #[test]
+    fn topological_order_puts_dependencies_first() {
+        let mut graph = DependencyGraph::new();
+        let [a, b, c, d, e] = [1, 2, 3, 4, 5].map(TaskId::new);
+        // E waits for the diamond D -> {B, C} -> A
+        graph.add_dependency(e, d);
+        graph.add_dependency(d, c);
+        graph.add_dependency(d, b);
+        graph.add_dependency(c, a);
+        graph.add_dependency(b, a);
+        assert_eq!(graph.topological_order(), Ok(vec![a, b, c, d, e]));
+        assert_eq!(graph.detect_cycle(), None);
+
+        // A waiting for E closes a cycle through the diamond
+        graph.add_dependency(a, e);
+        assert_eq!(graph.detect_cycle(), Some(vec![a, e, d, b, a]));
+        assert_eq!(graph.topological_order(), Err(vec![a, e, d, b, a]));
+    }
+
     #[test]
     fn new_graph_is_empty() {
         let graph = DependencyGraph::new();
@@ -273,8 +487,6 @@ mod tests {
         assert!(deps.contains(&task_b));
     }
 
-    // TODO(human): Uncomment these tests after implementing detect_cycle()
-
     #[test]
     fn detect_simple_cycle() {
         let mut graph = DependencyGraph::new();
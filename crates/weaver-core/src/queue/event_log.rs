@@ -0,0 +1,68 @@
+//! Lifecycle event log with consumer cursors, for external consumers.
+//!
+//! Unlike the journal (a bounded debugging aid), the event log keeps every
+//! task lifecycle event under an increasing `EventCursor`, so another system
+//! can tail it: read a page after its cursor, process it, commit the cursor.
+//! A consumer that restarts resumes from its last committed cursor, so
+//! delivery is at-least-once (events after the last commit are read again).
+//!
+//! Disabled by default (see `InMemoryQueue::with_event_log`). The log lives in
+//! the queue's memory: like attempt history, it is not part of snapshots.
+//!
+//! The event types are the v2 ones (`domain::lifecycle`), re-exported here.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use super::{JournalOp, TaskRecord};
+use crate::domain::{EventCursor, LifecycleEvent, RunId, TaskId};
+
+/// Append-only event log plus the committed cursor of each consumer.
+#[derive(Debug, Default)]
+pub(crate) struct EventLog {
+    /// `events[i]` has cursor `i + 1`.
+    events: Vec<LifecycleEvent>,
+    cursors: HashMap<String, EventCursor>,
+}
+
+impl EventLog {
+    pub(crate) fn append(
+        &mut self,
+        op: JournalOp,
+        task_id: TaskId,
+        record: &TaskRecord,
+        run_id: RunId,
+    ) {
+        let cursor = EventCursor(self.events.len() as u64 + 1);
+        self.events.push(LifecycleEvent {
+            cursor,
+            at: Utc::now(),
+            op,
+            task_id,
+            task_type: record.envelope.task_type().clone(),
+            job_id: record.job_id,
+            run_id,
+        });
+    }
+
+    /// Up to `limit` events after `after` (from the start if `None`), oldest first.
+    pub(crate) fn read(&self, after: Option<EventCursor>, limit: usize) -> Vec<LifecycleEvent> {
+        let start = after
+            .map_or(0, |cursor| cursor.0 as usize)
+            .min(self.events.len());
+        self.events[start..].iter().take(limit).cloned().collect()
+    }
+
+    pub(crate) fn cursor(&self, consumer: &str) -> Option<EventCursor> {
+        self.cursors.get(consumer).copied()
+    }
+
+    /// Record that `consumer` processed everything up to `cursor`.
+    ///
+    /// Cursors never move back, so a late commit of an older page is harmless.
+    pub(crate) fn commit(&mut self, consumer: &str, cursor: EventCursor) {
+        let committed = self.cursors.entry(consumer.to_string()).or_insert(cursor);
+        *committed = (*committed).max(cursor);
+    }
+}
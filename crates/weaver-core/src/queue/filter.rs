@@ -0,0 +1,114 @@
+//! Task filter for administrative bulk operations (purge / requeue).
+
+use std::time::{Duration, Instant};
+
+use super::{TaskRecord, TaskState};
+use crate::domain::{JobId, TaskType};
+
+/// Selects tasks for `Queue::purge()` / `Queue::requeue()`.
+///
+/// All set criteria must match (AND). The empty filter matches every task,
+/// so callers narrow it down with the `with_*` methods:
+///
+/// ```ignore
+/// // Drop dead "email" tasks that have been dead for over a day
+/// let filter = TaskFilter::new()
+///     .with_state(TaskState::Dead)
+///     .with_task_type(TaskType::new("email"))
+///     .with_older_than(Duration::from_secs(24 * 60 * 60));
+/// queue.purge(&filter).await?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    pub state: Option<TaskState>,
+    pub task_type: Option<TaskType>,
+    pub job_id: Option<JobId>,
+
+    /// Minimum time since the task's last state change.
+    pub older_than: Option<Duration>,
+}
+
+impl TaskFilter {
+    /// Filter that matches every task.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_state(mut self, state: TaskState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn with_task_type(mut self, task_type: TaskType) -> Self {
+        self.task_type = Some(task_type);
+        self
+    }
+
+    pub fn with_job_id(mut self, job_id: JobId) -> Self {
+        self.job_id = Some(job_id);
+        self
+    }
+
+    pub fn with_older_than(mut self, age: Duration) -> Self {
+        self.older_than = Some(age);
+        self
+    }
+
+    /// Does the record match every set criterion (as of `now`)?
+    pub fn matches(&self, record: &TaskRecord, now: Instant) -> bool {
+        self.state.is_none_or(|state| record.state == state)
+            && self
+                .task_type
+                .as_ref()
+                .is_none_or(|task_type| record.envelope.task_type() == task_type)
+            && self
+                .job_id
+                .is_none_or(|job_id| record.job_id == Some(job_id))
+            && self
+                .older_than
+                .is_none_or(|age| now.saturating_duration_since(record.updated_at) >= age)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{TaskEnvelope, TaskId};
+
+    fn record(task_type: &str) -> TaskRecord {
+        let envelope = TaskEnvelope::new(
+            TaskId::new(1),
+            TaskType::new(task_type),
+            serde_json::json!({}),
+        );
+        TaskRecord::new(envelope, 3)
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        assert!(TaskFilter::new().matches(&record("a"), Instant::now()));
+    }
+
+    #[test]
+    fn all_criteria_must_match() {
+        let mut dead = record("a");
+        dead.mark_dead("boom".into());
+        let now = dead.updated_at + Duration::from_secs(10);
+
+        let filter = TaskFilter::new()
+            .with_state(TaskState::Dead)
+            .with_task_type(TaskType::new("a"));
+        assert!(filter.matches(&dead, now));
+        assert!(!filter.matches(&record("a"), now));
+
+        let other_type = filter.clone().with_task_type(TaskType::new("b"));
+        assert!(!other_type.matches(&dead, now));
+        let other_job = filter.clone().with_job_id(JobId::new(7));
+        assert!(!other_job.matches(&dead, now));
+
+        let old_enough = filter.clone().with_older_than(Duration::from_secs(10));
+        assert!(old_enough.matches(&dead, now));
+        let too_young = filter.with_older_than(Duration::from_secs(11));
+        assert!(!too_young.matches(&dead, now));
+    }
+}
@@ -0,0 +1,124 @@
+//! Write-behind of attempt/decision history to a `HistorySink`, in batches.
+//!
+//! The queue records history under its lock, so `record()` only hands the
+//! record to a background task over a channel. That task writes a batch when
+//! it is full, when the oldest buffered record has waited `max_delay`, or
+//! when a task reaches a terminal state (`flush()`), so a finished task's
+//! history is never held back behind a quiet period.
+
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+use crate::ports::{HistoryRecord, HistorySink};
+use std::sync::Arc;
+
+/// When buffered history is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryBatching {
+    /// Write as soon as this many records are buffered.
+    pub max_batch: usize,
+
+    /// Longest time a record waits to be written.
+    pub max_delay: Duration,
+}
+
+impl Default for HistoryBatching {
+    /// 100 records or 1 second, whichever comes first.
+    fn default() -> Self {
+        Self {
+            max_batch: 100,
+            max_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+enum Command {
+    Record(Box<HistoryRecord>),
+    Flush(Option<oneshot::Sender<()>>),
+}
+
+/// Buffers history and writes it to a sink in batches (see module docs).
+///
+/// The background task stops, after writing what is left, once the writer
+/// is dropped.
+#[derive(Debug, Clone)]
+pub struct HistoryWriter {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl HistoryWriter {
+    /// Start the background task (needs a tokio runtime).
+    pub fn spawn(sink: Arc<dyn HistorySink>, batching: HistoryBatching) -> Self {
+        let (commands, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(sink, batching, rx));
+        Self { commands }
+    }
+
+    /// Buffer one record; never blocks.
+    pub(crate) fn record(&self, record: HistoryRecord) {
+        let _ = self.commands.send(Command::Record(Box::new(record)));
+    }
+
+    /// Write the buffer soon, without waiting for it.
+    pub(crate) fn flush(&self) {
+        let _ = self.commands.send(Command::Flush(None));
+    }
+
+    /// Write everything recorded so far and wait until the sink returns.
+    pub async fn flush_now(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.commands.send(Command::Flush(Some(tx))).is_ok() {
+            let _ = rx.await;
+        }
+    }
+}
+
+async fn run(
+    sink: Arc<dyn HistorySink>,
+    batching: HistoryBatching,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+    let mut buffer = Vec::new();
+    let mut deadline: Option<Instant> = None;
+    loop {
+        let command = match deadline {
+            Some(deadline) => tokio::select! {
+                command = commands.recv() => command,
+                _ = tokio::time::sleep_until(deadline) => Some(Command::Flush(None)),
+            },
+            None => commands.recv().await,
+        };
+        let mut ack = None;
+        match command {
+            Some(Command::Record(record)) => {
+                buffer.push(*record);
+                deadline.get_or_insert_with(|| Instant::now() + batching.max_delay);
+                if buffer.len() < batching.max_batch {
+                    continue;
+                }
+            }
+            Some(Command::Flush(reply)) => ack = reply,
+            None => {
+                write(sink.as_ref(), std::mem::take(&mut buffer)).await;
+                return;
+            }
+        }
+        write(sink.as_ref(), std::mem::take(&mut buffer)).await;
+        deadline = None;
+        if let Some(ack) = ack {
+            let _ = ack.send(());
+        }
+    }
+}
+
+async fn write(sink: &dyn HistorySink, batch: Vec<HistoryRecord>) {
+    if batch.is_empty() {
+        return;
+    }
+    let len = batch.len();
+    if let Err(e) = sink.write_batch(batch).await {
+        eprintln!("[queue] writing {len} history records failed: {e}");
+    }
+}
@@ -0,0 +1,75 @@
+//! Queue event journal: a ring buffer of recent queue operations.
+//!
+//! Debugging aid for ordering/wakeup races that unit tests can't reproduce.
+//! Disabled by default (see `InMemoryQueue::with_journal`).
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::domain::TaskId;
+
+/// Queue operation recorded in the journal (the v2 `LifecycleOp`).
+pub use crate::domain::LifecycleOp as JournalOp;
+
+/// One journal entry.
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub at: Instant,
+    pub op: JournalOp,
+    pub task_id: TaskId,
+
+    /// Tokio task that performed the operation (identifies the worker).
+    pub worker: Option<tokio::task::Id>,
+}
+
+/// Fixed-capacity ring buffer; the oldest entry is dropped when full.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    capacity: usize,
+    entries: VecDeque<JournalEntry>,
+}
+
+impl Journal {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn record(&mut self, op: JournalOp, task_id: TaskId) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(JournalEntry {
+            at: Instant::now(),
+            op,
+            task_id,
+            worker: tokio::task::try_id(),
+        });
+    }
+
+    /// Entries oldest first.
+    pub(crate) fn snapshot(&self) -> Vec<JournalEntry> {
+        self.entries.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer_drops_oldest_entries() {
+        let mut journal = Journal::new(2);
+        journal.record(JournalOp::Enqueue, TaskId::new(1));
+        journal.record(JournalOp::Lease, TaskId::new(1));
+        journal.record(JournalOp::Ack, TaskId::new(1));
+
+        let ops: Vec<JournalOp> = journal.snapshot().iter().map(|e| e.op).collect();
+        assert_eq!(ops, vec![JournalOp::Lease, JournalOp::Ack]);
+    }
+}
@@ -1,21 +1,58 @@
 //! Queue module: state management, retry logic, and in-memory implementation.
 
+mod affinity;
+mod backfill;
+mod cleanup;
+mod completion;
 mod dependency;
+mod event_log;
+mod filter;
+mod history;
+mod journal;
 mod memory;
+mod order;
+mod payload_index;
 mod record;
+mod reservation;
 mod retry;
+mod scheduler;
+mod snapshot;
 mod state;
+mod webhook;
 
+pub use affinity::TaskTypeFilter;
+pub use backfill::{Backfill, BackfilledJob};
+pub use cleanup::{CleanupHook, CleanupHooks, FinishedTask};
+pub use completion::{TaskCompletion, TaskHandle};
 pub use dependency::DependencyGraph;
+pub(crate) use dependency::{DrawnEdge, DrawnNode, EdgeStyle, render_dot, render_mermaid};
+pub use crate::domain::{EventCursor, LifecycleEvent};
+pub use filter::TaskFilter;
+pub use history::{HistoryBatching, HistoryWriter};
+pub use journal::{JournalEntry, JournalOp};
 pub use memory::InMemoryQueue;
+pub use order::LeaseOrder;
 pub use record::TaskRecord;
-pub use retry::RetryPolicy;
+pub use reservation::NamespaceReservations;
+pub use retry::{Jitter, RetryBatching, RetryPolicy};
+pub use scheduler::Scheduler;
+pub use snapshot::{
+    CompressedCodec, Compressor, JobSnapshot, JsonCodec, Migration, QueueSnapshot,
+    SNAPSHOT_SCHEMA_VERSION, SnapshotCodec, TaskSnapshot,
+};
 pub use state::TaskState;
+pub use webhook::{
+    WebhookDelivery, WebhookError, WebhookEventSink, WebhookNotifier, signature, verify_signature,
+};
+
+use std::collections::BTreeMap;
 
 use async_trait::async_trait;
+use tokio::sync::broadcast;
 
-use crate::domain::{Decision, Outcome, TaskEnvelope, TaskId, TaskSpec};
+use crate::domain::{Decision, JobId, Outcome, RunId, TaskEnvelope, TaskId, TaskSpec};
 use crate::error::WeaverError;
+use crate::runtime::TaskContext;
 
 /// A leased task for processing.
 ///
@@ -32,12 +69,22 @@ use crate::error::WeaverError;
 pub trait TaskLease: Send {
     fn envelope(&self) -> &TaskEnvelope;
 
+    /// Run of the queue that issued the lease (None if the queue has no runs).
+    fn run_id(&self) -> Option<RunId> {
+        None
+    }
+
     /// Get fresh TaskRecord for decision-making.
     ///
     /// Phase 4-1: Worker needs TaskRecord to call Decider.
     /// This method re-acquires TaskRecord from queue state to prevent stale data.
     async fn get_task_record(&self) -> Result<TaskRecord, WeaverError>;
 
+    /// Context handed to the handler (e.g. outcomes of declared dependencies).
+    ///
+    /// Queues that keep no attempt history return `TaskContext::default()`.
+    async fn task_context(&self) -> Result<TaskContext, WeaverError>;
+
     /// Complete task execution with Outcome and Decision.
     ///
     /// Phase 4-1: New completion method that takes explicit Decision.
@@ -52,13 +99,35 @@ pub trait TaskLease: Send {
         decision: Decision,
     ) -> Result<(), WeaverError>;
 
-    /// call when add child tasks.
+    /// Keep the lease alive (extend its visibility timeout).
+    ///
+    /// Long-running handlers call this periodically; a lease whose heartbeat
+    /// is stale is reclaimed by the queue and the task runs again.
+    /// Queues without lease expiry implement this as a no-op.
+    async fn heartbeat(&self) -> Result<(), WeaverError>;
+
+    /// Add child tasks to this task's job.
+    ///
+    /// A child whose `dependencies_hint` lists earlier sibling indices
+    /// (e.g. `[0]`) waits for those siblings; the others are ready at once.
     async fn add_child_tasks(&self, child_specs: Vec<TaskSpec>)
     -> Result<Vec<TaskId>, WeaverError>;
 
     /// Mark success.
     async fn ack(self: Box<Self>) -> Result<(), WeaverError>;
 
+    /// Hand the task back unfinished (e.g. a draining worker ran out of time).
+    ///
+    /// The task is Queued again and the attempt does not count toward its budget.
+    async fn release(self: Box<Self>) -> Result<(), WeaverError>;
+
+    /// Mark success, keeping the handler's `outcome` (artifacts) as the attempt's result.
+    ///
+    /// Dependents read it through `TaskContext::dependency_outcome`.
+    async fn succeed(self: Box<Self>, _outcome: Outcome) -> Result<(), WeaverError> {
+        self.ack().await
+    }
+
     /// Mark failure (queue decides retry/dead policy).
     ///
     /// **Deprecated in Phase 4-1**: Use `complete()` instead.
@@ -77,6 +146,209 @@ pub trait Queue: Send + Sync {
     /// Lease one ready task (waits until available, or returns None if shutdown).
     async fn lease(&self) -> Option<Box<dyn TaskLease>>;
 
+    /// Lease up to `n` ready tasks at once (waits until at least one is available).
+    ///
+    /// Lets a worker prefetch a batch under one lock acquisition.
+    /// Default: a single `lease()`.
+    async fn lease_many(&self, _n: usize) -> Vec<Box<dyn TaskLease>> {
+        self.lease().await.into_iter().collect()
+    }
+
+    /// Like `lease_many`, but never hands out a task that would take a worker
+    /// reserved for another namespace (see `NamespaceReservations`).
+    ///
+    /// Default: ignores the reservations.
+    async fn lease_many_reserved(
+        &self,
+        n: usize,
+        _reservations: &NamespaceReservations,
+    ) -> Vec<Box<dyn TaskLease>> {
+        self.lease_many(n).await
+    }
+
+    /// Like `lease_many_reserved` (or `lease_many` without reservations), but
+    /// only hands out tasks whose task type matches `task_types`; the others
+    /// stay queued for another worker pool.
+    ///
+    /// Default: leases as usual and releases the tasks outside the filter.
+    /// Queues should override this to skip them instead.
+    async fn lease_many_filtered(
+        &self,
+        n: usize,
+        task_types: &TaskTypeFilter,
+        reservations: Option<&NamespaceReservations>,
+    ) -> Vec<Box<dyn TaskLease>> {
+        let leases = match reservations {
+            Some(reservations) => self.lease_many_reserved(n, reservations).await,
+            None => self.lease_many(n).await,
+        };
+        if task_types.is_any() {
+            return leases;
+        }
+        let mut matching = Vec::with_capacity(leases.len());
+        for lease in leases {
+            if task_types.matches(lease.envelope().task_type()) {
+                matching.push(lease);
+            } else if let Err(e) = lease.release().await {
+                eprintln!("[queue] release of filtered-out lease failed: {}", e);
+            }
+        }
+        matching
+    }
+
+    /// Remove every task matching `filter`, together with its attempt and
+    /// decision history. Running tasks are never purged (a worker owns them).
+    ///
+    /// Returns the number of tasks removed.
+    async fn purge(&self, filter: &TaskFilter) -> Result<usize, WeaverError>;
+
+    /// Put Dead and RetryScheduled tasks matching `filter` back on the ready
+    /// queue. Dead tasks get a fresh attempt budget.
+    ///
+    /// Returns the number of tasks requeued.
+    async fn requeue(&self, filter: &TaskFilter) -> Result<usize, WeaverError>;
+
+    /// This run of the queue (None if the queue does not track runs).
+    async fn run_id(&self) -> Option<RunId> {
+        None
+    }
+
+    /// The outcome of the task's latest finished attempt (`None` until one
+    /// finishes).
+    ///
+    /// Queues that do not keep attempt history return an error.
+    async fn task_outcome(&self, _task_id: TaskId) -> Result<Option<Outcome>, WeaverError> {
+        Err(WeaverError::Other(
+            "attempt history is not supported by this queue".into(),
+        ))
+    }
+
+    /// Tasks whose indexed payload field `field` is `value`, in id order
+    /// (e.g. every task for customer 42).
+    ///
+    /// Queues without a payload index return an error.
+    async fn find_tasks_by_field(
+        &self,
+        _field: &str,
+        _value: &str,
+    ) -> Result<Vec<TaskId>, WeaverError> {
+        Err(WeaverError::Other(
+            "payload index is not supported by this queue".into(),
+        ))
+    }
+
+    /// Reclaim the Running tasks whose lease expired now, instead of waiting
+    /// for the next `lease()` to do it.
+    async fn reap_expired_leases(&self) -> Result<crate::observability::ReapedLeases, WeaverError> {
+        Err(WeaverError::Other(
+            "lease reaping is not supported by this queue".into(),
+        ))
+    }
+
     /// Observability hook (optional but useful).
     async fn counts_by_state(&self) -> Result<crate::observability::QueueCounts, WeaverError>;
+
+    /// Counts by state for each task type with at least one task (e.g. to see
+    /// which task types are backing up).
+    async fn counts_by_state_per_type(
+        &self,
+    ) -> Result<BTreeMap<String, crate::observability::StateCounts>, WeaverError> {
+        Err(WeaverError::Other(
+            "per-type counts are not supported by this queue".into(),
+        ))
+    }
+
+    /// Enqueue → start latency, execution duration and success rate per task
+    /// type over the last 1m / 5m / 1h.
+    async fn queue_stats(&self) -> Result<crate::observability::QueueStats, WeaverError> {
+        Err(WeaverError::Other(
+            "queue stats are not supported by this queue".into(),
+        ))
+    }
+
+    /// Receive every task state change from now on, instead of polling
+    /// `counts_by_state()`.
+    ///
+    /// A receiver that falls behind loses the oldest changes
+    /// (`RecvError::Lagged`); re-read the counts then.
+    async fn subscribe(
+        &self,
+    ) -> Result<broadcast::Receiver<crate::observability::TaskStateChanged>, WeaverError> {
+        Err(WeaverError::Other(
+            "state change subscriptions are not supported by this queue".into(),
+        ))
+    }
+
+    /// The task's attempts, outcomes, decisions and retries in the order they
+    /// happened, to answer "why is this task dead / still retrying?".
+    async fn explain(
+        &self,
+        _task_id: TaskId,
+    ) -> Result<crate::observability::ExplanationReport, WeaverError> {
+        Err(WeaverError::Other(
+            "explanations are not supported by this queue".into(),
+        ))
+    }
+
+    /// Completed / total tasks of `job_id`, elapsed time and a naive ETA.
+    async fn job_progress(
+        &self,
+        _job_id: JobId,
+    ) -> Result<crate::observability::JobProgress, WeaverError> {
+        Err(WeaverError::Other(
+            "job progress is not supported by this queue".into(),
+        ))
+    }
+
+    /// The tasks of `job_id` (including child tasks), their dependencies and
+    /// states, to render with `JobGraph::to_dot()` / `to_mermaid()`.
+    async fn job_graph(
+        &self,
+        _job_id: JobId,
+    ) -> Result<crate::observability::JobGraph, WeaverError> {
+        Err(WeaverError::Other(
+            "job graphs are not supported by this queue".into(),
+        ))
+    }
+
+    /// Counts by state of the tasks of `job_id` (including child tasks).
+    async fn counts_for_job(
+        &self,
+        _job_id: JobId,
+    ) -> Result<crate::observability::StateCounts, WeaverError> {
+        Err(WeaverError::Other(
+            "per-job counts are not supported by this queue".into(),
+        ))
+    }
+
+    /// Up to `limit` lifecycle events after `after` (from the start if `None`).
+    ///
+    /// Queues without an event log return an error.
+    async fn read_events(
+        &self,
+        _after: Option<EventCursor>,
+        _limit: usize,
+    ) -> Result<Vec<LifecycleEvent>, WeaverError> {
+        Err(WeaverError::Other(
+            "event log is not supported by this queue".into(),
+        ))
+    }
+
+    /// Cursor last committed by `consumer` (None: start from the beginning).
+    async fn event_cursor(&self, _consumer: &str) -> Result<Option<EventCursor>, WeaverError> {
+        Err(WeaverError::Other(
+            "event log is not supported by this queue".into(),
+        ))
+    }
+
+    /// Record that `consumer` processed every event up to `cursor`.
+    async fn commit_event_cursor(
+        &self,
+        _consumer: &str,
+        _cursor: EventCursor,
+    ) -> Result<(), WeaverError> {
+        Err(WeaverError::Other(
+            "event log is not supported by this queue".into(),
+        ))
+    }
 }
@@ -0,0 +1,18 @@
+//! Lease order: which ready task is handed out first.
+
+/// Order in which ready tasks are leased.
+///
+/// v1: Selected per queue (see `InMemoryQueue::with_lease_order`).
+/// Retried and newly unblocked tasks rejoin the ready queue as the newest entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LeaseOrder {
+    /// Oldest ready task first (queue).
+    #[default]
+    Fifo,
+
+    /// Newest ready task first (stack).
+    ///
+    /// Better cache locality and latency for interactive workloads, at the
+    /// cost of fairness: old tasks can starve under sustained load.
+    Lifo,
+}
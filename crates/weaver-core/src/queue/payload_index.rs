@@ -0,0 +1,93 @@
+//! Secondary index over payload fields, to find tasks by business key.
+//!
+//! Each task type may declare fields to index, e.g. `customer_id` at
+//! `payload.customer.id`; `InMemoryQueue::find_tasks_by_field("customer_id",
+//! "42")` then lists every task (of any configured type) whose payload has 42
+//! there, without scanning the queue.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::domain::{TaskEnvelope, TaskId, TaskType};
+
+/// One indexed field: a name and where to read it in the payload.
+#[derive(Debug, Clone)]
+struct IndexedField {
+    name: String,
+    /// Object keys from the payload root.
+    path: Vec<String>,
+}
+
+/// Field configuration plus (field, value) -> task ids.
+#[derive(Debug, Default)]
+pub(crate) struct PayloadIndex {
+    fields: HashMap<TaskType, Vec<IndexedField>>,
+    entries: HashMap<(String, String), BTreeSet<TaskId>>,
+}
+
+impl PayloadIndex {
+    /// Index `name` for tasks of `task_type`, read at the dotted `path`
+    /// (`customer.id`; a leading `$.` or `payload.` is accepted).
+    pub(crate) fn add_field(&mut self, task_type: TaskType, name: &str, path: &str) {
+        let path = path
+            .strip_prefix("$.")
+            .or_else(|| path.strip_prefix("payload."))
+            .unwrap_or(path);
+        self.fields
+            .entry(task_type)
+            .or_default()
+            .push(IndexedField {
+                name: name.to_string(),
+                path: path.split('.').map(str::to_string).collect(),
+            });
+    }
+
+    pub(crate) fn insert(&mut self, task_id: TaskId, envelope: &TaskEnvelope) {
+        for key in self.keys(envelope) {
+            self.entries.entry(key).or_default().insert(task_id);
+        }
+    }
+
+    /// Call with the envelope as it was indexed (before a payload change).
+    pub(crate) fn remove(&mut self, task_id: TaskId, envelope: &TaskEnvelope) {
+        for key in self.keys(envelope) {
+            if let Some(task_ids) = self.entries.get_mut(&key) {
+                task_ids.remove(&task_id);
+                if task_ids.is_empty() {
+                    self.entries.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Tasks whose `field` is `value`, in id order.
+    pub(crate) fn find(&self, field: &str, value: &str) -> Vec<TaskId> {
+        self.entries
+            .get(&(field.to_string(), value.to_string()))
+            .map(|task_ids| task_ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// (field, value) pairs of `envelope`; a missing field or a non-scalar
+    /// value is not indexed. Strings are taken as-is, numbers and booleans in
+    /// their JSON form (so `42` and `"42"` are found alike).
+    fn keys(&self, envelope: &TaskEnvelope) -> Vec<(String, String)> {
+        let Some(fields) = self.fields.get(envelope.task_type()) else {
+            return Vec::new();
+        };
+        fields
+            .iter()
+            .filter_map(|field| {
+                let value = field
+                    .path
+                    .iter()
+                    .try_fold(envelope.payload(), |value, key| value.get(key))?;
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    serde_json::Value::Number(_) | serde_json::Value::Bool(_) => value.to_string(),
+                    _ => return None,
+                };
+                Some((field.name.clone(), value))
+            })
+            .collect()
+    }
+}
@@ -1,9 +1,10 @@
 //! Task record: metadata + envelope.
 
+use std::collections::HashMap;
 use std::time::Instant;
 
 use super::TaskState;
-use crate::domain::{JobId, TaskEnvelope, TaskId};
+use crate::domain::{JobId, RunCondition, TaskEnvelope, TaskId};
 
 /// Metadata + envelope for a task in the queue.
 ///
@@ -27,9 +28,12 @@ pub struct TaskRecord {
     /// Last error message (if any).
     pub last_error: Option<String>,
 
-    /// When to retry next (for RetryScheduled state).
+    /// When to retry next (RetryScheduled), or when a debounced Queued task becomes visible.
     pub next_run_at: Option<Instant>,
 
+    /// Lease visibility timeout (Running only; extended by heartbeats).
+    pub lease_expires_at: Option<Instant>,
+
     /// Timestamps for observability.
     pub created_at: Instant,
     pub updated_at: Instant,
@@ -40,6 +44,20 @@ pub struct TaskRecord {
 
     // Task dependencies: this task cannot run until all tasks in this list are completed.
     pub depends_on: Vec<TaskId>,
+
+    /// Every dependency ever added (unlike `depends_on`, never shrinks).
+    pub declared_dependencies: Vec<TaskId>,
+
+    /// `TaskSpec::title` for job tasks (names the task for its dependents).
+    pub title: Option<String>,
+
+    /// `TaskSpec::join`: merge the results of `declared_dependencies` into the
+    /// payload when the task is released.
+    pub join: bool,
+
+    /// Run conditions of dependencies that do not wait for success
+    /// (`TaskSpec::run_if`); every other dependency is `OnSuccess`.
+    pub run_conditions: HashMap<TaskId, RunCondition>,
 }
 
 impl TaskRecord {
@@ -53,11 +71,16 @@ impl TaskRecord {
             max_attempts,
             last_error: None,
             next_run_at: None,
+            lease_expires_at: None,
             created_at: now,
             updated_at: now,
             parent_task_id: None,
             child_task_ids: Vec::new(),
             depends_on: Vec::new(),
+            declared_dependencies: Vec::new(),
+            title: None,
+            join: false,
+            run_conditions: HashMap::new(),
         }
     }
 
@@ -75,11 +98,16 @@ impl TaskRecord {
             max_attempts,
             last_error: None,
             next_run_at: None,
+            lease_expires_at: None,
             created_at: Instant::now(),
             updated_at: Instant::now(),
             parent_task_id: Some(parent_task_id),
             child_task_ids: Vec::new(),
             depends_on: Vec::new(),
+            declared_dependencies: Vec::new(),
+            title: None,
+            join: false,
+            run_conditions: HashMap::new(),
         }
     }
 
@@ -97,6 +125,11 @@ impl TaskRecord {
         self.updated_at = Instant::now();
     }
 
+    /// Has the lease of this Running task expired (stale heartbeat)?
+    pub fn is_lease_expired(&self, now: Instant) -> bool {
+        self.state == TaskState::Running && self.lease_expires_at.is_some_and(|at| at <= now)
+    }
+
     /// Mark as succeeded.
     pub fn mark_succeeded(&mut self) {
         self.state = TaskState::Succeeded;
@@ -110,6 +143,32 @@ impl TaskRecord {
         self.updated_at = Instant::now();
     }
 
+    /// Mark as cancelled (owning job was cancelled).
+    pub fn mark_cancelled(&mut self, reason: String) {
+        self.state = TaskState::Cancelled;
+        self.next_run_at = None;
+        self.last_error = Some(reason);
+        self.updated_at = Instant::now();
+    }
+
+    /// Mark as blocked (waiting for an operator to unblock it).
+    ///
+    /// Blocked attempts do not count against the retry budget.
+    pub fn mark_blocked(&mut self, reason: String) {
+        self.state = TaskState::Blocked;
+        self.attempts = self.attempts.saturating_sub(1);
+        self.last_error = Some(reason);
+        self.updated_at = Instant::now();
+    }
+
+    /// Give back an unfinished lease (the attempt does not count).
+    pub fn release(&mut self) {
+        self.state = TaskState::Queued;
+        self.attempts = self.attempts.saturating_sub(1);
+        self.lease_expires_at = None;
+        self.updated_at = Instant::now();
+    }
+
     /// Schedule retry with backoff.
     pub fn schedule_retry(&mut self, next_run_at: Instant, error: String) {
         self.state = TaskState::RetryScheduled;
@@ -118,7 +177,22 @@ impl TaskRecord {
         self.updated_at = Instant::now();
     }
 
-    /// Move from RetryScheduled back to Queued.
+    /// Hold a Queued task back until `visible_at` (debounce window).
+    pub fn defer_until(&mut self, visible_at: Instant) {
+        self.next_run_at = Some(visible_at);
+        self.updated_at = Instant::now();
+    }
+
+    /// Is this task waiting in the scheduled heap for `at`?
+    ///
+    /// Heap entries are never removed in place; an entry whose time no longer
+    /// matches the record (rescheduled, cancelled, ...) is stale.
+    pub fn is_scheduled_at(&self, at: Instant) -> bool {
+        matches!(self.state, TaskState::RetryScheduled | TaskState::Queued)
+            && self.next_run_at == Some(at)
+    }
+
+    /// Move from RetryScheduled (or a deferred Queued) back to Queued.
     pub fn requeue(&mut self) {
         self.state = TaskState::Queued;
         self.next_run_at = None;
@@ -131,8 +205,29 @@ impl TaskRecord {
             self.depends_on.push(task_id);
             self.updated_at = Instant::now();
         }
+        if !self.declared_dependencies.contains(&task_id) {
+            self.declared_dependencies.push(task_id);
+        }
     }
     
+    /// Add a dependency that runs this task on `when` instead of on success.
+    pub fn add_conditional_dependency(&mut self, task_id: TaskId, when: RunCondition) {
+        self.add_dependency(task_id);
+        if when == RunCondition::OnSuccess {
+            self.run_conditions.remove(&task_id);
+        } else {
+            self.run_conditions.insert(task_id, when);
+        }
+    }
+
+    /// The run condition of the dependency on `task_id`.
+    pub fn run_condition(&self, task_id: TaskId) -> RunCondition {
+        self.run_conditions
+            .get(&task_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
     /// Remove a dependency (called when the depended task completes).
     pub fn remove_dependency(&mut self, task_id: TaskId) {
         self.depends_on.retain(|&id| id != task_id);
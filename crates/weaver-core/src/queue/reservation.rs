@@ -0,0 +1,91 @@
+//! Namespace reservations: minimum worker capacity per namespace.
+//!
+//! A task's namespace is the first segment of its task type
+//! (`acme.billing.charge.v1` -> `acme`).
+
+use std::collections::HashMap;
+
+use crate::error::WeaverError;
+
+/// Minimum number of workers kept available per namespace.
+///
+/// With 10 workers and `acme` reserving 2, tasks of other namespaces never
+/// occupy more than 8 workers while fewer than 2 `acme` tasks are running,
+/// so `acme` tasks start without waiting for unrelated work to finish.
+///
+/// v1: Passed by `WorkerGroup` to `Queue::lease_many_reserved` (see
+/// `WorkerGroupConfig::namespace_reservations`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceReservations {
+    workers: usize,
+    min_workers: HashMap<String, usize>,
+}
+
+impl NamespaceReservations {
+    /// Reserve `min_workers` per namespace out of `workers` in total.
+    ///
+    /// Fails if the reservations add up to more than `workers`.
+    pub fn new(workers: usize, min_workers: HashMap<String, usize>) -> Result<Self, WeaverError> {
+        let reserved: usize = min_workers.values().sum();
+        if reserved > workers {
+            return Err(WeaverError::Other(format!(
+                "namespace reservations need {reserved} workers, only {workers} available"
+            )));
+        }
+        Ok(Self {
+            workers,
+            min_workers,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min_workers.values().all(|&min| min == 0)
+    }
+
+    /// May one more task of `namespace` start, given the tasks running now?
+    ///
+    /// Yes if the namespace is below its own minimum, or if the workers left
+    /// idle afterwards still cover every other namespace's unmet minimum.
+    pub(crate) fn allows(
+        &self,
+        namespace: Option<&str>,
+        running: &HashMap<&str, usize>,
+        running_total: usize,
+    ) -> bool {
+        let running_in = |ns: &str| running.get(ns).copied().unwrap_or(0);
+        if let Some(ns) = namespace
+            && running_in(ns) < self.min_workers.get(ns).copied().unwrap_or(0)
+        {
+            return true;
+        }
+        let unmet: usize = self
+            .min_workers
+            .iter()
+            .map(|(ns, &min)| min.saturating_sub(running_in(ns)))
+            .sum();
+        running_total + 1 + unmet <= self.workers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn other_namespaces_leave_reserved_workers_idle() {
+        let reservations =
+            NamespaceReservations::new(3, HashMap::from([("acme".to_string(), 1)])).unwrap();
+        let none = HashMap::new();
+        assert!(reservations.allows(Some("bulk"), &none, 0));
+        assert!(reservations.allows(Some("bulk"), &HashMap::from([("bulk", 1)]), 1));
+        // The third worker stays free for acme
+        assert!(!reservations.allows(Some("bulk"), &HashMap::from([("bulk", 2)]), 2));
+        assert!(!reservations.allows(None, &HashMap::from([("bulk", 2)]), 2));
+        assert!(reservations.allows(Some("acme"), &HashMap::from([("bulk", 2)]), 2));
+        // Once acme's minimum is met, everyone shares the rest
+        let running = HashMap::from([("acme", 1), ("bulk", 1)]);
+        assert!(reservations.allows(Some("bulk"), &running, 2));
+
+        assert!(NamespaceReservations::new(1, HashMap::from([("acme".to_string(), 2)])).is_err());
+    }
+}
@@ -4,8 +4,8 @@ use std::time::Duration;
 
 /// Retry policy for failed tasks.
 ///
-/// v1: Simple policy with configurable base delay.
-/// Future: Could add jitter, exponential backoff variants, etc.
+/// v1: Exponential backoff with configurable base delay.
+/// Jitter and a delay cap spread out retries of tasks that failed together.
 #[derive(Debug, Clone)]
 pub struct RetryPolicy {
     /// Base delay for the first retry.
@@ -13,6 +13,33 @@ pub struct RetryPolicy {
 
     /// Backoff multiplier for exponential backoff.
     pub multiplier: f64,
+
+    /// Upper bound for any delay, applied after jitter (None: unbounded).
+    pub max_delay: Option<Duration>,
+
+    /// How the exponential delay is randomized.
+    pub jitter: Jitter,
+}
+
+/// Randomization of the backoff delay.
+///
+/// Without jitter, tasks that fail at the same moment retry at the same
+/// moment again (thundering herd). `d` below is the exponential delay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Jitter {
+    /// Exactly `d`.
+    #[default]
+    None,
+
+    /// Uniform in `[0, d]`.
+    Full,
+
+    /// Uniform in `[d/2, d]`.
+    Equal,
+
+    /// Uniform in `[base_delay, 3 * previous d]` (AWS "decorrelated jitter",
+    /// using the un-jittered previous delay so the policy stays stateless).
+    Decorrelated,
 }
 
 impl RetryPolicy {
@@ -21,9 +48,23 @@ impl RetryPolicy {
         Self {
             base_delay: Duration::from_secs(2),
             multiplier: 2.0,
+            max_delay: None,
+            jitter: Jitter::None,
         }
     }
 
+    /// Set the jitter mode.
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Cap every delay at `max_delay`.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
     /// Calculate delay for the next retry based on attempt number.
     ///
     /// # Arguments
@@ -42,13 +83,59 @@ impl RetryPolicy {
     /// - attempt 3: 8s
     /// - attempt 4: 16s
     /// - attempt 5: 32s
+    ///
+    /// The result is then randomized by `jitter` and capped by `max_delay`.
     pub fn next_delay(&self, attempts: u32) -> Duration {
-        let base_secs = self.base_delay.as_secs_f64();
-        let delay_secs = base_secs * self.multiplier.powi((attempts.saturating_sub(1)) as i32);
-        Duration::from_secs_f64(delay_secs)
+        let sample = match self.jitter {
+            Jitter::None => 0.0,
+            _ => rand::random::<f64>(),
+        };
+        self.delay_with_sample(attempts, sample)
+    }
+
+    /// `next_delay` with the random draw supplied (`sample` in `[0, 1)`).
+    fn delay_with_sample(&self, attempts: u32, sample: f64) -> Duration {
+        let delay_secs = match self.jitter {
+            Jitter::None => self.exponential_secs(attempts),
+            Jitter::Full => self.exponential_secs(attempts) * sample,
+            Jitter::Equal => {
+                let half = self.exponential_secs(attempts) / 2.0;
+                half + half * sample
+            }
+            Jitter::Decorrelated => {
+                let low = self.base_delay.as_secs_f64();
+                let high = (3.0 * self.exponential_secs(attempts.saturating_sub(1))).max(low);
+                low + (high - low) * sample
+            }
+        };
+        let delay = Duration::try_from_secs_f64(delay_secs).unwrap_or(Duration::MAX);
+        match self.max_delay {
+            Some(max_delay) => delay.min(max_delay),
+            None => delay,
+        }
+    }
+
+    /// Un-jittered exponential delay in seconds (0 attempts: base delay).
+    fn exponential_secs(&self, attempts: u32) -> f64 {
+        let exponent = attempts.saturating_sub(1).min(i32::MAX as u32) as i32;
+        self.base_delay.as_secs_f64() * self.multiplier.powi(exponent)
     }
 }
 
+/// Caps how many retries of one task type are promoted per interval.
+///
+/// When a mass failure schedules many retries for nearly the same instant,
+/// promotion is spread over consecutive intervals instead of flooding the
+/// ready queue at once. Tasks over the cap are rescheduled to the next interval.
+#[derive(Debug, Clone)]
+pub struct RetryBatching {
+    /// Maximum retries promoted per task type within one interval.
+    pub max_per_batch: usize,
+
+    /// Length of one promotion interval.
+    pub interval: Duration,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,4 +165,45 @@ mod tests {
         assert_eq!(d2, Duration::from_secs(4));
         assert_eq!(d3, Duration::from_secs(8));
     }
+
+    #[test]
+    fn jitter_modes_stay_within_their_range() {
+        let policy = RetryPolicy::default_v1();
+
+        // attempt 3: d = 8s, previous d = 4s
+        let full = policy.clone().with_jitter(Jitter::Full);
+        assert_eq!(full.delay_with_sample(3, 0.0), Duration::ZERO);
+        assert_eq!(full.delay_with_sample(3, 0.5), Duration::from_secs(4));
+
+        let equal = policy.clone().with_jitter(Jitter::Equal);
+        assert_eq!(equal.delay_with_sample(3, 0.0), Duration::from_secs(4));
+        assert_eq!(equal.delay_with_sample(3, 0.5), Duration::from_secs(6));
+
+        let decorrelated = policy.with_jitter(Jitter::Decorrelated);
+        assert_eq!(
+            decorrelated.delay_with_sample(3, 0.0),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            decorrelated.delay_with_sample(3, 0.5),
+            Duration::from_secs(7)
+        );
+
+        for _ in 0..100 {
+            let delay = full.next_delay(3);
+            assert!(delay <= Duration::from_secs(8));
+        }
+    }
+
+    #[test]
+    fn max_delay_caps_backoff_and_huge_exponents() {
+        let policy = RetryPolicy::default_v1().with_max_delay(Duration::from_secs(10));
+
+        assert_eq!(policy.next_delay(3), Duration::from_secs(8));
+        assert_eq!(policy.next_delay(4), Duration::from_secs(10));
+        assert_eq!(policy.next_delay(u32::MAX), Duration::from_secs(10));
+
+        let jittered = policy.with_jitter(Jitter::Decorrelated);
+        assert!(jittered.next_delay(50) <= Duration::from_secs(10));
+    }
 }
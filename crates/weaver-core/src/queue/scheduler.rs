@@ -0,0 +1,178 @@
+//! Scheduler: submit recurring schedules' jobs in-process, as their periods close.
+//!
+//! For small apps running the v1 `InMemoryQueue` + `WorkerGroup` alone; no
+//! other component is needed. Missed periods from before the process started
+//! are not submitted (use `Backfill` for those).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::InMemoryQueue;
+use crate::domain::RecurringSchedule;
+
+/// Longest single sleep, so a wall-clock jump is noticed within a minute.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// Submits each schedule's job once per period, when the period ends.
+///
+/// The job for `[start, end)` is submitted at `end`, so `{period_date}` in a
+/// daily schedule names a complete day. If the process is suspended across
+/// several boundaries, every closed period is submitted on wake-up, oldest
+/// first.
+///
+/// ```ignore
+/// let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+/// let workers = WorkerGroup::spawn(4, queue.clone(), runtime, decider);
+/// let scheduler = Scheduler::spawn(queue.clone(), vec![daily_export]);
+/// // ...
+/// scheduler.shutdown().await;
+/// workers.shutdown_and_join().await;
+/// ```
+pub struct Scheduler {
+    shutdown_tx: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+impl Scheduler {
+    /// Start submitting `schedules`, beginning with the periods in progress now.
+    pub fn spawn(queue: Arc<InMemoryQueue>, schedules: Vec<RecurringSchedule>) -> Self {
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let handle = tokio::spawn(run(queue, schedules, shutdown_rx));
+        Self {
+            shutdown_tx,
+            handle,
+        }
+    }
+
+    /// Stop the scheduler. Jobs already submitted are left to the workers.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown_tx.send(true);
+        let _ = self.handle.await;
+    }
+}
+
+async fn run(
+    queue: Arc<InMemoryQueue>,
+    schedules: Vec<RecurringSchedule>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let now = Utc::now();
+    let mut starts: Vec<DateTime<Utc>> = schedules
+        .iter()
+        .map(|schedule| schedule.period.floor(now))
+        .collect();
+    loop {
+        let Some(due) = schedules
+            .iter()
+            .zip(&starts)
+            .map(|(schedule, start)| schedule.period.next(*start))
+            .min()
+        else {
+            return; // nothing to schedule
+        };
+        let wait = (due - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = shutdown_rx.wait_for(|shutdown| *shutdown) => return,
+            _ = tokio::time::sleep(wait.min(MAX_SLEEP)) => {}
+        }
+        submit_closed_periods(&queue, &schedules, &mut starts, Utc::now()).await;
+    }
+}
+
+/// Submit the job of every period that ended by `now`, advancing `starts`.
+///
+/// Returns the number of jobs submitted.
+async fn submit_closed_periods(
+    queue: &InMemoryQueue,
+    schedules: &[RecurringSchedule],
+    starts: &mut [DateTime<Utc>],
+    now: DateTime<Utc>,
+) -> usize {
+    let mut submitted = 0;
+    for (schedule, start) in schedules.iter().zip(starts) {
+        loop {
+            let end = schedule.period.next(*start);
+            if end > now {
+                break;
+            }
+            match queue.submit_job(schedule.job_for(*start, end)).await {
+                Ok(job_id) => {
+                    submitted += 1;
+                    eprintln!(
+                        "[scheduler] {}: submitted job {} for {} .. {}",
+                        schedule.name, job_id, start, end
+                    );
+                }
+                Err(e) => eprintln!(
+                    "[scheduler] {}: submit for {} .. {} failed: {}",
+                    schedule.name, start, end, e
+                ),
+            }
+            *start = end;
+        }
+    }
+    submitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{JobSpec, Period, TaskSpec, TaskType};
+    use crate::queue::{Queue, RetryPolicy};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[tokio::test]
+    async fn closed_periods_are_submitted_once_each_oldest_first() {
+        let queue = InMemoryQueue::new(RetryPolicy::default_v1());
+        let schedules = vec![
+            RecurringSchedule::new(
+                "hourly",
+                Period::Hourly,
+                JobSpec::new(vec![TaskSpec::new(
+                    "tick",
+                    TaskType::new("tick"),
+                    serde_json::json!({ "at": "{period_start}" }),
+                )]),
+            ),
+            RecurringSchedule::new("daily", Period::Daily, JobSpec::new(vec![])),
+        ];
+        let mut starts = vec![utc("2024-01-01T10:00:00Z"), utc("2024-01-01T00:00:00Z")];
+
+        // Still inside both periods
+        let now = utc("2024-01-01T10:59:59Z");
+        assert_eq!(
+            submit_closed_periods(&queue, &schedules, &mut starts, now).await,
+            0
+        );
+
+        // Woke up late: two hourly periods closed, the day has not
+        let now = utc("2024-01-01T12:00:00Z");
+        assert_eq!(
+            submit_closed_periods(&queue, &schedules, &mut starts, now).await,
+            2
+        );
+        assert_eq!(starts[0], now);
+        let first = queue.lease().await.unwrap();
+        assert_eq!(
+            first.envelope().payload()["at"],
+            "2024-01-01T10:00:00+00:00"
+        );
+        let second = queue.lease().await.unwrap();
+        assert_eq!(
+            second.envelope().payload()["at"],
+            "2024-01-01T11:00:00+00:00"
+        );
+
+        assert_eq!(
+            submit_closed_periods(&queue, &schedules, &mut starts, now).await,
+            0
+        );
+    }
+}
@@ -0,0 +1,387 @@
+//! Durable queue snapshots with a versioned schema.
+//!
+//! `TaskRecord` / `JobRecord` keep `Instant`s, which mean nothing outside the
+//! process that created them; snapshots store wall-clock times instead.
+//!
+//! Every snapshot carries `schema_version`. Decoding runs the migrations from
+//! the version it was written with up to `SNAPSHOT_SCHEMA_VERSION`, so a newer
+//! weaver can load snapshots written by an older release during upgrades.
+//!
+//! Not part of the snapshot: attempt/decision history, annotations, journal,
+//! and open leases (Running tasks are restored as Queued).
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{TaskRecord, TaskState};
+use crate::domain::{
+    JobId, JobRecord, JobSpec, JobStateView, RunCondition, RunId, TaskEnvelope, TaskId,
+};
+use crate::error::WeaverError;
+use crate::ports::compressor::{compress_framed, decompress_framed};
+
+/// Upgrades a snapshot by one schema version (`v` -> `v + 1`).
+///
+/// Migrations work on the raw JSON value, so old layouts never need Rust types.
+pub type Migration = fn(serde_json::Value) -> Result<serde_json::Value, WeaverError>;
+
+/// `MIGRATIONS[i]` upgrades a version `i + 1` snapshot to version `i + 2`.
+///
+/// Append a migration whenever the snapshot layout changes; never edit old ones.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Schema version written by this release.
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// Byte encoding of snapshots (the schema is independent of the encoding).
+pub trait SnapshotCodec: Send + Sync {
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, WeaverError>;
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, WeaverError>;
+}
+
+/// JSON encoding (the default).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl SnapshotCodec for JsonCodec {
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, WeaverError> {
+        serde_json::to_vec(value).map_err(|e| WeaverError::Other(format!("encode snapshot: {e}")))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, WeaverError> {
+        serde_json::from_slice(bytes)
+            .map_err(|e| WeaverError::Other(format!("decode snapshot: {e}")))
+    }
+}
+
+/// Byte compression for `CompressedCodec`; the port is shared with payloads
+/// and artifacts (`impls::Zstd`, `impls::Gzip`).
+pub use crate::ports::Compressor;
+
+/// Compresses encodings of `inner` that reach `threshold` bytes.
+///
+/// Smaller snapshots are stored as `inner` wrote them, and snapshots written
+/// before compression was enabled still decode.
+///
+/// ```ignore
+/// let codec = CompressedCodec::new(JsonCodec, Arc::new(impls::Zstd::default()), 64 * 1024);
+/// let bytes = queue.snapshot().await.encode(&codec)?;
+/// ```
+pub struct CompressedCodec<C> {
+    inner: C,
+    compressor: Arc<dyn Compressor>,
+    threshold: usize,
+}
+
+impl<C: SnapshotCodec> CompressedCodec<C> {
+    pub fn new(inner: C, compressor: Arc<dyn Compressor>, threshold: usize) -> Self {
+        Self {
+            inner,
+            compressor,
+            threshold,
+        }
+    }
+}
+
+impl<C: SnapshotCodec> SnapshotCodec for CompressedCodec<C> {
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, WeaverError> {
+        let bytes = self.inner.encode(value)?;
+        if bytes.len() < self.threshold {
+            return Ok(bytes);
+        }
+        compress_framed(self.compressor.as_ref(), &bytes)
+            .map_err(|e| WeaverError::Other(format!("compress snapshot: {e}")))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, WeaverError> {
+        match decompress_framed(self.compressor.as_ref(), bytes) {
+            None => self.inner.decode(bytes),
+            Some(decompressed) => self.inner.decode(
+                &decompressed
+                    .map_err(|e| WeaverError::Other(format!("decompress snapshot: {e}")))?,
+            ),
+        }
+    }
+}
+
+/// Point-in-time copy of an `InMemoryQueue` (see `InMemoryQueue::snapshot`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueSnapshot {
+    pub schema_version: u32,
+    pub taken_at: DateTime<Utc>,
+    /// Run that took the snapshot; its Running tasks are requeued on restore.
+    #[serde(default)]
+    pub run_id: Option<RunId>,
+    pub next_job_id: u64,
+    pub next_task_id: u64,
+    pub jobs: Vec<JobSnapshot>,
+    pub tasks: Vec<TaskSnapshot>,
+}
+
+/// Durable form of a `JobRecord`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSnapshot {
+    pub job_id: JobId,
+    pub spec: JobSpec,
+    pub state: JobStateView,
+    pub task_ids: Vec<TaskId>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deadline_at: Option<DateTime<Utc>>,
+}
+
+/// Durable form of a `TaskRecord`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSnapshot {
+    /// Queue key (may differ from `envelope.task_id()`).
+    pub task_id: TaskId,
+    pub envelope: TaskEnvelope,
+    pub state: TaskState,
+    pub job_id: Option<JobId>,
+    pub attempts: u32,
+    pub max_attempts: u32,
+    pub last_error: Option<String>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub parent_task_id: Option<TaskId>,
+    pub child_task_ids: Vec<TaskId>,
+
+    /// Unresolved dependencies.
+    pub depends_on: Vec<TaskId>,
+    #[serde(default)]
+    pub declared_dependencies: Vec<TaskId>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub join: bool,
+    /// Dependencies that do not wait for success, sorted by task.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub run_conditions: Vec<(TaskId, RunCondition)>,
+}
+
+impl QueueSnapshot {
+    /// Encode with the current schema version.
+    pub fn encode(&self, codec: &dyn SnapshotCodec) -> Result<Vec<u8>, WeaverError> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| WeaverError::Other(format!("encode snapshot: {e}")))?;
+        codec.encode(&value)
+    }
+
+    /// Decode a snapshot written by this or an older release.
+    pub fn decode(bytes: &[u8], codec: &dyn SnapshotCodec) -> Result<Self, WeaverError> {
+        let value = migrate(codec.decode(bytes)?, MIGRATIONS)?;
+        serde_json::from_value(value)
+            .map_err(|e| WeaverError::Other(format!("decode snapshot: {e}")))
+    }
+}
+
+/// Run `migrations` from the snapshot's version up to `migrations.len() + 1`.
+fn migrate(
+    mut value: serde_json::Value,
+    migrations: &[Migration],
+) -> Result<serde_json::Value, WeaverError> {
+    let current = migrations.len() as u64 + 1;
+    let version = value["schema_version"]
+        .as_u64()
+        .ok_or_else(|| WeaverError::Other("snapshot has no schema_version".to_string()))?;
+    if version == 0 || version > current {
+        return Err(WeaverError::Other(format!(
+            "unsupported snapshot schema_version {version} (this release reads 1..={current})"
+        )));
+    }
+
+    for (from, migration) in (version..current).zip(&migrations[version as usize - 1..]) {
+        value = migration(value)?;
+        value["schema_version"] = serde_json::json!(from + 1);
+    }
+    Ok(value)
+}
+
+/// Maps `Instant`s to wall-clock times and back, relative to one reading of both clocks.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WallClock {
+    instant: Instant,
+    wall: DateTime<Utc>,
+}
+
+impl WallClock {
+    pub(crate) fn now() -> Self {
+        Self {
+            instant: Instant::now(),
+            wall: Utc::now(),
+        }
+    }
+
+    pub(crate) fn wall(&self) -> DateTime<Utc> {
+        self.wall
+    }
+
+    pub(crate) fn to_wall(self, at: Instant) -> DateTime<Utc> {
+        match at.checked_duration_since(self.instant) {
+            Some(ahead) => self.wall + ahead,
+            None => self.wall - self.instant.duration_since(at),
+        }
+    }
+
+    /// Times before process start are clamped to now.
+    pub(crate) fn to_instant(self, at: DateTime<Utc>) -> Instant {
+        match (at - self.wall).to_std() {
+            Ok(ahead) => self.instant + ahead,
+            Err(_) => (self.wall - at)
+                .to_std()
+                .ok()
+                .and_then(|behind| self.instant.checked_sub(behind))
+                .unwrap_or(self.instant),
+        }
+    }
+}
+
+impl JobSnapshot {
+    pub(crate) fn from_record(job: &JobRecord, clock: WallClock) -> Self {
+        Self {
+            job_id: job.job_id,
+            spec: job.spec.clone(),
+            state: job.state.into(),
+            task_ids: job.task_ids.clone(),
+            created_at: clock.to_wall(job.created_at),
+            updated_at: clock.to_wall(job.updated_at),
+            deadline_at: job.deadline_at.map(|at| clock.to_wall(at)),
+        }
+    }
+
+    pub(crate) fn into_record(self, clock: WallClock) -> JobRecord {
+        let mut job = JobRecord::new(self.job_id, self.spec);
+        job.state = self.state.into();
+        job.task_ids = self.task_ids;
+        job.created_at = clock.to_instant(self.created_at);
+        job.updated_at = clock.to_instant(self.updated_at);
+        job.deadline_at = self.deadline_at.map(|at| clock.to_instant(at));
+        job
+    }
+}
+
+impl TaskSnapshot {
+    pub(crate) fn from_record(task_id: TaskId, record: &TaskRecord, clock: WallClock) -> Self {
+        Self {
+            task_id,
+            envelope: record.envelope.clone(),
+            state: record.state,
+            job_id: record.job_id,
+            attempts: record.attempts,
+            max_attempts: record.max_attempts,
+            last_error: record.last_error.clone(),
+            next_run_at: record.next_run_at.map(|at| clock.to_wall(at)),
+            created_at: clock.to_wall(record.created_at),
+            updated_at: clock.to_wall(record.updated_at),
+            parent_task_id: record.parent_task_id,
+            child_task_ids: record.child_task_ids.clone(),
+            depends_on: record.depends_on.clone(),
+            declared_dependencies: record.declared_dependencies.clone(),
+            title: record.title.clone(),
+            join: record.join,
+            run_conditions: {
+                let mut conditions: Vec<_> = record
+                    .run_conditions
+                    .iter()
+                    .map(|(&task_id, &when)| (task_id, when))
+                    .collect();
+                conditions.sort_by_key(|(task_id, _)| task_id.as_u64());
+                conditions
+            },
+        }
+    }
+
+    /// Rebuild the record; a Running task lost its lease and becomes Queued.
+    pub(crate) fn into_record(self, clock: WallClock) -> (TaskId, TaskRecord) {
+        let mut record = TaskRecord::new(self.envelope, self.max_attempts);
+        record.state = match self.state {
+            TaskState::Running => TaskState::Queued,
+            state => state,
+        };
+        record.job_id = self.job_id;
+        record.attempts = self.attempts;
+        record.last_error = self.last_error;
+        record.next_run_at = self.next_run_at.map(|at| clock.to_instant(at));
+        record.created_at = clock.to_instant(self.created_at);
+        record.updated_at = clock.to_instant(self.updated_at);
+        record.parent_task_id = self.parent_task_id;
+        record.child_task_ids = self.child_task_ids;
+        record.depends_on = self.depends_on;
+        record.declared_dependencies = self.declared_dependencies;
+        record.title = self.title;
+        record.join = self.join;
+        record.run_conditions = self.run_conditions.into_iter().collect();
+        (self.task_id, record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ports::CompressionError;
+    use crate::ports::compressor::RunLength;
+
+    #[test]
+    fn migrations_upgrade_old_snapshots_step_by_step() {
+        fn v1_to_v2(mut value: serde_json::Value) -> Result<serde_json::Value, WeaverError> {
+            value["tasks"] = value["items"].take();
+            Ok(value)
+        }
+        fn v2_to_v3(mut value: serde_json::Value) -> Result<serde_json::Value, WeaverError> {
+            value["jobs"] = serde_json::json!([]);
+            Ok(value)
+        }
+        let migrations: &[Migration] = &[v1_to_v2, v2_to_v3];
+
+        let v1 = serde_json::json!({ "schema_version": 1, "items": [1] });
+        let v3 = migrate(v1, migrations).unwrap();
+        assert_eq!(v3["schema_version"], 3);
+        assert_eq!(v3["tasks"], serde_json::json!([1]));
+        assert_eq!(v3["jobs"], serde_json::json!([]));
+
+        // Already current: untouched
+        let current = serde_json::json!({ "schema_version": 3, "tasks": [] });
+        assert_eq!(migrate(current.clone(), migrations).unwrap(), current);
+
+        // Written by a newer release
+        let newer = serde_json::json!({ "schema_version": 4 });
+        assert!(migrate(newer, migrations).is_err());
+        assert!(migrate(serde_json::json!({}), migrations).is_err());
+    }
+
+    #[test]
+    fn compressed_codec_compresses_large_snapshots_only() {
+        let codec = CompressedCodec::new(JsonCodec, Arc::new(RunLength), 64);
+        let small = serde_json::json!({ "schema_version": 1 });
+        let large = serde_json::json!({ "schema_version": 1, "pad": " ".repeat(1000) });
+
+        let bytes = codec.encode(&small).unwrap();
+        assert_eq!(bytes, JsonCodec.encode(&small).unwrap());
+        assert_eq!(codec.decode(&bytes).unwrap(), small);
+
+        let bytes = codec.encode(&large).unwrap();
+        assert!(bytes.starts_with(b"WVZ1\x03rle"));
+        assert!(bytes.len() < 100);
+        assert_eq!(codec.decode(&bytes).unwrap(), large);
+
+        // Written with another compressor
+        struct Other;
+        impl Compressor for Other {
+            fn name(&self) -> &str {
+                "zstd"
+            }
+            fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError> {
+                Ok(bytes.to_vec())
+            }
+            fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError> {
+                Ok(bytes.to_vec())
+            }
+        }
+        let other = CompressedCodec::new(JsonCodec, Arc::new(Other), 64);
+        assert!(other.decode(&bytes).is_err());
+    }
+}
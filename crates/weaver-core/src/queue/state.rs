@@ -9,6 +9,8 @@ use serde::{Deserialize, Serialize};
 /// - Queued -> Running -> RetryScheduled -> Queued (loop until max_attempts)
 /// - Queued -> Running -> Dead (when max_attempts exceeded)
 /// - Queued -> Running -> Decomposed (when task is decomposed into child tasks)
+/// - Queued -> Running -> Blocked -> Queued (when unblocked)
+/// - Any non-terminal state -> Cancelled (when the owning job is cancelled)
 ///
 /// Design note: Using an enum ensures exhaustive matching and prevents invalid states.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -30,6 +32,12 @@ pub enum TaskState {
 
     /// Decomposed into child tasks (task completed its role).
     Decomposed,
+
+    /// Cancelled together with its job (never runs again).
+    Cancelled,
+
+    /// Cannot proceed without outside help; waits for `InMemoryQueue::unblock`.
+    Blocked,
 }
 
 impl TaskState {
@@ -37,7 +45,7 @@ impl TaskState {
     pub fn is_terminal(self) -> bool {
         matches!(
             self,
-            TaskState::Succeeded | TaskState::Decomposed | TaskState::Dead
+            TaskState::Succeeded | TaskState::Decomposed | TaskState::Dead | TaskState::Cancelled
         )
     }
 
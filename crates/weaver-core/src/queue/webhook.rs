@@ -0,0 +1,516 @@
+//! Result webhooks: signed POSTs of final job/task states, with retry.
+//!
+//! The queue collects deliveries while it holds its lock and hands them to
+//! `WebhookNotifier` after releasing it (ADR-0003); each delivery runs on its
+//! own tokio task so a slow receiver never blocks a worker.
+//!
+//! # Request format
+//! - Body: `CallbackPayload` as JSON.
+//! - `X-Weaver-Delivery`: unique per delivery, identical across its retries
+//!   (receivers dedupe on it).
+//! - `X-Weaver-Attempt`: 1 for the first try.
+//! - `X-Weaver-Timestamp`: unix seconds at send time.
+//! - `X-Weaver-Signature` (signed webhooks only):
+//!   `sha256=<hex HMAC-SHA256(secret, "{timestamp}.{body}")>`.
+//!   Receivers check it with `verify_signature()` and should reject stale timestamps.
+//!
+//! Per-job callbacks (`Callback::webhook`) go to the URL the submitter chose.
+//! For operator alerting, `WebhookEventSink` sends every dead task and
+//! finished job to fixed URLs instead, from the queue's event stream.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::{Jitter, RetryPolicy, TaskState};
+use crate::domain::{CallbackPayload, DomainEvent};
+use crate::ports::{EventSink, EventSinkError, HttpClient, HttpRequest, NoopEventSink};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// One webhook to send.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookDelivery {
+    pub url: String,
+    pub secret: Option<String>,
+    pub payload: CallbackPayload,
+}
+
+/// Why a delivery attempt failed.
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    /// The request never got a response (connection refused, timeout, ...).
+    #[error("Webhook transport failed: {0}")]
+    Transport(String),
+
+    /// The receiver answered with a non-2xx status.
+    #[error("Webhook rejected with HTTP {0}")]
+    Rejected(u16),
+}
+
+/// Sends result webhooks with signing and retry.
+///
+/// Requests go out through the `HttpClient` port. Attach the notifier to a
+/// queue with `InMemoryQueue::with_webhooks()`:
+///
+/// ```
+/// # #![allow(deprecated)]
+/// use std::sync::Arc;
+///
+/// use weaver_core::ports::{EventSink, HttpClient};
+/// use weaver_core::queue::{InMemoryQueue, RetryPolicy, WebhookNotifier};
+///
+/// fn queue_with_webhooks(client: Arc<dyn HttpClient>, sink: Arc<dyn EventSink>) -> InMemoryQueue {
+///     let notifier = WebhookNotifier::new(client)
+///         .with_max_attempts(8)
+///         .with_event_sink(sink);
+///     InMemoryQueue::new(RetryPolicy::default_v1()).with_webhooks(Arc::new(notifier))
+/// }
+/// ```
+pub struct WebhookNotifier {
+    client: Arc<dyn HttpClient>,
+    retry_policy: RetryPolicy,
+    max_attempts: u32,
+    event_sink: Arc<dyn EventSink>,
+}
+
+impl WebhookNotifier {
+    /// Defaults: 5 attempts, 1s exponential backoff with full jitter, capped at 1 minute.
+    pub fn new(client: Arc<dyn HttpClient>) -> Self {
+        Self {
+            client,
+            retry_policy: RetryPolicy {
+                base_delay: Duration::from_secs(1),
+                ..RetryPolicy::default_v1()
+            }
+            .with_jitter(Jitter::Full)
+            .with_max_delay(Duration::from_secs(60)),
+            max_attempts: 5,
+            event_sink: Arc::new(NoopEventSink),
+        }
+    }
+
+    /// Backoff between attempts.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Give up after this many attempts (at least 1).
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Report deliveries that were given up on (`DomainEvent::WebhookDeliveryFailed`).
+    pub fn with_event_sink(mut self, event_sink: Arc<dyn EventSink>) -> Self {
+        self.event_sink = event_sink;
+        self
+    }
+
+    /// Send `delivery`, retrying transport errors, 408, 429 and 5xx responses.
+    ///
+    /// Returns the number of attempts it took.
+    pub async fn deliver(&self, delivery: &WebhookDelivery) -> Result<u32, (u32, WebhookError)> {
+        let body = serde_json::to_vec(&delivery.payload).expect("CallbackPayload serializes");
+        let delivery_id = ulid::Ulid::new().to_string();
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let timestamp = chrono::Utc::now().timestamp();
+            let request = build_request(delivery, &delivery_id, attempt, timestamp, &body);
+            let error = match self.client.send(&request).await {
+                Ok(response) if (200..300).contains(&response.status) => return Ok(attempt),
+                Ok(response) => WebhookError::Rejected(response.status),
+                Err(e) => WebhookError::Transport(e.to_string()),
+            };
+            if attempt >= self.max_attempts || !is_retryable(&error) {
+                return Err((attempt, error));
+            }
+            tokio::time::sleep(self.retry_policy.next_delay(attempt)).await;
+        }
+    }
+
+    /// `deliver()`, reporting a failed delivery to the event sink.
+    pub(crate) async fn deliver_or_report(&self, delivery: WebhookDelivery) {
+        let Err((attempts, error)) = self.deliver(&delivery).await else {
+            return;
+        };
+        eprintln!("[webhook] delivery to {} failed: {error}", delivery.url);
+        let event = DomainEvent::WebhookDeliveryFailed {
+            url: delivery.url,
+            attempts,
+            message: error.to_string(),
+        };
+        if let Err(e) = self.event_sink.emit(event).await {
+            eprintln!("[webhook] event emit failed: {e}");
+        }
+    }
+}
+
+/// Posts terminal task/job states from the event stream to fixed URLs.
+///
+/// A task going Dead (`DomainEvent::TaskDead`) and a job finishing, failed or
+/// not (`DomainEvent::JobCompleted`), are sent as `CallbackPayload`s in the
+/// same format as per-job callbacks; other events are ignored. Each delivery
+/// runs on its own tokio task with the notifier's retry, so `emit()` returns
+/// right away.
+///
+/// ```
+/// # #![allow(deprecated)]
+/// use std::sync::Arc;
+///
+/// use weaver_core::ports::HttpClient;
+/// use weaver_core::queue::{InMemoryQueue, RetryPolicy, WebhookEventSink, WebhookNotifier};
+///
+/// fn queue_with_alerts(client: Arc<dyn HttpClient>) -> InMemoryQueue {
+///     let notifier = Arc::new(WebhookNotifier::new(client));
+///     let alerts = WebhookEventSink::new(notifier)
+///         .with_url("https://ops.example.com/weaver")
+///         .with_signed_url("https://pager.example.com/hook", "s3cret");
+///     InMemoryQueue::new(RetryPolicy::default_v1()).with_event_sink(Arc::new(alerts))
+/// }
+/// ```
+pub struct WebhookEventSink {
+    notifier: Arc<WebhookNotifier>,
+    targets: Vec<(String, Option<String>)>,
+}
+
+impl WebhookEventSink {
+    /// No URLs yet; add them with `with_url` / `with_signed_url`.
+    pub fn new(notifier: Arc<WebhookNotifier>) -> Self {
+        Self {
+            notifier,
+            targets: Vec::new(),
+        }
+    }
+
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.targets.push((url.into(), None));
+        self
+    }
+
+    /// Deliveries to `url` carry `X-Weaver-Signature` (see `signature()`).
+    pub fn with_signed_url(mut self, url: impl Into<String>, secret: impl Into<String>) -> Self {
+        self.targets.push((url.into(), Some(secret.into())));
+        self
+    }
+
+    fn payload(event: &DomainEvent) -> Option<CallbackPayload> {
+        match event {
+            DomainEvent::TaskDead {
+                task_id,
+                task_type,
+                job_id,
+                error,
+            } => Some(CallbackPayload::Task {
+                task_id: *task_id,
+                job_id: *job_id,
+                task_type: task_type.clone(),
+                state: TaskState::Dead,
+                outcome: None,
+                error: Some(error.clone()),
+            }),
+            DomainEvent::JobCompleted {
+                job_id,
+                state,
+                succeeded_tasks,
+                failed_tasks,
+                cancelled_tasks,
+            } => Some(CallbackPayload::Job {
+                job_id: *job_id,
+                state: *state,
+                succeeded_tasks: *succeeded_tasks,
+                failed_tasks: *failed_tasks,
+                cancelled_tasks: *cancelled_tasks,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookEventSink {
+    async fn emit(&self, event: DomainEvent) -> Result<(), EventSinkError> {
+        let Some(payload) = Self::payload(&event) else {
+            return Ok(());
+        };
+        for (url, secret) in &self.targets {
+            let delivery = WebhookDelivery {
+                url: url.clone(),
+                secret: secret.clone(),
+                payload: payload.clone(),
+            };
+            let notifier = self.notifier.clone();
+            tokio::spawn(async move { notifier.deliver_or_report(delivery).await });
+        }
+        Ok(())
+    }
+}
+
+fn mac(secret: &str, timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC takes any key length");
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(body);
+    mac
+}
+
+/// Value of the `X-Weaver-Signature` header.
+pub fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let digest = mac(secret, timestamp, body).finalize().into_bytes();
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+/// Whether `header` is the `X-Weaver-Signature` for this timestamp and body.
+///
+/// The digests are compared in constant time, so a receiver using this does
+/// not leak how much of a forged signature was right.
+pub fn verify_signature(secret: &str, timestamp: i64, body: &[u8], header: &str) -> bool {
+    let Some(hex) = header.strip_prefix("sha256=") else {
+        return false;
+    };
+    if hex.len() != 64 || !hex.is_ascii() {
+        return false;
+    }
+    let digest: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect();
+    digest.is_some_and(|digest| mac(secret, timestamp, body).verify_slice(&digest).is_ok())
+}
+
+fn build_request(
+    delivery: &WebhookDelivery,
+    delivery_id: &str,
+    attempt: u32,
+    timestamp: i64,
+    body: &[u8],
+) -> HttpRequest {
+    let mut headers = vec![
+        ("Content-Type".to_string(), "application/json".to_string()),
+        ("X-Weaver-Delivery".to_string(), delivery_id.to_string()),
+        ("X-Weaver-Attempt".to_string(), attempt.to_string()),
+        ("X-Weaver-Timestamp".to_string(), timestamp.to_string()),
+    ];
+    if let Some(secret) = &delivery.secret {
+        headers.push((
+            "X-Weaver-Signature".to_string(),
+            signature(secret, timestamp, body),
+        ));
+    }
+    HttpRequest {
+        method: "POST".to_string(),
+        url: delivery.url.clone(),
+        headers,
+        body: body.to_vec(),
+    }
+}
+
+/// Other 4xx responses mean the receiver will never accept this payload.
+fn is_retryable(error: &WebhookError) -> bool {
+    match error {
+        WebhookError::Transport(_) => true,
+        WebhookError::Rejected(status) => matches!(status, 408 | 429 | 500..),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::domain::{JobId, JobStateView, TaskId, TaskType};
+    use crate::ports::{HttpError, HttpResponse};
+
+    /// Answers with the scripted statuses, then 200.
+    struct ScriptedTransport {
+        statuses: Mutex<Vec<u16>>,
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl ScriptedTransport {
+        fn new(mut statuses: Vec<u16>) -> Self {
+            statuses.reverse();
+            Self {
+                statuses: Mutex::new(statuses),
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl HttpClient for ScriptedTransport {
+        async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+            self.requests.lock().unwrap().push(request.clone());
+            Ok(HttpResponse {
+                status: self.statuses.lock().unwrap().pop().unwrap_or(200),
+                headers: Vec::new(),
+                body: Vec::new(),
+            })
+        }
+    }
+
+    fn delivery(secret: Option<&str>) -> WebhookDelivery {
+        WebhookDelivery {
+            url: "https://example.com/hook".to_string(),
+            secret: secret.map(str::to_string),
+            payload: CallbackPayload::Job {
+                job_id: JobId::new(1),
+                state: JobStateView::Completed,
+                succeeded_tasks: 1,
+                failed_tasks: 0,
+                cancelled_tasks: 0,
+            },
+        }
+    }
+
+    fn notifier(transport: Arc<ScriptedTransport>) -> WebhookNotifier {
+        WebhookNotifier::new(transport).with_retry_policy(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default_v1()
+        })
+    }
+
+    #[test]
+    fn signatures_verify_only_for_the_same_secret_timestamp_and_body() {
+        let header = signature("s3cret", 1_700_000_000, b"{}");
+        assert!(verify_signature("s3cret", 1_700_000_000, b"{}", &header));
+        assert!(!verify_signature("other", 1_700_000_000, b"{}", &header));
+        assert!(!verify_signature("s3cret", 1_700_000_001, b"{}", &header));
+        assert!(!verify_signature("s3cret", 1_700_000_000, b"{ }", &header));
+        assert!(!verify_signature(
+            "s3cret",
+            1_700_000_000,
+            b"{}",
+            &header[..70]
+        ));
+        assert!(!verify_signature(
+            "s3cret",
+            1_700_000_000,
+            b"{}",
+            &header[7..]
+        ));
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_with_the_same_delivery_id() {
+        let transport = Arc::new(ScriptedTransport::new(vec![503, 500]));
+        let attempts = notifier(transport.clone())
+            .deliver(&delivery(Some("s3cret")))
+            .await
+            .unwrap();
+        assert_eq!(attempts, 3);
+
+        let requests = transport.requests.lock().unwrap();
+        let ids: Vec<_> = requests
+            .iter()
+            .map(|r| r.header("x-weaver-delivery"))
+            .collect();
+        assert!(ids.iter().all(|id| *id == ids[0]));
+        assert_eq!(requests[2].header("X-Weaver-Attempt"), Some("3"));
+
+        // The receiver can verify the signature from the timestamp and body
+        let last = &requests[2];
+        let timestamp: i64 = last.header("X-Weaver-Timestamp").unwrap().parse().unwrap();
+        assert_eq!(last.method, "POST");
+        assert!(verify_signature(
+            "s3cret",
+            timestamp,
+            &last.body,
+            last.header("X-Weaver-Signature").unwrap()
+        ));
+    }
+
+    #[tokio::test]
+    async fn event_sink_posts_dead_tasks_and_finished_jobs() {
+        let transport = Arc::new(ScriptedTransport::new(vec![503]));
+        let sink = WebhookEventSink::new(Arc::new(notifier(transport.clone())))
+            .with_signed_url("https://example.com/alerts", "s3cret");
+
+        sink.emit(DomainEvent::TaskDead {
+            task_id: TaskId::new(3),
+            task_type: TaskType::new("email"),
+            job_id: None,
+            error: "smtp timeout".to_string(),
+        })
+        .await
+        .unwrap();
+        sink.emit(DomainEvent::TaskStarted {
+            task_id: TaskId::new(4),
+            task_type: TaskType::new("email"),
+            attempt: 1,
+        })
+        .await
+        .unwrap();
+        sink.emit(DomainEvent::JobCompleted {
+            job_id: JobId::new(1),
+            state: JobStateView::Failed,
+            succeeded_tasks: 1,
+            failed_tasks: 1,
+            cancelled_tasks: 0,
+        })
+        .await
+        .unwrap();
+
+        // Two deliveries, one of which is retried once after the 503
+        let delivered = async {
+            while transport.requests.lock().unwrap().len() < 3 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), delivered)
+            .await
+            .unwrap();
+
+        let requests = transport.requests.lock().unwrap();
+        let mut subjects: Vec<serde_json::Value> = requests
+            .iter()
+            .filter(|r| r.header("X-Weaver-Attempt") == Some("1"))
+            .map(|r| serde_json::from_slice(&r.body).unwrap())
+            .collect();
+        subjects.sort_by_key(|body| body["subject"].to_string());
+        assert_eq!(subjects[0]["subject"], "job");
+        assert_eq!(subjects[0]["state"], "failed");
+        assert_eq!(subjects[1]["subject"], "task");
+        assert_eq!(subjects[1]["error"], "smtp timeout");
+        assert!(
+            requests
+                .iter()
+                .all(|r| r.url == "https://example.com/alerts")
+        );
+        assert!(
+            requests
+                .iter()
+                .all(|r| r.header("X-Weaver-Signature").is_some())
+        );
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let transport = Arc::new(ScriptedTransport::new(vec![404]));
+        let (attempts, error) = notifier(transport.clone())
+            .deliver(&delivery(None))
+            .await
+            .unwrap_err();
+        assert_eq!(attempts, 1);
+        assert!(matches!(error, WebhookError::Rejected(404)));
+        assert_eq!(
+            transport.requests.lock().unwrap()[0].header("X-Weaver-Signature"),
+            None
+        );
+
+        let transport = Arc::new(ScriptedTransport::new(vec![429, 503, 503]));
+        let result = notifier(transport)
+            .with_max_attempts(3)
+            .deliver(&delivery(None))
+            .await;
+        assert!(matches!(result, Err((3, WebhookError::Rejected(503)))));
+    }
+}
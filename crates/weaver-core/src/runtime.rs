@@ -1,10 +1,18 @@
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
 
-use crate::domain::{Outcome, TaskEnvelope, TaskType};
+use crate::domain::{JobId, Outcome, TaskEnvelope, TaskId, TaskType, TraceContext};
 use crate::error::WeaverError;
+use crate::observability::TaskLogger;
+use crate::ports::{DistributedLock, LockError};
 
 /// A handler for a specific task type.
 ///
@@ -23,8 +31,506 @@ use crate::error::WeaverError;
 #[async_trait]
 pub trait TaskHandler: Send + Sync {
     async fn handle(&self, envelope: &TaskEnvelope) -> Result<Outcome, WeaverError>;
+
+    /// Like `handle`, with access to the task's `TaskContext`.
+    ///
+    /// Override this instead of `handle` to read upstream results; the
+    /// default ignores the context.
+    async fn handle_with_context(
+        &self,
+        envelope: &TaskEnvelope,
+        _ctx: &TaskContext,
+    ) -> Result<Outcome, WeaverError> {
+        self.handle(envelope).await
+    }
+}
+
+/// What a handler can see besides its own envelope.
+///
+/// Built by the worker from the lease (see `TaskLease::task_context`).
+///
+/// ```ignore
+/// async fn handle_with_context(&self, envelope: &TaskEnvelope, ctx: &TaskContext)
+///     -> Result<Outcome, WeaverError>
+/// {
+///     if ctx.is_last_attempt() {
+///         ctx.log("last attempt, skipping the optional enrichment");
+///     }
+///     tokio::select! {
+///         outcome = self.render(envelope) => outcome,
+///         _ = ctx.cancellation().cancelled() => Ok(Outcome::failure("job cancelled")),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TaskContext {
+    dependency_outcomes: HashMap<String, Outcome>,
+    task_id: Option<TaskId>,
+
+    /// 1-based; 0 when the queue keeps no attempt history.
+    attempt: u32,
+    max_attempts: u32,
+    job_id: Option<JobId>,
+    deadline: Option<Instant>,
+    cancellation: CancellationToken,
+
+    /// Span of this attempt (a child of the task's submission span).
+    trace: Option<TraceContext>,
+
+    /// Where this attempt's log lines are kept (see `TaskContext::logger`).
+    logger: Option<TaskLogger>,
+
+    /// Static configuration for this task type (see `ContextValues`).
+    values: HashMap<String, String>,
+    locks: Option<AttemptLocks>,
+    intents: Option<AttemptIntents>,
+    extensions: Extensions,
+}
+
+/// Values attached to a `TaskContext` by `HandlerLayer`s, one per type.
+#[derive(Clone, Default)]
+struct Extensions(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Extensions({})", self.0.len())
+    }
+}
+
+/// Locks taken through a `TaskContext`; all released when the attempt ends.
+#[derive(Clone)]
+struct AttemptLocks {
+    lock: Arc<dyn DistributedLock>,
+
+    /// Unique per attempt, so a retry never inherits a stale lock.
+    owner: String,
+    held: Arc<Mutex<Vec<String>>>,
+}
+
+impl fmt::Debug for AttemptLocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttemptLocks")
+            .field("owner", &self.owner)
+            .field("held", &self.held)
+            .finish()
+    }
+}
+
+/// Where `TaskContext::record_intent` writes, plus what earlier attempts left.
+#[derive(Clone)]
+struct AttemptIntents {
+    log: Arc<dyn IntentLog>,
+    unresolved: Vec<Intent>,
+}
+
+impl fmt::Debug for AttemptIntents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttemptIntents")
+            .field("unresolved", &self.unresolved)
+            .finish()
+    }
+}
+
+impl TaskContext {
+    pub fn new(dependency_outcomes: HashMap<String, Outcome>) -> Self {
+        Self {
+            dependency_outcomes,
+            ..Self::default()
+        }
+    }
+
+    /// Set the attempt being run: `attempt` of `max_attempts` (1-based).
+    pub fn with_attempt(mut self, task_id: TaskId, attempt: u32, max_attempts: u32) -> Self {
+        self.task_id = Some(task_id);
+        self.attempt = attempt;
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set the job the task belongs to and the job's deadline, if any.
+    pub fn with_job(mut self, job_id: JobId, deadline: Option<Instant>) -> Self {
+        self.job_id = Some(job_id);
+        self.deadline = deadline;
+        self
+    }
+
+    /// Replace the static values the handler reads through `value`.
+    pub fn with_values(mut self, values: HashMap<String, String>) -> Self {
+        self.values = values;
+        self
+    }
+
+    /// Set one static value (e.g. to override configuration in a test).
+    pub fn with_value(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    /// Static value configured for this task (see `ContextValues`).
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Use `cancellation` instead of a token nothing cancels.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Run the attempt in the span `trace` (set by the worker).
+    pub fn with_trace(mut self, trace: TraceContext) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Span of this attempt; send `traceparent()` on outgoing calls to
+    /// continue the trace downstream.
+    pub fn trace(&self) -> Option<TraceContext> {
+        self.trace
+    }
+
+    pub fn task_id(&self) -> Option<TaskId> {
+        self.task_id
+    }
+
+    /// Which attempt this is (1-based; 0 if unknown).
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Will a failure of this attempt be final?
+    pub fn is_last_attempt(&self) -> bool {
+        self.max_attempts > 0 && self.attempt >= self.max_attempts
+    }
+
+    pub fn job_id(&self) -> Option<JobId> {
+        self.job_id
+    }
+
+    /// When the job must be done by (from `Budget::deadline_ms`).
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Time left until the deadline (zero once it has passed).
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Cancelled when the task's job is cancelled.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// Keep this attempt's log lines in the queue's task log.
+    pub fn with_logger(mut self, logger: TaskLogger) -> Self {
+        self.logger = Some(logger);
+        self
+    }
+
+    /// Structured logger for this attempt; its lines are returned by
+    /// `InMemoryQueue::get_task_log`. None when the queue keeps no task log.
+    pub fn logger(&self) -> Option<&TaskLogger> {
+        self.logger.as_ref()
+    }
+
+    /// Log a line tagged with the task and attempt, like the worker's own logs.
+    ///
+    /// Also kept as an info line in the task log, if any.
+    pub fn log(&self, message: impl fmt::Display) {
+        if let Some(logger) = &self.logger {
+            logger.info(&message);
+        }
+        match self.task_id {
+            Some(task_id) => eprintln!(
+                "[{task_id} attempt {}/{}] {message}",
+                self.attempt, self.max_attempts
+            ),
+            None => eprintln!("[task] {message}"),
+        }
+    }
+
+    /// Attach `value` for handlers further down the chain (replaces a value
+    /// of the same type), e.g. an auth context injected by a `HandlerLayer`.
+    pub fn insert_extension<T: Any + Send + Sync>(&mut self, value: T) {
+        self.extensions.0.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// The value of type `T` attached with `insert_extension`.
+    pub fn extension<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.extensions
+            .0
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Let the handler take locks on `lock` as `owner` (one owner per attempt).
+    pub fn with_locks(mut self, lock: Arc<dyn DistributedLock>, owner: impl Into<String>) -> Self {
+        self.locks = Some(AttemptLocks {
+            lock,
+            owner: owner.into(),
+            held: Arc::new(Mutex::new(Vec::new())),
+        });
+        self
+    }
+
+    /// Let the handler record intents in `log`; `unresolved` are those left
+    /// by earlier attempts of the task.
+    pub fn with_intents(mut self, log: Arc<dyn IntentLog>, unresolved: Vec<Intent>) -> Self {
+        self.intents = Some(AttemptIntents { log, unresolved });
+        self
+    }
+
+    /// Intents recorded by earlier attempts and never resolved: their side
+    /// effects may or may not have happened, so check before redoing them.
+    pub fn unresolved_intents(&self) -> &[Intent] {
+        self.intents
+            .as_ref()
+            .map_or(&[], |intents| intents.unresolved.as_slice())
+    }
+
+    /// Record, before a side effect, that this attempt is about to do it.
+    ///
+    /// Two-phase protocol: `record_intent`, apply the effect, `resolve_intent`.
+    /// If the attempt dies in between, the retry finds the intent in
+    /// `unresolved_intents` and can reconcile (e.g. look up the payment by
+    /// the idempotency key stored in `detail`) instead of applying it twice.
+    /// Recording a key again replaces its detail.
+    pub async fn record_intent(
+        &self,
+        key: impl Into<String>,
+        detail: serde_json::Value,
+    ) -> Result<(), WeaverError> {
+        let (intents, task_id) = self.intent_log()?;
+        let intent = Intent {
+            key: key.into(),
+            detail,
+            attempt: self.attempt,
+            recorded_at: Utc::now(),
+        };
+        intents.log.record(task_id, intent).await
+    }
+
+    /// Mark the side effect recorded under `key` as applied (or reconciled).
+    pub async fn resolve_intent(&self, key: &str) -> Result<(), WeaverError> {
+        let (intents, task_id) = self.intent_log()?;
+        intents.log.resolve(task_id, key).await
+    }
+
+    fn intent_log(&self) -> Result<(&AttemptIntents, TaskId), WeaverError> {
+        match (&self.intents, self.task_id) {
+            (Some(intents), Some(task_id)) => Ok((intents, task_id)),
+            _ => Err(WeaverError::Other(
+                "intents are not supported by this queue".into(),
+            )),
+        }
+    }
+
+    /// Outcome of the declared dependency titled `task_name`.
+    ///
+    /// Job tasks are named by `TaskSpec::title` (the field name with
+    /// `typed_job!`). `None` if no dependency has that title.
+    pub fn dependency_outcome(&self, task_name: &str) -> Option<&Outcome> {
+        self.dependency_outcomes.get(task_name)
+    }
+
+    /// Lock the external `resource` for `ttl`, waiting up to `timeout` for it.
+    ///
+    /// The lock is released when the attempt ends (however it ends); `ttl`
+    /// only matters if the worker process dies first, so make it longer than
+    /// the handler may run.
+    pub async fn lock(
+        &self,
+        resource: &str,
+        ttl: Duration,
+        timeout: Duration,
+    ) -> Result<(), LockError> {
+        let locks = self.locks.as_ref().ok_or(LockError::NotConfigured)?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            if locks.lock.try_acquire(resource, &locks.owner, ttl).await? {
+                let mut held = locks.held.lock().unwrap();
+                if !held.iter().any(|r| r == resource) {
+                    held.push(resource.to_string());
+                }
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(LockError::Timeout(resource.to_string()));
+            }
+            tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Release every lock taken through this context (called by the worker).
+    pub async fn release_locks(&self) {
+        let Some(locks) = &self.locks else {
+            return;
+        };
+        let held = std::mem::take(&mut *locks.held.lock().unwrap());
+        for resource in held {
+            if let Err(e) = locks.lock.release(&resource, &locks.owner).await {
+                eprintln!("[runtime] releasing lock on {resource} failed: {e}");
+            }
+        }
+    }
+}
+
+/// Static key/values injected into every attempt's `TaskContext` (API base
+/// URLs, feature flags, ...), so handlers do not read the process
+/// environment and tests can set their own.
+///
+/// A value set for the task type wins over one set for its namespace (the
+/// first segment of the task type), which wins over a global value.
+///
+/// ```ignore
+/// let values = ContextValues::new()
+///     .with_value("api_base_url", "https://api.example.com")
+///     .with_namespace_value("acme", "api_base_url", "https://acme.example.com")
+///     .with_task_type_value(TaskType::new("acme.render.v1"), "use_gpu", "true");
+/// let config = WorkerGroupConfig { context_values: values, ..Default::default() };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ContextValues {
+    global: HashMap<String, String>,
+    namespaces: HashMap<String, HashMap<String, String>>,
+    task_types: HashMap<TaskType, HashMap<String, String>>,
+}
+
+impl ContextValues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `key` for every task type.
+    pub fn with_value(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.global.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set `key` for the task types in `namespace`.
+    pub fn with_namespace_value(
+        mut self,
+        namespace: impl Into<String>,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.namespaces
+            .entry(namespace.into())
+            .or_default()
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Set `key` for `task_type` only.
+    pub fn with_task_type_value(
+        mut self,
+        task_type: TaskType,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.task_types
+            .entry(task_type)
+            .or_default()
+            .insert(key.into(), value.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.global.is_empty() && self.namespaces.is_empty() && self.task_types.is_empty()
+    }
+
+    /// The values a task of `task_type` sees.
+    pub fn resolve(&self, task_type: &TaskType) -> HashMap<String, String> {
+        let mut values = self.global.clone();
+        if let Some(namespace) = task_type
+            .namespace()
+            .and_then(|namespace| self.namespaces.get(namespace))
+        {
+            values.extend(namespace.clone());
+        }
+        if let Some(task_type) = self.task_types.get(task_type) {
+            values.extend(task_type.clone());
+        }
+        values
+    }
+}
+
+/// A side effect an attempt announced before applying it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Intent {
+    pub key: String,
+    /// What the handler needs to reconcile (ids, idempotency keys, ...).
+    pub detail: serde_json::Value,
+    /// The attempt that recorded it.
+    pub attempt: u32,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Durable store of intents, per task (see `TaskContext::record_intent`).
+///
+/// Intents survive the attempt that recorded them until resolved or until
+/// the task succeeds.
+#[async_trait]
+pub trait IntentLog: Send + Sync {
+    async fn record(&self, task_id: TaskId, intent: Intent) -> Result<(), WeaverError>;
+    async fn resolve(&self, task_id: TaskId, key: &str) -> Result<(), WeaverError>;
+}
+
+/// Cooperative cancellation signal for a running handler.
+///
+/// Clones share the signal. Handlers poll `is_cancelled()` between steps or
+/// race their work against `cancelled()`; nothing is aborted forcibly.
+#[derive(Clone)]
+pub struct CancellationToken(Arc<watch::Sender<bool>>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self(Arc::new(watch::Sender::new(false)))
+    }
+
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Wait until the token is cancelled (returns at once if it already is).
+    pub async fn cancelled(&self) {
+        let mut cancelled_rx = self.0.subscribe();
+        // The sender lives as long as `self`, so this cannot fail
+        let _ = cancelled_rx.wait_for(|cancelled| *cancelled).await;
+    }
 }
 
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CancellationToken")
+            .field(&self.is_cancelled())
+            .finish()
+    }
+}
+
+/// How often `TaskContext::lock` retries a contended lock.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
 /// Registry of handlers (task_type -> handler).
 ///
 /// Design:
@@ -34,15 +540,32 @@ pub trait TaskHandler: Send + Sync {
 #[derive(Default)]
 pub struct HandlerRegistry {
     handlers: HashMap<TaskType, Arc<dyn TaskHandler>>,
+
+    /// Runs task types that have no handler of their own.
+    fallback: Option<Arc<dyn TaskHandler>>,
 }
 
 impl HandlerRegistry {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            fallback: None,
         }
     }
 
+    /// Route task types without a registered handler to `handler` (e.g. a
+    /// dead-letter, logging or forwarding handler) instead of failing them
+    /// with `HandlerNotFound`.
+    ///
+    /// The fallback reads the actual type from `envelope.task_type()`.
+    pub fn set_fallback(&mut self, handler: Arc<dyn TaskHandler>) {
+        self.fallback = Some(handler);
+    }
+
+    pub fn fallback(&self) -> Option<&Arc<dyn TaskHandler>> {
+        self.fallback.as_ref()
+    }
+
     /// Register a handler for a task type.
     ///
     /// If you want "last wins", change this to overwrite instead of error.
@@ -71,31 +594,230 @@ impl HandlerRegistry {
     }
 }
 
+/// Registry of handlers that can change while the runtime is running.
+///
+/// Lets a long-running process roll out a new handler version without a
+/// restart (see `Runtime::shared`). A lookup clones the handler's `Arc` and
+/// releases the lock before the handler runs (ADR-0003), so calls already in
+/// flight finish on the old handler while new calls get the new one.
+#[derive(Default)]
+pub struct SharedHandlerRegistry {
+    handlers: RwLock<HashMap<TaskType, Arc<dyn TaskHandler>>>,
+    fallback: RwLock<Option<Arc<dyn TaskHandler>>>,
+}
+
+impl SharedHandlerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a handler for a new task type (errors like `HandlerRegistry::register`).
+    pub fn register(
+        &self,
+        task_type: TaskType,
+        handler: Arc<dyn TaskHandler>,
+    ) -> Result<(), WeaverError> {
+        let mut handlers = self.handlers.write().unwrap();
+        if handlers.contains_key(&task_type) {
+            return Err(WeaverError::DuplicateHandler(task_type));
+        }
+        handlers.insert(task_type, handler);
+        Ok(())
+    }
+
+    /// Register `handler`, replacing the current one; returns the replaced handler.
+    pub fn register_or_replace(
+        &self,
+        task_type: TaskType,
+        handler: Arc<dyn TaskHandler>,
+    ) -> Option<Arc<dyn TaskHandler>> {
+        self.handlers.write().unwrap().insert(task_type, handler)
+    }
+
+    /// Remove the handler; tasks of this type then fail with `HandlerNotFound`.
+    pub fn unregister(&self, task_type: &TaskType) -> Option<Arc<dyn TaskHandler>> {
+        self.handlers.write().unwrap().remove(task_type)
+    }
+
+    pub fn get(&self, task_type: &TaskType) -> Option<Arc<dyn TaskHandler>> {
+        self.handlers.read().unwrap().get(task_type).cloned()
+    }
+
+    /// Like `HandlerRegistry::set_fallback`; returns the replaced fallback.
+    pub fn set_fallback(&self, handler: Arc<dyn TaskHandler>) -> Option<Arc<dyn TaskHandler>> {
+        self.fallback.write().unwrap().replace(handler)
+    }
+
+    /// Remove the fallback; unknown task types fail with `HandlerNotFound` again.
+    pub fn clear_fallback(&self) -> Option<Arc<dyn TaskHandler>> {
+        self.fallback.write().unwrap().take()
+    }
+
+    pub fn fallback(&self) -> Option<Arc<dyn TaskHandler>> {
+        self.fallback.read().unwrap().clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.handlers.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<HandlerRegistry> for SharedHandlerRegistry {
+    fn from(registry: HandlerRegistry) -> Self {
+        Self {
+            handlers: RwLock::new(registry.handlers),
+            fallback: RwLock::new(registry.fallback),
+        }
+    }
+}
+
+/// Where a `Runtime` looks its handlers up.
+enum Handlers {
+    /// Fixed before the runtime starts.
+    Fixed(Arc<HandlerRegistry>),
+    Shared(Arc<SharedHandlerRegistry>),
+}
+
+impl Handlers {
+    fn get(&self, task_type: &TaskType) -> Option<Arc<dyn TaskHandler>> {
+        match self {
+            Self::Fixed(registry) => registry.get(task_type).or(registry.fallback()).cloned(),
+            Self::Shared(registry) => registry.get(task_type).or_else(|| registry.fallback()),
+        }
+    }
+}
+
+/// Middleware around every handler call: logging, timing, payload
+/// validation, injecting values into the `TaskContext`, ...
+///
+/// A layer calls `next.run()` to continue the chain, possibly with a changed
+/// context, or returns without calling it to short-circuit the handler.
+///
+/// ```ignore
+/// struct Timing;
+///
+/// #[async_trait]
+/// impl HandlerLayer for Timing {
+///     async fn handle(
+///         &self,
+///         envelope: &TaskEnvelope,
+///         ctx: &TaskContext,
+///         next: Next<'_>,
+///     ) -> Result<Outcome, WeaverError> {
+///         let started = Instant::now();
+///         let result = next.run(envelope, ctx).await;
+///         eprintln!("{} took {:?}", envelope.task_type(), started.elapsed());
+///         result
+///     }
+/// }
+///
+/// let runtime = Runtime::new(registry).with_layer(Timing);
+/// ```
+#[async_trait]
+pub trait HandlerLayer: Send + Sync {
+    async fn handle(
+        &self,
+        envelope: &TaskEnvelope,
+        ctx: &TaskContext,
+        next: Next<'_>,
+    ) -> Result<Outcome, WeaverError>;
+}
+
+/// The rest of the chain: the remaining layers, then the handler.
+pub struct Next<'a> {
+    layers: &'a [Arc<dyn HandlerLayer>],
+    handler: &'a dyn TaskHandler,
+}
+
+impl Next<'_> {
+    pub async fn run(
+        self,
+        envelope: &TaskEnvelope,
+        ctx: &TaskContext,
+    ) -> Result<Outcome, WeaverError> {
+        match self.layers.split_first() {
+            Some((layer, layers)) => {
+                let next = Next {
+                    layers,
+                    handler: self.handler,
+                };
+                layer.handle(envelope, ctx, next).await
+            }
+            None => self.handler.handle_with_context(envelope, ctx).await,
+        }
+    }
+}
+
 /// Runtime executes a `TaskEnvelope` by dispatching to a registered handler.
 pub struct Runtime {
-    registry: Arc<HandlerRegistry>,
+    handlers: Handlers,
+
+    /// Outermost first.
+    layers: Vec<Arc<dyn HandlerLayer>>,
 }
 
 impl Runtime {
     pub fn new(registry: Arc<HandlerRegistry>) -> Self {
-        Self { registry }
+        Self {
+            handlers: Handlers::Fixed(registry),
+            layers: Vec::new(),
+        }
+    }
+
+    /// A runtime whose handlers can be replaced or removed while it runs.
+    pub fn shared(registry: Arc<SharedHandlerRegistry>) -> Self {
+        Self {
+            handlers: Handlers::Shared(registry),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Wrap every handler call in `layer`.
+    ///
+    /// Layers run in the order they are added (the first added is the
+    /// outermost). They only run for task types that have a handler.
+    pub fn with_layer(mut self, layer: impl HandlerLayer + 'static) -> Self {
+        self.layers.push(Arc::new(layer));
+        self
     }
 
-    pub fn registry(&self) -> &HandlerRegistry {
-        &self.registry
+    /// The fixed registry (`None` for a runtime built with `Runtime::shared`).
+    pub fn registry(&self) -> Option<&HandlerRegistry> {
+        match &self.handlers {
+            Handlers::Fixed(registry) => Some(registry),
+            Handlers::Shared(_) => None,
+        }
     }
 
     /// Execute one envelope.
     ///
     /// Phase 4-1: Returns Outcome to support Handler → Outcome → Decider flow.
     pub async fn execute(&self, envelope: &TaskEnvelope) -> Result<Outcome, WeaverError> {
+        self.execute_with_context(envelope, &TaskContext::default())
+            .await
+    }
+
+    /// Execute one envelope, passing `ctx` to the handler.
+    pub async fn execute_with_context(
+        &self,
+        envelope: &TaskEnvelope,
+        ctx: &TaskContext,
+    ) -> Result<Outcome, WeaverError> {
         let task_type = envelope.task_type();
         let handler = self
-            .registry
+            .handlers
             .get(&task_type)
             .ok_or_else(|| WeaverError::HandlerNotFound(task_type.clone()))?;
 
-        handler.handle(envelope).await
+        let next = Next {
+            layers: &self.layers,
+            handler: handler.as_ref(),
+        };
+        next.run(envelope, ctx).await
     }
 }
 
@@ -135,4 +857,195 @@ mod tests {
         let msg = err.to_string();
         assert!(msg.contains("handler"));
     }
+
+    struct FailHandler;
+
+    #[async_trait]
+    impl TaskHandler for FailHandler {
+        async fn handle(&self, _envelope: &TaskEnvelope) -> Result<Outcome, WeaverError> {
+            Ok(Outcome::failure("v2"))
+        }
+    }
+
+    #[test]
+    fn context_values_prefer_task_type_over_namespace_over_global() {
+        let values = ContextValues::new()
+            .with_value("endpoint", "global")
+            .with_value("region", "eu")
+            .with_namespace_value("acme", "endpoint", "acme")
+            .with_task_type_value(TaskType::new("acme.render.v1"), "endpoint", "render");
+
+        let render = values.resolve(&TaskType::new("acme.render.v1"));
+        assert_eq!(render["endpoint"], "render");
+        assert_eq!(render["region"], "eu");
+        assert_eq!(
+            values.resolve(&TaskType::new("acme.sync.v1"))["endpoint"],
+            "acme"
+        );
+        assert_eq!(
+            values.resolve(&TaskType::new("plain"))["endpoint"],
+            "global"
+        );
+
+        let ctx = TaskContext::default()
+            .with_values(render)
+            .with_value("endpoint", "test");
+        assert_eq!(ctx.value("endpoint"), Some("test"));
+        assert_eq!(ctx.value("missing"), None);
+    }
+
+    #[tokio::test]
+    async fn fallback_runs_unknown_task_types() {
+        let mut reg = HandlerRegistry::new();
+        reg.register(TaskType::new("ok"), Arc::new(OkHandler))
+            .unwrap();
+        reg.set_fallback(Arc::new(FailHandler));
+        let rt = Runtime::new(Arc::new(reg));
+
+        let known = TaskEnvelope::new(TaskId::new(1), TaskType::new("ok"), serde_json::json!({}));
+        let unknown =
+            TaskEnvelope::new(TaskId::new(2), TaskType::new("nope"), serde_json::json!({}));
+        assert_eq!(
+            rt.execute(&known).await.unwrap().kind,
+            crate::domain::OutcomeKind::Success
+        );
+        assert_eq!(
+            rt.execute(&unknown).await.unwrap().kind,
+            crate::domain::OutcomeKind::Failure
+        );
+    }
+
+    #[tokio::test]
+    async fn shared_registry_handlers_change_while_running() {
+        let mut reg = HandlerRegistry::new();
+        reg.register(TaskType::new("ok"), Arc::new(OkHandler))
+            .unwrap();
+        let shared = Arc::new(SharedHandlerRegistry::from(reg));
+        let rt = Runtime::shared(shared.clone());
+        let env = TaskEnvelope::new(TaskId::new(1), TaskType::new("ok"), serde_json::json!({}));
+        assert_eq!(
+            rt.execute(&env).await.unwrap().kind,
+            crate::domain::OutcomeKind::Success
+        );
+
+        assert!(
+            shared
+                .register(TaskType::new("ok"), Arc::new(FailHandler))
+                .is_err()
+        );
+        assert!(
+            shared
+                .register_or_replace(TaskType::new("ok"), Arc::new(FailHandler))
+                .is_some()
+        );
+        assert_eq!(
+            rt.execute(&env).await.unwrap().kind,
+            crate::domain::OutcomeKind::Failure
+        );
+
+        assert!(shared.unregister(&TaskType::new("ok")).is_some());
+        assert!(shared.is_empty());
+        assert!(rt.execute(&env).await.is_err());
+    }
+
+    /// Records when the chain enters and leaves it.
+    struct Trace {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl HandlerLayer for Trace {
+        async fn handle(
+            &self,
+            envelope: &TaskEnvelope,
+            ctx: &TaskContext,
+            next: Next<'_>,
+        ) -> Result<Outcome, WeaverError> {
+            self.log.lock().unwrap().push(format!("{}>", self.name));
+            let result = next.run(envelope, ctx).await;
+            self.log.lock().unwrap().push(format!("<{}", self.name));
+            result
+        }
+    }
+
+    /// Rejects payloads without a `name`, and tells the handler who called.
+    struct Validate;
+
+    #[derive(Debug, PartialEq)]
+    struct Caller(&'static str);
+
+    #[async_trait]
+    impl HandlerLayer for Validate {
+        async fn handle(
+            &self,
+            envelope: &TaskEnvelope,
+            ctx: &TaskContext,
+            next: Next<'_>,
+        ) -> Result<Outcome, WeaverError> {
+            if envelope.payload().get("name").is_none() {
+                return Ok(Outcome::failure("payload needs a name"));
+            }
+            let mut ctx = ctx.clone();
+            ctx.insert_extension(Caller("ops"));
+            next.run(envelope, &ctx).await
+        }
+    }
+
+    struct CallerHandler;
+
+    #[async_trait]
+    impl TaskHandler for CallerHandler {
+        async fn handle(&self, _envelope: &TaskEnvelope) -> Result<Outcome, WeaverError> {
+            unreachable!("handle_with_context is overridden")
+        }
+
+        async fn handle_with_context(
+            &self,
+            _envelope: &TaskEnvelope,
+            ctx: &TaskContext,
+        ) -> Result<Outcome, WeaverError> {
+            assert_eq!(ctx.extension::<Caller>(), Some(&Caller("ops")));
+            Ok(Outcome::success())
+        }
+    }
+
+    #[tokio::test]
+    async fn layers_wrap_the_handler_in_order_and_may_short_circuit() {
+        let mut reg = HandlerRegistry::new();
+        reg.register(TaskType::new("greet"), Arc::new(CallerHandler))
+            .unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let trace = |name| Trace {
+            name,
+            log: log.clone(),
+        };
+        let rt = Runtime::new(Arc::new(reg))
+            .with_layer(trace("outer"))
+            .with_layer(Validate)
+            .with_layer(trace("inner"));
+
+        let env = TaskEnvelope::new(
+            TaskId::new(1),
+            TaskType::new("greet"),
+            serde_json::json!({ "name": "Weaver" }),
+        );
+        let outcome = rt.execute(&env).await.unwrap();
+        assert_eq!(outcome.kind, crate::domain::OutcomeKind::Success);
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["outer>", "inner>", "<inner", "<outer"]
+        );
+
+        // Invalid payload: the inner layers and the handler never run
+        log.lock().unwrap().clear();
+        let env = TaskEnvelope::new(
+            TaskId::new(2),
+            TaskType::new("greet"),
+            serde_json::json!({}),
+        );
+        let outcome = rt.execute(&env).await.unwrap();
+        assert_eq!(outcome.kind, crate::domain::OutcomeKind::Failure);
+        assert_eq!(*log.lock().unwrap(), ["outer>", "<outer"]);
+    }
 }
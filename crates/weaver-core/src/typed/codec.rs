@@ -5,6 +5,7 @@
 //! - Generic functions での型変換
 //! - エラーハンドリング（Result composition）
 
+use super::migration::PayloadMigrations;
 use super::task::Task;
 
 /// PayloadCodec は Task と serde_json::Value 間の変換を担当
@@ -38,11 +39,39 @@ impl PayloadCodec {
         Ok(value)
     }
 
+    /// Handler の戻り値を `Artifact::Output` に入れる値にする
+    pub fn encode_output<T: Task>(output: &T::Output) -> Result<serde_json::Value, CodecError> {
+        serde_json::to_value(output).map_err(|e| CodecError::SerializeFailed(e.to_string()))
+    }
+
+    /// `Artifact::Output` の値を `T::Output` に戻す
+    pub fn decode_output<T: Task>(output: serde_json::Value) -> Result<T::Output, CodecError> {
+        serde_json::from_value(output).map_err(|e| CodecError::DeserializeFailed(e.to_string()))
+    }
+
     pub fn decode<T: Task>(payload: serde_json::Value) -> Result<T, CodecError>{
         let task = serde_json::from_value::<T>(payload)
             .map_err(|e| CodecError::DeserializeFailed(e.to_string()))?;
         Ok(task)
     }
+
+    /// `task_type` の payload を `T::TYPE` まで変換してから decode する
+    ///
+    /// キューに残っている古い版の envelope を新しい版の型で読むため。
+    pub fn decode_migrated<T: Task>(
+        task_type: &str,
+        payload: serde_json::Value,
+        migrations: &PayloadMigrations,
+    ) -> Result<T, CodecError> {
+        let (migrated_type, payload) = migrations.migrate(task_type, payload)?;
+        if migrated_type != T::TYPE {
+            return Err(CodecError::DeserializeFailed(format!(
+                "{task_type} cannot be migrated to {}",
+                T::TYPE
+            )));
+        }
+        Self::decode(payload)
+    }
 }
 
 #[cfg(test)]
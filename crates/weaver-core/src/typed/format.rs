@@ -0,0 +1,253 @@
+//! PayloadFormat - payload のバイト表現（JSON / MessagePack / CBOR）
+//!
+//! # 学習ポイント
+//! - Task ⟷ `serde_json::Value` は PayloadCodec、Value ⟷ bytes は PayloadFormat の二段
+//! - 形式は envelope の `content_type` で宣言する。JSON 以外の payload は
+//!   bytes を base64 にした文字列として envelope に入る（store は JSON のまま扱える）
+//! - 受け取り側は `PayloadFormats::decode_payload` で宣言どおりに Value に戻してから
+//!   Handler に渡す
+//! - 大きな payload は `compress_payload` で圧縮できる（`ports::Compressor`）。
+//!   Compressor の名前を envelope の `content_encoding` に記録し、payload は
+//!   圧縮した bytes の base64 になる。読む側は同じ名前の Compressor を登録しておく
+//!
+//! # 実装
+//! - `JsonFormat`: 常に使える（content_type を付けない envelope はこれ）
+//! - `impls::MessagePackFormat`（feature `msgpack`）
+//! - `impls::CborFormat`（feature `cbor`）
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use super::codec::CodecError;
+use crate::domain::TaskEnvelope;
+use crate::ports::Compressor;
+
+/// JSON の content_type（envelope に content_type がなければこれ）
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// PayloadFormat は Value をバイト列にする形式
+pub trait PayloadFormat: Send + Sync {
+    /// envelope に記録する content_type（例: `application/msgpack`）
+    fn content_type(&self) -> &'static str;
+
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, CodecError>;
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, CodecError>;
+}
+
+/// JsonFormat は serde_json の形式
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFormat;
+
+impl PayloadFormat for JsonFormat {
+    fn content_type(&self) -> &'static str {
+        JSON_CONTENT_TYPE
+    }
+
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, CodecError> {
+        serde_json::to_vec(value).map_err(|e| CodecError::SerializeFailed(e.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, CodecError> {
+        serde_json::from_slice(bytes).map_err(|e| CodecError::DeserializeFailed(e.to_string()))
+    }
+}
+
+/// PayloadFormats は content_type ごとの PayloadFormat（と名前ごとの Compressor）
+///
+/// # 使用例
+/// ```ignore
+/// let mut formats = PayloadFormats::new();
+/// formats.register(MessagePackFormat);
+/// let payload = formats.decode_payload(lease.envelope())?;
+/// handler.handle_dyn(payload).await?;
+/// ```
+#[derive(Clone, Default)]
+pub struct PayloadFormats {
+    formats: HashMap<&'static str, Arc<dyn PayloadFormat>>,
+    compressors: HashMap<String, Arc<dyn Compressor>>,
+}
+
+impl PayloadFormats {
+    /// JSON だけを読める PayloadFormats
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `format` の content_type の payload を読めるようにする
+    pub fn register(&mut self, format: impl PayloadFormat + 'static) {
+        self.formats.insert(format.content_type(), Arc::new(format));
+    }
+
+    /// `compressor` の名前で圧縮された payload を読めるようにする
+    pub fn register_compressor(&mut self, compressor: impl Compressor + 'static) {
+        self.compressors
+            .insert(compressor.name().to_string(), Arc::new(compressor));
+    }
+
+    /// envelope の payload を宣言された形式から Value に戻す
+    pub fn decode_payload(&self, envelope: &TaskEnvelope) -> Result<serde_json::Value, CodecError> {
+        if envelope.has_json_payload() {
+            return Ok(envelope.payload().clone());
+        }
+        let content_type = envelope.content_type();
+        let encoded = envelope.payload().as_str().ok_or_else(|| {
+            CodecError::DeserializeFailed(format!("{content_type} payload must be base64"))
+        })?;
+        let mut bytes = base64_decode(encoded)?;
+        if let Some(encoding) = envelope.content_encoding() {
+            let compressor = self.compressors.get(encoding).ok_or_else(|| {
+                CodecError::DeserializeFailed(format!("no compressor for {encoding}"))
+            })?;
+            bytes = compressor
+                .decompress(&bytes)
+                .map_err(|e| CodecError::DeserializeFailed(e.to_string()))?;
+        }
+        if content_type == JSON_CONTENT_TYPE {
+            return JsonFormat.decode(&bytes);
+        }
+        let format = self.formats.get(content_type).ok_or_else(|| {
+            CodecError::DeserializeFailed(format!("no payload format for {content_type}"))
+        })?;
+        format.decode(&bytes)
+    }
+}
+
+/// `value` を `format` で envelope に入れる payload にする（JSON ならそのまま）
+pub fn encode_payload(
+    format: &dyn PayloadFormat,
+    value: serde_json::Value,
+) -> Result<serde_json::Value, CodecError> {
+    if format.content_type() == JSON_CONTENT_TYPE {
+        return Ok(value);
+    }
+    Ok(serde_json::Value::String(base64_encode(
+        &format.encode(&value)?,
+    )))
+}
+
+/// `envelope` の payload が `threshold` バイト以上なら `compressor` で圧縮する
+///
+/// 圧縮済み・小さい payload はそのまま返す。
+pub fn compress_payload(
+    envelope: TaskEnvelope,
+    compressor: &dyn Compressor,
+    threshold: usize,
+) -> Result<TaskEnvelope, CodecError> {
+    if envelope.content_encoding().is_some() {
+        return Ok(envelope);
+    }
+    let bytes = if envelope.has_json_payload() {
+        JsonFormat.encode(envelope.payload())?
+    } else {
+        let encoded = envelope.payload().as_str().ok_or_else(|| {
+            CodecError::SerializeFailed(format!(
+                "{} payload must be base64",
+                envelope.content_type()
+            ))
+        })?;
+        base64_decode(encoded)?
+    };
+    if bytes.len() < threshold {
+        return Ok(envelope);
+    }
+    let compressed = compressor
+        .compress(&bytes)
+        .map_err(|e| CodecError::SerializeFailed(e.to_string()))?;
+    let mut envelope = envelope.with_content_encoding(compressor.name());
+    *envelope.payload_mut() = serde_json::Value::String(base64_encode(&compressed));
+    Ok(envelope)
+}
+
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+pub(crate) fn base64_decode(s: &str) -> Result<Vec<u8>, CodecError> {
+    STANDARD
+        .decode(s)
+        .map_err(|e| CodecError::DeserializeFailed(format!("invalid base64 payload: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{TaskId, TaskType};
+
+    /// テスト用: JSON を bytes のまま運ぶ形式
+    struct RawJson;
+
+    impl PayloadFormat for RawJson {
+        fn content_type(&self) -> &'static str {
+            "application/x-raw-json"
+        }
+
+        fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, CodecError> {
+            JsonFormat.encode(value)
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, CodecError> {
+            JsonFormat.decode(bytes)
+        }
+    }
+
+    #[test]
+    fn payloads_are_decoded_by_their_declared_content_type() {
+        for bytes in [&b""[..], b"f", b"fo", b"foo", b"foob", b"\xff\x00\x80"] {
+            assert_eq!(base64_decode(&base64_encode(bytes)).unwrap(), bytes);
+        }
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+
+        let value = serde_json::json!({ "id": 7, "tags": ["a", "b"] });
+        let payload = encode_payload(&RawJson, value.clone()).unwrap();
+        assert!(payload.is_string());
+        let envelope = TaskEnvelope::new(TaskId::new(1), TaskType::new("test"), payload)
+            .with_content_type(RawJson.content_type());
+
+        let mut formats = PayloadFormats::new();
+        assert!(formats.decode_payload(&envelope).is_err());
+        formats.register(RawJson);
+        assert_eq!(formats.decode_payload(&envelope).unwrap(), value);
+    }
+
+    #[test]
+    fn large_payloads_are_compressed_and_named_in_the_envelope() {
+        use crate::ports::compressor::RunLength;
+
+        let value = serde_json::json!({ "pad": " ".repeat(1000) });
+        let envelope = TaskEnvelope::new(TaskId::new(1), TaskType::new("test"), value.clone());
+
+        let small = compress_payload(envelope.clone(), &RunLength, 4096).unwrap();
+        assert_eq!(small.payload(), &value);
+        assert_eq!(small.content_encoding(), None);
+
+        let compressed = compress_payload(envelope, &RunLength, 64).unwrap();
+        assert_eq!(compressed.content_encoding(), Some("rle"));
+        assert_eq!(compressed.content_type(), JSON_CONTENT_TYPE);
+        assert!(!compressed.has_json_payload());
+        assert!(compressed.payload().as_str().unwrap().len() < 100);
+
+        let mut formats = PayloadFormats::new();
+        let error = formats.decode_payload(&compressed).unwrap_err();
+        assert!(
+            error.to_string().contains("no compressor for rle"),
+            "{error}"
+        );
+        formats.register_compressor(RunLength);
+        assert_eq!(formats.decode_payload(&compressed).unwrap(), value);
+
+        // 別の形式の payload も、形式の bytes を圧縮する
+        let raw = TaskEnvelope::new(
+            TaskId::new(2),
+            TaskType::new("test"),
+            encode_payload(&RawJson, value.clone()).unwrap(),
+        )
+        .with_content_type(RawJson.content_type());
+        let compressed = compress_payload(raw, &RunLength, 64).unwrap();
+        formats.register(RawJson);
+        assert_eq!(formats.decode_payload(&compressed).unwrap(), value);
+    }
+}
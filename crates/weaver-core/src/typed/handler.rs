@@ -6,12 +6,15 @@
 //! - Type erasure パターン (TypedHandler<T, H> → DynHandler)
 
 use super::task::{Task, TestTask, AnotherTestTask};
+use super::codec::PayloadCodec;
 use crate::domain::errors::WeaverError;
-use crate::domain::outcome::Outcome;
+use crate::domain::outcome::{Artifact, Outcome};
 use async_trait::async_trait;
 use std::marker::PhantomData;
 
-/// Handler は Task を実行して Outcome を返す
+/// Handler は Task を実行して `T::Output` を返す
+///
+/// `Ok` は成功（結果は `Artifact::Output` として残る）、`Err` は失敗（Decider がリトライを決める）。
 ///
 /// # 使用例
 /// ```ignore
@@ -19,9 +22,9 @@ use std::marker::PhantomData;
 ///
 /// #[async_trait]
 /// impl Handler<MyTask> for MyTaskHandler {
-///     async fn handle(&self, task: MyTask) -> Result<Outcome, WeaverError> {
+///     async fn handle(&self, task: MyTask) -> Result<(), WeaverError> {
 ///         println!("Processing: {}", task.message);
-///         Ok(Outcome::success())
+///         Ok(())
 ///     }
 /// }
 /// ```
@@ -31,7 +34,7 @@ use std::marker::PhantomData;
 /// - コンパイル時に Task と Handler の対応が保証される
 #[async_trait]
 pub trait Handler<T: Task>: Send + Sync {
-    async fn handle(&self, task: T) -> Result<Outcome, WeaverError>;
+    async fn handle(&self, task: T) -> Result<T::Output, WeaverError>;
 }
 
 /// DynHandler は object-safe な Handler の抽象化
@@ -68,7 +71,10 @@ impl<T: Task, H: Handler<T>> DynHandler for TypedHandler<T, H> {
     async fn handle_dyn(&self, payload: serde_json::Value) -> Result<Outcome, WeaverError> {
         let task: T = serde_json::from_value(payload)
             .map_err(|e| WeaverError::new(format!("json decode: {e}")))?;
-        self.handler.handle(task).await
+        let output = self.handler.handle(task).await?;
+        let output = PayloadCodec::encode_output::<T>(&output)
+            .map_err(|e| WeaverError::new(format!("{}: {e}", T::TYPE)))?;
+        Ok(Outcome::success().with_artifact(Artifact::Output(output)))
     }
 
     fn task_type(&self) -> &str {
@@ -80,8 +86,8 @@ pub struct TestTaskHandler;
 
 #[async_trait]
 impl Handler<TestTask> for TestTaskHandler {
-    async fn handle(&self, _task: TestTask) -> Result<Outcome, WeaverError> {
-        Ok(Outcome::success())
+    async fn handle(&self, _task: TestTask) -> Result<(), WeaverError> {
+        Ok(())
     }
 }
 
@@ -89,8 +95,8 @@ pub struct AnotherTestTaskHandler;
 
 #[async_trait]
 impl Handler<AnotherTestTask> for AnotherTestTaskHandler {
-    async fn handle(&self, _task: AnotherTestTask) -> Result<Outcome, WeaverError> {
-        Ok(Outcome::success())
+    async fn handle(&self, _task: AnotherTestTask) -> Result<(), WeaverError> {
+        Ok(())
     }
 }
 
@@ -0,0 +1,302 @@
+//! Job trait - 型付き Job の定義
+//!
+//! # 学習ポイント
+//! - Associated Types (`type Tasks`)
+//! - PhantomData で型だけを持つハンドル（`TaskRef<T>`）
+//! - macro_rules! によるボイラープレート生成（`typed_job!`）
+//!
+//! # 流れ
+//! 1. struct のフィールド（それぞれ Task）と依存関係を宣言する
+//! 2. `Job::into_spec()` で JobSpec と `TaskRef` の組（`Tasks`）に変換する
+//! 3. 完了後、`JobReport` から `TaskRef` で各タスクの結果を取り出す
+//!
+//! task_type はフィールドの型から決まり、結果の取り出しはフィールド名で行うため、
+//! 文字列やインデックスの typo がコンパイルエラーになる。
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::codec::{CodecError, PayloadCodec};
+use super::task::Task;
+use crate::domain::{AttemptRecord, JobResult, JobSpec, Outcome, TaskId, TaskSpec, TaskType};
+
+/// TaskRef は Job 内のタスクを指す型付きハンドル
+///
+/// JobSpec.tasks の位置を持つ。`T` は取り出し時の型チェックにだけ使う。
+pub struct TaskRef<T> {
+    index: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> TaskRef<T> {
+    /// JobSpec.tasks 内の位置
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+// derive だと `T: Clone` などを要求してしまうため手で実装する
+impl<T> Clone for TaskRef<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TaskRef<T> {}
+
+impl<T> fmt::Debug for TaskRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TaskRef({})", self.index)
+    }
+}
+
+/// JobPlan は Job を組み立てる途中の JobSpec
+#[derive(Debug, Default)]
+pub struct JobPlan {
+    tasks: Vec<TaskSpec>,
+}
+
+impl JobPlan {
+    /// タスクを追加する（`after` のタスクが成功してから実行される）
+    ///
+    /// `after` は追加済みのタスクだけを指せるので、依存は必ず前方参照になる（循環しない）。
+    pub fn add<T: Task>(
+        &mut self,
+        title: &str,
+        task: T,
+        after: &[usize],
+    ) -> Result<TaskRef<T>, CodecError> {
+        let index = self.tasks.len();
+        let payload = PayloadCodec::encode(&task)?;
+        let mut spec = TaskSpec::new(title, TaskType::new(T::TYPE), payload);
+        if !after.is_empty() {
+            spec.dependencies_hint = Some(serde_json::json!(after));
+        }
+        self.tasks.push(spec);
+        Ok(TaskRef {
+            index,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn into_spec(self) -> JobSpec {
+        JobSpec::new(self.tasks)
+    }
+}
+
+/// Job は struct のフィールド（Task）と依存関係を JobSpec に対応付ける
+///
+/// 通常は `typed_job!` で実装する。
+///
+/// # 使用例
+/// ```ignore
+/// typed_job! {
+///     pub struct Deploy => DeployTasks {
+///         build: BuildTask,
+///         test: TestTask => [build],
+///         release: ReleaseTask => [build, test],
+///     }
+/// }
+///
+/// let (spec, tasks) = deploy.into_spec()?;
+/// let job_id = queue.submit_job(spec).await?;
+/// // ...
+/// let report = JobReport::new(queue.get_result(job_id).await?);
+/// let outcome = report.outcome(tasks.release);
+/// ```
+pub trait Job: Sized {
+    /// フィールドごとの `TaskRef` をまとめた型
+    type Tasks;
+
+    /// タスクを plan に追加する
+    fn plan(self, plan: &mut JobPlan) -> Result<Self::Tasks, CodecError>;
+
+    /// JobSpec と `Tasks` に変換する
+    fn into_spec(self) -> Result<(JobSpec, Self::Tasks), CodecError> {
+        let mut plan = JobPlan::default();
+        let tasks = self.plan(&mut plan)?;
+        Ok((plan.into_spec(), tasks))
+    }
+}
+
+/// Job struct と `Job` の実装、フィールドごとの `TaskRef` を持つ struct を生成する
+///
+/// - `field: Type` はタスク（`Type: Task`）
+/// - `=> [a, b]` はそのタスクが待つ先行フィールド（前に書いたものだけ）
+///
+/// 依存先の typo や後方参照はコンパイルエラーになる。
+#[macro_export]
+macro_rules! typed_job {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident => $tasks:ident {
+            $($field:ident : $ty:ty $(=> [$($dep:ident),* $(,)?])?),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(pub $field: $ty,)+
+        }
+
+        /// 各タスクの `TaskRef`（`JobReport` から結果を取り出すのに使う）
+        #[derive(Debug, Clone, Copy)]
+        $vis struct $tasks {
+            $(pub $field: $crate::typed::TaskRef<$ty>,)+
+        }
+
+        impl $crate::typed::Job for $name {
+            type Tasks = $tasks;
+
+            fn plan(
+                self,
+                plan: &mut $crate::typed::JobPlan,
+            ) -> ::std::result::Result<$tasks, $crate::typed::CodecError> {
+                $(
+                    let $field = plan.add(
+                        stringify!($field),
+                        self.$field,
+                        &[$($($dep.index()),*)?],
+                    )?;
+                )+
+                Ok($tasks { $($field),+ })
+            }
+        }
+    };
+}
+
+/// JobReport は完了した Job の結果を `TaskRef` で引けるようにしたもの
+#[derive(Debug, Clone)]
+pub struct JobReport {
+    result: JobResult,
+}
+
+impl JobReport {
+    pub fn new(result: JobResult) -> Self {
+        Self { result }
+    }
+
+    /// 元の JobResult
+    pub fn result(&self) -> &JobResult {
+        &self.result
+    }
+
+    /// タスクの TaskId（submit_job は JobSpec.tasks の順に TaskId を振る）
+    pub fn task_id<T>(&self, task: TaskRef<T>) -> Option<TaskId> {
+        self.result.task_ids.get(task.index).copied()
+    }
+
+    /// タスクの attempt（古い順）
+    pub fn attempts<T>(&self, task: TaskRef<T>) -> Vec<&AttemptRecord> {
+        let Some(task_id) = self.task_id(task) else {
+            return Vec::new();
+        };
+        let mut attempts: Vec<&AttemptRecord> = self
+            .result
+            .attempts
+            .iter()
+            .filter(|attempt| attempt.task_id == task_id)
+            .collect();
+        attempts.sort_by_key(|attempt| attempt.attempt_id);
+        attempts
+    }
+
+    /// タスクの最後の attempt の Outcome（まだ実行されていなければ `None`）
+    pub fn outcome<T>(&self, task: TaskRef<T>) -> Option<&Outcome> {
+        self.attempts(task).last().map(|attempt| &attempt.outcome)
+    }
+
+    /// タスクの入力（payload）を `T` として取り出す
+    pub fn input<T: Task>(&self, task: TaskRef<T>) -> Result<T, CodecError> {
+        let attempt = self.attempts(task).last().copied().ok_or_else(|| {
+            CodecError::DeserializeFailed(format!("task {} has no attempts", task.index))
+        })?;
+        PayloadCodec::decode(attempt.action.clone())
+    }
+
+    /// タスクの結果（Handler が返した `T::Output`）。最後の attempt が結果を残していなければ `None`
+    pub fn output<T: Task>(&self, task: TaskRef<T>) -> Result<Option<T::Output>, CodecError> {
+        self.outcome(task)
+            .and_then(Outcome::output)
+            .map(|output| PayloadCodec::decode_output::<T>(output.clone()))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::domain::{Decision, OutcomeKind};
+    use crate::queue::{InMemoryQueue, Queue, RetryPolicy};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Build {
+        commit: String,
+    }
+
+    impl Task for Build {
+        const TYPE: &'static str = "test.deploy.build.v1";
+        type Output = ();
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Release {
+        channel: String,
+    }
+
+    impl Task for Release {
+        const TYPE: &'static str = "test.deploy.release.v1";
+        type Output = ();
+    }
+
+    typed_job! {
+        struct Deploy => DeployTasks {
+            build: Build,
+            release: Release => [build],
+        }
+    }
+
+    #[tokio::test]
+    async fn typed_job_compiles_to_spec_and_reads_results_by_field() {
+        let deploy = Deploy {
+            build: Build {
+                commit: "abc123".to_string(),
+            },
+            release: Release {
+                channel: "stable".to_string(),
+            },
+        };
+        let (spec, tasks) = deploy.into_spec().unwrap();
+        assert_eq!(spec.tasks[0].task_type.as_str(), Build::TYPE);
+        assert_eq!(spec.tasks[0].title.as_deref(), Some("build"));
+        assert_eq!(
+            spec.tasks[1].dependencies_hint,
+            Some(serde_json::json!([0]))
+        );
+
+        let queue = InMemoryQueue::new(RetryPolicy::default_v1());
+        let job_id = queue.submit_job(spec).await.unwrap();
+        // release waits for build
+        let build = queue.lease().await.unwrap();
+        assert_eq!(build.envelope().task_type().as_str(), Build::TYPE);
+        build.ack().await.unwrap();
+        let release = queue.lease().await.unwrap();
+        assert_eq!(release.envelope().task_type().as_str(), Release::TYPE);
+        let decision = Decision::MarkDead {
+            reason: "no approval".to_string(),
+        };
+        release
+            .complete(Outcome::failure("no approval"), decision)
+            .await
+            .unwrap();
+
+        let report = JobReport::new(queue.get_result(job_id).await.unwrap());
+        assert_eq!(
+            report.outcome(tasks.release).map(|outcome| outcome.kind),
+            Some(OutcomeKind::Failure)
+        );
+        assert_eq!(report.input(tasks.release).unwrap().channel, "stable");
+        assert!(report.task_id(tasks.build).is_some());
+    }
+}
@@ -0,0 +1,151 @@
+//! PayloadMigrations - 古い版の payload を新しい版に書き換える
+//!
+//! # 学習ポイント
+//! - クロージャを `Arc<dyn Fn>` で持つ（登録後は共有するだけ）
+//! - 版の連鎖（v1 → v2 → v3）を辿る。循環は登録時に拒否する
+//!
+//! # 流れ
+//! 1. `orders.charge.v1` を `orders.charge.v2` に上げるとき、v1 → v2 の変換を登録する
+//! 2. キューに残っている v1 の envelope は、`TypedRegistry::get("orders.charge.v1")`
+//!    が v2 の Handler の前に変換を挟んで返す
+//! 3. Handler は v2 の型だけを知っていればよい
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::codec::CodecError;
+use super::handler::DynHandler;
+use super::registry::RegistryError;
+use crate::domain::errors::WeaverError;
+use crate::domain::outcome::Outcome;
+
+/// 1 段の変換（`from` の payload → `to` の payload）
+pub type MigrationFn =
+    dyn Fn(serde_json::Value) -> Result<serde_json::Value, CodecError> + Send + Sync;
+
+/// PayloadMigrations は task_type ごとの「次の版への変換」を持つ
+///
+/// # 使用例
+/// ```ignore
+/// registry.register_migration("orders.charge.v1", "orders.charge.v2", |mut payload| {
+///     payload["currency"] = json!("JPY");
+///     Ok(payload)
+/// })?;
+/// ```
+#[derive(Clone, Default)]
+pub struct PayloadMigrations {
+    /// from → (to, 変換)
+    steps: HashMap<String, (String, Arc<MigrationFn>)>,
+}
+
+impl fmt::Debug for PayloadMigrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.steps.iter().map(|(from, (to, _))| (from, to)))
+            .finish()
+    }
+}
+
+impl PayloadMigrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `from` の payload を `to` の payload に変換する段を登録する
+    ///
+    /// 1 つの `from` からの変換は 1 つだけ。連鎖が循環する登録は拒否する。
+    pub fn register(
+        &mut self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        migrate: impl Fn(serde_json::Value) -> Result<serde_json::Value, CodecError>
+        + Send
+        + Sync
+        + 'static,
+    ) -> Result<(), RegistryError> {
+        let (from, to) = (from.into(), to.into());
+        if self.steps.contains_key(&from) {
+            return Err(RegistryError::DuplicateMigration(from));
+        }
+        if from == to || self.chain(&to).any(|(next, _)| next == from) {
+            return Err(RegistryError::MigrationCycle(from));
+        }
+        self.steps.insert(from, (to, Arc::new(migrate)));
+        Ok(())
+    }
+
+    /// `task_type` から辿れる段（次の版, 変換）を順に
+    fn chain<'a>(
+        &'a self,
+        task_type: &str,
+    ) -> impl Iterator<Item = (&'a str, &'a Arc<MigrationFn>)> + use<'a> {
+        let mut next = self.steps.get(task_type);
+        std::iter::from_fn(move || {
+            let (to, migrate) = next?;
+            next = self.steps.get(to);
+            Some((to.as_str(), migrate))
+        })
+    }
+
+    /// `task_type` を最新まで上げたときの task_type（変換がなければ `None`）
+    pub fn latest(&self, task_type: &str) -> Option<&str> {
+        self.chain(task_type).last().map(|(to, _)| to)
+    }
+
+    /// `payload` を最新の版まで変換して、その task_type と一緒に返す
+    pub fn migrate(
+        &self,
+        task_type: &str,
+        mut payload: serde_json::Value,
+    ) -> Result<(String, serde_json::Value), CodecError> {
+        let mut current = task_type;
+        for (to, migrate) in self.chain(task_type) {
+            payload = migrate(payload)?;
+            current = to;
+        }
+        Ok((current.to_string(), payload))
+    }
+
+    /// `task_type` の payload を変換してから `inner` に渡す Handler
+    pub(crate) fn handler_for(
+        &self,
+        task_type: &str,
+        inner: Arc<dyn DynHandler>,
+    ) -> MigratingHandler {
+        MigratingHandler {
+            task_type: task_type.to_string(),
+            steps: self
+                .chain(task_type)
+                .map(|(to, migrate)| (to.to_string(), Arc::clone(migrate)))
+                .collect(),
+            inner,
+        }
+    }
+}
+
+/// 古い版の payload を変換してから新しい版の Handler に渡す
+pub(crate) struct MigratingHandler {
+    task_type: String,
+    steps: Vec<(String, Arc<MigrationFn>)>,
+    inner: Arc<dyn DynHandler>,
+}
+
+#[async_trait]
+impl DynHandler for MigratingHandler {
+    async fn handle_dyn(&self, mut payload: serde_json::Value) -> Result<Outcome, WeaverError> {
+        let mut from = self.task_type.as_str();
+        for (to, migrate) in &self.steps {
+            payload = migrate(payload)
+                .map_err(|e| WeaverError::new(format!("migrate {from} -> {to}: {e}")))?;
+            from = to;
+        }
+        self.inner.handle_dyn(payload).await
+    }
+
+    fn task_type(&self) -> &str {
+        &self.task_type
+    }
+}
@@ -6,14 +6,29 @@
 //! # 二層構造
 //! - **表層（Typed）**: `Task` trait, `Handler<T>` trait - 型安全
 //! - **内部（Dyn）**: `DynHandler` trait - object-safe, type erasure
+//!
+//! Job も同様に `Job` trait（`typed_job!` で生成）でタスクの組を型で表す。
 
 pub mod task;
 pub mod handler;
 pub mod registry;
 pub mod codec;
+pub mod job;
+pub mod migration;
+pub mod format;
 
 // 主要な trait/型 を再エクスポート
 pub use self::task::Task;
-pub use self::handler::{Handler, DynHandler};
+/// `#[derive(WeaverTask)]` と `#[task(type = "...")]` で `Task` を実装する
+pub use weaver_macros::WeaverTask;
+pub use self::handler::Handler;
+// 内部（Dyn）層: registry の実装詳細なのでドキュメントには出さない
+#[doc(hidden)]
+pub use self::handler::DynHandler;
 pub use self::registry::{TypedRegistry, RegistryError};
 pub use self::codec::{PayloadCodec, CodecError};
+pub use self::job::{Job, JobPlan, JobReport, TaskRef};
+pub use self::migration::PayloadMigrations;
+pub use self::format::{
+    JSON_CONTENT_TYPE, JsonFormat, PayloadFormat, PayloadFormats, compress_payload, encode_payload,
+};
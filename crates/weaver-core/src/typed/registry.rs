@@ -5,9 +5,15 @@
 //! - Generic methods での登録と型安全性
 //! - Arc による共有所有権
 
+use crate::domain::{TaskTypeError, TaskTypeV2};
 use crate::typed::handler::TypedHandler;
 
+use super::codec::CodecError;
+use super::format::{PayloadFormat, PayloadFormats};
+use crate::domain::TaskEnvelope;
+use crate::ports::Compressor;
 use super::handler::{DynHandler, Handler};
+use super::migration::PayloadMigrations;
 use super::task::Task;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -27,8 +33,18 @@ use std::sync::Arc;
 /// - `register::<T: Task>(handler: impl Handler<T>)` で登録
 /// - 内部的に TypedHandler でラップして DynHandler に変換
 /// - HashMap<String, Arc<dyn DynHandler>> で管理
+/// - clone は Handler の Arc を共有するだけなので安い（App が worker に配るときに使う）
+#[derive(Clone)]
 pub struct TypedRegistry {
     handlers: HashMap<String, Arc<dyn DynHandler>>,
+    /// 登録されていない task_type を受け取る Handler
+    fallback: Option<Arc<dyn DynHandler>>,
+    /// true なら命名規約に従わない task_type も登録できる
+    allow_nonconforming: bool,
+    /// 古い版の task_type の payload を新しい版に上げる変換
+    migrations: PayloadMigrations,
+    /// JSON 以外の payload の形式
+    formats: PayloadFormats,
 }
 
 /// RegistryError は TypedRegistry の操作エラー
@@ -36,13 +52,51 @@ pub struct TypedRegistry {
 pub enum RegistryError {
     #[error("Handler for task type '{0}' is already registered")]
     AlreadyRegistered(String),
+
+    #[error(transparent)]
+    InvalidTaskType(#[from] TaskTypeError),
+
+    #[error("A migration from task type '{0}' is already registered")]
+    DuplicateMigration(String),
+
+    #[error("Migrating from task type '{0}' would loop back to it")]
+    MigrationCycle(String),
 }
 
 impl TypedRegistry {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            fallback: None,
+            allow_nonconforming: false,
+            migrations: PayloadMigrations::new(),
+            formats: PayloadFormats::new(),
+        }
+    }
+
+    /// 命名規約（`TaskType::validate`）に従わない task_type の登録を許す
+    ///
+    /// 規約より前からある task_type を移行するまでの逃げ道。
+    pub fn allow_nonconforming_task_types(&mut self) {
+        self.allow_nonconforming = true;
+    }
+
+    fn check(&self, task_type: &str) -> Result<(), RegistryError> {
+        if self.handlers.contains_key(task_type) {
+            return Err(RegistryError::AlreadyRegistered(task_type.to_string()));
         }
+        if !self.allow_nonconforming {
+            TaskTypeV2::validate(task_type)?;
+        }
+        Ok(())
+    }
+
+    /// 登録されていない task_type を `handler` に回す（dead-letter・ログ・転送など）
+    ///
+    /// `get()` は登録済みの Handler がなければ fallback を返す。
+    /// 起動時検証（`registered_types()`）には含まれない。
+    pub fn set_fallback(&mut self, handler: impl DynHandler + 'static) {
+        self.fallback = Some(Arc::new(handler));
     }
 
     pub fn register<T: Task, H: Handler<T> + 'static>(
@@ -50,16 +104,75 @@ impl TypedRegistry {
         handler: H,
     ) -> Result<(), RegistryError> {
         let task_type = T::TYPE.to_string();
-        if self.handlers.contains_key(&task_type) {
-            return Err(RegistryError::AlreadyRegistered(task_type));
-        }
+        self.check(&task_type)?;
         let typed_handler = TypedHandler::new(handler);
         self.handlers.insert(task_type, Arc::new(typed_handler));
         Ok(())
     }
 
+    /// 型を持たない DynHandler を、それ自身の `task_type()` で登録する
+    ///
+    /// payload を Rust の型にしない handler（WASM モジュールなど）用。
+    pub fn register_dyn(
+        &mut self,
+        handler: impl DynHandler + 'static,
+    ) -> Result<(), RegistryError> {
+        let task_type = handler.task_type().to_string();
+        self.check(&task_type)?;
+        self.handlers.insert(task_type, Arc::new(handler));
+        Ok(())
+    }
+
+    /// 古い版 `from` の payload を `to` の payload に変換する段を登録する
+    ///
+    /// `from` の Handler がなければ、`get(from)` は変換を挟んで最新の版の Handler を返す。
+    /// 変換は連鎖できる（v1 → v2 → v3）。
+    pub fn register_migration(
+        &mut self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        migrate: impl Fn(serde_json::Value) -> Result<serde_json::Value, CodecError>
+        + Send
+        + Sync
+        + 'static,
+    ) -> Result<(), RegistryError> {
+        self.migrations.register(from, to, migrate)
+    }
+
+    /// 登録済みの変換（`PayloadCodec::decode_migrated` に渡す）
+    pub fn migrations(&self) -> &PayloadMigrations {
+        &self.migrations
+    }
+
+    /// `format` の content_type の payload を読めるようにする（JSON は常に読める）
+    pub fn register_format(&mut self, format: impl PayloadFormat + 'static) {
+        self.formats.register(format);
+    }
+
+    /// `compressor` で圧縮された payload を読めるようにする（`Runtime::with_compression` と組にする）
+    pub fn register_compressor(&mut self, compressor: impl Compressor + 'static) {
+        self.formats.register_compressor(compressor);
+    }
+
+    /// envelope の payload を宣言された形式から Value に戻す（`handle_dyn` に渡す前に）
+    pub fn decode_payload(&self, envelope: &TaskEnvelope) -> Result<serde_json::Value, CodecError> {
+        self.formats.decode_payload(envelope)
+    }
+
     pub fn get(&self, task_type: &str) -> Option<Arc<dyn DynHandler>> {
-        self.handlers.get(task_type).cloned()
+        if let Some(handler) = self.handlers.get(task_type) {
+            return Some(Arc::clone(handler));
+        }
+        let migrated = self
+            .migrations
+            .latest(task_type)
+            .and_then(|latest| self.handlers.get(latest));
+        match migrated {
+            Some(inner) => Some(Arc::new(
+                self.migrations.handler_for(task_type, Arc::clone(inner)),
+            )),
+            None => self.fallback.clone(),
+        }
     }
 
     pub fn registered_types(&self) -> Vec<String>{
@@ -118,4 +231,101 @@ mod tests {
         assert!(retrieved_test.is_some());
         assert!(retrieved_another.is_some());
     }
-}
\ No newline at end of file
+
+    struct DeadLetter;
+
+    #[async_trait::async_trait]
+    impl DynHandler for DeadLetter {
+        async fn handle_dyn(
+            &self,
+            _payload: serde_json::Value,
+        ) -> Result<crate::domain::Outcome, crate::domain::errors::WeaverError> {
+            Ok(crate::domain::Outcome::failure("no handler"))
+        }
+
+        fn task_type(&self) -> &str {
+            "*"
+        }
+    }
+
+    #[test]
+    fn test_fallback_for_unknown_task_type() {
+        let mut registry = TypedRegistry::new();
+        registry
+            .register::<TestTask, _>(TestTaskHandler {})
+            .unwrap();
+        assert!(registry.get("unknown.v1").is_none());
+
+        registry.set_fallback(DeadLetter);
+        assert_eq!(registry.get("unknown.v1").unwrap().task_type(), "*");
+        assert_eq!(
+            registry.get(TestTask::TYPE).unwrap().task_type(),
+            TestTask::TYPE
+        );
+        assert_eq!(
+            registry.registered_types(),
+            vec![TestTask::TYPE.to_string()]
+        );
+    }
+
+    struct Legacy;
+
+    #[async_trait::async_trait]
+    impl DynHandler for Legacy {
+        async fn handle_dyn(
+            &self,
+            _payload: serde_json::Value,
+        ) -> Result<crate::domain::Outcome, crate::domain::errors::WeaverError> {
+            Ok(crate::domain::Outcome::success())
+        }
+
+        fn task_type(&self) -> &str {
+            "send_email"
+        }
+    }
+
+    #[test]
+    fn nonconforming_task_types_are_rejected_unless_allowed() {
+        let mut registry = TypedRegistry::new();
+        assert!(matches!(
+            registry.register_dyn(Legacy),
+            Err(RegistryError::InvalidTaskType(_))
+        ));
+
+        registry.allow_nonconforming_task_types();
+        registry.register_dyn(Legacy).unwrap();
+        assert!(registry.get("send_email").is_some());
+    }
+
+    #[tokio::test]
+    async fn old_payloads_are_migrated_to_the_registered_version() {
+        use crate::typed::PayloadCodec;
+        use serde_json::json;
+
+        let mut registry = TypedRegistry::new();
+        registry
+            .register::<TestTask, _>(TestTaskHandler {})
+            .unwrap();
+        registry
+            .register_migration("test.task.create.v0", TestTask::TYPE, |payload| {
+                Ok(json!({ "value": payload["amount"] }))
+            })
+            .unwrap();
+
+        let handler = registry.get("test.task.create.v0").unwrap();
+        assert_eq!(handler.task_type(), "test.task.create.v0");
+        assert!(handler.handle_dyn(json!({ "amount": 7 })).await.is_ok());
+        let task: TestTask = PayloadCodec::decode_migrated(
+            "test.task.create.v0",
+            json!({ "amount": 7 }),
+            registry.migrations(),
+        )
+        .unwrap();
+        assert_eq!(task.value, 7);
+
+        assert!(matches!(
+            registry.register_migration(TestTask::TYPE, "test.task.create.v0", Ok),
+            Err(RegistryError::MigrationCycle(_))
+        ));
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! # 学習ポイント
 //! - Associated Constants (`const TYPE`)
+//! - Associated Types (`type Output`): Handler の戻り値の型
 //! - Trait bounds の組み合わせ (Serialize + DeserializeOwned + Send + Sync + 'static)
 
 use std::collections::HashMap;
@@ -20,6 +21,16 @@ use serde::{Deserialize, Serialize};
 ///
 /// impl Task for MyTask {
 ///     const TYPE: &'static str = "my_namespace.my_task.v1";
+///     type Output = ();
+/// }
+/// ```
+///
+/// 同じことを derive で書ける（task_type の命名規約はコンパイル時に検査される）:
+/// ```ignore
+/// #[derive(Serialize, Deserialize, WeaverTask)]
+/// #[task(type = "my_namespace.my_task.v1")]
+/// struct MyTask {
+///     message: String,
 /// }
 /// ```
 ///
@@ -35,6 +46,19 @@ pub trait Task: Serialize + DeserializeOwned + Send + Sync + 'static {
     /// - `{namespace}.{domain}.{action}.v{major}`
     /// - 例: `acme.billing.charge.v1`
     const TYPE: &'static str;
+
+    /// Handler が返す結果の型（なければ `()`）
+    ///
+    /// `Artifact::Output` として保存され、`Runtime::result::<T>()` で型付きで取り出せる。
+    type Output: Serialize + DeserializeOwned + Send + 'static;
+
+    /// payload の JSON Schema（`domain::PayloadSchema` で検証する。draft 2020-12）
+    ///
+    /// あれば `Runtime::submit` が投入前に検査し、合わない payload はリトライを
+    /// 消費せずにその場でエラーになる。既定はなし。
+    fn payload_schema() -> Option<serde_json::Value> {
+        None
+    }
 }
 
 // 一時的にテスト用の Task 型をいくつか定義します。
@@ -46,6 +70,7 @@ pub struct TestTask {
 
 impl Task for TestTask {
     const TYPE: &'static str = "test.task.create.v1";
+    type Output = ();
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,4 +81,5 @@ pub struct AnotherTestTask{
 
 impl Task for AnotherTestTask {
     const TYPE: &'static str = "test.task.another.v1";
+    type Output = ();
 }
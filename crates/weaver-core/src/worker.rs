@@ -1,22 +1,238 @@
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
 use tokio::sync::watch;
-use tokio::task::JoinHandle;
+use tokio::task::{self, JoinHandle, JoinSet};
 
-use crate::domain::{Decider, Outcome, OutcomeKind};
-use crate::queue::Queue;
-use crate::runtime::Runtime;
+use crate::domain::events::DomainEvent;
+use crate::domain::{Decider, Outcome, OutcomeKind, TraceContext};
+use crate::error::WeaverError;
+use crate::ports::{DistributedLock, EventSink, NoopEventSink, Span, SpanExporter, SpanStatus};
+use crate::queue::{NamespaceReservations, Queue, RetryPolicy, TaskLease, TaskTypeFilter};
+use crate::runtime::{ContextValues, Runtime, TaskContext};
+
+/// What the supervisor does when a worker task fails.
+///
+/// A worker fails when it panics or when its loop returns before shutdown
+/// was requested.
+#[derive(Debug, Clone)]
+pub enum RestartPolicy {
+    /// Never restart. The first panic stops the whole group and is re-raised
+    /// from `shutdown_and_join()` (structured concurrency).
+    Never,
+
+    /// Respawn the failed worker after a backoff delay.
+    ///
+    /// Crash-loop protection: a worker is respawned at most `max_restarts`
+    /// times within `window`. Past that limit it stays down and the supervisor
+    /// emits `DomainEvent::WorkerGaveUp`.
+    OnFailure {
+        /// Delay before respawn; grows with the restarts inside the window.
+        backoff: RetryPolicy,
+        max_restarts: u32,
+        window: Duration,
+    },
+}
+
+impl RestartPolicy {
+    /// Default policy for v1: restart with 100ms base delay, doubling per crash,
+    /// at most 5 restarts per minute.
+    pub fn default_v1() -> Self {
+        RestartPolicy::OnFailure {
+            backoff: RetryPolicy {
+                base_delay: Duration::from_millis(100),
+                multiplier: 2.0,
+                ..RetryPolicy::default_v1()
+            },
+            max_restarts: 5,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Configuration for `WorkerGroup::spawn_with_config`.
+#[derive(Clone)]
+pub struct WorkerGroupConfig {
+    /// Restart behaviour for panicked workers.
+    pub restart_policy: RestartPolicy,
+
+    /// Report a worker via `event_sink` once it has crashed this many times
+    /// within the restart window.
+    pub crash_report_threshold: u32,
+
+    /// Where crash-loop reports and give-up alerts are sent.
+    pub event_sink: Arc<dyn EventSink>,
+
+    /// Call `TaskLease::heartbeat()` this often while a handler runs.
+    ///
+    /// Must be shorter than the queue's lease TTL. None: no heartbeats.
+    pub heartbeat_interval: Option<Duration>,
+
+    /// Leases a worker pulls per `Queue::lease_many()` call (at least 1).
+    ///
+    /// Prefetched leases wait in the worker without heartbeats, so keep
+    /// `prefetch × task duration` below the queue's lease TTL.
+    pub prefetch: usize,
+
+    /// Minimum workers kept available per namespace (the first segment of
+    /// the task type), e.g. `{"acme": 2}` out of 10 workers.
+    ///
+    /// Enforced by the queue when leasing (`Queue::lease_many_reserved`).
+    /// The reservations must not add up to more than the group size.
+    pub namespace_reservations: HashMap<String, usize>,
+
+    /// Task types this group leases, e.g. a dedicated pool for `render`
+    /// next to a group with `TaskTypeFilter::except` for everything else.
+    ///
+    /// Enforced by the queue when leasing (`Queue::lease_many_filtered`).
+    pub task_types: TaskTypeFilter,
+
+    /// Lock service handlers reach through `TaskContext::lock`.
+    ///
+    /// Locks are released when the attempt ends. None: `lock` fails.
+    pub lock: Option<Arc<dyn DistributedLock>>,
+
+    /// Resize the group with the queue depth. None: the size only changes
+    /// through `WorkerGroup::scale_to()`.
+    pub autoscale: Option<AutoscaleConfig>,
+
+    /// Static values handlers read through `TaskContext::value`.
+    pub context_values: ContextValues,
+
+    /// Where a span per attempt is sent, parented to the task's submission
+    /// span (`TaskEnvelope::trace`). None: no spans are recorded.
+    ///
+    /// Handlers see their attempt's span through `TaskContext::trace`.
+    pub span_exporter: Option<Arc<dyn SpanExporter>>,
+}
+
+impl Default for WorkerGroupConfig {
+    fn default() -> Self {
+        Self {
+            restart_policy: RestartPolicy::default_v1(),
+            crash_report_threshold: 3,
+            event_sink: Arc::new(NoopEventSink),
+            heartbeat_interval: None,
+            prefetch: 1,
+            namespace_reservations: HashMap::new(),
+            task_types: TaskTypeFilter::Any,
+            lock: None,
+            autoscale: None,
+            context_values: ContextValues::default(),
+            span_exporter: None,
+        }
+    }
+}
+
+/// Queue-depth autoscaling (`WorkerGroupConfig::autoscale`).
+///
+/// Every `interval` the backlog (queued + retry_scheduled tasks) is sampled
+/// and the group is sized to `backlog / backlog_per_worker` workers, within
+/// `min_workers..=max_workers`. Growing happens at once so bursts drain
+/// quickly; shrinking waits until the backlog has asked for fewer workers
+/// for `scale_down_delay`, so a dip between bursts does not thrash the group.
+#[derive(Debug, Clone)]
+pub struct AutoscaleConfig {
+    pub min_workers: usize,
+    pub max_workers: usize,
+
+    /// Waiting tasks one worker is expected to absorb (at least 1).
+    pub backlog_per_worker: usize,
+
+    /// How often the queue depth is sampled.
+    pub interval: Duration,
+    pub scale_down_delay: Duration,
+}
+
+impl AutoscaleConfig {
+    /// Scale between `min_workers` and `max_workers`; one worker per 10
+    /// waiting tasks, sampled every second, shrinking after 30s.
+    pub fn new(min_workers: usize, max_workers: usize) -> Self {
+        Self {
+            min_workers,
+            max_workers: max_workers.max(min_workers),
+            backlog_per_worker: 10,
+            interval: Duration::from_secs(1),
+            scale_down_delay: Duration::from_secs(30),
+        }
+    }
+}
 
 /// Worker group handle.
-/// - `shutdown_tx` を drop するとワーカー全体が止まる
-/// - `join()` で全ワーカーの終了を待てる
+/// - Workers run inside a `JoinSet` owned by a supervisor task
+/// - `request_shutdown()` で全ワーカーに停止を通知
+/// - `shutdown_and_join()` で全ワーカーの終了を待てる（panic は呼び出し元に伝播）
+/// - `drain()` は猶予時間を過ぎた処理中のリースを queue に返す
+/// - `scale_to()` で実行中に worker 数を変えられる
 pub struct WorkerGroup {
     shutdown_tx: watch::Sender<bool>,
-    joins: Vec<JoinHandle<()>>,
+    scaler: Scaler,
+    drain_tx: watch::Sender<Drain>,
+    released: Arc<AtomicUsize>,
+    supervisor: JoinHandle<Option<Box<dyn Any + Send>>>,
+}
+
+/// Drain progress, broadcast to workers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Drain {
+    Off,
+    /// Prefetched leases are released; running handlers may finish.
+    Draining,
+    /// Grace period over: running handlers are abandoned and their leases released.
+    Expired,
+}
+
+/// Resizes a group; shared by `WorkerGroup` and its autoscaler.
+#[derive(Clone)]
+struct Scaler {
+    size_tx: watch::Sender<usize>,
+    reservations_tx: watch::Sender<Option<Arc<NamespaceReservations>>>,
+    namespace_reservations: HashMap<String, usize>,
+}
+
+impl Scaler {
+    fn size(&self) -> usize {
+        *self.size_tx.borrow()
+    }
+
+    fn scale_to(&self, n: usize) -> Result<(), WeaverError> {
+        let reservations = build_reservations(n, &self.namespace_reservations)?;
+        // reservations first, so new workers never lease with the old size
+        self.reservations_tx.send_replace(reservations);
+        self.size_tx.send_replace(n);
+        Ok(())
+    }
+}
+
+/// Everything a worker needs; cloned for every (re)spawn.
+#[derive(Clone)]
+struct WorkerContext {
+    queue: Arc<dyn Queue>,
+    runtime: Arc<Runtime>,
+    decider: Arc<dyn Decider>,
+    heartbeat_interval: Option<Duration>,
+    prefetch: usize,
+    /// Rebuilt for the new group size on every `scale_to()`.
+    reservations: watch::Receiver<Option<Arc<NamespaceReservations>>>,
+    task_types: Arc<TaskTypeFilter>,
+    lock: Option<Arc<dyn DistributedLock>>,
+    context_values: Arc<ContextValues>,
+    span_exporter: Option<Arc<dyn SpanExporter>>,
+    shutdown_rx: watch::Receiver<bool>,
+
+    /// Target group size; workers with an id at or above it retire.
+    size_rx: watch::Receiver<usize>,
+    drain_rx: watch::Receiver<Drain>,
+
+    /// Leases handed back to the queue while draining.
+    released: Arc<AtomicUsize>,
 }
 
 impl WorkerGroup {
-    /// Spawn `n` workers.
+    /// Spawn `n` workers with the default configuration.
     ///
     /// Phase 4-1: Added `decider` parameter for Handler → Outcome → Decider flow.
     pub fn spawn(
@@ -25,22 +241,109 @@ impl WorkerGroup {
         runtime: Arc<Runtime>,
         decider: Arc<dyn Decider>,
     ) -> Self {
+        Self::spawn_with_config(n, queue, runtime, decider, WorkerGroupConfig::default())
+    }
+
+    /// Spawn `n` workers supervised according to `config`.
+    ///
+    /// # Panics
+    /// If `config.namespace_reservations` need more than `n` workers.
+    pub fn spawn_with_config(
+        n: usize,
+        queue: Arc<dyn Queue>,
+        runtime: Arc<Runtime>,
+        decider: Arc<dyn Decider>,
+        config: WorkerGroupConfig,
+    ) -> Self {
+        let reservations = build_reservations(n, &config.namespace_reservations)
+            .unwrap_or_else(|e| panic!("invalid WorkerGroupConfig: {e}"));
+        let (reservations_tx, reservations_rx) = watch::channel(reservations);
         let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (size_tx, size_rx) = watch::channel(n);
+        let (drain_tx, drain_rx) = watch::channel(Drain::Off);
+        let released = Arc::new(AtomicUsize::new(0));
+        let ctx = WorkerContext {
+            queue,
+            runtime,
+            decider,
+            heartbeat_interval: config.heartbeat_interval,
+            prefetch: config.prefetch.max(1),
+            reservations: reservations_rx,
+            task_types: Arc::new(config.task_types.clone()),
+            lock: config.lock.clone(),
+            context_values: Arc::new(config.context_values.clone()),
+            span_exporter: config.span_exporter.clone(),
+            shutdown_rx,
+            size_rx,
+            drain_rx,
+            released: Arc::clone(&released),
+        };
 
-        let mut joins = Vec::with_capacity(n);
+        let mut set = JoinSet::new();
+        let mut worker_ids = HashMap::with_capacity(n);
         for worker_id in 0..n {
-            let q = Arc::clone(&queue);
-            let rt = Arc::clone(&runtime);
-            let dec = Arc::clone(&decider);
-            let mut rx = shutdown_rx.clone();
+            spawn_worker(&mut set, &mut worker_ids, worker_id, &ctx, Duration::ZERO);
+        }
 
-            let join = tokio::spawn(async move {
-                worker_loop(worker_id, q, rt, dec, &mut rx).await;
-            });
-            joins.push(join);
+        let scaler = Scaler {
+            size_tx,
+            reservations_tx,
+            namespace_reservations: config.namespace_reservations.clone(),
+        };
+        if let Some(autoscale_config) = config.autoscale.clone() {
+            // Exits on shutdown; not joined.
+            tokio::spawn(autoscale(
+                Arc::clone(&ctx.queue),
+                scaler.clone(),
+                autoscale_config,
+                ctx.shutdown_rx.clone(),
+            ));
+        }
+        let supervisor = tokio::spawn(supervise(
+            set,
+            worker_ids,
+            ctx,
+            config,
+            shutdown_tx.clone(),
+            scaler.size_tx.clone(),
+        ));
+
+        Self {
+            shutdown_tx,
+            scaler,
+            drain_tx,
+            released,
+            supervisor,
         }
+    }
+
+    /// Number of workers the group is scaled to.
+    ///
+    /// Workers removed by a scale-down may still be finishing their leases.
+    pub fn size(&self) -> usize {
+        self.scaler.size()
+    }
+
+    /// Change the number of workers without restarting the group.
+    ///
+    /// Growing spawns new workers right away. Shrinking retires the workers
+    /// with the highest ids: they take no new leases, finish the ones they
+    /// hold (prefetched included), then exit. Scaling to 0 pauses the group.
+    ///
+    /// Fails, leaving the size unchanged, if the namespace reservations need
+    /// more than `n` workers.
+    pub fn scale_to(&self, n: usize) -> Result<(), WeaverError> {
+        self.scaler.scale_to(n)
+    }
+
+    /// Add `n` workers (see `scale_to()`).
+    pub fn add_workers(&self, n: usize) -> Result<(), WeaverError> {
+        self.scale_to(self.size().saturating_add(n))
+    }
 
-        Self { shutdown_tx, joins }
+    /// Retire up to `n` workers (see `scale_to()`).
+    pub fn remove_workers(&self, n: usize) -> Result<(), WeaverError> {
+        self.scale_to(self.size().saturating_sub(n))
     }
 
     /// Request shutdown for all workers.
@@ -52,69 +355,495 @@ impl WorkerGroup {
     }
 
     /// Shutdown and wait for all workers.
+    ///
+    /// With `RestartPolicy::Never`, a worker panic is re-raised here.
     pub async fn shutdown_and_join(self) {
         self.request_shutdown();
-        for j in self.joins {
-            let _ = j.await;
-        }
+        join_supervisor(self.supervisor.await);
+    }
+
+    /// Stop leasing and wait up to `grace` for running handlers.
+    ///
+    /// Prefetched leases that have not started are released at once; handlers
+    /// still running after `grace` are dropped and their leases released too,
+    /// so the tasks run again elsewhere instead of waiting for lease expiry.
+    /// A released attempt does not count toward the task's budget.
+    ///
+    /// Returns the number of released leases. Panics propagate like
+    /// `shutdown_and_join()`.
+    pub async fn drain(mut self, grace: Duration) -> usize {
+        // Draining first, so workers woken by the shutdown release their prefetch
+        let _ = self.drain_tx.send(Drain::Draining);
+        self.request_shutdown();
+        let joined = tokio::select! {
+            joined = &mut self.supervisor => joined,
+            _ = tokio::time::sleep(grace) => {
+                let _ = self.drain_tx.send(Drain::Expired);
+                (&mut self.supervisor).await
+            }
+        };
+        join_supervisor(joined);
+        self.released.load(Ordering::SeqCst)
+    }
+}
+
+/// Re-raise a worker panic reported by the supervisor.
+fn join_supervisor(joined: Result<Option<Box<dyn Any + Send>>, task::JoinError>) {
+    match joined {
+        Ok(Some(panic)) => std::panic::resume_unwind(panic),
+        Ok(None) => {}
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(_) => {}
     }
 }
 
-async fn worker_loop(
+/// Namespace reservations for a group of `workers` (None if none are configured).
+fn build_reservations(
+    workers: usize,
+    min_workers: &HashMap<String, usize>,
+) -> Result<Option<Arc<NamespaceReservations>>, WeaverError> {
+    if min_workers.is_empty() {
+        return Ok(None);
+    }
+    NamespaceReservations::new(workers, min_workers.clone()).map(|r| Some(Arc::new(r)))
+}
+
+/// Spawn one worker into the set, optionally after `delay` (restart backoff).
+fn spawn_worker(
+    set: &mut JoinSet<usize>,
+    worker_ids: &mut HashMap<task::Id, usize>,
     worker_id: usize,
-    queue: Arc<dyn Queue>,
-    runtime: Arc<Runtime>,
-    decider: Arc<dyn Decider>,
-    shutdown_rx: &mut watch::Receiver<bool>,
+    ctx: &WorkerContext,
+    delay: Duration,
 ) {
+    let mut ctx = ctx.clone();
+    let handle = set.spawn(async move {
+        if !delay.is_zero() {
+            // Shutdown during backoff: worker_loop exits on its first check.
+            tokio::select! {
+                _ = ctx.shutdown_rx.changed() => {}
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+        worker_loop(worker_id, ctx).await;
+        worker_id
+    });
+    worker_ids.insert(handle.id(), worker_id);
+}
+
+/// Supervisor: joins workers, restarts failed ones, reports crash loops and
+/// spawns workers when the group is scaled up.
+///
+/// Runs until shutdown is requested and every worker has exited. Returns the first panic payload when it must be propagated
+/// (`RestartPolicy::Never`).
+async fn supervise(
+    mut set: JoinSet<usize>,
+    mut worker_ids: HashMap<task::Id, usize>,
+    ctx: WorkerContext,
+    config: WorkerGroupConfig,
+    shutdown_tx: watch::Sender<bool>,
+    size_tx: watch::Sender<usize>,
+) -> Option<Box<dyn Any + Send>> {
+    // Restart timestamps per worker, pruned to the restart window.
+    let mut restarts: HashMap<usize, VecDeque<Instant>> = HashMap::new();
+    let mut propagated = None;
+    let mut shutdown_rx = ctx.shutdown_rx.clone();
+    // Cloned from the spawn-time receiver, so a resize made before this task
+    // first runs is still seen as a change. Holding `size_tx` keeps
+    // `changed()` from failing once the group handle is dropped.
+    let mut size_rx = ctx.size_rx.clone();
+    let _size_tx = size_tx;
+
     loop {
-        // shutdown が来ていたら抜ける
-        if *shutdown_rx.borrow() {
+        if *shutdown_rx.borrow() && set.is_empty() {
             break;
         }
+        let joined = tokio::select! {
+            Some(joined) = set.join_next_with_id() => joined,
+            Ok(()) = size_rx.changed() => {
+                let size = *size_rx.borrow_and_update();
+                if !*shutdown_rx.borrow() {
+                    // Retiring workers below the new size keep running; spawn the rest.
+                    for worker_id in 0..size {
+                        if !worker_ids.values().any(|&id| id == worker_id) {
+                            spawn_worker(&mut set, &mut worker_ids, worker_id, &ctx, Duration::ZERO);
+                        }
+                    }
+                }
+                continue;
+            }
+            Ok(()) = shutdown_rx.changed() => continue,
+        };
+
+        let shutting_down = *ctx.shutdown_rx.borrow();
+        let retired = |worker_id: usize| worker_id >= *size_rx.borrow();
+        let (worker_id, message, payload) = match joined {
+            Ok((id, worker_id)) => {
+                worker_ids.remove(&id);
+                if shutting_down || retired(worker_id) {
+                    continue;
+                }
+                (worker_id, "worker exited unexpectedly".to_string(), None)
+            }
+            Err(err) => {
+                let Some(worker_id) = worker_ids.remove(&err.id()) else {
+                    continue;
+                };
+                if !err.is_panic() {
+                    continue; // cancelled
+                }
+                let payload = err.into_panic();
+                (worker_id, panic_message(payload.as_ref()), Some(payload))
+            }
+        };
+        eprintln!("[worker-{worker_id}] failed: {message}");
+
+        match &config.restart_policy {
+            RestartPolicy::Never => {
+                // Stop the siblings gracefully, then propagate the first panic.
+                let _ = shutdown_tx.send(true);
+                if let Some(payload) = payload {
+                    propagated.get_or_insert(payload);
+                }
+            }
+            RestartPolicy::OnFailure {
+                backoff,
+                max_restarts,
+                window,
+            } => {
+                if shutting_down || retired(worker_id) {
+                    continue;
+                }
+
+                let now = Instant::now();
+                let history = restarts.entry(worker_id).or_default();
+                while history
+                    .front()
+                    .is_some_and(|&at| now.duration_since(at) > *window)
+                {
+                    history.pop_front();
+                }
+
+                if history.len() as u32 >= *max_restarts {
+                    let event = DomainEvent::WorkerGaveUp {
+                        worker_id,
+                        restarts: history.len() as u32,
+                        window: *window,
+                        message,
+                    };
+                    eprintln!("[worker-{worker_id}] restart limit reached, giving up");
+                    emit(config.event_sink.as_ref(), worker_id, event).await;
+                    continue;
+                }
+
+                history.push_back(now);
+                let count = history.len() as u32;
+                if count >= config.crash_report_threshold {
+                    let event = DomainEvent::WorkerCrashLoop {
+                        worker_id,
+                        crashes: count,
+                        message,
+                    };
+                    emit(config.event_sink.as_ref(), worker_id, event).await;
+                }
+
+                let delay = backoff.next_delay(count);
+                spawn_worker(&mut set, &mut worker_ids, worker_id, &ctx, delay);
+            }
+        }
+    }
+
+    propagated
+}
+
+/// Autoscaler sizing decisions, kept apart from the sampling loop.
+struct Autoscaler {
+    config: AutoscaleConfig,
+
+    /// Since when the backlog has asked for fewer workers than are running.
+    shrink_since: Option<Instant>,
+}
+
+impl Autoscaler {
+    fn new(config: AutoscaleConfig) -> Self {
+        Self {
+            config,
+            shrink_since: None,
+        }
+    }
 
-        // lease は「待つ」可能性があるので select で shutdown と競合させる
-        let lease = tokio::select! {
-            _ = shutdown_rx.changed() => {
-                // 変更が入ったら次のループで判定
+    /// The size to scale to, given the current size and backlog.
+    fn next_size(&mut self, current: usize, backlog: usize, now: Instant) -> usize {
+        let config = &self.config;
+        let wanted = backlog.div_ceil(config.backlog_per_worker.max(1)).clamp(
+            config.min_workers,
+            config.max_workers.max(config.min_workers),
+        );
+        if wanted >= current {
+            self.shrink_since = None;
+            return wanted;
+        }
+        let since = *self.shrink_since.get_or_insert(now);
+        if now.duration_since(since) < config.scale_down_delay {
+            return current;
+        }
+        self.shrink_since = None;
+        wanted
+    }
+}
+
+/// Autoscaler task: samples the queue depth and resizes the group until shutdown.
+async fn autoscale(
+    queue: Arc<dyn Queue>,
+    scaler: Scaler,
+    config: AutoscaleConfig,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut autoscaler = Autoscaler::new(config);
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.wait_for(|shutdown| *shutdown) => return,
+            _ = interval.tick() => {}
+        }
+        let counts = match queue.counts_by_state().await {
+            Ok(counts) => counts,
+            Err(e) => {
+                eprintln!("[autoscaler] counts_by_state failed: {}", e);
                 continue;
             }
-            lease = queue.lease() => lease,
         };
+        let backlog = counts.queued + counts.retry_scheduled;
+        let current = scaler.size();
+        let next = autoscaler.next_size(current, backlog, Instant::now());
+        if next == current {
+            continue;
+        }
+        eprintln!("[autoscaler] backlog {backlog}: scaling {current} -> {next} workers");
+        if let Err(e) = scaler.scale_to(next) {
+            eprintln!("[autoscaler] scale_to({next}) failed: {}", e);
+        }
+    }
+}
 
-        let Some(lease) = lease else {
-            // Queue 側が「いま何もない」を None で返す設計なら少し待つ
-            // (すでに内部で待つ設計なら、この分岐自体が不要)
-            tokio::task::yield_now().await;
+/// Emit a supervisor event; failures are logged, never fatal.
+async fn emit(sink: &dyn EventSink, worker_id: usize, event: DomainEvent) {
+    if let Err(e) = sink.emit(event).await {
+        eprintln!("[worker-{worker_id}] event emit failed: {e}");
+    }
+}
+
+/// Best-effort extraction of a panic message.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+async fn worker_loop(worker_id: usize, mut ctx: WorkerContext) {
+    // prefetch したリースは shutdown 後も処理してから抜ける（Running のまま残さない）
+    let mut prefetched: VecDeque<Box<dyn TaskLease>> = VecDeque::new();
+    loop {
+        let lease = match prefetched.pop_front() {
+            Some(lease) => lease,
+            None => {
+                // shutdown が来ていたら、または scale down で外されたら抜ける
+                if *ctx.shutdown_rx.borrow() || worker_id >= *ctx.size_rx.borrow() {
+                    break;
+                }
+
+                // lease は「待つ」可能性があるので select で shutdown と競合させる
+                let reservations = ctx.reservations.borrow().clone();
+                let leases = tokio::select! {
+                    _ = ctx.shutdown_rx.changed() => {
+                        // 変更が入ったら次のループで判定
+                        continue;
+                    }
+                    _ = ctx.size_rx.changed() => continue,
+                    leases = lease_next(
+                        ctx.queue.as_ref(),
+                        ctx.prefetch,
+                        reservations.as_deref(),
+                        &ctx.task_types,
+                    ) => leases,
+                };
+                prefetched.extend(leases);
+
+                let Some(lease) = prefetched.pop_front() else {
+                    // Queue 側が「いま何もない」を空で返す設計なら少し待つ
+                    // (すでに内部で待つ設計なら、この分岐自体が不要)
+                    tokio::task::yield_now().await;
+                    continue;
+                };
+                lease
+            }
+        };
+
+        if *ctx.drain_rx.borrow() != Drain::Off {
+            release(worker_id, lease, &ctx).await;
             continue;
+        }
+        process_lease(worker_id, lease, &ctx).await;
+    }
+}
+
+/// Hand an unfinished lease back to the queue (drain).
+async fn release(worker_id: usize, lease: Box<dyn TaskLease>, ctx: &WorkerContext) {
+    match lease.release().await {
+        Ok(()) => {
+            ctx.released.fetch_add(1, Ordering::SeqCst);
+        }
+        Err(e) => eprintln!("[worker-{worker_id}] release failed: {}", e),
+    }
+}
+
+/// Resolve once the drain grace period is over (never, if it never ends).
+async fn drain_expired(drain_rx: &mut watch::Receiver<Drain>) {
+    if drain_rx
+        .wait_for(|drain| *drain == Drain::Expired)
+        .await
+        .is_err()
+    {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Lease the next batch, honouring the task type filter and namespace
+/// reservations if configured.
+async fn lease_next(
+    queue: &dyn Queue,
+    prefetch: usize,
+    reservations: Option<&NamespaceReservations>,
+    task_types: &TaskTypeFilter,
+) -> Vec<Box<dyn TaskLease>> {
+    if !task_types.is_any() {
+        return queue
+            .lease_many_filtered(prefetch, task_types, reservations)
+            .await;
+    }
+    match reservations {
+        Some(reservations) => queue.lease_many_reserved(prefetch, reservations).await,
+        None => queue.lease_many(prefetch).await,
+    }
+}
+
+/// Run one lease in its own span, then release the locks its handler took.
+async fn process_lease(worker_id: usize, lease: Box<dyn TaskLease>, ctx: &WorkerContext) {
+    let mut task_context = lease.task_context().await.unwrap_or_else(|e| {
+        eprintln!("[worker-{worker_id}] task_context failed: {}", e);
+        TaskContext::default()
+    });
+    if !ctx.context_values.is_empty() {
+        let values = ctx.context_values.resolve(lease.envelope().task_type());
+        task_context = task_context.with_values(values);
+    }
+    if let Some(lock) = &ctx.lock {
+        let owner = format!("worker-{worker_id}/{}", ulid::Ulid::new());
+        task_context = task_context.with_locks(Arc::clone(lock), owner);
+    }
+    // Every attempt is a child of the submission, so retries line up under it
+    let parent = lease.envelope().trace();
+    let span_context = parent.map_or_else(TraceContext::new_root, |parent| parent.child());
+    task_context = task_context.with_trace(span_context);
+    let mut attributes = vec![
+        (
+            "weaver.task_id".to_string(),
+            lease.envelope().task_id().to_string(),
+        ),
+        (
+            "weaver.task_type".to_string(),
+            lease.envelope().task_type().to_string(),
+        ),
+        (
+            "weaver.attempt".to_string(),
+            task_context.attempt().to_string(),
+        ),
+    ];
+    if let Some(job_id) = task_context.job_id() {
+        attributes.push(("weaver.job_id".to_string(), job_id.to_string()));
+    }
+    let name = format!("process {}", lease.envelope().task_type());
+    let start = SystemTime::now();
+
+    let status = execute_lease(worker_id, lease, ctx, &task_context).await;
+    task_context.release_locks().await;
+
+    if let Some(exporter) = &ctx.span_exporter {
+        let span = Span {
+            context: span_context,
+            parent,
+            name,
+            start,
+            end: SystemTime::now(),
+            attributes,
+            status,
         };
+        let exporter = Arc::clone(exporter);
+        // Off the worker's path: a slow collector must not hold up the next lease
+        tokio::spawn(async move {
+            if let Err(e) = exporter.export(span).await {
+                eprintln!("[worker-{worker_id}] span export failed: {e}");
+            }
+        });
+    }
+}
 
-        // Phase 4-1: Handler → Outcome → Decider → Decision flow
-        let envelope = lease.envelope().clone();
-
-        let outcome_result = runtime.execute(&envelope).await;
-
-        match outcome_result {
-            Ok(outcome) => match outcome.kind {
-                OutcomeKind::Success => {
-                    // Check if Handler proposed decomposition (child_tasks present)
-                    if outcome.child_tasks.is_some() {
-                        // Go through Decider flow for decomposition
-                        let task_record = lease.get_task_record().await.unwrap_or_else(|e| {
-                            panic!("[worker-{worker_id}] get_task_record failed: {}", e);
-                        });
-                        let decision = decider.decide(&task_record, &outcome);
-                        lease.complete(outcome, decision).await.unwrap_or_else(|e| {
-                            eprintln!("[worker-{worker_id}] complete failed: {}", e);
-                        });
-                    } else {
-                        // Simple success, just ack
-                        lease.ack().await.unwrap_or_else(|e| {
-                            eprintln!("[worker-{worker_id}] ack failed: {}", e);
-                        });
-                    }
+/// Phase 4-1: Handler → Outcome → Decider → Decision flow for one lease.
+///
+/// Returns how the attempt ended, for its span.
+async fn execute_lease(
+    worker_id: usize,
+    lease: Box<dyn TaskLease>,
+    ctx: &WorkerContext,
+    task_context: &TaskContext,
+) -> SpanStatus {
+    let (runtime, decider) = (&ctx.runtime, &ctx.decider);
+    let envelope = lease.envelope().clone();
+
+    // Keep the lease alive while the handler runs
+    let execution = runtime.execute_with_context(&envelope, task_context);
+    tokio::pin!(execution);
+    let mut heartbeat = ctx
+        .heartbeat_interval
+        .map(|interval| tokio::time::interval_at(tokio::time::Instant::now() + interval, interval));
+    let mut drain_rx = ctx.drain_rx.clone();
+    let outcome_result = loop {
+        tokio::select! {
+            result = &mut execution => break result,
+            _ = drain_expired(&mut drain_rx) => {
+                eprintln!("[worker-{worker_id}] drain grace period over, releasing lease");
+                release(worker_id, lease, ctx).await;
+                return SpanStatus::Error("lease released after the drain grace period".into());
+            }
+            _ = next_heartbeat(&mut heartbeat) => {
+                if let Err(e) = lease.heartbeat().await {
+                    eprintln!("[worker-{worker_id}] heartbeat failed: {}", e);
                 }
-                OutcomeKind::Failure | OutcomeKind::Blocked => {
+            }
+        }
+    };
+
+    let status = match &outcome_result {
+        Ok(outcome) if outcome.kind == OutcomeKind::Success => SpanStatus::Ok,
+        Ok(outcome) => SpanStatus::Error(
+            outcome
+                .reason
+                .clone()
+                .unwrap_or_else(|| format!("{:?}", outcome.kind)),
+        ),
+        Err(handler_error) => SpanStatus::Error(handler_error.to_string()),
+    };
+    match outcome_result {
+        Ok(outcome) => match outcome.kind {
+            OutcomeKind::Success => {
+                // Check if Handler proposed decomposition (child_tasks present)
+                if outcome.child_tasks.is_some() {
+                    // Go through Decider flow for decomposition
                     let task_record = lease.get_task_record().await.unwrap_or_else(|e| {
                         panic!("[worker-{worker_id}] get_task_record failed: {}", e);
                     });
@@ -122,31 +851,60 @@ async fn worker_loop(
                     lease.complete(outcome, decision).await.unwrap_or_else(|e| {
                         eprintln!("[worker-{worker_id}] complete failed: {}", e);
                     });
+                } else {
+                    // Simple success; keep the outcome for dependents
+                    lease.succeed(outcome).await.unwrap_or_else(|e| {
+                        eprintln!("[worker-{worker_id}] ack failed: {}", e);
+                    });
                 }
-            },
-            Err(handler_error) => {
-                // Convert infrastructure error to business failure outcome
-                let outcome = Outcome {
-                    kind: OutcomeKind::Failure,
-                    artifacts: Vec::new(),
-                    reason: Some(handler_error.to_string()),
-                    retry_hint: None,
-                    alternatives: Vec::new(),
-                    child_tasks: None,
-                };
-                let decision = decider.decide(
-                    &lease.get_task_record().await.unwrap_or_else(|e| {
-                        panic!("[worker-{worker_id}] get_task_record failed: {}", e);
-                    }),
-                    &outcome,
-                );
-                eprintln!("[worker-{worker_id}] handler error: {}", handler_error);
-                if let Err(e) = lease.complete(outcome, decision).await {
-                    eprintln!("[worker-{worker_id}] complete failed: {e}");
-                }
+            }
+            OutcomeKind::Failure | OutcomeKind::Blocked => {
+                let task_record = lease.get_task_record().await.unwrap_or_else(|e| {
+                    panic!("[worker-{worker_id}] get_task_record failed: {}", e);
+                });
+                let decision = decider.decide(&task_record, &outcome);
+                lease.complete(outcome, decision).await.unwrap_or_else(|e| {
+                    eprintln!("[worker-{worker_id}] complete failed: {}", e);
+                });
+            }
+        },
+        Err(handler_error) => {
+            // Convert infrastructure error to business failure outcome
+            let outcome = Outcome {
+                kind: OutcomeKind::Failure,
+                artifacts: Vec::new(),
+                reason: Some(handler_error.to_string()),
+                retry_hint: None,
+                alternatives: Vec::new(),
+                child_tasks: None,
+                blocked_on: None,
+            };
+            let decision = decider.decide(
+                &lease.get_task_record().await.unwrap_or_else(|e| {
+                    panic!("[worker-{worker_id}] get_task_record failed: {}", e);
+                }),
+                &outcome,
+            );
+            eprintln!("[worker-{worker_id}] handler error: {}", handler_error);
+            if let Some(logger) = task_context.logger() {
+                logger.error(format_args!("handler error: {handler_error}"));
+            }
+            if let Err(e) = lease.complete(outcome, decision).await {
+                eprintln!("[worker-{worker_id}] complete failed: {e}");
             }
         }
     }
+    status
+}
+
+/// Wait for the next heartbeat tick (forever when heartbeats are disabled).
+async fn next_heartbeat(heartbeat: &mut Option<tokio::time::Interval>) {
+    match heartbeat {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
 }
 
 #[cfg(test)]
@@ -190,6 +948,7 @@ mod tests {
         let queue = Arc::new(InMemoryQueue::new(RetryPolicy {
             base_delay: Duration::from_millis(50), // Short delay for test
             multiplier: 1.0,                       // No exponential backoff
+            ..RetryPolicy::default_v1()
         }));
 
         let mut registry = HandlerRegistry::new();
@@ -204,6 +963,7 @@ mod tests {
         let decider = Arc::new(DefaultDecider::new(RetryPolicy {
             base_delay: Duration::from_millis(50),
             multiplier: 1.0,
+            ..RetryPolicy::default_v1()
         }));
 
         // Start 1 worker
@@ -247,6 +1007,82 @@ mod tests {
         panic!("Task did not complete successfully within timeout");
     }
 
+    #[tokio::test]
+    async fn test_worker_exports_a_span_per_attempt_under_the_submission() {
+        struct ChannelExporter(tokio::sync::mpsc::UnboundedSender<Span>);
+
+        #[async_trait]
+        impl SpanExporter for ChannelExporter {
+            async fn export(&self, span: Span) -> Result<(), crate::ports::SpanExportError> {
+                self.0.send(span).unwrap();
+                Ok(())
+            }
+        }
+
+        let retry = RetryPolicy {
+            base_delay: Duration::from_millis(10),
+            multiplier: 1.0,
+            ..RetryPolicy::default_v1()
+        };
+        let queue = Arc::new(InMemoryQueue::new(retry.clone()));
+        let mut registry = HandlerRegistry::new();
+        registry
+            .register(TaskType::new("flaky"), Arc::new(FailingHandler::new(1)))
+            .unwrap();
+        let runtime = Arc::new(Runtime::new(Arc::new(registry)));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let config = WorkerGroupConfig {
+            span_exporter: Some(Arc::new(ChannelExporter(tx))),
+            ..WorkerGroupConfig::default()
+        };
+        let workers = WorkerGroup::spawn_with_config(
+            1,
+            queue.clone(),
+            runtime,
+            Arc::new(DefaultDecider::new(retry)),
+            config,
+        );
+
+        // Submitted while handling a traced request
+        let submission = TraceContext::from_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        )
+        .unwrap();
+        let envelope = TaskEnvelope::new(
+            TaskId::new(1),
+            TaskType::new("flaky"),
+            serde_json::json!({}),
+        )
+        .with_trace(submission);
+        queue.enqueue(envelope).await.unwrap();
+
+        let mut spans = Vec::new();
+        for _ in 0..2 {
+            let span = tokio::time::timeout(Duration::from_secs(3), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            spans.push(span);
+        }
+        workers.shutdown_and_join().await;
+
+        for (span, attempt) in spans.iter().zip(["1", "2"]) {
+            assert_eq!(span.name, "process flaky");
+            assert_eq!(span.context.trace_id(), submission.trace_id());
+            assert_eq!(span.parent, Some(submission));
+            assert!(
+                span.attributes
+                    .contains(&("weaver.attempt".to_string(), attempt.to_string()))
+            );
+            assert!(span.start <= span.end);
+        }
+        assert_ne!(spans[0].context, spans[1].context);
+        assert!(
+            matches!(&spans[0].status, SpanStatus::Error(reason) if reason.contains("intentional failure"))
+        );
+        assert_eq!(spans[1].status, SpanStatus::Ok);
+    }
+
     #[tokio::test]
     async fn test_worker_max_attempts_exceeded() {
         // Setup: Queue, Runtime with always-failing handler, DefaultDecider
@@ -254,6 +1090,7 @@ mod tests {
         let queue = Arc::new(InMemoryQueue::new(RetryPolicy {
             base_delay: Duration::from_millis(10),
             multiplier: 1.0,
+            ..RetryPolicy::default_v1()
         }));
 
         let mut registry = HandlerRegistry::new();
@@ -268,6 +1105,7 @@ mod tests {
         let decider = Arc::new(DefaultDecider::new(RetryPolicy {
             base_delay: Duration::from_millis(10),
             multiplier: 1.0,
+            ..RetryPolicy::default_v1()
         }));
 
         // Start 1 worker
@@ -467,4 +1305,682 @@ mod tests {
             counts.dead
         );
     }
+
+    /// Test handler that panics N times before succeeding
+    struct PanickingHandler {
+        remaining_panics: AtomicU32,
+    }
+
+    impl PanickingHandler {
+        fn new(n: u32) -> Self {
+            Self {
+                remaining_panics: AtomicU32::new(n),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl TaskHandler for PanickingHandler {
+        async fn handle(&self, _envelope: &TaskEnvelope) -> Result<Outcome, crate::error::WeaverError> {
+            if self.remaining_panics.load(Ordering::SeqCst) > 0 {
+                self.remaining_panics.fetch_sub(1, Ordering::SeqCst);
+                panic!("intentional panic");
+            }
+            Ok(Outcome::success())
+        }
+    }
+
+    /// Test sink that keeps every emitted event
+    #[derive(Default)]
+    struct RecordingSink {
+        events: std::sync::Mutex<Vec<DomainEvent>>,
+    }
+
+    #[async_trait]
+    impl EventSink for RecordingSink {
+        async fn emit(&self, event: DomainEvent) -> Result<(), crate::ports::EventSinkError> {
+            self.events.lock().unwrap().push(event);
+            Ok(())
+        }
+    }
+
+    fn panicking_runtime(n: u32) -> Arc<Runtime> {
+        let mut registry = HandlerRegistry::new();
+        registry
+            .register(TaskType::new("panicking"), Arc::new(PanickingHandler::new(n)))
+            .unwrap();
+        Arc::new(Runtime::new(Arc::new(registry)))
+    }
+
+    #[tokio::test]
+    async fn test_worker_restarts_after_panic_and_reports_crash_loop() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let sink = Arc::new(RecordingSink::default());
+        let config = WorkerGroupConfig {
+            restart_policy: RestartPolicy::OnFailure {
+                backoff: RetryPolicy {
+                    base_delay: Duration::from_millis(10),
+                    multiplier: 1.0,
+                    ..RetryPolicy::default_v1()
+                },
+                max_restarts: 5,
+                window: Duration::from_secs(60),
+            },
+            crash_report_threshold: 2,
+            event_sink: sink.clone(),
+            ..WorkerGroupConfig::default()
+        };
+
+        // Panics on the first 3 tasks, succeeds on the 4th
+        let workers = WorkerGroup::spawn_with_config(
+            1,
+            queue.clone(),
+            panicking_runtime(3),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+        for i in 0..4 {
+            let envelope =
+                TaskEnvelope::new(TaskId::new(i), TaskType::new("panicking"), serde_json::json!({}));
+            queue.enqueue(envelope).await.unwrap();
+        }
+
+        for _ in 0..50 {
+            let counts = queue.counts_by_state().await.unwrap();
+            if counts.succeeded == 1 {
+                workers.shutdown_and_join().await;
+
+                // Crashes #2 and #3 reach the threshold
+                let events = sink.events.lock().unwrap();
+                assert_eq!(events.len(), 2);
+                assert!(matches!(
+                    &events[1],
+                    DomainEvent::WorkerCrashLoop { worker_id: 0, crashes: 3, message }
+                        if message == "intentional panic"
+                ));
+                return;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+
+        panic!("Worker was not restarted after panic");
+    }
+
+    #[tokio::test]
+    async fn test_worker_panic_is_propagated_without_restart() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let config = WorkerGroupConfig {
+            restart_policy: RestartPolicy::Never,
+            ..WorkerGroupConfig::default()
+        };
+        let workers = WorkerGroup::spawn_with_config(
+            2,
+            queue.clone(),
+            panicking_runtime(1),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+        let envelope =
+            TaskEnvelope::new(TaskId::new(1), TaskType::new("panicking"), serde_json::json!({}));
+        queue.enqueue(envelope).await.unwrap();
+
+        // The panicking task stays Running; wait for it to be picked up
+        for _ in 0..50 {
+            if queue.counts_by_state().await.unwrap().running == 1 {
+                break;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        sleep(Duration::from_millis(50)).await;
+
+        let joined = tokio::spawn(workers.shutdown_and_join()).await;
+        let err = joined.expect_err("panic should be propagated");
+        assert!(err.is_panic());
+    }
+
+    #[tokio::test]
+    async fn test_worker_gives_up_after_restart_limit() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let sink = Arc::new(RecordingSink::default());
+        let config = WorkerGroupConfig {
+            restart_policy: RestartPolicy::OnFailure {
+                backoff: RetryPolicy {
+                    base_delay: Duration::from_millis(10),
+                    multiplier: 1.0,
+                    ..RetryPolicy::default_v1()
+                },
+                max_restarts: 2,
+                window: Duration::from_secs(60),
+            },
+            crash_report_threshold: u32::MAX,
+            event_sink: sink.clone(),
+            ..WorkerGroupConfig::default()
+        };
+
+        // Every task panics: 1 initial run + 2 restarts, then the worker stays down
+        let workers = WorkerGroup::spawn_with_config(
+            1,
+            queue.clone(),
+            panicking_runtime(u32::MAX),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+        for i in 0..5 {
+            let envelope =
+                TaskEnvelope::new(TaskId::new(i), TaskType::new("panicking"), serde_json::json!({}));
+            queue.enqueue(envelope).await.unwrap();
+        }
+
+        for _ in 0..50 {
+            let gave_up = !sink.events.lock().unwrap().is_empty();
+            if gave_up {
+                // No more restarts: the remaining tasks stay untouched
+                sleep(Duration::from_millis(100)).await;
+                let counts = queue.counts_by_state().await.unwrap();
+                assert_eq!(counts.queued, 2);
+
+                workers.shutdown_and_join().await;
+
+                let events = sink.events.lock().unwrap();
+                assert_eq!(events.len(), 1);
+                assert!(matches!(
+                    &events[0],
+                    DomainEvent::WorkerGaveUp { worker_id: 0, restarts: 2, .. }
+                ));
+                return;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+
+        panic!("Worker did not give up after reaching the restart limit");
+    }
+
+    /// Test handler that takes a while and counts its invocations
+    struct SlowHandler {
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl TaskHandler for SlowHandler {
+        async fn handle(
+            &self,
+            _envelope: &TaskEnvelope,
+        ) -> Result<Outcome, crate::error::WeaverError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            sleep(Duration::from_millis(150)).await;
+            Ok(Outcome::success())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_worker_heartbeat_keeps_long_task_leased() {
+        let queue = Arc::new(
+            InMemoryQueue::new(RetryPolicy::default_v1()).with_lease_ttl(Duration::from_millis(50)),
+        );
+        let handler = Arc::new(SlowHandler {
+            calls: AtomicU32::new(0),
+        });
+        let mut registry = HandlerRegistry::new();
+        registry
+            .register(TaskType::new("slow"), handler.clone())
+            .unwrap();
+        let config = WorkerGroupConfig {
+            heartbeat_interval: Some(Duration::from_millis(15)),
+            ..WorkerGroupConfig::default()
+        };
+
+        let workers = WorkerGroup::spawn_with_config(
+            2,
+            queue.clone(),
+            Arc::new(Runtime::new(Arc::new(registry))),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+        let envelope =
+            TaskEnvelope::new(TaskId::new(1), TaskType::new("slow"), serde_json::json!({}));
+        queue.enqueue(envelope).await.unwrap();
+
+        for _ in 0..50 {
+            if queue.counts_by_state().await.unwrap().succeeded == 1 {
+                workers.shutdown_and_join().await;
+                // Never reaped, so the second worker never picked it up
+                assert_eq!(handler.calls.load(Ordering::Relaxed), 1);
+                return;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+
+        panic!("Long-running task did not succeed");
+    }
+
+    #[tokio::test]
+    async fn test_worker_prefetch_processes_all_tasks() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let mut registry = HandlerRegistry::new();
+        registry
+            .register(TaskType::new("flaky"), Arc::new(FailingHandler::new(0)))
+            .unwrap();
+        let config = WorkerGroupConfig {
+            prefetch: 4,
+            ..WorkerGroupConfig::default()
+        };
+
+        for i in 0..10 {
+            let envelope = TaskEnvelope::new(
+                TaskId::new(i),
+                TaskType::new("flaky"),
+                serde_json::json!({}),
+            );
+            queue.enqueue(envelope).await.unwrap();
+        }
+        let workers = WorkerGroup::spawn_with_config(
+            2,
+            queue.clone(),
+            Arc::new(Runtime::new(Arc::new(registry))),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+
+        for _ in 0..50 {
+            if queue.counts_by_state().await.unwrap().succeeded == 10 {
+                workers.shutdown_and_join().await;
+                return;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+
+        panic!("Prefetching workers did not process all tasks");
+    }
+
+    /// One worker holding a running slow task and a prefetched one.
+    async fn slow_worker_with_prefetch(
+        handler: Arc<SlowHandler>,
+    ) -> (Arc<InMemoryQueue>, WorkerGroup) {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let mut registry = HandlerRegistry::new();
+        registry.register(TaskType::new("slow"), handler).unwrap();
+        for i in 0..2 {
+            let envelope =
+                TaskEnvelope::new(TaskId::new(i), TaskType::new("slow"), serde_json::json!({}));
+            queue.enqueue(envelope).await.unwrap();
+        }
+        let config = WorkerGroupConfig {
+            prefetch: 2,
+            ..WorkerGroupConfig::default()
+        };
+        let workers = WorkerGroup::spawn_with_config(
+            1,
+            queue.clone(),
+            Arc::new(Runtime::new(Arc::new(registry))),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+        while queue.counts_by_state().await.unwrap().running < 2 {
+            sleep(Duration::from_millis(5)).await;
+        }
+        (queue, workers)
+    }
+
+    #[tokio::test]
+    async fn test_drain_releases_leases_left_after_grace_period() {
+        let handler = Arc::new(SlowHandler {
+            calls: AtomicU32::new(0),
+        });
+        let (queue, workers) = slow_worker_with_prefetch(handler.clone()).await;
+
+        assert_eq!(workers.drain(Duration::from_millis(20)).await, 2);
+        let counts = queue.counts_by_state().await.unwrap();
+        assert_eq!(counts.queued, 2);
+        assert_eq!(counts.running, 0);
+        // The abandoned attempt does not use up the budget
+        let lease = queue.lease().await.unwrap();
+        assert_eq!(lease.get_task_record().await.unwrap().attempts, 1);
+        assert_eq!(handler.calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_drain_lets_running_handlers_finish_within_grace_period() {
+        let handler = Arc::new(SlowHandler {
+            calls: AtomicU32::new(0),
+        });
+        let (queue, workers) = slow_worker_with_prefetch(handler).await;
+
+        // Only the prefetched lease that never started is released
+        assert_eq!(workers.drain(Duration::from_secs(5)).await, 1);
+        let counts = queue.counts_by_state().await.unwrap();
+        assert_eq!(counts.succeeded, 1);
+        assert_eq!(counts.queued, 1);
+    }
+
+    /// Takes a long lock on "repo" and returns without releasing it
+    struct LockingHandler;
+
+    #[async_trait]
+    impl TaskHandler for LockingHandler {
+        async fn handle(
+            &self,
+            _envelope: &TaskEnvelope,
+        ) -> Result<Outcome, crate::error::WeaverError> {
+            unreachable!("called through handle_with_context")
+        }
+
+        async fn handle_with_context(
+            &self,
+            _envelope: &TaskEnvelope,
+            ctx: &TaskContext,
+        ) -> Result<Outcome, crate::error::WeaverError> {
+            ctx.lock("repo", Duration::from_secs(60), Duration::from_secs(1))
+                .await
+                .map_err(|e| crate::error::WeaverError::Other(e.to_string()))?;
+            Ok(Outcome::success())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_worker_releases_handler_locks_when_the_attempt_ends() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let lock = Arc::new(crate::impls::InMemoryLock::new());
+        let mut registry = HandlerRegistry::new();
+        registry
+            .register(TaskType::new("push"), Arc::new(LockingHandler))
+            .unwrap();
+        let config = WorkerGroupConfig {
+            lock: Some(lock.clone()),
+            ..WorkerGroupConfig::default()
+        };
+        let workers = WorkerGroup::spawn_with_config(
+            2,
+            queue.clone(),
+            Arc::new(Runtime::new(Arc::new(registry))),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+        for i in 0..3 {
+            let envelope =
+                TaskEnvelope::new(TaskId::new(i), TaskType::new("push"), serde_json::json!({}));
+            queue.enqueue(envelope).await.unwrap();
+        }
+
+        for _ in 0..50 {
+            if queue.counts_by_state().await.unwrap().succeeded == 3 {
+                workers.shutdown_and_join().await;
+                // Each attempt released its lock, long before the 60s TTL
+                let probe = lock.try_acquire("repo", "probe", Duration::from_secs(1));
+                assert!(probe.await.unwrap());
+                return;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+
+        panic!("Tasks contending for the lock did not all succeed");
+    }
+
+    /// Succeeds only if the worker injected the configured endpoint
+    struct EndpointHandler;
+
+    #[async_trait]
+    impl TaskHandler for EndpointHandler {
+        async fn handle(
+            &self,
+            _envelope: &TaskEnvelope,
+        ) -> Result<Outcome, crate::error::WeaverError> {
+            unreachable!("called through handle_with_context")
+        }
+
+        async fn handle_with_context(
+            &self,
+            _envelope: &TaskEnvelope,
+            ctx: &TaskContext,
+        ) -> Result<Outcome, crate::error::WeaverError> {
+            match ctx.value("endpoint") {
+                Some("https://acme.test") => Ok(Outcome::success()),
+                other => Ok(Outcome::failure(format!("unexpected endpoint {other:?}"))),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_worker_injects_context_values_for_the_task_type() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let mut registry = HandlerRegistry::new();
+        registry
+            .register(TaskType::new("acme.sync"), Arc::new(EndpointHandler))
+            .unwrap();
+        let config = WorkerGroupConfig {
+            context_values: ContextValues::new()
+                .with_value("endpoint", "https://default.test")
+                .with_namespace_value("acme", "endpoint", "https://acme.test"),
+            ..WorkerGroupConfig::default()
+        };
+        let workers = WorkerGroup::spawn_with_config(
+            1,
+            queue.clone(),
+            Arc::new(Runtime::new(Arc::new(registry))),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+        let envelope = TaskEnvelope::new(
+            TaskId::new(1),
+            TaskType::new("acme.sync"),
+            serde_json::json!({}),
+        );
+        queue.enqueue(envelope).await.unwrap();
+
+        wait_for_counts(&queue, "endpoint was not injected", |c| c.succeeded == 1).await;
+        workers.shutdown_and_join().await;
+    }
+
+    /// Wait until `done` holds for the queue counts, or panic with `what`.
+    async fn wait_for_counts(
+        queue: &InMemoryQueue,
+        what: &str,
+        done: impl Fn(&crate::observability::QueueCounts) -> bool,
+    ) {
+        for _ in 0..100 {
+            if done(&queue.counts_by_state().await.unwrap()) {
+                return;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        panic!("{what}: {:?}", queue.counts_by_state().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_scale_to_changes_the_number_of_leasing_workers() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let mut registry = HandlerRegistry::new();
+        let handler = Arc::new(SlowHandler {
+            calls: AtomicU32::new(0),
+        });
+        registry.register(TaskType::new("slow"), handler).unwrap();
+        let workers = WorkerGroup::spawn(
+            1,
+            queue.clone(),
+            Arc::new(Runtime::new(Arc::new(registry))),
+            Arc::new(DefaultDecider::default_v1()),
+        );
+        for i in 0..3 {
+            let envelope =
+                TaskEnvelope::new(TaskId::new(i), TaskType::new("slow"), serde_json::json!({}));
+            queue.enqueue(envelope).await.unwrap();
+        }
+
+        workers.scale_to(3).unwrap();
+        wait_for_counts(&queue, "new workers did not lease", |c| c.running == 3).await;
+
+        // Scaled to 0: running tasks finish, nothing new is leased
+        workers.remove_workers(5).unwrap();
+        assert_eq!(workers.size(), 0);
+        wait_for_counts(&queue, "retired workers left tasks", |c| c.succeeded == 3).await;
+        let envelope =
+            TaskEnvelope::new(TaskId::new(3), TaskType::new("slow"), serde_json::json!({}));
+        queue.enqueue(envelope).await.unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(queue.counts_by_state().await.unwrap().queued, 1);
+
+        workers.add_workers(1).unwrap();
+        wait_for_counts(&queue, "paused group did not resume", |c| c.succeeded == 4).await;
+        workers.shutdown_and_join().await;
+    }
+
+    #[tokio::test]
+    async fn test_scale_to_keeps_room_for_namespace_reservations() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let config = WorkerGroupConfig {
+            namespace_reservations: HashMap::from([("acme".to_string(), 2)]),
+            ..WorkerGroupConfig::default()
+        };
+        let workers = WorkerGroup::spawn_with_config(
+            3,
+            queue,
+            Arc::new(Runtime::new(Arc::new(HandlerRegistry::new()))),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+
+        assert!(workers.scale_to(1).is_err());
+        assert_eq!(workers.size(), 3);
+        workers.scale_to(2).unwrap();
+        workers.shutdown_and_join().await;
+    }
+
+    #[test]
+    fn test_autoscaler_grows_at_once_and_shrinks_after_delay() {
+        let config = AutoscaleConfig {
+            backlog_per_worker: 5,
+            scale_down_delay: Duration::from_secs(30),
+            ..AutoscaleConfig::new(1, 4)
+        };
+        let mut autoscaler = Autoscaler::new(config);
+        let start = Instant::now();
+
+        assert_eq!(autoscaler.next_size(1, 12, start), 3);
+        assert_eq!(autoscaler.next_size(3, 100, start), 4); // capped at max
+        // Backlog gone: keep the workers until the delay has passed
+        assert_eq!(autoscaler.next_size(4, 0, start), 4);
+        assert_eq!(
+            autoscaler.next_size(4, 0, start + Duration::from_secs(10)),
+            4
+        );
+        // A burst in between restarts the delay
+        assert_eq!(
+            autoscaler.next_size(4, 20, start + Duration::from_secs(20)),
+            4
+        );
+        assert_eq!(
+            autoscaler.next_size(4, 0, start + Duration::from_secs(40)),
+            4
+        );
+        assert_eq!(
+            autoscaler.next_size(4, 0, start + Duration::from_secs(70)),
+            1
+        ); // floor at min
+    }
+
+    #[tokio::test]
+    async fn test_autoscaled_group_follows_the_backlog() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let mut registry = HandlerRegistry::new();
+        let handler = Arc::new(SlowHandler {
+            calls: AtomicU32::new(0),
+        });
+        registry.register(TaskType::new("slow"), handler).unwrap();
+        let config = WorkerGroupConfig {
+            autoscale: Some(AutoscaleConfig {
+                backlog_per_worker: 2,
+                interval: Duration::from_millis(10),
+                scale_down_delay: Duration::from_millis(50),
+                ..AutoscaleConfig::new(0, 3)
+            }),
+            ..WorkerGroupConfig::default()
+        };
+        let workers = WorkerGroup::spawn_with_config(
+            0,
+            queue.clone(),
+            Arc::new(Runtime::new(Arc::new(registry))),
+            Arc::new(DefaultDecider::default_v1()),
+            config,
+        );
+        for i in 0..6 {
+            let envelope =
+                TaskEnvelope::new(TaskId::new(i), TaskType::new("slow"), serde_json::json!({}));
+            queue.enqueue(envelope).await.unwrap();
+        }
+
+        wait_for_counts(&queue, "autoscaler did not grow", |c| c.running == 3).await;
+        assert_eq!(workers.size(), 3);
+        wait_for_counts(&queue, "tasks did not finish", |c| c.succeeded == 6).await;
+        for _ in 0..50 {
+            if workers.size() == 0 {
+                workers.shutdown_and_join().await;
+                return;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Autoscaler did not shrink the idle group");
+    }
+
+    /// Counts tasks that reached a pool not meant for their task type.
+    struct PoolHandler {
+        task_type: &'static str,
+        misrouted: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl TaskHandler for PoolHandler {
+        async fn handle(
+            &self,
+            envelope: &TaskEnvelope,
+        ) -> Result<Outcome, crate::error::WeaverError> {
+            if envelope.task_type().as_str() != self.task_type {
+                self.misrouted.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(Outcome::success())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_type_filters_route_tasks_to_dedicated_pools() {
+        let queue = Arc::new(InMemoryQueue::new(RetryPolicy::default_v1()));
+        let misrouted = Arc::new(AtomicU32::new(0));
+        let pool = |task_type: &'static str, task_types: TaskTypeFilter| {
+            let handler = Arc::new(PoolHandler {
+                task_type,
+                misrouted: misrouted.clone(),
+            });
+            let mut registry = HandlerRegistry::new();
+            for name in ["render", "email"] {
+                registry
+                    .register(TaskType::new(name), handler.clone())
+                    .unwrap();
+            }
+            let config = WorkerGroupConfig {
+                task_types,
+                ..WorkerGroupConfig::default()
+            };
+            WorkerGroup::spawn_with_config(
+                2,
+                queue.clone(),
+                Arc::new(Runtime::new(Arc::new(registry))),
+                Arc::new(DefaultDecider::default_v1()),
+                config,
+            )
+        };
+        let render = pool("render", TaskTypeFilter::only([TaskType::new("render")]));
+        let light = pool("email", TaskTypeFilter::except([TaskType::new("render")]));
+        for i in 0..6 {
+            let task_type = if i % 2 == 0 { "render" } else { "email" };
+            let envelope = TaskEnvelope::new(
+                TaskId::new(i),
+                TaskType::new(task_type),
+                serde_json::json!({}),
+            );
+            queue.enqueue(envelope).await.unwrap();
+        }
+
+        wait_for_counts(&queue, "tasks did not finish", |c| c.succeeded == 6).await;
+        assert_eq!(misrouted.load(Ordering::SeqCst), 0);
+        render.shutdown_and_join().await;
+        light.shutdown_and_join().await;
+    }
 }
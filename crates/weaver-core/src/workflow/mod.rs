@@ -0,0 +1,560 @@
+//! Workflow - 定義ファイル（JSON / YAML）から JobSpec を作る
+//!
+//! # 学習ポイント
+//! - Rust を書かない人が DAG を定義できるように、タスク・依存・リトライ・予算をファイルに書く
+//! - 読んだ値を 1 ノードずつ検証し、エラーは `tasks[1].depends_on[0]` のように場所を指す。
+//!   知らないキーもエラー（typo を黙って無視しない）
+//! - 依存の循環もここで見つける（submit まで待たない）
+//! - JSON は serde_json、YAML は serde_yaml で読む。YAML はアンカー / エイリアスと `<<` の
+//!   マージを使える。重複したキーとタグ（`!`）はエラー
+//!
+//! # 形式
+//! ```yaml
+//! name: nightly_etl
+//! budget:                      # 省略可。書かなかった項目は Budget::default()
+//!   max_attempts_per_task: 3
+//!   deadline_ms: 3600000
+//! tasks:
+//!   - name: extract            # 必須・一意（TaskSpec::title）
+//!     type: etl.extract.v1     # 必須
+//!     payload: {from: "s3://raw"}
+//!     retry: {max_attempts: 5} # このタスクだけ budget を上書き
+//!   - name: load
+//!     type: etl.load.v1
+//!     depends_on: [extract]    # 名前か 0 始まりの番号
+//!   - name: alert
+//!     type: notify.v1
+//!     run_if: [{task: load, when: on_failure}]
+//! ```
+//!
+//! ほかに `intent`, `goal`, `constraints`, `join` を書ける（意味は TaskSpec と同じ）。
+
+#![allow(deprecated)]
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::domain::{Budget, JobSpec, RunCondition, RunIf, TaskId, TaskRef, TaskSpec, TaskType};
+use crate::queue::DependencyGraph;
+
+/// 定義ファイルの形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkflowFormat {
+    Json,
+    Yaml,
+}
+
+impl WorkflowFormat {
+    /// 拡張子で決める（`.yaml` / `.yml` は YAML、それ以外は JSON）
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// 検証済みのワークフロー
+#[derive(Debug, Clone)]
+pub struct Workflow {
+    /// `name`（任意。ログや一覧の表示用）
+    pub name: Option<String>,
+    pub job: JobSpec,
+}
+
+impl Workflow {
+    /// ファイルを読む（形式は拡張子で決める）
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WorkflowError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| WorkflowError::Io {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        Self::parse(&text, WorkflowFormat::from_path(path))
+    }
+
+    /// テキストを読む
+    pub fn parse(text: &str, format: WorkflowFormat) -> Result<Self, WorkflowError> {
+        let value = match format {
+            WorkflowFormat::Json => {
+                serde_json::from_str(text).map_err(|e| WorkflowError::Syntax {
+                    line: e.line(),
+                    message: e.to_string(),
+                })?
+            }
+            WorkflowFormat::Yaml => yaml_value(text)?,
+        };
+        Self::from_value(&value)
+    }
+
+    /// 読み込み済みの値を検証する
+    pub fn from_value(value: &Value) -> Result<Self, WorkflowError> {
+        workflow(Node::root(value))
+    }
+
+    /// submit できる JobSpec
+    pub fn into_job_spec(self) -> JobSpec {
+        self.job
+    }
+}
+
+/// YAML を JSON の値にする（空のドキュメントは null）
+fn yaml_value(text: &str) -> Result<Value, WorkflowError> {
+    let syntax = |e: serde_yaml::Error| WorkflowError::Syntax {
+        line: e.location().map_or(1, |location| location.line()),
+        message: e.to_string(),
+    };
+    // いったん serde_yaml::Value にするのは、重複したキーをエラーにしてマージを展開するため
+    let mut value: serde_yaml::Value = serde_yaml::from_str(text).map_err(syntax)?;
+    value.apply_merge().map_err(syntax)?;
+    // タグ付きの値や文字列にできないキーは JSON にならない
+    Value::deserialize(value).map_err(|e| WorkflowError::Invalid {
+        path: String::new(),
+        message: e.to_string(),
+    })
+}
+
+/// ワークフローを読めなかった理由
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkflowError {
+    /// ファイルを開けない
+    Io { path: String, message: String },
+
+    /// JSON / YAML として読めない
+    Syntax { line: usize, message: String },
+
+    /// 中身が不正。`path` は `tasks[1].depends_on[0]` のような場所（ルートは空）
+    Invalid { path: String, message: String },
+}
+
+impl fmt::Display for WorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, message } => write!(f, "{path}: {message}"),
+            Self::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            Self::Invalid { path, message } if path.is_empty() => write!(f, "{message}"),
+            Self::Invalid { path, message } => write!(f, "{path}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for WorkflowError {}
+
+/// 値と、ルートからの場所
+#[derive(Clone, Copy)]
+struct Node<'a> {
+    value: &'a Value,
+    parent: Option<(&'a Node<'a>, Step<'a>)>,
+}
+
+#[derive(Clone, Copy)]
+enum Step<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+impl<'a> Node<'a> {
+    fn root(value: &'a Value) -> Self {
+        Self {
+            value,
+            parent: None,
+        }
+    }
+
+    fn path(&self) -> String {
+        let Some((parent, step)) = self.parent else {
+            return String::new();
+        };
+        let mut path = parent.path();
+        match step {
+            Step::Key(key) if path.is_empty() => path.push_str(key),
+            Step::Key(key) => {
+                path.push('.');
+                path.push_str(key);
+            }
+            Step::Index(index) => path.push_str(&format!("[{index}]")),
+        }
+        path
+    }
+
+    fn invalid(&self, message: impl Into<String>) -> WorkflowError {
+        WorkflowError::Invalid {
+            path: self.path(),
+            message: message.into(),
+        }
+    }
+
+    /// マッピングで、`allowed` 以外のキーがないこと
+    fn fields(&self, allowed: &[&str]) -> Result<&'a Map<String, Value>, WorkflowError> {
+        let fields = self
+            .value
+            .as_object()
+            .ok_or_else(|| self.invalid(format!("expected a mapping, got {}", self.value)))?;
+        if let Some(unknown) = fields.keys().find(|key| !allowed.contains(&key.as_str())) {
+            return Err(self.invalid(format!(
+                "unknown field `{unknown}` (expected one of: {})",
+                allowed.join(", ")
+            )));
+        }
+        Ok(fields)
+    }
+
+    fn child(&'a self, key: &'a str) -> Option<Node<'a>> {
+        let value = self.value.get(key)?;
+        Some(Node {
+            value,
+            parent: Some((self, Step::Key(key))),
+        })
+    }
+
+    fn items(&'a self) -> Result<Vec<Node<'a>>, WorkflowError> {
+        let items = self
+            .value
+            .as_array()
+            .ok_or_else(|| self.invalid(format!("expected a list, got {}", self.value)))?;
+        Ok(items
+            .iter()
+            .enumerate()
+            .map(|(index, value)| Node {
+                value,
+                parent: Some((self, Step::Index(index))),
+            })
+            .collect())
+    }
+
+    fn string(&self) -> Result<&'a str, WorkflowError> {
+        self.value
+            .as_str()
+            .ok_or_else(|| self.invalid(format!("expected a string, got {}", self.value)))
+    }
+
+    fn number(&self) -> Result<u64, WorkflowError> {
+        self.value.as_u64().ok_or_else(|| {
+            self.invalid(format!(
+                "expected a non-negative integer, got {}",
+                self.value
+            ))
+        })
+    }
+
+    fn count(&self) -> Result<u32, WorkflowError> {
+        match u32::try_from(self.number()?) {
+            Ok(0) | Err(_) => Err(self.invalid(format!("must be between 1 and {}", u32::MAX))),
+            Ok(count) => Ok(count),
+        }
+    }
+
+    fn flag(&self) -> Result<bool, WorkflowError> {
+        self.value
+            .as_bool()
+            .ok_or_else(|| self.invalid(format!("expected true or false, got {}", self.value)))
+    }
+}
+
+fn workflow(root: Node) -> Result<Workflow, WorkflowError> {
+    root.fields(&["name", "budget", "tasks"])?;
+    let name = root
+        .child("name")
+        .map(|name| name.string().map(str::to_string))
+        .transpose()?;
+    let budget = match root.child("budget") {
+        Some(node) => budget(node)?,
+        None => Budget::default(),
+    };
+    let Some(tasks_node) = root.child("tasks") else {
+        return Err(root.invalid("missing field `tasks`"));
+    };
+    let tasks = tasks_node.items()?;
+    if tasks.is_empty() {
+        return Err(tasks_node.invalid("a workflow needs at least one task"));
+    }
+
+    // 依存は後ろのタスクも名前で指せるので、先に名前を集める
+    let mut names: HashMap<&str, usize> = HashMap::new();
+    for (index, task) in tasks.iter().enumerate() {
+        task.fields(TASK_FIELDS)?;
+        let Some(name) = task.child("name") else {
+            return Err(task.invalid("missing field `name`"));
+        };
+        if let Some(first) = names.insert(name.string()?, index) {
+            return Err(name.invalid(format!(
+                "duplicate task name `{}` (also tasks[{first}])",
+                name.string()?
+            )));
+        }
+    }
+
+    let mut specs = Vec::with_capacity(tasks.len());
+    let mut graph = DependencyGraph::new();
+    for (index, node) in tasks.iter().enumerate() {
+        let (spec, deps) = task(node, index, &names, tasks.len())?;
+        for dep in deps {
+            graph.add_dependency(TaskId::new(index as u128), TaskId::new(dep as u128));
+        }
+        specs.push(spec);
+    }
+    if let Some(cycle) = graph.detect_cycle() {
+        let label = |task_id: &TaskId| {
+            specs[task_id.as_u64() as usize]
+                .title
+                .clone()
+                .unwrap_or_default()
+        };
+        let first = cycle[0].as_u64() as usize;
+        let path = cycle.iter().map(label).collect::<Vec<_>>().join(" -> ");
+        return Err(tasks[first].invalid(format!("dependency cycle: {path}")));
+    }
+
+    let mut job = JobSpec::new(specs);
+    job.budget = budget;
+    Ok(Workflow { name, job })
+}
+
+const TASK_FIELDS: &[&str] = &[
+    "name",
+    "type",
+    "payload",
+    "intent",
+    "goal",
+    "constraints",
+    "depends_on",
+    "run_if",
+    "join",
+    "retry",
+];
+
+fn budget(node: Node) -> Result<Budget, WorkflowError> {
+    node.fields(&[
+        "max_attempts_per_task",
+        "max_total_attempts",
+        "deadline_ms",
+        "max_no_progress_steps",
+    ])?;
+    let mut budget = Budget::default();
+    if let Some(n) = node.child("max_attempts_per_task") {
+        budget.max_attempts_per_task = n.count()?;
+    }
+    if let Some(n) = node.child("max_total_attempts") {
+        budget.max_total_attempts = Some(n.count()?);
+    }
+    if let Some(n) = node.child("deadline_ms") {
+        budget.deadline_ms = Some(n.number()?);
+    }
+    if let Some(n) = node.child("max_no_progress_steps") {
+        budget.max_no_progress_steps = Some(n.count()?);
+    }
+    Ok(budget)
+}
+
+/// `tasks[index]` と、待つタスクの番号
+fn task(
+    node: &Node,
+    index: usize,
+    names: &HashMap<&str, usize>,
+    count: usize,
+) -> Result<(TaskSpec, Vec<usize>), WorkflowError> {
+    let name = node
+        .child("name")
+        .expect("checked by workflow()")
+        .string()?;
+    let Some(task_type) = node.child("type") else {
+        return Err(node.invalid("missing field `type`"));
+    };
+    let task_type = match task_type.string()? {
+        "" => return Err(task_type.invalid("task type must not be empty")),
+        task_type => TaskType::new(task_type),
+    };
+    let payload = node
+        .child("payload")
+        .map_or_else(|| serde_json::json!({}), |payload| payload.value.clone());
+    let mut spec = TaskSpec::new(name, task_type, payload);
+    if let Some(intent) = node.child("intent") {
+        spec.intent = Some(intent.string()?.to_string());
+    }
+    spec.goal = node.child("goal").map(|goal| goal.value.clone());
+    spec.constraints = node.child("constraints").map(|c| c.value.clone());
+    if let Some(join) = node.child("join") {
+        spec.join = join.flag()?;
+    }
+    if let Some(retry) = node.child("retry") {
+        retry.fields(&["max_attempts"])?;
+        if let Some(max_attempts) = retry.child("max_attempts") {
+            spec.max_attempts = Some(max_attempts.count()?);
+        }
+    }
+
+    // 名前か番号を、同じワークフローのタスクに解決する
+    let reference = |node: &Node| -> Result<(TaskRef, usize), WorkflowError> {
+        let (task_ref, dep) = match node.value {
+            Value::String(dep_name) => match names.get(dep_name.as_str()) {
+                Some(&dep) => (TaskRef::Name(dep_name.clone()), dep),
+                None => return Err(node.invalid(format!("no task named `{dep_name}`"))),
+            },
+            Value::Number(_) => match node.number()? as usize {
+                dep if dep < count => (TaskRef::Index(dep), dep),
+                dep => {
+                    return Err(
+                        node.invalid(format!("no task at index {dep} (the workflow has {count})"))
+                    );
+                }
+            },
+            other => {
+                return Err(node.invalid(format!("expected a task name or index, got {other}")));
+            }
+        };
+        if dep == index {
+            return Err(node.invalid("a task cannot depend on itself"));
+        }
+        Ok((task_ref, dep))
+    };
+
+    let mut deps = Vec::new();
+    if let Some(depends_on) = node.child("depends_on") {
+        for item in depends_on.items()? {
+            let (task_ref, dep) = reference(&item)?;
+            spec.depends_on.push(task_ref);
+            deps.push(dep);
+        }
+    }
+    if let Some(run_if) = node.child("run_if") {
+        for item in run_if.items()? {
+            item.fields(&["task", "when"])?;
+            let Some(task) = item.child("task") else {
+                return Err(item.invalid("missing field `task`"));
+            };
+            let (task_ref, dep) = reference(&task)?;
+            let when = match item.child("when") {
+                None => return Err(item.invalid("missing field `when`")),
+                Some(when) => match when.string()? {
+                    "on_success" => RunCondition::OnSuccess,
+                    "on_failure" => RunCondition::OnFailure,
+                    "always" => RunCondition::Always,
+                    other => {
+                        return Err(when.invalid(format!(
+                            "unknown condition `{other}` (expected on_success, on_failure or always)"
+                        )));
+                    }
+                },
+            };
+            spec.run_if.push(RunIf {
+                task: task_ref,
+                when,
+            });
+            deps.push(dep);
+        }
+    }
+    Ok((spec, deps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ETL: &str = r#"
+name: nightly_etl
+budget:
+  max_attempts_per_task: 3
+tasks:
+  - name: extract
+    type: etl.extract.v1
+    payload: {from: "s3://raw"}
+    retry: {max_attempts: 5}
+  - name: load
+    type: etl.load.v1
+    depends_on: [extract]
+  - name: alert
+    type: notify.v1
+    run_if:
+      - task: load
+        when: on_failure
+"#;
+
+    #[test]
+    fn yaml_and_json_build_the_same_job() {
+        let from_yaml = Workflow::parse(ETL, WorkflowFormat::Yaml).unwrap();
+        assert_eq!(from_yaml.name.as_deref(), Some("nightly_etl"));
+        let job = from_yaml.into_job_spec();
+        assert_eq!(job.budget.max_attempts_per_task, 3);
+        assert_eq!(job.tasks[0].max_attempts, Some(5));
+        assert_eq!(job.tasks[0].payload["from"], "s3://raw");
+        assert_eq!(job.tasks[1].depends_on, vec![TaskRef::from("extract")]);
+        assert_eq!(job.tasks[2].run_if[0].when, RunCondition::OnFailure);
+
+        let json = serde_json::json!({
+            "name": "nightly_etl",
+            "budget": { "max_attempts_per_task": 3 },
+            "tasks": [
+                { "name": "extract", "type": "etl.extract.v1", "payload": { "from": "s3://raw" }, "retry": { "max_attempts": 5 } },
+                { "name": "load", "type": "etl.load.v1", "depends_on": ["extract"] },
+                { "name": "alert", "type": "notify.v1", "run_if": [{ "task": "load", "when": "on_failure" }] }
+            ]
+        });
+        let from_json = Workflow::parse(&json.to_string(), WorkflowFormat::Json).unwrap();
+        assert_eq!(from_json.job.tasks, job.tasks);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_node() {
+        let error = |text: &str| {
+            Workflow::parse(text, WorkflowFormat::Yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(&ETL.replace("depends_on: [extract]", "depends_on: [extrct]")),
+            "tasks[1].depends_on[0]: no task named `extrct`"
+        );
+        assert_eq!(
+            error(&ETL.replace("when: on_failure", "when: on_error")),
+            "tasks[2].run_if[0].when: unknown condition `on_error` (expected on_success, on_failure or always)"
+        );
+        assert!(
+            error(&ETL.replace("retry:", "retries:"))
+                .starts_with("tasks[0]: unknown field `retries`")
+        );
+        assert_eq!(
+            error(&ETL.replace("payload: {from: \"s3://raw\"}", "depends_on: [alert]")),
+            "tasks[0]: dependency cycle: extract -> alert -> load -> extract"
+        );
+    }
+
+    #[test]
+    fn yaml_merges_anchors_and_rejects_duplicate_keys_and_tags() {
+        let text = r#"
+tasks:
+  - &extract
+    name: extract
+    type: etl.v1
+    retry: {max_attempts: 2}
+  - <<: *extract
+    name: load
+    depends_on: [extract]
+"#;
+        let job = Workflow::parse(text, WorkflowFormat::Yaml)
+            .unwrap()
+            .into_job_spec();
+        assert_eq!(job.tasks[1].title.as_deref(), Some("load"));
+        assert_eq!(job.tasks[1].task_type, TaskType::new("etl.v1"));
+        assert_eq!(job.tasks[1].max_attempts, Some(2));
+
+        let text = "tasks:\n  - name: a\n    type: t\n    name: b\n";
+        let error = Workflow::parse(text, WorkflowFormat::Yaml).unwrap_err();
+        assert!(matches!(error, WorkflowError::Syntax { .. }), "{error}");
+        assert!(error.to_string().contains("duplicate entry"), "{error}");
+
+        let error = Workflow::parse("tasks: !custom []\n", WorkflowFormat::Yaml).unwrap_err();
+        assert!(matches!(error, WorkflowError::Invalid { .. }), "{error}");
+        assert_eq!(
+            Workflow::parse("", WorkflowFormat::Yaml)
+                .unwrap_err()
+                .to_string(),
+            Workflow::parse("null", WorkflowFormat::Json)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
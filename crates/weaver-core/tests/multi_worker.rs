@@ -0,0 +1,188 @@
+//! 複数 worker による end-to-end テスト（`--features integration`）
+//!
+//! 1 プロセスの中で、共有ストアを取り合う複数の worker について次の不変条件を確かめる:
+//! - タスクが失われない（投入したものはすべて一度は実行され、成功で終わる）
+//! - 二重実行しない（失敗を挟まない限り、同じタスクを 2 回実行しない）
+//!
+//! # 構成
+//! - `Cluster` が共有ストアと worker を起動する。worker はそれぞれ独立した adapter を持ち、
+//!   共有されるのはストアだけ（claim の排他はストアが担う）
+//! - ストアは v1 の `InMemoryQueue`（`QueueAsTaskStore` 経由）
+//!
+//! # 範囲外
+//! コンテナ（Postgres / Redis）、別プロセスの worker、outbox の PublisherLoop は扱わない。
+//! プロセスをまたいで共有できる TaskStore / DeliveryQueue がまだなく、`QueueAsTaskStore` は
+//! outbox を持たないため（dev/learning/tasks.md の PR-9 に記録）。
+
+#![cfg(feature = "integration")]
+#![allow(deprecated)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use weaver_core::domain::{DefaultDecider, Outcome, TaskEnvelope, TaskId, TaskType};
+use weaver_core::error::WeaverError;
+use weaver_core::impls::{QueueAsTaskStore, RuntimeAsWorkerLoop};
+use weaver_core::queue::{InMemoryQueue, Queue, RetryPolicy};
+use weaver_core::runtime::{HandlerRegistry, Runtime, TaskHandler};
+
+/// 実行を記録する handler（payload の `n` ごとの実行回数）
+///
+/// `fail_first` に含まれる `n` は 1 回目だけ失敗する。
+#[derive(Default)]
+struct RecordingHandler {
+    executions: Mutex<HashMap<u64, u32>>,
+    fail_first: Vec<u64>,
+}
+
+#[async_trait]
+impl TaskHandler for RecordingHandler {
+    async fn handle(&self, envelope: &TaskEnvelope) -> Result<Outcome, WeaverError> {
+        let n = envelope.payload()["n"].as_u64().expect("payload has n");
+        let count = {
+            let mut executions = self.executions.lock().unwrap();
+            let count = executions.entry(n).or_default();
+            *count += 1;
+            *count
+        };
+        // 他の worker と実行が重なるように少し待つ
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        if count == 1 && self.fail_first.contains(&n) {
+            return Ok(Outcome::failure("first attempt fails"));
+        }
+        Ok(Outcome::success())
+    }
+}
+
+/// 共有ストアと worker 群
+struct Cluster {
+    queue: Arc<InMemoryQueue>,
+    handler: Arc<RecordingHandler>,
+    shutdown: watch::Sender<bool>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Cluster {
+    /// in-memory ストアで `workers` 台の worker を起動する
+    fn start_in_memory(workers: usize, handler: RecordingHandler) -> Self {
+        let retry = RetryPolicy {
+            base_delay: Duration::from_millis(5),
+            ..RetryPolicy::default_v1()
+        };
+        let queue = Arc::new(InMemoryQueue::new(retry.clone()));
+        let handler = Arc::new(handler);
+        let (shutdown, shutdown_rx) = watch::channel(false);
+
+        let workers = (0..workers)
+            .map(|i| {
+                let mut registry = HandlerRegistry::new();
+                registry
+                    .register(TaskType::new("record"), handler.clone())
+                    .unwrap();
+                // worker ごとに adapter を分ける（別プロセス相当）
+                let adapter = Arc::new(QueueAsTaskStore::new(queue.clone()));
+                let worker = RuntimeAsWorkerLoop::new(
+                    adapter.clone(),
+                    adapter,
+                    Arc::new(Runtime::new(Arc::new(registry))),
+                    Arc::new(DefaultDecider::new(retry.clone())),
+                )
+                .with_worker_id(format!("worker-{i}"));
+                let shutdown_rx = shutdown_rx.clone();
+                tokio::spawn(async move { worker.run(shutdown_rx).await })
+            })
+            .collect();
+
+        Self {
+            queue,
+            handler,
+            shutdown,
+            workers,
+        }
+    }
+
+    async fn submit(&self, n: u64) {
+        let env = TaskEnvelope::new(
+            TaskId::new(u128::from(n)),
+            TaskType::new("record"),
+            serde_json::json!({ "n": n }),
+        );
+        self.queue.enqueue(env).await.unwrap();
+    }
+
+    /// 全タスクが終端状態になるまで待つ
+    async fn wait_until_settled(&self, timeout: Duration) {
+        tokio::time::timeout(timeout, async {
+            loop {
+                let counts = self.queue.counts_by_state().await.unwrap();
+                if counts.queued + counts.running + counts.retry_scheduled == 0 {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("tasks did not settle in time");
+    }
+
+    async fn stop(self) -> Arc<RecordingHandler> {
+        self.shutdown.send(true).unwrap();
+        for worker in self.workers {
+            worker.await.unwrap();
+        }
+        self.handler
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn no_lost_tasks_and_no_double_execution() {
+    const TASKS: u64 = 200;
+    let cluster = Cluster::start_in_memory(2, RecordingHandler::default());
+    for n in 0..TASKS {
+        cluster.submit(n).await;
+    }
+    cluster.wait_until_settled(Duration::from_secs(10)).await;
+
+    let counts = cluster.queue.counts_by_state().await.unwrap();
+    assert_eq!(counts.succeeded as u64, TASKS);
+    assert_eq!(counts.dead, 0);
+
+    let handler = cluster.stop().await;
+    let executions = handler.executions.lock().unwrap();
+    assert_eq!(executions.len() as u64, TASKS, "lost tasks");
+    let doubles: Vec<_> = executions.iter().filter(|(_, count)| **count > 1).collect();
+    assert!(doubles.is_empty(), "executed more than once: {doubles:?}");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn retried_tasks_run_exactly_once_more() {
+    const TASKS: u64 = 50;
+    let handler = RecordingHandler {
+        fail_first: (0..TASKS).step_by(5).collect(),
+        ..RecordingHandler::default()
+    };
+    let cluster = Cluster::start_in_memory(2, handler);
+    for n in 0..TASKS {
+        cluster.submit(n).await;
+    }
+    cluster.wait_until_settled(Duration::from_secs(10)).await;
+
+    let counts = cluster.queue.counts_by_state().await.unwrap();
+    assert_eq!(counts.succeeded as u64, TASKS);
+
+    let handler = cluster.stop().await;
+    let executions = handler.executions.lock().unwrap();
+    for n in 0..TASKS {
+        let expected = if handler.fail_first.contains(&n) {
+            2
+        } else {
+            1
+        };
+        assert_eq!(executions[&n], expected, "task {n}");
+    }
+}
@@ -0,0 +1,139 @@
+//! weaver-macros - weaver-core の derive マクロ
+//!
+//! 直接使わず、`weaver_core::typed::WeaverTask`（または prelude）から使う。
+//!
+//! # 学習ポイント
+//! - proc-macro crate は derive だけを公開できる（型や関数は weaver-core 側に置く）
+//! - 属性の値は `syn::Meta::parse_nested_meta` で読む
+//! - 命名規約の違反は `syn::Error` を文字列リテラルの位置で返す
+//!   （コンパイルエラーが `#[task(type = "...")]` を指す）
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, LitStr, Type, parse_macro_input};
+
+/// `Task` を実装する derive
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize, WeaverTask)]
+/// #[task(type = "acme.billing.charge.v1", output = Receipt)]
+/// struct Charge {
+///     amount: u64,
+/// }
+/// ```
+///
+/// - `type`: task_type（必須）。命名規約をコンパイル時に検査する
+/// - `output`: Handler の戻り値の型（省略時は `()`）
+///
+/// `Serialize` / `Deserialize` は `Task` の前提なので、従来どおり serde の derive を並べる。
+#[proc_macro_derive(WeaverTask, attributes(task))]
+pub fn derive_weaver_task(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut task_type: Option<LitStr> = None;
+    let mut output: Option<Type> = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("task"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("type") {
+                let value: LitStr = meta.value()?.parse()?;
+                if let Err(reason) = validate_task_type(&value.value()) {
+                    return Err(syn::Error::new(value.span(), reason));
+                }
+                task_type = Some(value);
+                Ok(())
+            } else if meta.path.is_ident("output") {
+                output = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `type` or `output`"))
+            }
+        })?;
+    }
+    let Some(task_type) = task_type else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "missing `#[task(type = \"namespace.domain.action.v1\")]`",
+        ));
+    };
+    let output = output.map_or_else(|| quote!(()), |output| quote!(#output));
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::weaver_core::typed::Task for #name #ty_generics #where_clause {
+            const TYPE: &'static str = #task_type;
+            type Output = #output;
+        }
+    })
+}
+
+/// task_type の命名規約 `{namespace}.{domain}[.{action}].v{major}` を検査する
+///
+/// 各セグメントは英小文字で始まり、英小文字・数字・`_` だけからなる。
+/// `weaver_core::domain::TaskTypeV2::validate` と同じ規則（あちらは実行時用）。
+fn validate_task_type(value: &str) -> Result<(), String> {
+    let segments: Vec<&str> = value.split('.').collect();
+    let Some((version, names)) = segments.split_last() else {
+        unreachable!("split always yields a segment");
+    };
+    if !(2..=3).contains(&names.len()) {
+        return Err(format!(
+            "task type `{value}` must look like `namespace.domain.action.v1`"
+        ));
+    }
+    for name in names {
+        let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid {
+            return Err(format!(
+                "task type `{value}`: segment `{name}` must be lowercase `a-z`, `0-9` or `_`, starting with a letter"
+            ));
+        }
+    }
+    let major = version.strip_prefix('v').unwrap_or("");
+    if major.is_empty() || major.parse::<u32>().is_err() {
+        return Err(format!(
+            "task type `{value}` must end with a major version like `.v1`"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_types_must_follow_the_naming_convention() {
+        assert!(validate_task_type("acme.billing.charge.v1").is_ok());
+        assert!(validate_task_type("ns.foo.v12").is_ok());
+        assert!(validate_task_type("etl_2.load.v1").is_ok());
+
+        for invalid in [
+            "",
+            "foo",
+            "foo.v1",
+            "acme.billing.charge",
+            "acme.billing.charge.v",
+            "acme.billing.charge.1",
+            "Acme.billing.charge.v1",
+            "acme..charge.v1",
+            "acme.billing-api.charge.v1",
+            "acme.2fa.verify.v1",
+            "acme.billing.charge.extra.v1",
+        ] {
+            assert!(validate_task_type(invalid).is_err(), "{invalid}");
+        }
+    }
+}